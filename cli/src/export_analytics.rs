@@ -0,0 +1,33 @@
+use anyhow::Result;
+
+use abstutil::Timer;
+use geom::Time;
+use sim::{Analytics, SimFlags, TableFormat};
+
+pub fn run(
+    mut flags: SimFlags,
+    prebaked: Option<String>,
+    until: String,
+    output_dir: String,
+    format: TableFormat,
+) -> Result<()> {
+    flags.initialize();
+    let mut timer = Timer::new("export analytics");
+    let (map, mut sim, _) = flags.load_synchronously(&mut timer);
+
+    let paths = if let Some(path) = prebaked {
+        let analytics: Analytics = abstio::maybe_read_binary(path, &mut timer)?;
+        analytics.export_tables(&map, &output_dir, format)?
+    } else {
+        let until = Time::parse(&until)?;
+        if until > sim.time() {
+            sim.timed_step(&map, until - sim.time(), &mut None, &mut timer);
+        }
+        sim.get_analytics()
+            .export_tables(&map, &output_dir, format)?
+    };
+    for path in paths {
+        println!("Wrote {}", path);
+    }
+    Ok(())
+}
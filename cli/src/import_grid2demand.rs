@@ -56,6 +56,7 @@ fn parse_trips(csv_path: String) -> Result<Vec<ExternalPerson>> {
                 destination: ExternalTripEndpoint::Position(destination),
                 mode,
                 purpose: TripPurpose::Work,
+                freight: None,
             }],
         });
     }
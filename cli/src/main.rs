@@ -6,12 +6,15 @@ extern crate log;
 
 mod augment_scenario;
 mod clip_osm;
+mod export_analytics;
 mod generate_houses;
 mod geojson_to_osmosis;
 mod import_grid2demand;
 mod import_scenario;
 mod one_step_import;
 mod osm2lanes;
+mod run_experiments;
+mod sim_trace;
 
 use anyhow::Result;
 use structopt::StructOpt;
@@ -70,6 +73,25 @@ enum Command {
         #[structopt(long)]
         out_path: String,
     },
+    /// Runs a simulation (or loads prebaked results) and writes every series from the analytics as
+    /// a table, keyed by OSM and GTFS IDs.
+    ExportAnalytics {
+        #[structopt(flatten)]
+        flags: sim::SimFlags,
+        /// Instead of running the simulation, export these prebaked results. The map to interpret
+        /// them against still comes from the load path.
+        #[structopt(long)]
+        prebaked: Option<String>,
+        /// Run the simulation until this time
+        #[structopt(long, default_value = "24:00:00")]
+        until: String,
+        /// The directory to write one file per table into
+        #[structopt(long)]
+        output_dir: String,
+        /// csv or parquet
+        #[structopt(long, default_value = "csv")]
+        format: sim::TableFormat,
+    },
     /// Reads a GeoJSON file, extracts a polygon from every feature, and writes numbered files in
     /// the https://wiki.openstreetmap.org/wiki/Osmosis/Polygon_Filter_File_Format format as
     /// output.
@@ -188,6 +210,46 @@ enum Command {
         #[structopt(flatten)]
         opts: map_model::RawToMapOptions,
     },
+    /// Runs every combination of scenarios, map edits, scenario modifiers, and RNG seeds listed
+    /// in a JSON manifest, using all CPU cores. Writes a summary of each run, plus confidence
+    /// intervals across seeds.
+    RunExperiments {
+        /// The path to a JSON manifest, with `scenarios` (paths), `edits` (paths, or null for no
+        /// edits), `modifiers` (a list of lists of scenario modifiers), `rng_seeds`, and `until`
+        /// (a time like "24:00:00")
+        #[structopt(long)]
+        manifest: String,
+        /// The directory to write results into
+        #[structopt(long)]
+        output_dir: String,
+        /// Options applied to every run. The run name gets the run number appended.
+        #[structopt(flatten)]
+        opts: sim::SimOptions,
+    },
+    /// Runs a simulation, recording a compact hash of every step, for comparing against another
+    /// run later.
+    RecordTrace {
+        #[structopt(flatten)]
+        flags: sim::SimFlags,
+        /// Run the simulation until this time
+        #[structopt(long, default_value = "24:00:00")]
+        until: String,
+        /// The path to write the trace, ending in .bin
+        #[structopt(long)]
+        output: String,
+        /// Halt the simulation right after this step, and include the state of the agent involved
+        /// in the trace
+        #[structopt(long)]
+        dump_at_step: Option<usize>,
+    },
+    /// Compares two traces from RecordTrace, and reports the first step where they diverge. Each
+    /// run is replayed up to that step to show the state of the agent involved.
+    CompareTraces {
+        #[structopt()]
+        first: String,
+        #[structopt()]
+        second: String,
+    },
     /// Regenerate all maps and scenarios from scratch.
     RegenerateEverything {
         /// If this command is being run in the cloud, parallelize the jobs by specifying which
@@ -247,6 +309,13 @@ async fn main() -> Result<()> {
             clip_path,
             out_path,
         } => clip_osm::run(pbf_path, clip_path, out_path)?,
+        Command::ExportAnalytics {
+            flags,
+            prebaked,
+            until,
+            output_dir,
+            format,
+        } => export_analytics::run(flags, prebaked, until, output_dir, format)?,
         Command::GeoJSONToOsmosis { input } => geojson_to_osmosis::run(input)?,
         Command::ImportGrid2Demand { input, map } => import_grid2demand::run(input, map)?,
         Command::ImportScenario {
@@ -301,6 +370,18 @@ async fn main() -> Result<()> {
             )
             .await
         }
+        Command::RunExperiments {
+            manifest,
+            output_dir,
+            opts,
+        } => run_experiments::run(manifest, output_dir, opts)?,
+        Command::RecordTrace {
+            flags,
+            until,
+            output,
+            dump_at_step,
+        } => sim_trace::record(flags, until, output, dump_at_step)?,
+        Command::CompareTraces { first, second } => sim_trace::compare(first, second)?,
         Command::RegenerateEverything {
             shard_num,
             num_shards,
@@ -59,7 +59,7 @@ fn transform(road: &Road, driving_side: &str) -> Option<TestCase> {
                 LaneType::Biking => "cycleway",
                 // Until we decide on the schema for some of these other lane types, don't generate
                 // test cases for any roads wth them
-                LaneType::Bus => {
+                LaneType::Bus | LaneType::LoadingZone => {
                     return None;
                 }
                 LaneType::SharedLeftTurn => "shared_left_turn",
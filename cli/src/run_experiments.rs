@@ -0,0 +1,400 @@
+//! Runs the cartesian product of scenarios, map edits, scenario modifiers, and RNG seeds described
+//! by a manifest, then summarizes each run and compares configurations across seeds.
+//!
+//! The output directory will contain:
+//!
+//! - `runs.csv`: one row per run, with trip counts, trip times, and mode share
+//! - `summary.csv`: for every configuration (everything except the seed), the mean of each metric
+//!   across seeds, with a 95% confidence interval
+//! - `roads/run_N.csv`: the number of agents crossing each road during run N
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Duration, Time};
+use map_model::{Map, MapEdits};
+use sim::{Sim, SimOptions};
+use synthpop::{Scenario, ScenarioModifier, TripMode};
+
+#[derive(Deserialize)]
+struct Manifest {
+    /// Paths to scenario files
+    scenarios: Vec<String>,
+    /// Paths to map edits. `null` means the unedited map.
+    #[serde(default = "no_edits")]
+    edits: Vec<Option<String>>,
+    /// Each entry is a list of modifiers applied to the scenario together.
+    #[serde(default = "no_modifiers")]
+    modifiers: Vec<Vec<ScenarioModifier>>,
+    #[serde(default = "default_rng_seeds")]
+    rng_seeds: Vec<u64>,
+    /// How long to run each simulation, like "24:00:00"
+    #[serde(default = "end_of_day")]
+    until: String,
+}
+
+fn no_edits() -> Vec<Option<String>> {
+    vec![None]
+}
+
+fn no_modifiers() -> Vec<Vec<ScenarioModifier>> {
+    vec![Vec::new()]
+}
+
+fn default_rng_seeds() -> Vec<u64> {
+    vec![sim::SimFlags::RNG_SEED]
+}
+
+fn end_of_day() -> String {
+    "24:00:00".to_string()
+}
+
+/// Everything about a run except for the RNG seed
+#[derive(Clone)]
+struct Config {
+    scenario: String,
+    edits: Option<String>,
+    modifiers: usize,
+}
+
+struct RunResult {
+    run: usize,
+    config: usize,
+    rng_seed: u64,
+    metrics: Vec<(String, f64)>,
+}
+
+pub fn run(manifest: String, output_dir: String, opts: SimOptions) -> Result<()> {
+    let mut timer = Timer::new("run experiments");
+    let manifest: Manifest = abstio::maybe_read_json(manifest, &mut timer)?;
+    let until = Time::parse(&manifest.until)?;
+    if manifest.scenarios.is_empty() || manifest.edits.is_empty() || manifest.rng_seeds.is_empty() {
+        bail!("The manifest needs at least one scenario, edits entry, and RNG seed");
+    }
+    let modifiers = if manifest.modifiers.is_empty() {
+        no_modifiers()
+    } else {
+        manifest.modifiers
+    };
+    fs_err::create_dir_all(format!("{}/roads", output_dir))?;
+
+    let mut configs = Vec::new();
+    for scenario in &manifest.scenarios {
+        for edits in &manifest.edits {
+            for idx in 0..modifiers.len() {
+                configs.push(Config {
+                    scenario: scenario.clone(),
+                    edits: edits.clone(),
+                    modifiers: idx,
+                });
+            }
+        }
+    }
+    println!(
+        "Running {} configurations with {} seeds each",
+        prettyprint_usize(configs.len()),
+        prettyprint_usize(manifest.rng_seeds.len())
+    );
+
+    // Loading a map and applying edits is expensive, so share one map among all runs that need
+    // it. Every map is loaded up-front, so that all runs can happen in parallel.
+    let mut groups = Vec::new();
+    for chunk in configs.chunks(modifiers.len()) {
+        let scenario: Scenario = abstio::maybe_read_binary(chunk[0].scenario.clone(), &mut timer)?;
+        let mut map = Map::load_synchronously(scenario.map_name.path(), &mut timer);
+        if let Some(ref path) = chunk[0].edits {
+            let edits = MapEdits::load_from_file(&map, path.clone(), &mut timer)?;
+            map.must_apply_edits(edits, &mut timer);
+            map.recalculate_pathfinding_after_edits(&mut timer);
+        }
+        groups.push((map, scenario));
+    }
+
+    let mut requests = Vec::new();
+    for (idx, config) in configs.iter().enumerate() {
+        let (map, scenario) = &groups[idx / modifiers.len()];
+        for rng_seed in &manifest.rng_seeds {
+            requests.push((
+                requests.len(),
+                idx,
+                map,
+                scenario,
+                &modifiers[config.modifiers],
+                *rng_seed,
+            ));
+        }
+    }
+    let opts = &opts;
+    let output_dir = output_dir.as_str();
+    let mut results = Vec::new();
+    for result in timer.parallelize(
+        "run simulations",
+        requests,
+        |(run, config, map, scenario, modifiers, rng_seed)| {
+            run_once(
+                map, scenario, modifiers, rng_seed, until, opts, run, config, output_dir,
+            )
+        },
+    ) {
+        results.push(result?);
+    }
+
+    write_runs(output_dir, &configs, &modifiers, &results)?;
+    write_summary(output_dir, &configs, &modifiers, &results)?;
+    println!("Wrote results to {}", output_dir);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_once(
+    map: &Map,
+    scenario: &Scenario,
+    modifiers: &[ScenarioModifier],
+    rng_seed: u64,
+    until: Time,
+    opts: &SimOptions,
+    run: usize,
+    config: usize,
+    output_dir: &str,
+) -> Result<RunResult> {
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    let mut timer = Timer::throwaway();
+    let mut scenario = scenario.clone();
+    for m in modifiers {
+        scenario = m.apply(map, scenario);
+    }
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut opts = opts.clone();
+    opts.run_name = format!("{}_{}", opts.run_name, run);
+    let mut sim = Sim::new(map, opts);
+    sim.instantiate(&scenario, map, &mut rng, &mut timer);
+    sim.timed_step(map, until - Time::START_OF_DAY, &mut None, &mut timer);
+    let analytics = sim.get_analytics();
+
+    let mut durations = Vec::new();
+    let mut cancelled = 0;
+    let mut per_mode: BTreeMap<TripMode, usize> = BTreeMap::new();
+    for (_, _, mode, maybe_duration) in &analytics.finished_trips {
+        if let Some(duration) = maybe_duration {
+            durations.push(*duration);
+            *per_mode.entry(*mode).or_insert(0) += 1;
+        } else {
+            cancelled += 1;
+        }
+    }
+    durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut metrics = vec![
+        ("finished_trips".to_string(), durations.len() as f64),
+        ("cancelled_trips".to_string(), cancelled as f64),
+        (
+            "mean_trip_time_seconds".to_string(),
+            if durations.is_empty() {
+                0.0
+            } else {
+                durations.iter().map(|d| d.inner_seconds()).sum::<f64>() / durations.len() as f64
+            },
+        ),
+        (
+            "median_trip_time_seconds".to_string(),
+            percentile(&durations, 50),
+        ),
+        (
+            "p90_trip_time_seconds".to_string(),
+            percentile(&durations, 90),
+        ),
+    ];
+    for mode in TripMode::all() {
+        metrics.push((
+            format!("mode_share_{:?}", mode).to_lowercase(),
+            if durations.is_empty() {
+                0.0
+            } else {
+                per_mode.get(&mode).cloned().unwrap_or(0) as f64 / durations.len() as f64
+            },
+        ));
+    }
+
+    let mut per_road: BTreeMap<map_model::RoadID, usize> = BTreeMap::new();
+    for ((r, _, _), count) in &analytics.road_thruput.counts {
+        *per_road.entry(*r).or_insert(0) += *count;
+    }
+    let mut writer = csv::Writer::from_path(format!("{}/roads/run_{}.csv", output_dir, run))?;
+    writer.write_record(&["osm_way_id", "osm_node1", "osm_node2", "count"])?;
+    for (r, count) in per_road {
+        let id = map.get_r(r).orig_id;
+        writer.write_record(&[
+            id.osm_way_id.0.to_string(),
+            id.i1.0.to_string(),
+            id.i2.0.to_string(),
+            count.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+
+    Ok(RunResult {
+        run,
+        config,
+        rng_seed,
+        metrics,
+    })
+}
+
+fn percentile(sorted: &[Duration], pct: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    sorted[(sorted.len() - 1) * pct / 100].inner_seconds()
+}
+
+fn describe_config(config: &Config, modifiers: &[Vec<ScenarioModifier>]) -> [String; 3] {
+    [
+        config.scenario.clone(),
+        config.edits.clone().unwrap_or_default(),
+        abstutil::to_json_terse(&modifiers[config.modifiers]),
+    ]
+}
+
+fn write_runs(
+    output_dir: &str,
+    configs: &[Config],
+    modifiers: &[Vec<ScenarioModifier>],
+    results: &[RunResult],
+) -> Result<()> {
+    let mut writer = csv::Writer::from_path(format!("{}/runs.csv", output_dir))?;
+    let mut header = vec![
+        "run",
+        "config",
+        "scenario",
+        "edits",
+        "modifiers",
+        "rng_seed",
+    ]
+    .into_iter()
+    .map(|x| x.to_string())
+    .collect::<Vec<_>>();
+    header.extend(results[0].metrics.iter().map(|(name, _)| name.clone()));
+    writer.write_record(&header)?;
+    for result in results {
+        let mut row = vec![result.run.to_string(), result.config.to_string()];
+        row.extend(describe_config(&configs[result.config], modifiers));
+        row.push(result.rng_seed.to_string());
+        row.extend(result.metrics.iter().map(|(_, x)| x.to_string()));
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_summary(
+    output_dir: &str,
+    configs: &[Config],
+    modifiers: &[Vec<ScenarioModifier>],
+    results: &[RunResult],
+) -> Result<()> {
+    let mut writer = csv::Writer::from_path(format!("{}/summary.csv", output_dir))?;
+    writer.write_record(&[
+        "config",
+        "scenario",
+        "edits",
+        "modifiers",
+        "metric",
+        "num_seeds",
+        "mean",
+        "stddev",
+        "ci95_low",
+        "ci95_high",
+    ])?;
+    for (idx, config) in configs.iter().enumerate() {
+        let runs: Vec<&RunResult> = results.iter().filter(|r| r.config == idx).collect();
+        for (metric, (name, _)) in runs[0].metrics.iter().enumerate() {
+            let values: Vec<f64> = runs.iter().map(|r| r.metrics[metric].1).collect();
+            let (mean, stddev, half_width) = confidence_interval(&values);
+            let mut row = vec![idx.to_string()];
+            row.extend(describe_config(config, modifiers));
+            row.extend(vec![
+                name.clone(),
+                values.len().to_string(),
+                mean.to_string(),
+                stddev.to_string(),
+                (mean - half_width).to_string(),
+                (mean + half_width).to_string(),
+            ]);
+            writer.write_record(&row)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Returns the mean, sample standard deviation, and the half-width of a 95% confidence interval
+/// for the mean, using Student's t-distribution since there are usually only a few seeds.
+fn confidence_interval(values: &[f64]) -> (f64, f64, f64) {
+    let n = values.len();
+    let mean = values.iter().sum::<f64>() / n as f64;
+    if n < 2 {
+        return (mean, 0.0, 0.0);
+    }
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let stddev = variance.sqrt();
+    (mean, stddev, t_critical(n - 1) * stddev / (n as f64).sqrt())
+}
+
+/// Two-sided 95% critical values of Student's t-distribution
+fn t_critical(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    if degrees_of_freedom <= TABLE.len() {
+        TABLE[degrees_of_freedom - 1]
+    } else {
+        1.96
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 50), 0.0);
+        let sorted: Vec<Duration> = (1..=11).map(|x| Duration::seconds(x as f64)).collect();
+        assert_eq!(percentile(&sorted, 0), 1.0);
+        assert_eq!(percentile(&sorted, 50), 6.0);
+        assert_eq!(percentile(&sorted, 90), 10.0);
+        assert_eq!(percentile(&sorted, 100), 11.0);
+    }
+
+    #[test]
+    fn test_t_critical() {
+        assert_eq!(t_critical(1), 12.706);
+        assert_eq!(t_critical(4), 2.776);
+        assert_eq!(t_critical(30), 2.042);
+        // Past the table, use the normal distribution
+        assert_eq!(t_critical(31), 1.96);
+        assert_eq!(t_critical(1000), 1.96);
+    }
+
+    #[test]
+    fn test_confidence_interval() {
+        // One seed has no spread
+        assert_eq!(confidence_interval(&[5.0]), (5.0, 0.0, 0.0));
+        assert_eq!(confidence_interval(&[3.0, 3.0, 3.0]), (3.0, 0.0, 0.0));
+
+        // The sample variance of 2, 4, 4, 4, 5, 5, 7, 9 is 32 / 7
+        let (mean, stddev, half_width) =
+            confidence_interval(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(mean, 5.0);
+        let expected_stddev = (32.0_f64 / 7.0).sqrt();
+        assert!((stddev - expected_stddev).abs() < 1e-9);
+        assert!((half_width - 2.365 * expected_stddev / 8.0_f64.sqrt()).abs() < 1e-9);
+    }
+}
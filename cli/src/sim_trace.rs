@@ -0,0 +1,151 @@
+//! Finds where two runs of the same scenario diverge. First record a trace from each run (using
+//! different code or map edits), then compare them. Comparing replays each run up to the divergent
+//! step to show the state of the agent involved. Replaying uses the current build, so when the
+//! runs differ by code changes, only the run from this build can be reproduced; re-record the
+//! other trace with `--dump-at-step` from its own build.
+
+use anyhow::Result;
+use structopt::StructOpt;
+
+use abstutil::Timer;
+use geom::{Duration, Time};
+use sim::{SimFlags, SimTrace, TraceStep};
+
+pub fn record(
+    mut flags: SimFlags,
+    until: String,
+    output: String,
+    dump_at_step: Option<usize>,
+) -> Result<()> {
+    flags.initialize();
+    let mut timer = Timer::new("record trace");
+    let until = Time::parse(&until)?;
+    let (map, mut sim, _) = flags.load_synchronously(&mut timer);
+    sim.enable_trace(dump_at_step);
+    if until > sim.time() {
+        sim.timed_step(&map, until - sim.time(), &mut None, &mut timer);
+    }
+    let mut trace = sim.take_trace().unwrap();
+    trace.sim_flags = sim_flags_from_args();
+    println!(
+        "Recorded {} steps, ending at {}",
+        abstutil::prettyprint_usize(trace.steps().len()),
+        sim.time()
+    );
+    if let Some((step, ref dump)) = trace.dump {
+        println!("Agent state after step {}:\n{}", step, dump);
+    }
+    abstio::write_binary(output, &trace);
+    Ok(())
+}
+
+pub fn compare(first: String, second: String) -> Result<()> {
+    let mut timer = Timer::new("compare traces");
+    let first: SimTrace = abstio::maybe_read_binary(first, &mut timer)?;
+    let second: SimTrace = abstio::maybe_read_binary(second, &mut timer)?;
+
+    let divergence = if let Some(x) = first.find_divergence(&second) {
+        x
+    } else {
+        println!(
+            "The traces are identical for all {} steps",
+            abstutil::prettyprint_usize(first.steps().len())
+        );
+        return Ok(());
+    };
+    println!("The runs first diverge at step {}", divergence.step);
+    for (label, trace, step) in [
+        ("First", &first, &divergence.ours),
+        ("Second", &second, &divergence.theirs),
+    ] {
+        println!();
+        describe(label, trace, step.as_ref(), divergence.step)?;
+    }
+    Ok(())
+}
+
+fn describe(label: &str, trace: &SimTrace, step: Option<&TraceStep>, idx: usize) -> Result<()> {
+    let step = if let Some(step) = step {
+        step
+    } else {
+        println!(
+            "{} run: the trace ended after {} steps",
+            label,
+            trace.steps().len()
+        );
+        return Ok(());
+    };
+    println!(
+        "{} run: at {}, {}",
+        label,
+        step.time,
+        step.describe_command()
+    );
+    let agent = if let Some(agent) = step.agent() {
+        agent
+    } else {
+        return Ok(());
+    };
+    if let Some((dump_step, ref dump)) = trace.dump {
+        if dump_step == idx {
+            println!("{}", dump);
+            return Ok(());
+        }
+    }
+    match replay(trace, step, idx)? {
+        Some(dump) => {
+            println!("{}", dump);
+        }
+        None => {
+            println!(
+                "This build doesn't reproduce the {} run. To see the state of {}, re-record \
+                 this trace with --dump-at-step={} using the build that produced it",
+                label.to_lowercase(),
+                agent,
+                idx
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Re-run the simulation that produced a trace, halting right after one step. Returns the dump of
+/// the agent involved, or None if this build's run doesn't match the trace up to that step.
+fn replay(trace: &SimTrace, step: &TraceStep, idx: usize) -> Result<Option<String>> {
+    let mut flags = SimFlags::from_iter_safe(
+        std::iter::once("replay".to_string()).chain(trace.sim_flags.clone()),
+    )?;
+    flags.initialize();
+    let mut timer = Timer::new(format!("replay until step {}", idx));
+    let (map, mut sim, _) = flags.load_synchronously(&mut timer);
+    sim.enable_trace(Some(idx));
+    // The simulation halts right after the step, well before this
+    let until = step.time + Duration::seconds(1.0);
+    if until > sim.time() {
+        sim.timed_step(&map, until - sim.time(), &mut None, &mut timer);
+    }
+    let replayed = sim.take_trace().unwrap();
+    if replayed.steps()[..] != trace.steps()[..=idx] {
+        return Ok(None);
+    }
+    Ok(replayed.dump.map(|(_, dump)| dump))
+}
+
+/// The arguments passed to `record-trace` that describe the simulation, skipping the ones only
+/// used for recording.
+fn sim_flags_from_args() -> Vec<String> {
+    let mut result = Vec::new();
+    // Skip the binary and the subcommand
+    let mut args = std::env::args().skip(2);
+    while let Some(arg) = args.next() {
+        if ["--until", "--output", "--dump-at-step"].contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with("--until=")
+            && !arg.starts_with("--output=")
+            && !arg.starts_with("--dump-at-step=")
+        {
+            result.push(arg);
+        }
+    }
+    result
+}
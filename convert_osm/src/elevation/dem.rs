@@ -0,0 +1,472 @@
+//! Look up elevation from digital elevation model rasters stored locally, without any external
+//! tools or network access. All rasters are assumed to use WGS84 longitude/latitude coordinates;
+//! projected rasters are skipped.
+
+use std::io::{BufRead, BufReader, Cursor};
+
+use anyhow::Result;
+
+use abstutil::Timer;
+use geom::{Distance, GPSBounds, LonLat};
+use map_model::raw::RawMap;
+
+/// SRTM tiles mark missing data with this value.
+const HGT_VOID: f32 = -32768.0;
+
+pub fn add_data(map: &mut RawMap, timer: &mut Timer) -> Result<()> {
+    let rasters = load_rasters(&map.gps_bounds, timer)?;
+    if rasters.is_empty() {
+        bail!(
+            "no rasters in {} cover the map",
+            abstio::path_shared_input("elevation")
+        );
+    }
+
+    // Only intersections get an elevation; calculate_inclines uses just the endpoints of each
+    // road, so there's no point sampling anywhere else.
+    let mut missing = 0;
+    timer.start_iter("look up elevation of intersections", map.roads.len());
+    for (id, r) in &map.roads {
+        timer.next();
+        let gps_pts = map
+            .gps_bounds
+            .convert_back(&[r.center_points[0], *r.center_points.last().unwrap()]);
+        match (lookup(&rasters, gps_pts[0]), lookup(&rasters, gps_pts[1])) {
+            (Some(start), Some(end)) => {
+                map.intersections.get_mut(&id.i1).unwrap().elevation = Distance::meters(start);
+                map.intersections.get_mut(&id.i2).unwrap().elevation = Distance::meters(end);
+            }
+            _ => {
+                missing += 1;
+            }
+        }
+    }
+    if missing > 0 {
+        warn!(
+            "{} roads have an endpoint not covered by any local elevation raster",
+            missing
+        );
+    }
+    Ok(())
+}
+
+fn lookup(rasters: &[Raster], gps: LonLat) -> Option<f64> {
+    // If rasters overlap, the first one with a value wins
+    rasters.iter().find_map(|r| r.sample(gps))
+}
+
+fn load_rasters(bounds: &GPSBounds, timer: &mut Timer) -> Result<Vec<Raster>> {
+    let mut rasters = Vec::new();
+    for path in abstio::list_dir(abstio::path_shared_input("elevation")) {
+        let lowercase = path.to_lowercase();
+        let result = if lowercase.ends_with(".hgt") {
+            // Don't bother reading tiles that can't cover the map
+            match hgt_southwest_corner(&path) {
+                Some(sw) if overlaps(bounds, sw.x(), sw.y(), sw.x() + 1.0, sw.y() + 1.0) => {
+                    timer.start(format!("read {}", path));
+                    let result = abstio::slurp_file(&path).and_then(|bytes| parse_hgt(bytes, sw));
+                    timer.stop(format!("read {}", path));
+                    result
+                }
+                _ => continue,
+            }
+        } else if lowercase.ends_with(".asc") {
+            abstio::slurp_file(&path).and_then(parse_ascii_grid)
+        } else if lowercase.ends_with(".tif") || lowercase.ends_with(".tiff") {
+            abstio::slurp_file(&path).and_then(parse_geotiff)
+        } else {
+            continue;
+        };
+        match result {
+            Ok(raster) => {
+                if !raster.is_geographic() {
+                    warn!(
+                        "Skipping elevation raster {}: it doesn't use longitude/latitude",
+                        path
+                    );
+                } else if raster.overlaps(bounds) {
+                    info!("Using elevation raster {}", path);
+                    rasters.push(raster);
+                }
+            }
+            Err(err) => {
+                warn!("Skipping elevation raster {}: {}", path, err);
+            }
+        }
+    }
+    Ok(rasters)
+}
+
+fn overlaps(bounds: &GPSBounds, min_lon: f64, min_lat: f64, max_lon: f64, max_lat: f64) -> bool {
+    min_lon <= bounds.max_lon
+        && max_lon >= bounds.min_lon
+        && min_lat <= bounds.max_lat
+        && max_lat >= bounds.min_lat
+}
+
+/// A grid of elevation values in meters. Values are stored row-major, starting from the
+/// northwest corner.
+struct Raster {
+    /// The longitude of the center of the westmost column
+    west: f64,
+    /// The latitude of the center of the northmost row
+    north: f64,
+    /// Degrees of longitude between adjacent columns
+    cell_width: f64,
+    /// Degrees of latitude between adjacent rows
+    cell_height: f64,
+    cols: usize,
+    rows: usize,
+    values: Vec<f32>,
+    nodata: Option<f32>,
+}
+
+impl Raster {
+    fn east(&self) -> f64 {
+        self.west + self.cell_width * (self.cols - 1) as f64
+    }
+
+    fn south(&self) -> f64 {
+        self.north - self.cell_height * (self.rows - 1) as f64
+    }
+
+    fn is_geographic(&self) -> bool {
+        self.west >= -180.0 && self.east() <= 180.0 && self.south() >= -90.0 && self.north <= 90.0
+    }
+
+    fn overlaps(&self, bounds: &GPSBounds) -> bool {
+        overlaps(bounds, self.west, self.south(), self.east(), self.north)
+    }
+
+    fn get(&self, col: usize, row: usize) -> Option<f64> {
+        let value = self.values[row * self.cols + col];
+        if !value.is_finite() || Some(value) == self.nodata {
+            return None;
+        }
+        Some(value as f64)
+    }
+
+    /// Bilinearly interpolate between the 4 cells surrounding a point. If some of those cells are
+    /// missing data, just use the nearest valid one.
+    fn sample(&self, gps: LonLat) -> Option<f64> {
+        let x = (gps.x() - self.west) / self.cell_width;
+        let y = (self.north - gps.y()) / self.cell_height;
+        if x < 0.0 || y < 0.0 || x > (self.cols - 1) as f64 || y > (self.rows - 1) as f64 {
+            return None;
+        }
+        let col0 = (x.floor() as usize).min(self.cols - 1);
+        let row0 = (y.floor() as usize).min(self.rows - 1);
+        let col1 = (col0 + 1).min(self.cols - 1);
+        let row1 = (row0 + 1).min(self.rows - 1);
+        let dx = x - col0 as f64;
+        let dy = y - row0 as f64;
+
+        let corners = [
+            (self.get(col0, row0), (1.0 - dx) * (1.0 - dy)),
+            (self.get(col1, row0), dx * (1.0 - dy)),
+            (self.get(col0, row1), (1.0 - dx) * dy),
+            (self.get(col1, row1), dx * dy),
+        ];
+        if corners.iter().all(|(value, _)| value.is_some()) {
+            return Some(
+                corners
+                    .iter()
+                    .map(|(value, weight)| value.unwrap() * weight)
+                    .sum(),
+            );
+        }
+        corners
+            .iter()
+            .filter_map(|(value, weight)| value.map(|v| (v, *weight)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(value, _)| value)
+    }
+}
+
+/// SRTM tiles are named after their southwest corner, like `N47W122.hgt`.
+fn hgt_southwest_corner(path: &str) -> Option<LonLat> {
+    let name = abstutil::basename(path).to_uppercase();
+    if name.len() != 7 || !name.is_ascii() {
+        return None;
+    }
+    let lat = name[1..3].parse::<f64>().ok()?;
+    let lon = name[4..7].parse::<f64>().ok()?;
+    let lat = match &name[0..1] {
+        "N" => lat,
+        "S" => -lat,
+        _ => {
+            return None;
+        }
+    };
+    let lon = match &name[3..4] {
+        "E" => lon,
+        "W" => -lon,
+        _ => {
+            return None;
+        }
+    };
+    Some(LonLat::new(lon, lat))
+}
+
+/// An SRTM tile is a square grid of big-endian 16-bit signed integers, covering one degree. The
+/// outermost rows and columns overlap with neighboring tiles.
+fn parse_hgt(bytes: Vec<u8>, southwest: LonLat) -> Result<Raster> {
+    let num_values = bytes.len() / 2;
+    let size = (num_values as f64).sqrt() as usize;
+    if size < 2 || size * size * 2 != bytes.len() {
+        bail!("{} bytes isn't a square grid of 16-bit values", bytes.len());
+    }
+    let values = bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_be_bytes([pair[0], pair[1]]) as f32)
+        .collect();
+    let spacing = 1.0 / (size - 1) as f64;
+    Ok(Raster {
+        west: southwest.x(),
+        north: southwest.y() + 1.0,
+        cell_width: spacing,
+        cell_height: spacing,
+        cols: size,
+        rows: size,
+        values,
+        nodata: Some(HGT_VOID),
+    })
+}
+
+/// Parses the ESRI ASCII grid format, described at
+/// <https://desktop.arcgis.com/en/arcmap/latest/manage-data/raster-and-images/esri-ascii-raster-format.htm>.
+fn parse_ascii_grid(bytes: Vec<u8>) -> Result<Raster> {
+    let mut ncols = None;
+    let mut nrows = None;
+    let mut x_corner = None;
+    let mut y_corner = None;
+    let mut x_center = None;
+    let mut y_center = None;
+    let mut cellsize = None;
+    let mut nodata = None;
+    let mut values = Vec::new();
+    for line in BufReader::new(Cursor::new(bytes)).lines() {
+        let line = line?;
+        let mut parts = line.split_whitespace().peekable();
+        let first = match parts.peek() {
+            Some(x) => x.to_lowercase(),
+            None => continue,
+        };
+        // The header lines are all a keyword and a number
+        if first.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            parts.next();
+            let value = parts
+                .next()
+                .ok_or_else(|| anyhow!("header {} missing a value", first))?
+                .parse::<f64>()?;
+            match first.as_ref() {
+                "ncols" => {
+                    ncols = Some(value as usize);
+                }
+                "nrows" => {
+                    nrows = Some(value as usize);
+                }
+                "xllcorner" => {
+                    x_corner = Some(value);
+                }
+                "yllcorner" => {
+                    y_corner = Some(value);
+                }
+                "xllcenter" => {
+                    x_center = Some(value);
+                }
+                "yllcenter" => {
+                    y_center = Some(value);
+                }
+                "cellsize" => {
+                    cellsize = Some(value);
+                }
+                "nodata_value" => {
+                    nodata = Some(value as f32);
+                }
+                _ => bail!("unknown header {}", first),
+            }
+            continue;
+        }
+        for x in parts {
+            values.push(x.parse::<f32>()?);
+        }
+    }
+
+    let cols = ncols.ok_or_else(|| anyhow!("missing ncols"))?;
+    let rows = nrows.ok_or_else(|| anyhow!("missing nrows"))?;
+    let cellsize = cellsize.ok_or_else(|| anyhow!("missing cellsize"))?;
+    if cols < 2 || rows < 2 {
+        bail!("grid is only {}x{}", cols, rows);
+    }
+    if values.len() != cols * rows {
+        bail!("expected {}x{} values, got {}", cols, rows, values.len());
+    }
+    // Normalize to the center of the southwest cell
+    let (west, south) = match (x_corner, y_corner, x_center, y_center) {
+        (Some(x), Some(y), _, _) => (x + cellsize / 2.0, y + cellsize / 2.0),
+        (_, _, Some(x), Some(y)) => (x, y),
+        _ => bail!("missing the lower-left corner or center"),
+    };
+    Ok(Raster {
+        west,
+        north: south + cellsize * (rows - 1) as f64,
+        cell_width: cellsize,
+        cell_height: cellsize,
+        cols,
+        rows,
+        values,
+        nodata,
+    })
+}
+
+/// Reads a single-band GeoTIFF. Only the ModelPixelScale and ModelTiepoint tags are used to
+/// georeference the image; rotated rasters aren't supported.
+fn parse_geotiff(bytes: Vec<u8>) -> Result<Raster> {
+    use tiff::decoder::{Decoder, DecodingResult};
+    use tiff::tags::Tag;
+    use tiff::ColorType;
+
+    let mut decoder = Decoder::new(Cursor::new(bytes))?;
+    if !matches!(decoder.colortype()?, ColorType::Gray(_)) {
+        bail!("not a single-band raster");
+    }
+    let (cols, rows) = decoder.dimensions()?;
+    let (cols, rows) = (cols as usize, rows as usize);
+    if cols < 2 || rows < 2 {
+        bail!("raster is only {}x{}", cols, rows);
+    }
+
+    let scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag)?;
+    let tiepoint = decoder.get_tag_f64_vec(Tag::ModelTiepointTag)?;
+    if scale.len() < 2 || tiepoint.len() < 6 {
+        bail!("malformed georeferencing tags");
+    }
+    let nodata = match decoder.find_tag(Tag::GdalNodata)? {
+        Some(tiff::decoder::ifd::Value::Ascii(x)) => x.trim_end_matches('\0').parse::<f32>().ok(),
+        _ => None,
+    };
+
+    let values: Vec<f32> = match decoder.read_image()? {
+        DecodingResult::U8(v) => v.into_iter().map(|x| x as f32).collect(),
+        DecodingResult::U16(v) => v.into_iter().map(|x| x as f32).collect(),
+        DecodingResult::U32(v) => v.into_iter().map(|x| x as f32).collect(),
+        DecodingResult::F32(v) => v,
+        DecodingResult::F64(v) => v.into_iter().map(|x| x as f32).collect(),
+        _ => bail!("unsupported sample format"),
+    };
+    if values.len() != cols * rows {
+        bail!("expected {}x{} values, got {}", cols, rows, values.len());
+    }
+
+    // The tiepoint maps a raster position (i, j) to a model position (x, y). Assume the
+    // "PixelIsArea" convention, so the tiepoint refers to the corner of a cell.
+    let (cell_width, cell_height) = (scale[0], scale[1]);
+    let (i, j, x, y) = (tiepoint[0], tiepoint[1], tiepoint[3], tiepoint[4]);
+    let west_edge = x - i * cell_width;
+    let north_edge = y + j * cell_height;
+    Ok(Raster {
+        west: west_edge + cell_width / 2.0,
+        north: north_edge - cell_height / 2.0,
+        cell_width,
+        cell_height,
+        cols,
+        rows,
+        values,
+        nodata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx_eq(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "got {}, expected {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_hgt() {
+        // A 3x3 tile, stored from the northwest corner. The center of the east edge is void.
+        let values: Vec<i16> = vec![10, 20, 30, 40, 50, -32768, 70, 80, 90];
+        let bytes = values.iter().flat_map(|x| x.to_be_bytes()).collect();
+        let raster = parse_hgt(bytes, hgt_southwest_corner("N47W122.hgt").unwrap()).unwrap();
+        assert_eq!(raster.west, -122.0);
+        assert_eq!(raster.north, 48.0);
+        assert!(raster.is_geographic());
+
+        approx_eq(raster.sample(LonLat::new(-122.0, 48.0)), 10.0);
+        approx_eq(raster.sample(LonLat::new(-121.0, 47.0)), 90.0);
+        // Halfway between 10, 20, 40, and 50
+        approx_eq(raster.sample(LonLat::new(-121.75, 47.75)), 30.0);
+        // Next to the void, just use the nearest valid cell
+        approx_eq(raster.sample(LonLat::new(-121.4, 47.6)), 50.0);
+        assert_eq!(raster.sample(LonLat::new(-122.1, 47.5)), None);
+
+        assert!(parse_hgt(vec![0; 6], LonLat::new(0.0, 0.0)).is_err());
+        assert_eq!(hgt_southwest_corner("X01E002.hgt"), None);
+        assert_eq!(
+            hgt_southwest_corner("data/S01E002.hgt"),
+            Some(LonLat::new(2.0, -1.0))
+        );
+    }
+
+    #[test]
+    fn test_ascii_grid() {
+        let input = "ncols 3\nnrows 2\nxllcorner -122.5\nyllcorner 47.5\ncellsize 0.5\nNODATA_value -9999\n1 2 3\n4 -9999 6\n";
+        let raster = parse_ascii_grid(input.as_bytes().to_vec()).unwrap();
+        assert_eq!((raster.cols, raster.rows), (3, 2));
+        assert_eq!(raster.west, -122.25);
+        assert_eq!(raster.north, 48.25);
+        approx_eq(raster.sample(LonLat::new(-122.25, 48.25)), 1.0);
+        approx_eq(raster.sample(LonLat::new(-121.25, 47.75)), 6.0);
+        // Next to the missing cell, just use the nearest valid one
+        approx_eq(raster.sample(LonLat::new(-122.125, 48.25)), 1.0);
+
+        assert!(parse_ascii_grid(b"ncols 2\nnrows 2\ncellsize 1\n1 2 3 4\n".to_vec()).is_err());
+        assert!(parse_ascii_grid(
+            b"ncols 2\nnrows 2\nxllcenter 0\nyllcenter 0\ncellsize 1\n1 2 3\n".to_vec()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_geotiff() {
+        use tiff::encoder::{colortype, TiffEncoder};
+        use tiff::tags::Tag;
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = TiffEncoder::new(Cursor::new(&mut bytes)).unwrap();
+            let mut image = encoder.new_image::<colortype::Gray32Float>(2, 2).unwrap();
+            image
+                .encoder()
+                .write_tag(Tag::ModelPixelScaleTag, &[0.5, 0.25, 0.0][..])
+                .unwrap();
+            image
+                .encoder()
+                .write_tag(
+                    Tag::ModelTiepointTag,
+                    &[0.0, 0.0, 0.0, -122.0, 48.0, 0.0][..],
+                )
+                .unwrap();
+            image.write_data(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        }
+
+        let raster = parse_geotiff(bytes).unwrap();
+        assert_eq!((raster.cols, raster.rows), (2, 2));
+        // The tiepoint is the corner of the northwest cell, not its center
+        assert_eq!(raster.west, -121.75);
+        assert_eq!(raster.north, 47.875);
+        approx_eq(raster.sample(LonLat::new(-121.75, 47.875)), 1.0);
+        approx_eq(raster.sample(LonLat::new(-121.25, 47.625)), 4.0);
+        approx_eq(raster.sample(LonLat::new(-121.5, 47.75)), 2.5);
+        assert_eq!(raster.sample(LonLat::new(-121.0, 47.75)), None);
+    }
+}
@@ -7,6 +7,7 @@ use fs_err::File;
 use geom::{Distance, PolyLine};
 use map_model::raw::{OriginalRoad, RawMap};
 
+/// Look up elevation by running the abstreet/elevation_lookups Docker image.
 pub fn add_data(map: &mut RawMap) -> Result<()> {
     // TODO It'd be nice to include more timing breakdown here, but if we bail out early,
     // it's tedious to call timer.stop().
@@ -74,15 +75,7 @@ fn generate_input(map: &RawMap) -> Result<Vec<OriginalRoad>> {
         // TODO Handle cul-de-sacs
         if let Ok(pl) = PolyLine::new(r.center_points.clone()) {
             ids.push(*id);
-            // Sample points along the road. Smaller step size gives more detail, but is slower.
-            let mut pts = Vec::new();
-            for (pt, _) in pl.step_along(Distance::meters(5.0), Distance::ZERO) {
-                pts.push(pt);
-            }
-            // Always ask for the intersection
-            if *pts.last().unwrap() != pl.last_pt() {
-                pts.push(pl.last_pt());
-            }
+            let pts = super::sample_points(&pl);
             for (idx, gps) in map.gps_bounds.convert_back(&pts).into_iter().enumerate() {
                 write!(f, "{},{}", gps.x(), gps.y())?;
                 if idx != pts.len() - 1 {
@@ -133,27 +126,5 @@ fn scrape_output(map: &mut RawMap, ids: Vec<OriginalRoad>) -> Result<()> {
         bail!("Output had {} lines, but we made {} queries", cnt, num_ids);
     }
 
-    // Calculate the incline for each road here, before the road gets trimmed for intersection
-    // geometry. If we did this after trimming, we'd miss some of the horizontal distance.
-    for (id, road) in &mut map.roads {
-        let rise = map.intersections[&id.i2].elevation - map.intersections[&id.i1].elevation;
-        let run = road.length();
-        if !(rise / run).is_finite() {
-            // TODO Warn?
-            continue;
-        }
-        road.percent_incline = rise / run;
-        // Per https://wiki.openstreetmap.org/wiki/Key:incline#Common_.26_extreme_inclines, we
-        // shouldn't often see values outside a certain range. Adjust this when we import
-        // somewhere exceeding this...
-        if road.percent_incline.abs() > 0.3 {
-            error!(
-                "{} is unexpectedly steep! Incline is {}%",
-                id,
-                road.percent_incline * 100.0
-            );
-        }
-    }
-
     Ok(())
 }
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use abstutil::Timer;
+use geom::{Distance, PolyLine, Pt2D};
+use map_model::raw::RawMap;
+
+mod dem;
+mod docker;
+
+/// Where should intersection elevation come from?
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ElevationBackend {
+    /// Don't look up elevation at all; everything stays flat.
+    Skip,
+    /// Run the abstreet/elevation_lookups Docker image. This needs Docker and network access.
+    Docker,
+    /// Read SRTM `.hgt` tiles, GeoTIFFs, and ESRI ASCII grids from
+    /// `data/input/shared/elevation`. If no local raster covers the map, fall back to Docker.
+    LocalRasters,
+}
+
+impl Default for ElevationBackend {
+    fn default() -> ElevationBackend {
+        ElevationBackend::Docker
+    }
+}
+
+/// Fill out `RawIntersection::elevation` and `RawRoad::percent_incline`.
+pub fn add_data(map: &mut RawMap, backend: &ElevationBackend, timer: &mut Timer) -> Result<()> {
+    match backend {
+        ElevationBackend::Skip => {
+            return Ok(());
+        }
+        ElevationBackend::Docker => {
+            docker::add_data(map)?;
+        }
+        ElevationBackend::LocalRasters => {
+            if let Err(err) = dem::add_data(map, timer) {
+                warn!(
+                    "Couldn't use local elevation rasters ({}), falling back to Docker",
+                    err
+                );
+                docker::add_data(map)?;
+            }
+        }
+    }
+    calculate_inclines(map);
+    Ok(())
+}
+
+/// Sample points along a road's center line. Smaller step size gives more detail, but is slower.
+fn sample_points(pl: &PolyLine) -> Vec<Pt2D> {
+    let mut pts = Vec::new();
+    for (pt, _) in pl.step_along(Distance::meters(5.0), Distance::ZERO) {
+        pts.push(pt);
+    }
+    // Always ask for the intersection
+    if *pts.last().unwrap() != pl.last_pt() {
+        pts.push(pl.last_pt());
+    }
+    pts
+}
+
+fn calculate_inclines(map: &mut RawMap) {
+    // Calculate the incline for each road here, before the road gets trimmed for intersection
+    // geometry. If we did this after trimming, we'd miss some of the horizontal distance.
+    for (id, road) in &mut map.roads {
+        let rise = map.intersections[&id.i2].elevation - map.intersections[&id.i1].elevation;
+        let run = road.length();
+        if !(rise / run).is_finite() {
+            // TODO Warn?
+            continue;
+        }
+        road.percent_incline = rise / run;
+        // Per https://wiki.openstreetmap.org/wiki/Key:incline#Common_.26_extreme_inclines, we
+        // shouldn't often see values outside a certain range. Adjust this when we import
+        // somewhere exceeding this...
+        if road.percent_incline.abs() > 0.3 {
+            error!(
+                "{} is unexpectedly steep! Incline is {}%",
+                id,
+                road.percent_incline * 100.0
+            );
+        }
+    }
+}
@@ -7,7 +7,7 @@ use abstutil::{Tags, Timer};
 use geom::{Distance, FindClosest, HashablePt2D, Polygon, Pt2D, Ring};
 use kml::{ExtraShape, ExtraShapes};
 use map_model::raw::{RawArea, RawBuilding, RawMap, RawParkingLot, RawRoad, RestrictionType};
-use map_model::{osm, Amenity, AreaType, Direction, DrivingSide, NamePerLanguage};
+use map_model::{osm, Amenity, AreaType, Direction, DrivingSide, NamePerLanguage, TimeWindows};
 
 use crate::osm_geom::{get_multipolygon_members, glue_multipolygon, multipoly_geometry};
 use crate::Options;
@@ -17,11 +17,17 @@ pub struct OsmExtract {
     pub roads: Vec<(WayID, RawRoad)>,
     /// Traffic signals to the direction they apply
     pub traffic_signals: HashMap<HashablePt2D, Direction>,
+    /// Give-way signs and mini-roundabouts, to the direction they apply if it's tagged
+    pub yields: HashMap<HashablePt2D, Option<Direction>>,
     pub osm_node_ids: HashMap<HashablePt2D, NodeID>,
     /// (ID, restriction type, from way ID, via node ID, to way ID)
     pub simple_turn_restrictions: Vec<(RestrictionType, WayID, NodeID, WayID)>,
     /// (relation ID, from way ID, via way ID, to way ID)
     pub complicated_turn_restrictions: Vec<(RelationID, WayID, WayID, WayID)>,
+    /// (restriction type, from way ID, via node ID, to way ID, when the restriction applies)
+    pub conditional_turn_restrictions: Vec<(RestrictionType, WayID, NodeID, WayID, TimeWindows)>,
+    /// (relation ID, from way ID, via node ID, to way ID, [(from lane, [to lanes])])
+    pub lane_connectivity: Vec<(RelationID, WayID, NodeID, WayID, Vec<(usize, Vec<usize>)>)>,
     /// (location, amenity)
     pub amenities: Vec<(Pt2D, Amenity)>,
     /// Crosswalks located at these points, which should be on a RawRoad's center line
@@ -35,7 +41,16 @@ pub fn extract_osm(
     opts: &Options,
     timer: &mut Timer,
 ) -> OsmExtract {
-    let mut doc = crate::reader::read(osm_input_path, &map.gps_bounds, timer).unwrap();
+    let mut doc = crate::reader::read(
+        osm_input_path,
+        &map.gps_bounds,
+        clip_path.as_ref().map(|_| &map.boundary_polygon),
+        timer,
+    )
+    .unwrap();
+    for path in &opts.osm_changes {
+        crate::reader::apply_change_file(&mut doc, path, timer).unwrap();
+    }
 
     // TODO Hacks to override OSM data. There's no problem upstream, but we want to accomplish
     // various things for A/B Street.
@@ -59,9 +74,12 @@ pub fn extract_osm(
     let mut out = OsmExtract {
         roads: Vec::new(),
         traffic_signals: HashMap::new(),
+        yields: HashMap::new(),
         osm_node_ids: HashMap::new(),
         simple_turn_restrictions: Vec::new(),
         complicated_turn_restrictions: Vec::new(),
+        conditional_turn_restrictions: Vec::new(),
+        lane_connectivity: Vec::new(),
         amenities: Vec::new(),
         crosswalks: HashSet::new(),
     };
@@ -79,6 +97,17 @@ pub fn extract_osm(
             };
             out.traffic_signals.insert(node.pt.to_hashable(), dir);
         }
+        if node
+            .tags
+            .is_any(osm::HIGHWAY, vec!["give_way", "mini_roundabout"])
+        {
+            let dir = match node.tags.get("direction").map(|x| x.as_str()) {
+                Some("forward") => Some(Direction::Fwd),
+                Some("backward") => Some(Direction::Back),
+                _ => None,
+            };
+            out.yields.insert(node.pt.to_hashable(), dir);
+        }
         if node.tags.is(osm::HIGHWAY, "crossing") {
             out.crosswalks.insert(node.pt.to_hashable());
         }
@@ -114,6 +143,8 @@ pub fn extract_osm(
                     osm_tags: way.tags.clone(),
                     turn_restrictions: Vec::new(),
                     complicated_turn_restrictions: Vec::new(),
+                    conditional_turn_restrictions: Vec::new(),
+                    lane_connectivity: Vec::new(),
                     percent_incline: 0.0,
                     // Start assuming there's a crosswalk everywhere, and maybe filter it down
                     // later
@@ -257,6 +288,55 @@ pub fn extract_osm(
                     }
                 }
             }
+            // Only handle time conditions via a node, like
+            // "no_left_turn @ (Mo-Fr 07:00-09:00)"
+            if let (Some(value), Some(from), Some(via), Some(to)) = (
+                rel.tags.get("restriction:conditional"),
+                from_way_id,
+                via_node_id,
+                to_way_id,
+            ) {
+                for (restriction, times) in TimeWindows::parse_conditional(value) {
+                    if let Some(rt) = RestrictionType::new(&restriction) {
+                        out.conditional_turn_restrictions
+                            .push((rt, from, via, to, times));
+                    }
+                }
+            }
+        } else if rel.tags.is("type", "connectivity") {
+            let mut from_way_id: Option<WayID> = None;
+            let mut via_node_id: Option<NodeID> = None;
+            let mut to_way_id: Option<WayID> = None;
+            for (role, member) in &rel.members {
+                match (role.as_ref(), member) {
+                    ("from", OsmID::Way(w)) => {
+                        from_way_id = Some(*w);
+                    }
+                    ("via", OsmID::Node(n)) => {
+                        via_node_id = Some(*n);
+                    }
+                    ("to", OsmID::Way(w)) => {
+                        to_way_id = Some(*w);
+                    }
+                    _ => {}
+                }
+            }
+            match (
+                from_way_id,
+                via_node_id,
+                to_way_id,
+                rel.tags
+                    .get("connectivity")
+                    .and_then(|x| parse_connectivity(x)),
+            ) {
+                (Some(from), Some(via), Some(to), Some(lanes)) => {
+                    out.lane_connectivity.push((id, from, via, to, lanes));
+                }
+                _ => {
+                    // TODO Handle connectivity via a way
+                    warn!("Skipping connectivity relation {}", id);
+                }
+            }
         } else if is_bldg(&rel.tags) {
             match multipoly_geometry(id, rel, &doc) {
                 Ok(polygon) => {
@@ -532,6 +612,24 @@ fn get_bldg_amenities(tags: &Tags) -> Vec<Amenity> {
     amenities
 }
 
+/// Parses a value like `1:1|2:2,(3)` into 0-based (from lane, to lanes). Optional lanes, in
+/// parentheses, are treated like any other.
+fn parse_connectivity(value: &str) -> Option<Vec<(usize, Vec<usize>)>> {
+    let parse_lane = |x: &str| {
+        x.trim_matches(|c| c == '(' || c == ')')
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+    };
+    let mut result = Vec::new();
+    for group in value.split('|') {
+        let (from, to) = group.split_once(':')?;
+        let to = to.split(',').map(parse_lane).collect::<Option<Vec<_>>>()?;
+        result.push((parse_lane(from)?, to));
+    }
+    Some(result)
+}
+
 fn get_area_type(tags: &Tags) -> Option<AreaType> {
     if tags.is_any("leisure", vec!["garden", "park", "golf_course"]) {
         return Some(AreaType::Park);
@@ -1,16 +1,20 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
 
 use anyhow::Result;
+use chrono::{Datelike, NaiveDate, Weekday};
 use fs_err::File;
 use serde::Deserialize;
 
 use abstutil::MultiMap;
-use geom::{LonLat, PolyLine, Pt2D};
+use geom::{Duration, LonLat, PolyLine, Pt2D, Time};
 use kml::{ExtraShape, ExtraShapes};
 use map_model::raw::{RawMap, RawTransitRoute, RawTransitStop};
 use map_model::PathConstraints;
 
-pub fn import(map: &mut RawMap) -> Result<()> {
+/// If `service_date` (in YYYYMMDD format) is specified, also import the schedule of every trip
+/// running on that date.
+pub fn import(map: &mut RawMap, service_date: Option<&str>) -> Result<()> {
     // Collect metadata about routes
     for rec in csv::Reader::from_reader(File::open(map.name.city.input_path("gtfs/routes.txt"))?)
         .deserialize()
@@ -35,6 +39,7 @@ pub fn import(map: &mut RawMap) -> Result<()> {
             shape: PolyLine::dummy(),
             stops: Vec::new(),
             route_type,
+            schedule: Vec::new(),
         });
     }
 
@@ -42,12 +47,14 @@ pub fn import(map: &mut RawMap) -> Result<()> {
     let mut route_to_shapes = MultiMap::new();
     // Map (route_id, shape_id) to trip_id
     let mut route_and_shape_to_trips = MultiMap::new();
+    let mut trip_to_service: HashMap<TripID, ServiceID> = HashMap::new();
     for rec in csv::Reader::from_reader(File::open(map.name.city.input_path("gtfs/trips.txt"))?)
         .deserialize()
     {
         let rec: Trip = rec?;
         route_to_shapes.insert(rec.route_id.clone(), rec.shape_id.clone());
-        route_and_shape_to_trips.insert((rec.route_id, rec.shape_id), rec.trip_id);
+        route_and_shape_to_trips.insert((rec.route_id, rec.shape_id), rec.trip_id.clone());
+        trip_to_service.insert(rec.trip_id, rec.service_id);
     }
 
     // Scrape all shape data. Map from shape_id to points and the sequence number
@@ -102,8 +109,7 @@ pub fn import(map: &mut RawMap) -> Result<()> {
     }
     map.transit_routes = transit_routes;
 
-    // For now, every route uses exactly one trip ID, and there's no schedule. Just pick an
-    // arbitrary trip per route.
+    // Every route uses the stops of exactly one trip ID. Just pick an arbitrary trip per route.
     let mut route_to_trip = HashMap::new();
     for (route_id, shape_id) in &route_to_shape {
         let trips = route_and_shape_to_trips.get((route_id.clone(), shape_id.clone()));
@@ -112,31 +118,88 @@ pub fn import(map: &mut RawMap) -> Result<()> {
         }
     }
 
-    // Scrape the trip ID -> (stop ID, sequence number)
-    let mut trip_to_stops: HashMap<TripID, Vec<(StopID, usize)>> = HashMap::new();
+    // Scrape the trip ID -> (stop ID, sequence number, arrival time)
+    let mut trip_to_stops: HashMap<TripID, Vec<(StopID, usize, Option<Time>)>> = HashMap::new();
     for rec in
         csv::Reader::from_reader(File::open(map.name.city.input_path("gtfs/stop_times.txt"))?)
             .deserialize()
     {
         let rec: StopTime = rec?;
+        // Only timepoints are required to have times
+        let arrival_time = if rec.arrival_time.trim().is_empty() {
+            None
+        } else {
+            Some(Time::parse(rec.arrival_time.trim())?)
+        };
         trip_to_stops
             .entry(rec.trip_id)
             .or_insert_with(Vec::new)
-            .push((rec.stop_id, rec.stop_sequence));
+            .push((rec.stop_id, rec.stop_sequence, arrival_time));
+    }
+    for stops in trip_to_stops.values_mut() {
+        stops.sort_by_key(|(_, seq, _)| *seq);
     }
 
     // Assign the stops for every route
     let mut stop_ids = HashSet::new();
     for route in &mut map.transit_routes {
         let trip_id = route_to_trip[&RouteID(route.gtfs_id.clone())];
-        let mut stops = trip_to_stops.remove(&trip_id).unwrap_or_else(Vec::new);
-        stops.sort_by_key(|(_, seq)| *seq);
-        for (stop_id, _) in stops {
+        for (stop_id, _, _) in trip_to_stops.get(trip_id).cloned().unwrap_or_else(Vec::new) {
             route.stops.push(stop_id.0.clone());
             stop_ids.insert(stop_id);
         }
     }
 
+    if let Some(date) = service_date {
+        let active_services = active_services(map, date)?;
+        let frequencies = read_frequencies(map)?;
+
+        for route in &mut map.transit_routes {
+            let route_id = RouteID(route.gtfs_id.clone());
+            let shape_id = route_to_shape[&route_id].clone();
+            for trip_id in route_and_shape_to_trips.get((route_id, shape_id)) {
+                if !trip_to_service
+                    .get(trip_id)
+                    .map(|service| active_services.contains(service))
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                let stops = if let Some(stops) = trip_to_stops.get(trip_id) {
+                    stops
+                } else {
+                    continue;
+                };
+                // Trips sharing a shape usually serve the same stops, but not always
+                if stops.len() != route.stops.len()
+                    || stops
+                        .iter()
+                        .zip(route.stops.iter())
+                        .any(|((stop_id, _, _), expected)| &stop_id.0 != expected)
+                {
+                    warn!(
+                        "Trip {:?} of route {} serves different stops, skipping it",
+                        trip_id, route.gtfs_id
+                    );
+                    continue;
+                }
+                let times = match interpolate_times(stops) {
+                    Some(times) => times,
+                    None => {
+                        warn!("Trip {:?} has no times at its first or last stop", trip_id);
+                        continue;
+                    }
+                };
+
+                match frequencies.get(trip_id) {
+                    Some(windows) => route.schedule.extend(repeat_trip(&times, windows)),
+                    None => route.schedule.push(times),
+                }
+            }
+            route.schedule.sort_by_key(|times| times[0]);
+        }
+    }
+
     // Scrape stop metadata
     for rec in csv::Reader::from_reader(File::open(map.name.city.input_path("gtfs/stops.txt"))?)
         .deserialize()
@@ -160,9 +223,26 @@ pub fn import(map: &mut RawMap) -> Result<()> {
     // Make sure all of the stops are valid and used by some route
     let mut used_stops = HashSet::new();
     for route in &mut map.transit_routes {
-        route.stops.retain(|stop_id| {
-            used_stops.insert(stop_id.clone());
-            map.transit_stops.contains_key(stop_id)
+        let keep: Vec<bool> = route
+            .stops
+            .iter()
+            .map(|stop_id| {
+                used_stops.insert(stop_id.clone());
+                map.transit_stops.contains_key(stop_id)
+            })
+            .collect();
+        // Keep the schedule lined up with the stops
+        for times in &mut route.schedule {
+            let mut idx = 0;
+            times.retain(|_| {
+                idx += 1;
+                keep[idx - 1]
+            });
+        }
+        let mut idx = 0;
+        route.stops.retain(|_| {
+            idx += 1;
+            keep[idx - 1]
         });
     }
     map.transit_routes.retain(|route| !route.stops.is_empty());
@@ -184,6 +264,8 @@ struct TripID(String);
 struct StopID(String);
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 struct RouteID(String);
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+struct ServiceID(String);
 
 #[derive(Deserialize)]
 struct Route {
@@ -199,6 +281,7 @@ struct Trip {
     route_id: RouteID,
     shape_id: ShapeID,
     trip_id: TripID,
+    service_id: ServiceID,
 }
 
 #[derive(Deserialize)]
@@ -222,6 +305,176 @@ struct StopTime {
     trip_id: TripID,
     stop_id: StopID,
     stop_sequence: usize,
+    arrival_time: String,
+}
+
+#[derive(Deserialize)]
+struct Calendar {
+    service_id: ServiceID,
+    monday: u8,
+    tuesday: u8,
+    wednesday: u8,
+    thursday: u8,
+    friday: u8,
+    saturday: u8,
+    sunday: u8,
+    start_date: String,
+    end_date: String,
+}
+
+#[derive(Deserialize)]
+struct CalendarDate {
+    service_id: ServiceID,
+    date: String,
+    exception_type: u8,
+}
+
+#[derive(Deserialize)]
+struct Frequency {
+    trip_id: TripID,
+    start_time: String,
+    end_time: String,
+    headway_secs: f64,
+}
+
+/// Which services run on this date? Either calendar.txt or calendar_dates.txt may be missing.
+fn active_services(map: &RawMap, date: &str) -> Result<HashSet<ServiceID>> {
+    let open = |path: String| -> Result<Option<File>> {
+        if abstio::file_exists(&path) {
+            Ok(Some(File::open(path)?))
+        } else {
+            Ok(None)
+        }
+    };
+    services_on_date(
+        date,
+        open(map.name.city.input_path("gtfs/calendar.txt"))?,
+        open(map.name.city.input_path("gtfs/calendar_dates.txt"))?,
+    )
+}
+
+fn services_on_date<R: Read>(
+    date: &str,
+    calendar: Option<R>,
+    calendar_dates: Option<R>,
+) -> Result<HashSet<ServiceID>> {
+    let parsed_date = NaiveDate::parse_from_str(date, "%Y%m%d")?;
+    let mut services = HashSet::new();
+
+    if let Some(calendar) = calendar {
+        for rec in csv::Reader::from_reader(calendar).deserialize() {
+            let rec: Calendar = rec?;
+            let runs_on_weekday = match parsed_date.weekday() {
+                Weekday::Mon => rec.monday,
+                Weekday::Tue => rec.tuesday,
+                Weekday::Wed => rec.wednesday,
+                Weekday::Thu => rec.thursday,
+                Weekday::Fri => rec.friday,
+                Weekday::Sat => rec.saturday,
+                Weekday::Sun => rec.sunday,
+            } == 1;
+            // YYYYMMDD strings compare in date order
+            if runs_on_weekday && rec.start_date.as_str() <= date && date <= rec.end_date.as_str() {
+                services.insert(rec.service_id);
+            }
+        }
+    }
+
+    if let Some(calendar_dates) = calendar_dates {
+        for rec in csv::Reader::from_reader(calendar_dates).deserialize() {
+            let rec: CalendarDate = rec?;
+            if rec.date != date {
+                continue;
+            }
+            match rec.exception_type {
+                1 => {
+                    services.insert(rec.service_id);
+                }
+                2 => {
+                    services.remove(&rec.service_id);
+                }
+                x => {
+                    warn!("Unknown calendar_dates exception_type {}", x);
+                }
+            }
+        }
+    }
+
+    if services.is_empty() {
+        bail!("No transit service runs on {}", date);
+    }
+    Ok(services)
+}
+
+/// Trip ID to (start time, end time, headway)
+type Frequencies = HashMap<TripID, Vec<(Time, Time, Duration)>>;
+
+/// frequencies.txt is optional.
+fn read_frequencies(map: &RawMap) -> Result<Frequencies> {
+    let path = map.name.city.input_path("gtfs/frequencies.txt");
+    if !abstio::file_exists(&path) {
+        return Ok(HashMap::new());
+    }
+    parse_frequencies(File::open(path)?)
+}
+
+fn parse_frequencies<R: Read>(input: R) -> Result<Frequencies> {
+    let mut frequencies = HashMap::new();
+    for rec in csv::Reader::from_reader(input).deserialize() {
+        let rec: Frequency = rec?;
+        if rec.headway_secs <= 0.0 {
+            warn!("Trip {:?} has a weird headway", rec.trip_id);
+            continue;
+        }
+        frequencies
+            .entry(rec.trip_id)
+            .or_insert_with(Vec::new)
+            .push((
+                Time::parse(rec.start_time.trim())?,
+                Time::parse(rec.end_time.trim())?,
+                Duration::seconds(rec.headway_secs),
+            ));
+    }
+    Ok(frequencies)
+}
+
+/// A frequency-based trip is only a template. Repeat its times every headway during each window,
+/// not including the end of the window.
+fn repeat_trip(times: &[Time], windows: &[(Time, Time, Duration)]) -> Vec<Vec<Time>> {
+    let mut schedule = Vec::new();
+    for (start, end, headway) in windows {
+        let mut t = *start;
+        while t < *end {
+            let shift = t - times[0];
+            schedule.push(times.iter().map(|x| *x + shift).collect());
+            t += *headway;
+        }
+    }
+    schedule
+}
+
+/// Only some stops along a trip might have times. Fill in the rest, assuming a constant pace
+/// between the known times. Returns None if the first or last stop is missing a time.
+fn interpolate_times(stops: &[(StopID, usize, Option<Time>)]) -> Option<Vec<Time>> {
+    let mut times = Vec::new();
+    let mut last_known = (0, stops.get(0)?.2?);
+    for (idx, (_, _, time)) in stops.iter().enumerate() {
+        if let Some(time) = time {
+            // Fill in the gap since the last known time
+            let (prev_idx, prev_time) = last_known;
+            for gap_idx in (prev_idx + 1)..idx {
+                let pct = ((gap_idx - prev_idx) as f64) / ((idx - prev_idx) as f64);
+                times.push(prev_time + pct * (*time - prev_time));
+            }
+            times.push(*time);
+            last_known = (idx, *time);
+        }
+    }
+    if times.len() == stops.len() {
+        Some(times)
+    } else {
+        None
+    }
 }
 
 fn dump_kml(map: &RawMap) {
@@ -255,3 +508,116 @@ fn dump_kml(map: &RawMap) {
         &ExtraShapes { shapes },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hm(h: usize, m: usize) -> Time {
+        Time::START_OF_DAY + Duration::hours(h) + Duration::minutes(m)
+    }
+
+    fn services(date: &str, calendar: &str, calendar_dates: &str) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = services_on_date(
+            date,
+            Some(calendar.as_bytes()),
+            Some(calendar_dates.as_bytes()),
+        )?
+        .into_iter()
+        .map(|id| id.0)
+        .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    #[test]
+    fn test_services_on_date() {
+        let calendar = "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date
+weekday,1,1,1,1,1,0,0,20210101,20211231
+weekend,0,0,0,0,0,1,1,20210101,20211231
+expired,1,1,1,1,1,1,1,20200101,20201231
+";
+        // The 5th is a Monday holiday
+        let calendar_dates = "service_id,date,exception_type
+weekday,20210705,2
+holiday,20210705,1
+";
+        assert_eq!(
+            services("20210705", calendar, calendar_dates).unwrap(),
+            vec!["holiday"]
+        );
+        assert_eq!(
+            services("20210706", calendar, calendar_dates).unwrap(),
+            vec!["weekday"]
+        );
+        assert_eq!(
+            services("20210710", calendar, calendar_dates).unwrap(),
+            vec!["weekend"]
+        );
+        assert_eq!(
+            services("20220103", calendar, calendar_dates)
+                .unwrap_err()
+                .to_string(),
+            "No transit service runs on 20220103"
+        );
+
+        // Either file may be missing
+        let only_dates: Vec<String> =
+            services_on_date("20210705", None, Some(calendar_dates.as_bytes()))
+                .unwrap()
+                .into_iter()
+                .map(|id| id.0)
+                .collect();
+        assert_eq!(only_dates, vec!["holiday"]);
+    }
+
+    #[test]
+    fn test_frequencies() {
+        let frequencies = parse_frequencies(
+            "trip_id,start_time,end_time,headway_secs
+t1,07:00:00,08:00:00,1200
+t1,17:00:00, 17:30:00,900
+t2,07:00:00,08:00:00,0
+"
+            .as_bytes(),
+        )
+        .unwrap();
+        // The weird headway is skipped
+        assert_eq!(frequencies.len(), 1);
+
+        // The template's own times don't matter, just the time between stops
+        let template = vec![hm(0, 0), hm(0, 5), hm(0, 12)];
+        let schedule = repeat_trip(&template, &frequencies[&TripID("t1".to_string())]);
+        assert_eq!(
+            schedule.iter().map(|times| times[0]).collect::<Vec<_>>(),
+            vec![hm(7, 0), hm(7, 20), hm(7, 40), hm(17, 0), hm(17, 15)]
+        );
+        assert_eq!(schedule[1], vec![hm(7, 20), hm(7, 25), hm(7, 32)]);
+    }
+
+    #[test]
+    fn test_interpolate_times() {
+        let stops = |times: Vec<Option<Time>>| -> Vec<(StopID, usize, Option<Time>)> {
+            times
+                .into_iter()
+                .enumerate()
+                .map(|(idx, time)| (StopID(format!("stop{}", idx)), idx, time))
+                .collect()
+        };
+
+        assert_eq!(
+            interpolate_times(&stops(vec![
+                Some(hm(7, 0)),
+                None,
+                None,
+                Some(hm(7, 30)),
+                Some(hm(7, 35))
+            ])),
+            Some(vec![hm(7, 0), hm(7, 10), hm(7, 20), hm(7, 30), hm(7, 35)])
+        );
+        // The first and last stops need times
+        assert_eq!(interpolate_times(&stops(vec![None, Some(hm(7, 0))])), None);
+        assert_eq!(interpolate_times(&stops(vec![Some(hm(7, 0)), None])), None);
+        assert_eq!(interpolate_times(&stops(Vec::new())), None);
+    }
+}
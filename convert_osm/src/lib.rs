@@ -14,6 +14,8 @@ use map_model::raw::{OriginalRoad, RawMap};
 use map_model::{osm, raw, Amenity, MapConfig};
 use serde::{Deserialize, Serialize};
 
+pub use self::elevation::ElevationBackend;
+
 mod clip;
 mod elevation;
 mod extract;
@@ -41,6 +43,13 @@ pub struct Options {
     pub filter_crosswalks: bool,
     /// Configure public transit using this URL to a static GTFS feed in .zip format.
     pub gtfs_url: Option<String>,
+    /// Use the GTFS feed's schedule for this date, in YYYYMMDD format. If None, transit vehicles
+    /// just run every 30 minutes.
+    pub gtfs_service_date: Option<String>,
+    /// How to look up elevation for intersections.
+    pub elevation: ElevationBackend,
+    /// OSM change files (.osc) to apply on top of the input, in order.
+    pub osm_changes: Vec<String>,
 }
 
 /// What roads will have on-street parking lanes? Data from
@@ -108,7 +117,7 @@ pub fn convert(
 
     // TODO Make this bail out on failure, after the new dependencies are clearly explained.
     timer.start("add elevation data");
-    if let Err(err) = elevation::add_data(&mut map) {
+    if let Err(err) = elevation::add_data(&mut map, &opts.elevation, timer) {
         error!("No elevation data: {}", err);
     }
     timer.stop("add elevation data");
@@ -126,7 +135,7 @@ pub fn convert(
     }
 
     if opts.gtfs_url.is_some() {
-        gtfs::import(&mut map).unwrap();
+        gtfs::import(&mut map, opts.gtfs_service_date.as_deref()).unwrap();
     }
 
     map.config = opts.map_config;
@@ -4,9 +4,14 @@ use anyhow::Result;
 
 use abstio::slurp_file;
 use abstutil::{prettyprint_usize, Tags, Timer};
-use geom::{GPSBounds, LonLat, Pt2D};
+use geom::{GPSBounds, LonLat, Polygon, Pt2D};
 use map_model::osm::{NodeID, OsmID, RelationID, WayID};
 
+mod osc;
+mod pbf;
+
+pub use self::osc::apply_change_file;
+
 // References to missing objects are just filtered out.
 // Per https://wiki.openstreetmap.org/wiki/OSM_XML#Certainties_and_Uncertainties, we assume
 // elements come in order: nodes, ways, then relations.
@@ -41,7 +46,18 @@ pub struct Relation {
     pub members: Vec<(String, OsmID)>,
 }
 
-pub fn read(path: &str, input_gps_bounds: &GPSBounds, timer: &mut Timer) -> Result<Document> {
+/// Reads an `.osm` XML or `.osm.pbf` file. A `.pbf` file is clipped to the boundary while
+/// reading; an XML file is assumed to already be clipped, so the boundary is ignored.
+pub fn read(
+    path: &str,
+    input_gps_bounds: &GPSBounds,
+    boundary: Option<&Polygon>,
+    timer: &mut Timer,
+) -> Result<Document> {
+    if path.ends_with(".pbf") {
+        return pbf::read(path, input_gps_bounds, boundary, timer);
+    }
+
     timer.start(format!("read {}", path));
     let bytes = slurp_file(path)?;
     let raw_string = std::str::from_utf8(&bytes)?;
@@ -180,8 +196,7 @@ fn read_tags(obj: roxmltree::Node) -> Tags {
     for child in obj.children() {
         if child.tag_name().name() == "tag" {
             let key = child.attribute("k").unwrap();
-            // Filter out really useless data
-            if key.starts_with("tiger:") || key.starts_with("old_name:") {
+            if is_useless_tag(key) {
                 continue;
             }
             tags.insert(key, child.attribute("v").unwrap());
@@ -190,6 +205,11 @@ fn read_tags(obj: roxmltree::Node) -> Tags {
     tags
 }
 
+/// Filter out really useless data
+fn is_useless_tag(key: &str) -> bool {
+    key.starts_with("tiger:") || key.starts_with("old_name:")
+}
+
 fn scrape_bounds(doc: &roxmltree::Document) -> GPSBounds {
     let mut b = GPSBounds::new();
     for obj in doc.descendants() {
@@ -0,0 +1,301 @@
+//! Applies an OSM change file (`.osc`) on top of an already-read `Document`. See
+//! <https://wiki.openstreetmap.org/wiki/OsmChange>. This lets a map be updated from minutely or
+//! daily diffs without downloading a whole new extract.
+
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+
+use abstio::slurp_file;
+use abstutil::{prettyprint_usize, Timer};
+use geom::LonLat;
+use map_model::osm::{NodeID, OsmID, RelationID, WayID};
+
+use super::{read_tags, Document, Node, Relation, Way};
+
+/// Objects are applied in the order they appear. New objects outside the document's bounds are
+/// skipped, as are references to missing objects, just like when reading the original file.
+pub fn apply_change_file(doc: &mut Document, path: &str, timer: &mut Timer) -> Result<()> {
+    timer.start(format!("apply {}", path));
+    let bytes = slurp_file(path)?;
+    let raw_string = std::str::from_utf8(&bytes)?;
+    let changes = apply_changes(doc, raw_string).with_context(|| format!("applying {}", path))?;
+    timer.stop(format!("apply {}", path));
+    info!(
+        "Applied {} changes from {}",
+        prettyprint_usize(changes),
+        path
+    );
+    Ok(())
+}
+
+/// Returns the number of objects created, modified, or deleted.
+fn apply_changes(doc: &mut Document, raw_string: &str) -> Result<usize> {
+    let tree = roxmltree::Document::parse(raw_string)?;
+    let root = tree.root_element();
+    if root.tag_name().name() != "osmChange" {
+        bail!("not an OSM change file");
+    }
+
+    let mut changes = 0;
+    for action in root.children().filter(|n| n.is_element()) {
+        let delete = match action.tag_name().name() {
+            "create" | "modify" => false,
+            "delete" => true,
+            _ => continue,
+        };
+        for obj in action.children().filter(|n| n.is_element()) {
+            let id = parse_attr::<i64>(obj, "id")?;
+            match obj.tag_name().name() {
+                "node" => {
+                    let id = NodeID(id);
+                    if delete {
+                        doc.nodes.remove(&id);
+                    } else {
+                        let pt = LonLat::new(parse_attr(obj, "lon")?, parse_attr(obj, "lat")?);
+                        // Existing nodes are always updated, even if they move out of bounds,
+                        // since ways partly inside the boundary keep all of their nodes.
+                        if doc.nodes.contains_key(&id) || doc.gps_bounds.contains(pt) {
+                            doc.nodes.insert(
+                                id,
+                                Node {
+                                    pt: pt.to_pt(&doc.gps_bounds),
+                                    tags: read_tags(obj),
+                                },
+                            );
+                        }
+                    }
+                }
+                "way" => {
+                    let id = WayID(id);
+                    doc.ways.remove(&id);
+                    if !delete {
+                        let mut nodes = Vec::new();
+                        for child in obj.children() {
+                            if child.tag_name().name() == "nd" {
+                                let n = NodeID(parse_attr(child, "ref")?);
+                                if doc.nodes.contains_key(&n) {
+                                    nodes.push(n);
+                                }
+                            }
+                        }
+                        if !nodes.is_empty() {
+                            doc.ways.insert(
+                                id,
+                                Way {
+                                    nodes,
+                                    // Filled out below
+                                    pts: Vec::new(),
+                                    tags: read_tags(obj),
+                                },
+                            );
+                        }
+                    }
+                }
+                "relation" => {
+                    let id = RelationID(id);
+                    doc.relations.remove(&id);
+                    if !delete {
+                        let mut members = Vec::new();
+                        for child in obj.children() {
+                            if child.tag_name().name() != "member" {
+                                continue;
+                            }
+                            let r = parse_attr::<i64>(child, "ref")?;
+                            let member = match parse_attr::<String>(child, "type")?.as_ref() {
+                                "node" => OsmID::Node(NodeID(r)),
+                                "way" => OsmID::Way(WayID(r)),
+                                "relation" => OsmID::Relation(RelationID(r)),
+                                _ => continue,
+                            };
+                            members.push((parse_attr(child, "role")?, member));
+                        }
+                        doc.relations.insert(
+                            id,
+                            Relation {
+                                tags: read_tags(obj),
+                                members,
+                            },
+                        );
+                    }
+                }
+                _ => continue,
+            }
+            changes += 1;
+        }
+    }
+
+    // Nodes may have moved or been deleted without the ways using them being in the change file,
+    // so recalculate all geometry. Then filter out references to anything now missing.
+    let nodes = &doc.nodes;
+    for way in doc.ways.values_mut() {
+        way.nodes.retain(|n| nodes.contains_key(n));
+        way.pts = way.nodes.iter().map(|n| nodes[n].pt).collect();
+    }
+    doc.ways.retain(|_, way| !way.nodes.is_empty());
+    let ways = &doc.ways;
+    let relation_ids: BTreeSet<RelationID> = doc.relations.keys().cloned().collect();
+    for relation in doc.relations.values_mut() {
+        relation.members.retain(|(_, member)| match member {
+            OsmID::Node(n) => nodes.contains_key(n),
+            OsmID::Way(w) => ways.contains_key(w),
+            OsmID::Relation(r) => relation_ids.contains(r),
+        });
+    }
+
+    Ok(changes)
+}
+
+fn parse_attr<T: FromStr>(obj: roxmltree::Node, key: &str) -> Result<T>
+where
+    <T as FromStr>::Err: std::error::Error + Send + Sync + 'static,
+{
+    // <nd> and <member> don't have IDs, so describe the object they belong to
+    let describe = || match (obj.attribute("id"), obj.parent_element()) {
+        (Some(id), _) => format!("<{}> {}", obj.tag_name().name(), id),
+        (None, Some(parent)) => format!(
+            "<{}> in <{}> {}",
+            obj.tag_name().name(),
+            parent.tag_name().name(),
+            parent.attribute("id").unwrap_or("without an id")
+        ),
+        (None, None) => format!("<{}>", obj.tag_name().name()),
+    };
+    let value = obj
+        .attribute(key)
+        .ok_or_else(|| anyhow!("{} is missing {}", describe(), key))?;
+    value
+        .parse::<T>()
+        .with_context(|| format!("{} has an invalid {}=\"{}\"", describe(), key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use abstutil::Tags;
+    use geom::GPSBounds;
+
+    use super::*;
+
+    fn doc() -> Document {
+        let gps_bounds = GPSBounds::from(vec![
+            LonLat::new(-122.31, 47.59),
+            LonLat::new(-122.29, 47.61),
+        ]);
+        let mut doc = Document {
+            gps_bounds,
+            nodes: BTreeMap::new(),
+            ways: BTreeMap::new(),
+            relations: BTreeMap::new(),
+        };
+        for (id, lon) in [(1, -122.305), (2, -122.3), (3, -122.295)] {
+            doc.nodes.insert(
+                NodeID(id),
+                Node {
+                    pt: LonLat::new(lon, 47.6).to_pt(&doc.gps_bounds),
+                    tags: Tags::empty(),
+                },
+            );
+        }
+        let nodes = vec![NodeID(1), NodeID(2), NodeID(3)];
+        doc.ways.insert(
+            WayID(100),
+            Way {
+                pts: nodes.iter().map(|n| doc.nodes[n].pt).collect(),
+                nodes,
+                tags: Tags::new(btreemap("highway", "residential")),
+            },
+        );
+        doc.relations.insert(
+            RelationID(200),
+            Relation {
+                tags: Tags::new(btreemap("type", "route")),
+                members: vec![(String::new(), OsmID::Way(WayID(100)))],
+            },
+        );
+        doc
+    }
+
+    fn btreemap(k: &str, v: &str) -> BTreeMap<String, String> {
+        vec![(k.to_string(), v.to_string())].into_iter().collect()
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let mut doc = doc();
+        let changes = apply_changes(
+            &mut doc,
+            r#"<osmChange version="0.6">
+                <modify>
+                    <node id="2" lon="-122.3" lat="47.601"/>
+                    <way id="100">
+                        <nd ref="1"/>
+                        <nd ref="2"/>
+                        <nd ref="3"/>
+                        <tag k="highway" v="tertiary"/>
+                    </way>
+                </modify>
+                <create>
+                    <node id="4" lon="-122.0" lat="47.0"/>
+                    <way id="101">
+                        <nd ref="3"/>
+                        <nd ref="4"/>
+                        <tag k="highway" v="service"/>
+                    </way>
+                    <relation id="201">
+                        <member type="way" ref="101" role="outer"/>
+                        <member type="way" ref="999" role="outer"/>
+                    </relation>
+                </create>
+                <delete>
+                    <node id="3"/>
+                </delete>
+            </osmChange>"#,
+        )
+        .unwrap();
+        assert_eq!(changes, 6);
+
+        // The new node is out of bounds, so it's skipped
+        assert_eq!(
+            doc.nodes.keys().cloned().collect::<Vec<_>>(),
+            vec![NodeID(1), NodeID(2)]
+        );
+        // The way loses the deleted node, and its geometry follows the node that moved
+        let way = &doc.ways[&WayID(100)];
+        assert_eq!(way.nodes, vec![NodeID(1), NodeID(2)]);
+        assert_eq!(
+            way.pts[1],
+            LonLat::new(-122.3, 47.601).to_pt(&doc.gps_bounds)
+        );
+        assert_eq!(way.tags.get("highway"), Some(&"tertiary".to_string()));
+        // None of the new way's nodes remain
+        assert!(!doc.ways.contains_key(&WayID(101)));
+        assert!(doc.relations[&RelationID(201)].members.is_empty());
+        assert_eq!(doc.relations[&RelationID(200)].members.len(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = apply_changes(&mut doc(), "<osm></osm>").unwrap_err();
+        assert_eq!(err.to_string(), "not an OSM change file");
+
+        let err = apply_changes(
+            &mut doc(),
+            r#"<osmChange><create><node id="5" lon="-122.3"/></create></osmChange>"#,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "<node> 5 is missing lat");
+
+        let err = apply_changes(
+            &mut doc(),
+            r#"<osmChange><modify><way id="100"><nd ref="x"/></way></modify></osmChange>"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "<nd> in <way> 100 has an invalid ref=\"x\""
+        );
+    }
+}
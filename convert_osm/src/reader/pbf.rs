@@ -0,0 +1,281 @@
+//! Reads `.osm.pbf` files directly, clipping to a boundary while reading, so large extracts never
+//! need to be converted to XML. This matches `osmconvert -B=boundary.poly --complete-ways`: ways
+//! partly inside the boundary keep all of their nodes, so borders can be calculated later.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::BufReader;
+
+use anyhow::Result;
+use fs_err::File;
+use osmio::obj_types::ArcOSMObj;
+use osmio::pbf::PBFReader;
+use osmio::{OSMObjBase, OSMObjectType, OSMReader, Relation as _, Way as _};
+
+use abstutil::{prettyprint_usize, Tags, Timer};
+use geom::{GPSBounds, LonLat, Polygon};
+use map_model::osm::{NodeID, OsmID, RelationID, WayID};
+
+use super::{is_useless_tag, Document, Node, Relation, Way};
+
+/// If no boundary is specified, everything is read, and the bounds are calculated from all nodes.
+pub fn read(
+    path: &str,
+    input_gps_bounds: &GPSBounds,
+    boundary: Option<&Polygon>,
+    timer: &mut Timer,
+) -> Result<Document> {
+    let mut doc = Document {
+        gps_bounds: input_gps_bounds.clone(),
+        nodes: BTreeMap::new(),
+        ways: BTreeMap::new(),
+        relations: BTreeMap::new(),
+    };
+
+    // The file is read twice, so only the IDs of the objects to keep are held in memory, not
+    // the entire file. Like the XML reader, this assumes nodes come first, then ways, then
+    // relations.
+    timer.start(format!("find objects to keep from {}", path));
+    let mut keep_nodes = HashSet::new();
+    let mut keep_ways = HashSet::new();
+    let mut keep_relations = HashSet::new();
+    let mut all_bounds = GPSBounds::new();
+    {
+        let mut inside_nodes = HashSet::new();
+        for obj in open(path)?.objects() {
+            match obj {
+                ArcOSMObj::Node(node) => {
+                    if let Some(pt) = lon_lat(&node) {
+                        let inside = if let Some(polygon) = boundary {
+                            doc.gps_bounds.contains(pt)
+                                && polygon.contains_pt(pt.to_pt(&doc.gps_bounds))
+                        } else {
+                            all_bounds.update(pt);
+                            true
+                        };
+                        if inside {
+                            inside_nodes.insert(node.id());
+                        }
+                    }
+                }
+                ArcOSMObj::Way(way) => {
+                    if way.nodes().iter().any(|n| inside_nodes.contains(n)) {
+                        keep_ways.insert(way.id());
+                        keep_nodes.extend(way.nodes().iter().cloned());
+                    }
+                }
+                ArcOSMObj::Relation(relation) => {
+                    if relation.members().any(|(obj_type, id, _)| match obj_type {
+                        OSMObjectType::Node => inside_nodes.contains(&id),
+                        OSMObjectType::Way => keep_ways.contains(&id),
+                        OSMObjectType::Relation => keep_relations.contains(&id),
+                    }) {
+                        keep_relations.insert(relation.id());
+                    }
+                }
+            }
+        }
+        // Also keep nodes inside the boundary that aren't part of a way, like amenities
+        keep_nodes.extend(inside_nodes);
+    }
+    timer.stop(format!("find objects to keep from {}", path));
+
+    if doc.gps_bounds == GPSBounds::new() {
+        doc.gps_bounds = all_bounds;
+    }
+
+    timer.start(format!("read {}", path));
+    for obj in open(path)?.objects() {
+        match obj {
+            ArcOSMObj::Node(node) => {
+                let id = NodeID(node.id());
+                if !keep_nodes.contains(&id.0) {
+                    continue;
+                }
+                if doc.nodes.contains_key(&id) {
+                    bail!("Duplicate {}, your .osm.pbf is corrupt", id);
+                }
+                if let Some(pt) = lon_lat(&node) {
+                    doc.nodes.insert(
+                        id,
+                        Node {
+                            pt: pt.to_pt(&doc.gps_bounds),
+                            tags: read_tags(&node),
+                        },
+                    );
+                }
+            }
+            ArcOSMObj::Way(way) => {
+                let id = WayID(way.id());
+                if !keep_ways.contains(&id.0) {
+                    continue;
+                }
+                if doc.ways.contains_key(&id) {
+                    bail!("Duplicate {}, your .osm.pbf is corrupt", id);
+                }
+                let mut nodes = Vec::new();
+                let mut pts = Vec::new();
+                for n in way.nodes() {
+                    let n = NodeID(*n);
+                    // Just skip missing nodes
+                    if let Some(node) = doc.nodes.get(&n) {
+                        nodes.push(n);
+                        pts.push(node.pt);
+                    }
+                }
+                if !nodes.is_empty() {
+                    doc.ways.insert(
+                        id,
+                        Way {
+                            nodes,
+                            pts,
+                            tags: read_tags(&way),
+                        },
+                    );
+                }
+            }
+            ArcOSMObj::Relation(relation) => {
+                let id = RelationID(relation.id());
+                if !keep_relations.contains(&id.0) {
+                    continue;
+                }
+                if doc.relations.contains_key(&id) {
+                    bail!("Duplicate {}, your .osm.pbf is corrupt", id);
+                }
+                let mut members = Vec::new();
+                for (obj_type, member, role) in relation.members() {
+                    // References to missing objects are filtered out
+                    let member = match obj_type {
+                        OSMObjectType::Node => {
+                            if !doc.nodes.contains_key(&NodeID(member)) {
+                                continue;
+                            }
+                            OsmID::Node(NodeID(member))
+                        }
+                        OSMObjectType::Way => {
+                            if !doc.ways.contains_key(&WayID(member)) {
+                                continue;
+                            }
+                            OsmID::Way(WayID(member))
+                        }
+                        OSMObjectType::Relation => {
+                            if !doc.relations.contains_key(&RelationID(member)) {
+                                continue;
+                            }
+                            OsmID::Relation(RelationID(member))
+                        }
+                    };
+                    members.push((role.to_string(), member));
+                }
+                doc.relations.insert(
+                    id,
+                    Relation {
+                        tags: read_tags(&relation),
+                        members,
+                    },
+                );
+            }
+        }
+    }
+    timer.stop(format!("read {}", path));
+    info!(
+        "Found {} nodes, {} ways, {} relations",
+        prettyprint_usize(doc.nodes.len()),
+        prettyprint_usize(doc.ways.len()),
+        prettyprint_usize(doc.relations.len())
+    );
+
+    Ok(doc)
+}
+
+fn open(path: &str) -> Result<PBFReader<BufReader<File>>> {
+    Ok(PBFReader::new(BufReader::new(File::open(path)?)))
+}
+
+fn lon_lat<N: osmio::Node>(node: &N) -> Option<LonLat> {
+    let (lat, lon) = node.lat_lon()?;
+    Some(LonLat::new(lon.into(), lat.into()))
+}
+
+fn read_tags<O: OSMObjBase>(obj: &O) -> Tags {
+    let mut tags = Tags::empty();
+    for (key, value) in obj.tags() {
+        if !is_useless_tag(key) {
+            tags.insert(key, value);
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::Distance;
+
+    use super::*;
+
+    #[test]
+    fn test_matches_xml_reader() {
+        // The .pbf has a few objects outside the boundary. The .osm is what osmconvert produces
+        // when clipping it.
+        let gps_bounds = GPSBounds::from(vec![
+            LonLat::new(-122.31, 47.59),
+            LonLat::new(-122.29, 47.61),
+        ]);
+        let boundary = gps_bounds.to_bounds().get_rectangle();
+        let mut timer = Timer::throwaway();
+        let pbf = read(
+            "../tests/input/pbf_clip.osm.pbf",
+            &gps_bounds,
+            Some(&boundary),
+            &mut timer,
+        )
+        .unwrap();
+        let xml = super::super::read(
+            "../tests/input/pbf_clip.osm",
+            &gps_bounds,
+            Some(&boundary),
+            &mut timer,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pbf.nodes.keys().collect::<Vec<_>>(),
+            xml.nodes.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            pbf.ways.keys().collect::<Vec<_>>(),
+            xml.ways.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            pbf.relations.keys().collect::<Vec<_>>(),
+            xml.relations.keys().collect::<Vec<_>>()
+        );
+
+        // The .pbf stores coordinates with less precision
+        let threshold = Distance::meters(1.0);
+        for (id, node) in &pbf.nodes {
+            assert!(
+                node.pt.approx_eq(xml.nodes[id].pt, threshold),
+                "{} moved",
+                id
+            );
+            assert_eq!(node.tags, xml.nodes[id].tags, "{} tags differ", id);
+        }
+        for (id, way) in &pbf.ways {
+            assert_eq!(way.nodes, xml.ways[id].nodes, "{} nodes differ", id);
+            assert_eq!(way.tags, xml.ways[id].tags, "{} tags differ", id);
+            for (pt1, pt2) in way.pts.iter().zip(xml.ways[id].pts.iter()) {
+                assert!(pt1.approx_eq(*pt2, threshold), "{} moved", id);
+            }
+        }
+        for (id, relation) in &pbf.relations {
+            assert_eq!(
+                relation.members, xml.relations[id].members,
+                "{} differs",
+                id
+            );
+            assert_eq!(relation.tags, xml.relations[id].tags, "{} differs", id);
+        }
+        // Useless tags are removed by both
+        assert!(!pbf.nodes[&NodeID(7)].tags.contains_key("tiger:county"));
+    }
+}
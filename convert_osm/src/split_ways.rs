@@ -64,6 +64,8 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
                 point: pt.to_pt2d(),
                 intersection_type: if input.traffic_signals.remove(pt).is_some() {
                     IntersectionType::TrafficSignal
+                } else if input.yields.contains_key(pt) {
+                    IntersectionType::Yield
                 } else {
                     IntersectionType::StopSign
                 },
@@ -74,13 +76,13 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
         );
     }
 
-    // Set roundabouts to their center
+    // Set roundabouts to their center. Everybody entering has to yield.
     for (id, point) in roundabout_centers {
         map.intersections.insert(
             id,
             RawIntersection {
                 point,
-                intersection_type: IntersectionType::StopSign,
+                intersection_type: IntersectionType::Yield,
                 // Filled out later
                 elevation: Distance::ZERO,
                 trim_roads_for_merging: BTreeMap::new(),
@@ -160,6 +162,49 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
             .push((rt, to));
     }
 
+    // Resolve conditional turn restrictions (via a node)
+    let mut restrictions = Vec::new();
+    for (restriction, from_osm, via_osm, to_osm, times) in input.conditional_turn_restrictions {
+        let roads = map.roads_per_intersection(via_osm);
+        if let (Some(from), Some(to)) = (
+            roads.iter().find(|r| r.osm_way_id == from_osm),
+            roads.iter().find(|r| r.osm_way_id == to_osm),
+        ) {
+            restrictions.push((*from, restriction, *to, times));
+        }
+    }
+    for (from, rt, to, times) in restrictions {
+        map.roads
+            .get_mut(&from)
+            .unwrap()
+            .conditional_turn_restrictions
+            .push((rt, to, times));
+    }
+
+    // Resolve lane connectivity (via a node)
+    let mut connectivity = Vec::new();
+    for (rel_osm, from_osm, via_osm, to_osm, lanes) in input.lane_connectivity {
+        let roads = map.roads_per_intersection(via_osm);
+        if let (Some(from), Some(to)) = (
+            roads.iter().find(|r| r.osm_way_id == from_osm),
+            roads.iter().find(|r| r.osm_way_id == to_osm),
+        ) {
+            connectivity.push((*from, *to, lanes));
+        } else {
+            warn!(
+                "Couldn't resolve connectivity from way {} to way {} via node {}. See {}",
+                from_osm, to_osm, via_osm, rel_osm
+            );
+        }
+    }
+    for (from, to, lanes) in connectivity {
+        map.roads
+            .get_mut(&from)
+            .unwrap()
+            .lane_connectivity
+            .push((to, lanes));
+    }
+
     // Resolve complicated turn restrictions (via a way). TODO Only handle via ways immediately
     // connected to both roads, for now
     let mut complicated_restrictions = Vec::new();
@@ -210,6 +255,53 @@ pub fn split_up_roads(map: &mut RawMap, mut input: OsmExtract, timer: &mut Timer
             .push((via, to));
     }
 
+    // Where a roundabout meets other roads, circulating traffic has priority and entering traffic
+    // yields, instead of stopping.
+    for (id, r) in &map.roads {
+        if !r.osm_tags.is("junction", "roundabout") {
+            continue;
+        }
+        for i in [id.i1, id.i2] {
+            let i = map.intersections.get_mut(&i).unwrap();
+            if i.intersection_type == IntersectionType::StopSign {
+                i.intersection_type = IntersectionType::Yield;
+            }
+        }
+    }
+
+    // Give-way signs are usually tagged on the approach to a junction, not the junction itself.
+    // Make that junction yield, and remember which road has to give way.
+    // (https://wiki.openstreetmap.org/wiki/Tag:highway=give_way#How_to_map)
+    for (pt, dir) in &input.yields {
+        if let Some(r) = pt_to_road.get(pt) {
+            let dir = dir.unwrap_or_else(|| {
+                // Without a direction, the sign applies to the closest junction
+                let pt = pt.to_pt2d();
+                if pt.dist_to(map.intersections[&r.i1].point)
+                    < pt.dist_to(map.intersections[&r.i2].point)
+                {
+                    Direction::Back
+                } else {
+                    Direction::Fwd
+                }
+            });
+            let (i, tag) = if dir == Direction::Fwd {
+                (r.i2, osm::GIVE_WAY_FWD)
+            } else {
+                (r.i1, osm::GIVE_WAY_BACK)
+            };
+            let i = map.intersections.get_mut(&i).unwrap();
+            if i.intersection_type == IntersectionType::StopSign {
+                i.intersection_type = IntersectionType::Yield;
+            }
+            map.roads
+                .get_mut(r)
+                .unwrap()
+                .osm_tags
+                .insert(tag.to_string(), "true".to_string());
+        }
+    }
+
     timer.start("match traffic signals to intersections");
     // Handle traffic signals tagged on incoming ways and not at intersections
     // (https://wiki.openstreetmap.org/wiki/Tag:highway=traffic%20signals?uselang=en#Tag_all_incoming_ways).
@@ -384,6 +384,9 @@ pub fn color_for_trip_phase(app: &App, tpt: TripPhaseType) -> Color {
         TripPhaseType::Parking => app.cs.parking_trip,
         TripPhaseType::WaitingForBus(_, _) => app.cs.bus_layer,
         TripPhaseType::RidingBus(_, _, _) => app.cs.bus_trip,
+        TripPhaseType::WaitingForRideHail => app.cs.bus_layer,
+        TripPhaseType::RidingRideHail(_) => app.cs.unzoomed_ride_hail,
+        TripPhaseType::Delivering(_) => app.cs.unzoomed_freight,
         TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
         TripPhaseType::DelayedStart => Color::YELLOW,
     }
@@ -69,6 +69,8 @@ fn lane(lane: &Lane) -> Option<serde_json::Map<String, serde_json::value::Value>
         match lane.lane_type {
             LaneType::Driving => "drive_lane".into(),
             LaneType::Parking => "parking".into(),
+            // TODO Is there a better type?
+            LaneType::LoadingZone => "parking".into(),
             LaneType::Sidewalk => "sidewalk".into(),
             // TODO Nope
             LaneType::Shoulder => "sidewalk".into(),
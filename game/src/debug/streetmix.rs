@@ -38,7 +38,7 @@ fn lane(lane: &Lane, dir: Direction) -> serde_json::Map<String, serde_json::valu
             Direction::Fwd => ("drive-lane", "inbound|car"),
             Direction::Back => ("drive-lane", "outbound|car"),
         },
-        LaneType::Parking => match dir {
+        LaneType::Parking | LaneType::LoadingZone => match dir {
             Direction::Fwd => ("parking-lane", "inbound|left"),
             Direction::Back => ("parking-lane", "outbound|right"),
         },
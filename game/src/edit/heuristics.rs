@@ -17,7 +17,11 @@ pub fn add_new_lane(road: &mut EditRoad, lt: LaneType, osm_tags: &Tags) -> usize
                 .map(|x| x + 1)
                 .unwrap_or(road.lanes_ltr.len());
         }
-        LaneType::Biking | LaneType::Bus | LaneType::Parking | LaneType::Construction => {
+        LaneType::Biking
+        | LaneType::Bus
+        | LaneType::Parking
+        | LaneType::LoadingZone
+        | LaneType::Construction => {
             let relevant_lanes: Vec<&LaneSpec> =
                 road.lanes_ltr.iter().filter(|x| x.lt == lt).collect();
             dir = if !relevant_lanes.is_empty() {
@@ -91,6 +95,7 @@ pub fn add_new_lane(road: &mut EditRoad, lt: LaneType, osm_tags: &Tags) -> usize
             lt,
             dir,
             width: LaneSpec::typical_lane_widths(lt, osm_tags)[0].0,
+            allowed_turns: None,
         },
     );
     idx
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use geom::{Bounds, CornerRadii, Distance, Polygon, Pt2D, UnitFmt};
 use map_gui::render::{Renderable, OUTLINE_THICKNESS};
@@ -6,11 +6,13 @@ use map_gui::tools::PopupMsg;
 use map_gui::ID;
 use map_model::{
     BufferType, Direction, EditCmd, EditRoad, LaneID, LaneSpec, LaneType, MapEdits, Road, RoadID,
+    TurnType,
 };
 use widgetry::{
     lctrl, Choice, Color, ControlState, DragDrop, Drawable, EdgeInsets, EventCtx, GeomBatch,
     GeomBatchStack, GfxCtx, HorizontalAlignment, Image, Key, Line, Outcome, Panel, PersistentSplit,
-    Spinner, StackAxis, State, Text, TextExt, VerticalAlignment, Widget, DEFAULT_CORNER_RADIUS,
+    Spinner, StackAxis, State, Text, TextExt, Toggle, VerticalAlignment, Widget,
+    DEFAULT_CORNER_RADIUS,
 };
 
 use crate::app::{App, Transition};
@@ -98,7 +100,7 @@ impl RoadEditor {
             let mut parking = 0;
             let mut driving = 0;
             for spec in &new.lanes_ltr {
-                if spec.lt == LaneType::Parking {
+                if spec.lt == LaneType::Parking || spec.lt == LaneType::LoadingZone {
                     parking += 1;
                 } else if spec.lt == LaneType::Driving {
                     driving += 1;
@@ -284,6 +286,8 @@ impl State<App> for RoadEditor {
                 } else if x == "flip direction" {
                     return self.modify_current_lane(ctx, app, Some(0), |new, idx| {
                         new.lanes_ltr[idx].dir = new.lanes_ltr[idx].dir.opposite();
+                        // The lane now ends at the other intersection
+                        new.lanes_ltr[idx].allowed_turns = None;
                     });
                 } else if let Some(lt) = x.strip_prefix("change to ") {
                     let lt = if lt == "buffer" {
@@ -307,7 +311,7 @@ impl State<App> for RoadEditor {
                     };
 
                     // Special check here
-                    if lt == LaneType::Parking
+                    if (lt == LaneType::Parking || lt == LaneType::LoadingZone)
                         && app
                             .primary
                             .map
@@ -381,6 +385,33 @@ impl State<App> for RoadEditor {
                         new.lanes_ltr[idx].width = width;
                     });
                 }
+                "allow left turns"
+                | "allow going straight"
+                | "allow right turns"
+                | "allow U-turns" => {
+                    let allowed: BTreeSet<TurnType> = turn_type_choices()
+                        .into_iter()
+                        .filter(|(_, label)| self.main_panel.is_checked(label))
+                        .map(|(tt, _)| tt)
+                        .collect();
+                    if allowed.is_empty() {
+                        self.recalc_all_panels(ctx, app);
+                        return Transition::Push(PopupMsg::new_state(
+                            ctx,
+                            "Error",
+                            vec!["Vehicles in this lane have to be able to go somewhere."],
+                        ));
+                    }
+                    // No restrictions at all is represented differently
+                    let allowed_turns = if allowed.len() == turn_type_choices().len() {
+                        None
+                    } else {
+                        Some(allowed)
+                    };
+                    return self.modify_current_lane(ctx, app, Some(0), |new, idx| {
+                        new.lanes_ltr[idx].allowed_turns = allowed_turns.clone();
+                    });
+                }
                 "lane cards" => {
                     // hovering index changed
                     panels_need_recalc = true;
@@ -573,6 +604,7 @@ fn make_main_panel(
         (LaneType::Bus, Some(Key::T)),
         (LaneType::Sidewalk, Some(Key::S)),
         (LaneType::Parking, Some(Key::P)),
+        (LaneType::LoadingZone, Some(Key::L)),
         (LaneType::Construction, Some(Key::C)),
     ];
     // All the buffer lanes are grouped into a PersistentSplit
@@ -758,10 +790,12 @@ fn make_main_panel(
                                 // If the selected lane is already this type, we can't change it. Hopefully no need to
                                 // explain this.
                                 btn = btn.disabled(true);
-                            } else if lt == LaneType::Parking
+                            } else if (lt == LaneType::Parking || lt == LaneType::LoadingZone)
                                 && current_lts
                                     .iter()
-                                    .filter(|x| **x == LaneType::Parking)
+                                    .filter(|x| {
+                                        **x == LaneType::Parking || **x == LaneType::LoadingZone
+                                    })
                                     .count()
                                     == 2
                             {
@@ -836,6 +870,27 @@ fn make_main_panel(
                 ])
                 .section(ctx),
             ]),
+            if lane.lane_type == LaneType::Driving || lane.lane_type == LaneType::Bus {
+                Widget::row(
+                    vec![Line("Turns").secondary().into_widget(ctx).centered_vert()]
+                        .into_iter()
+                        .chain(turn_type_choices().into_iter().map(|(tt, label)| {
+                            Toggle::checkbox(
+                                ctx,
+                                label,
+                                None,
+                                lane.allowed_turns
+                                    .as_ref()
+                                    .map(|set| set.contains(&tt))
+                                    .unwrap_or(true),
+                            )
+                        }))
+                        .collect(),
+                )
+                .section(ctx)
+            } else {
+                Widget::nothing()
+            },
         ])
     } else {
         Widget::nothing()
@@ -962,6 +1017,7 @@ fn lane_type_to_icon(lt: LaneType) -> Option<&'static str> {
     match lt {
         LaneType::Driving => Some("system/assets/edit/driving.svg"),
         LaneType::Parking => Some("system/assets/edit/parking.svg"),
+        LaneType::LoadingZone => Some("system/assets/edit/loading_zone.svg"),
         LaneType::Sidewalk | LaneType::Shoulder => Some("system/assets/edit/sidewalk.svg"),
         LaneType::Biking => Some("system/assets/edit/bike.svg"),
         LaneType::Bus => Some("system/assets/edit/bus.svg"),
@@ -979,6 +1035,16 @@ fn lane_type_to_icon(lt: LaneType) -> Option<&'static str> {
     }
 }
 
+/// The turns that can be allowed or banned per lane, and the checkbox labels for them
+fn turn_type_choices() -> Vec<(TurnType, &'static str)> {
+    vec![
+        (TurnType::Left, "allow left turns"),
+        (TurnType::Straight, "allow going straight"),
+        (TurnType::Right, "allow right turns"),
+        (TurnType::UTurn, "allow U-turns"),
+    ]
+}
+
 fn width_choices(app: &App, l: LaneID) -> Vec<Choice<Distance>> {
     let lane = app.primary.map.get_l(l);
     let mut choices = LaneSpec::typical_lane_widths(
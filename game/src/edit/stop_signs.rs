@@ -20,6 +20,7 @@ use crate::sandbox::GameplayMode;
 
 // TODO For now, individual turns can't be manipulated. Banning turns could be useful, but I'm not
 // sure what to do about the player orphaning a section of the map.
+/// Also edits yield intersections, which use the same per-road signs.
 pub struct StopSignEditor {
     id: IntersectionID,
     mode: GameplayMode,
@@ -36,6 +37,7 @@ impl StopSignEditor {
         mode: GameplayMode,
     ) -> Box<dyn State<App>> {
         app.primary.current_selection = None;
+        let is_yield = app.primary.map.get_i(id).is_yield();
         let geom = app
             .primary
             .map
@@ -49,7 +51,13 @@ impl StopSignEditor {
             .collect();
 
         let panel = Panel::new_builder(Widget::col(vec![
-            Line("Stop sign editor").small_heading().into_widget(ctx),
+            Line(if is_yield {
+                "Yield editor"
+            } else {
+                "Stop sign editor"
+            })
+            .small_heading()
+            .into_widget(ctx),
             ctx.style()
                 .btn_outline
                 .text("reset to default")
@@ -64,6 +72,14 @@ impl StopSignEditor {
                 .text("close intersection for construction")
                 .hotkey(Key::C)
                 .build_def(ctx),
+            ctx.style()
+                .btn_outline
+                .text(if is_yield {
+                    "convert to stop sign"
+                } else {
+                    "convert to yield / roundabout"
+                })
+                .build_def(ctx),
             ctx.style()
                 .btn_outline
                 .text("convert to traffic signal")
@@ -104,10 +120,27 @@ impl SimpleState<App> for StopSignEditor {
                 edits.commands.push(EditCmd::ChangeIntersection {
                     i: self.id,
                     old: app.primary.map.get_i_edit(self.id),
-                    new: EditIntersection::StopSign(ControlStopSign::new(
-                        &app.primary.map,
-                        self.id,
-                    )),
+                    new: edit_for(
+                        ControlStopSign::new(&app.primary.map, self.id),
+                        app.primary.map.get_i(self.id).is_yield(),
+                    ),
+                });
+                apply_map_edits(ctx, app, edits);
+                Transition::Replace(StopSignEditor::new_state(
+                    ctx,
+                    app,
+                    self.id,
+                    self.mode.clone(),
+                ))
+            }
+            "convert to yield / roundabout" | "convert to stop sign" => {
+                let mut edits = app.primary.map.get_edits().clone();
+                // Entering traffic yields on the same roads that used to stop
+                let sign = app.primary.map.get_stop_sign(self.id).clone();
+                edits.commands.push(EditCmd::ChangeIntersection {
+                    i: self.id,
+                    old: app.primary.map.get_i_edit(self.id),
+                    new: edit_for(sign, x == "convert to yield / roundabout"),
                 });
                 apply_map_edits(ctx, app, edits);
                 Transition::Replace(StopSignEditor::new_state(
@@ -175,10 +208,12 @@ impl SimpleState<App> for StopSignEditor {
 
         if let Some(r) = self.selected_sign {
             let mut sign = app.primary.map.get_stop_sign(self.id).clone();
-            let label = if sign.roads[&r].must_stop {
-                "remove stop sign"
-            } else {
-                "add stop sign"
+            let is_yield = app.primary.map.get_i(self.id).is_yield();
+            let label = match (sign.roads[&r].must_stop, is_yield) {
+                (true, false) => "remove stop sign",
+                (false, false) => "add stop sign",
+                (true, true) => "remove yield sign",
+                (false, true) => "add yield sign",
             };
             if app.per_obj.left_click(ctx, label) {
                 sign.flip_sign(r);
@@ -187,7 +222,7 @@ impl SimpleState<App> for StopSignEditor {
                 edits.commands.push(EditCmd::ChangeIntersection {
                     i: self.id,
                     old: app.primary.map.get_i_edit(self.id),
-                    new: EditIntersection::StopSign(sign),
+                    new: edit_for(sign, is_yield),
                 });
                 apply_map_edits(ctx, app, edits);
                 return Transition::Replace(StopSignEditor::new_state(
@@ -226,7 +261,11 @@ impl SimpleState<App> for StopSignEditor {
         if let Some(r) = self.selected_sign {
             let mut osd = Text::new();
             osd.add_appended(vec![
-                Line("Stop sign for "),
+                Line(if map.get_i(self.id).is_yield() {
+                    "Yield sign for "
+                } else {
+                    "Stop sign for "
+                }),
                 Line(
                     app.primary
                         .map
@@ -241,3 +280,11 @@ impl SimpleState<App> for StopSignEditor {
         }
     }
 }
+
+fn edit_for(sign: ControlStopSign, is_yield: bool) -> EditIntersection {
+    if is_yield {
+        EditIntersection::Yield(sign)
+    } else {
+        EditIntersection::StopSign(sign)
+    }
+}
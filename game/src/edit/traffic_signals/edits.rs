@@ -1,11 +1,12 @@
-use geom::Duration;
+use geom::{Distance, Duration, Time};
 use map_gui::tools::{ChooseSomething, FilePicker, PopupMsg};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, StageType,
+    Actuation, ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID,
+    StageType,
 };
 use widgetry::{
     Choice, DrawBaselayer, EventCtx, Key, Line, Panel, SimpleState, Spinner, State, Text, TextExt,
-    Widget,
+    TextSpan, Toggle, Widget,
 };
 
 use crate::app::{App, Transition};
@@ -14,7 +15,10 @@ use crate::edit::{apply_map_edits, check_sidewalk_connectivity, StopSignEditor};
 use crate::sandbox::GameplayMode;
 
 pub struct ChangeDuration {
+    plan: usize,
     idx: usize,
+    // Not exposed in the panel; just preserve it
+    detector_length: Distance,
 }
 
 impl ChangeDuration {
@@ -22,9 +26,19 @@ impl ChangeDuration {
         ctx: &mut EventCtx,
         app: &App,
         signal: &ControlTrafficSignal,
+        plan: usize,
         idx: usize,
     ) -> Box<dyn State<App>> {
         let i = app.primary.map.get_i(signal.id);
+        let stage = &signal.plan_stages(plan)[idx];
+        let stage_type = &stage.stage_type;
+        let actuation = match stage_type {
+            StageType::Actuated(ref a) => a.clone(),
+            _ => Actuation::new(
+                stage_type.simple_duration(),
+                stage_type.simple_duration() * 2.0,
+            ),
+        };
         let panel = Panel::new_builder(Widget::col(vec![
             Widget::row(vec![
                 Line("How long should this stage last?")
@@ -37,8 +51,8 @@ impl ChangeDuration {
                 Spinner::widget(
                     ctx,
                     "duration",
-                    (signal.get_min_crossing_time(idx, i), Duration::minutes(5)),
-                    signal.stages[idx].stage_type.simple_duration(),
+                    (stage.min_crossing_time(i), Duration::minutes(5)),
+                    stage_type.simple_duration(),
                     Duration::seconds(1.0),
                 ),
             ]),
@@ -46,15 +60,10 @@ impl ChangeDuration {
                 .secondary()
                 .into_widget(ctx),
             Widget::col(vec![
-                Text::from_all(match signal.stages[idx].stage_type {
-                    StageType::Fixed(_) => vec![
-                        Line("Fixed timing").small_heading(),
-                        Line(" (Adjust both values below to enable variable timing)"),
-                    ],
-                    StageType::Variable(_, _, _) => vec![
-                        Line("Variable timing").small_heading(),
-                        Line(" (Set either values below to 0 to use fixed timing."),
-                    ],
+                Text::from_all(match stage_type {
+                    StageType::Fixed(_) => timing_label(false, false),
+                    StageType::Variable(_, _, _) => timing_label(false, true),
+                    StageType::Actuated(_) => timing_label(true, false),
                 })
                 .into_widget(ctx)
                 .named("timing type"),
@@ -66,9 +75,9 @@ impl ChangeDuration {
                         ctx,
                         "additional",
                         (Duration::ZERO, Duration::minutes(5)),
-                        match signal.stages[idx].stage_type {
-                            StageType::Fixed(_) => Duration::ZERO,
-                            StageType::Variable(_, _, additional) => additional,
+                        match stage_type {
+                            StageType::Variable(_, _, additional) => *additional,
+                            StageType::Fixed(_) | StageType::Actuated(_) => Duration::ZERO,
                         },
                         Duration::seconds(1.0),
                     ),
@@ -81,9 +90,9 @@ impl ChangeDuration {
                         ctx,
                         "delay",
                         (Duration::ZERO, Duration::seconds(300.0)),
-                        match signal.stages[idx].stage_type {
-                            StageType::Fixed(_) => Duration::ZERO,
-                            StageType::Variable(_, delay, _) => delay,
+                        match stage_type {
+                            StageType::Variable(_, delay, _) => *delay,
+                            StageType::Fixed(_) | StageType::Actuated(_) => Duration::ZERO,
                         },
                         Duration::seconds(1.0),
                     ),
@@ -92,6 +101,48 @@ impl ChangeDuration {
             .padding(10)
             .bg(app.cs.inner_panel_bg)
             .outline(ctx.style().section_outline),
+            Widget::col(vec![
+                Toggle::switch(
+                    ctx,
+                    "actuated by detectors",
+                    None,
+                    matches!(stage_type, StageType::Actuated(_)),
+                ),
+                Line("The duration above becomes the minimum green time")
+                    .secondary()
+                    .into_widget(ctx),
+                Widget::row(vec![
+                    "Maximum green time:".text_widget(ctx).centered_vert(),
+                    Spinner::widget(
+                        ctx,
+                        "max green",
+                        (Duration::ZERO, Duration::minutes(5)),
+                        actuation.max_green,
+                        Duration::seconds(1.0),
+                    ),
+                ]),
+                Widget::row(vec![
+                    "End the stage after no vehicles are detected for:"
+                        .text_widget(ctx)
+                        .centered_vert(),
+                    Spinner::widget(
+                        ctx,
+                        "passage",
+                        (Duration::seconds(1.0), Duration::seconds(30.0)),
+                        actuation.passage,
+                        Duration::seconds(1.0),
+                    ),
+                ]),
+                Toggle::checkbox(
+                    ctx,
+                    "serve every cycle for pedestrians",
+                    None,
+                    actuation.pedestrian_recall,
+                ),
+            ])
+            .padding(10)
+            .bg(app.cs.inner_panel_bg)
+            .outline(ctx.style().section_outline),
             ctx.style()
                 .btn_solid_primary
                 .text("Apply")
@@ -99,7 +150,14 @@ impl ChangeDuration {
                 .build_def(ctx),
         ]))
         .build(ctx);
-        <dyn SimpleState<_>>::new_state(panel, Box::new(ChangeDuration { idx }))
+        <dyn SimpleState<_>>::new_state(
+            panel,
+            Box::new(ChangeDuration {
+                plan,
+                idx,
+                detector_length: actuation.detector_length,
+            }),
+        )
     }
 }
 
@@ -117,18 +175,27 @@ impl SimpleState<App> for ChangeDuration {
                 let dt = panel.spinner("duration");
                 let delay = panel.spinner("delay");
                 let additional = panel.spinner("additional");
-                let new_type = if delay == Duration::ZERO || additional == Duration::ZERO {
+                let new_type = if panel.is_checked("actuated by detectors") {
+                    StageType::Actuated(Actuation {
+                        min_green: dt,
+                        max_green: panel.spinner::<Duration>("max green").max(dt),
+                        passage: panel.spinner("passage"),
+                        pedestrian_recall: panel.is_checked("serve every cycle for pedestrians"),
+                        detector_length: self.detector_length,
+                    })
+                } else if delay == Duration::ZERO || additional == Duration::ZERO {
                     StageType::Fixed(dt)
                 } else {
                     StageType::Variable(dt, delay, additional)
                 };
+                let plan = self.plan;
                 let idx = self.idx;
                 Transition::Multi(vec![
                     Transition::Pop,
                     Transition::ModifyState(Box::new(move |state, ctx, app| {
                         let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
                         editor.add_new_edit(ctx, app, idx, |ts| {
-                            ts.stages[idx].stage_type = new_type.clone();
+                            ts.plan_stages_mut(plan)[idx].stage_type = new_type.clone();
                         });
                     })),
                 ])
@@ -143,21 +210,11 @@ impl SimpleState<App> for ChangeDuration {
         _: &mut App,
         panel: &mut Panel,
     ) -> Option<Transition> {
-        let new_label = Text::from_all(
-            if panel.spinner::<Duration>("delay") == Duration::ZERO
-                || panel.spinner::<Duration>("additional") == Duration::ZERO
-            {
-                vec![
-                    Line("Fixed timing").small_heading(),
-                    Line(" (Adjust both values below to enable variable timing)"),
-                ]
-            } else {
-                vec![
-                    Line("Variable timing").small_heading(),
-                    Line(" (Set either values below to 0 to use fixed timing."),
-                ]
-            },
-        )
+        let new_label = Text::from_all(timing_label(
+            panel.is_checked("actuated by detectors"),
+            panel.spinner::<Duration>("delay") != Duration::ZERO
+                && panel.spinner::<Duration>("additional") != Duration::ZERO,
+        ))
         .into_widget(ctx);
         panel.replace(ctx, "timing type", new_label);
         None
@@ -175,10 +232,74 @@ impl SimpleState<App> for ChangeDuration {
     }
 }
 
+/// Pick when a new time-of-day plan starts. It begins as a copy of whatever plan was running then.
+pub fn add_plan(ctx: &mut EventCtx, signal: &ControlTrafficSignal) -> Box<dyn State<App>> {
+    let mut choices = Vec::new();
+    for hour in 1..24 {
+        let start_time = Duration::hours(hour);
+        if signal
+            .time_of_day_plans
+            .iter()
+            .any(|p| p.start_time == start_time)
+        {
+            continue;
+        }
+        choices.push(Choice::new(
+            format!(
+                "starting at {}",
+                (Time::START_OF_DAY + start_time).ampm_tostring()
+            ),
+            start_time,
+        ));
+    }
+    ChooseSomething::new_state(
+        ctx,
+        "When should the new timing plan start?",
+        choices,
+        Box::new(move |start_time, _, _| {
+            Transition::Multi(vec![
+                Transition::Pop,
+                Transition::ModifyState(Box::new(move |state, ctx, app| {
+                    let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                    editor.add_new_edit(ctx, app, 0, |ts| {
+                        ts.add_plan(start_time).unwrap();
+                    });
+                    let plan = app
+                        .primary
+                        .map
+                        .get_traffic_signal(*editor.members.iter().next().unwrap())
+                        .plan_at(Time::START_OF_DAY + start_time);
+                    editor.change_plan(ctx, app, plan);
+                })),
+            ])
+        }),
+    )
+}
+
+fn timing_label(actuated: bool, variable: bool) -> Vec<TextSpan> {
+    if actuated {
+        vec![
+            Line("Actuated timing").small_heading(),
+            Line(" (Turn off detectors below to use fixed or variable timing)"),
+        ]
+    } else if variable {
+        vec![
+            Line("Variable timing").small_heading(),
+            Line(" (Set either values below to 0 to use fixed timing."),
+        ]
+    } else {
+        vec![
+            Line("Fixed timing").small_heading(),
+            Line(" (Adjust both values below to enable variable timing)"),
+        ]
+    }
+}
+
 pub fn edit_entire_signal(
     ctx: &mut EventCtx,
     app: &App,
     i: IntersectionID,
+    plan: usize,
     mode: GameplayMode,
     original: BundleEdits,
 ) -> Box<dyn State<App>> {
@@ -193,6 +314,7 @@ pub fn edit_entire_signal(
     let use_template = "use template";
     let all_walk = "add an all-walk stage at the end";
     let major_minor_timing = "use timing pattern for a major/minor intersection";
+    let coordinate = "coordinate with a corridor cycle length";
     let stop_sign = "convert to stop signs";
     let close = "close intersection for construction";
     let reset = "reset to default";
@@ -209,6 +331,7 @@ pub fn edit_entire_signal(
         choices.push(all_walk.to_string());
     }
     choices.push(major_minor_timing.to_string());
+    choices.push(coordinate.to_string());
     // TODO Conflating stop signs and construction here
     if mode.can_edit_stop_signs() {
         choices.push(stop_sign.to_string());
@@ -291,6 +414,27 @@ pub fn edit_entire_signal(
                     }
                 }),
             )),
+            x if x == coordinate => Transition::Replace(ChooseSomething::new_state(
+                ctx,
+                "Share which cycle length with the corridor?",
+                vec![
+                    Choice::new("not coordinated", None),
+                    Choice::new("60s cycle", Some(Duration::seconds(60.0))),
+                    Choice::new("90s cycle", Some(Duration::seconds(90.0))),
+                    Choice::new("120s cycle", Some(Duration::seconds(120.0))),
+                ],
+                Box::new(move |cycle_length, _, _| {
+                    Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::ModifyState(Box::new(move |state, ctx, app| {
+                            let editor = state.downcast_mut::<TrafficSignalEditor>().unwrap();
+                            editor.add_new_edit(ctx, app, 0, |ts| {
+                                *ts.plan_cycle_length_mut(plan) = cycle_length;
+                            });
+                        })),
+                    ])
+                }),
+            )),
             x if x == stop_sign => {
                 original.apply(app);
 
@@ -3,7 +3,7 @@ use std::collections::{BTreeSet, VecDeque};
 use anyhow::Result;
 
 use abstutil::Timer;
-use geom::{Distance, Line, Polygon, Pt2D};
+use geom::{Distance, Duration, Line, Polygon, Pt2D, Time};
 use map_gui::options::TrafficSignalStyle;
 use map_gui::render::{traffic_signal, DrawMovement, DrawOptions};
 use map_gui::tools::PopupMsg;
@@ -36,6 +36,8 @@ pub struct TrafficSignalEditor {
 
     mode: GameplayMode,
     members: BTreeSet<IntersectionID>,
+    // Which time-of-day plan is being edited
+    plan: usize,
     current_stage: usize,
 
     movements: Vec<DrawMovement>,
@@ -74,9 +76,10 @@ impl TrafficSignalEditor {
         synced.apply(app);
 
         let mut editor = TrafficSignalEditor {
-            side_panel: make_side_panel(ctx, app, &members, 0),
+            side_panel: make_side_panel(ctx, app, &members, 0, 0),
             top_panel: make_top_panel(ctx, app, false, false),
             mode,
+            plan: 0,
             current_stage: 0,
             movements: Vec::new(),
             movement_selected: None,
@@ -95,17 +98,25 @@ impl TrafficSignalEditor {
 
     fn change_stage(&mut self, ctx: &mut EventCtx, app: &App, idx: usize) {
         if self.current_stage == idx {
-            let mut new = make_side_panel(ctx, app, &self.members, self.current_stage);
+            let mut new = make_side_panel(ctx, app, &self.members, self.plan, self.current_stage);
             new.restore(ctx, &self.side_panel);
             self.side_panel = new;
         } else {
             self.current_stage = idx;
-            self.side_panel = make_side_panel(ctx, app, &self.members, self.current_stage);
+            self.side_panel =
+                make_side_panel(ctx, app, &self.members, self.plan, self.current_stage);
         }
 
         self.recalc_draw_current(ctx, app);
     }
 
+    fn change_plan(&mut self, ctx: &mut EventCtx, app: &App, plan: usize) {
+        self.plan = plan;
+        self.current_stage = 0;
+        self.side_panel = make_side_panel(ctx, app, &self.members, self.plan, self.current_stage);
+        self.recalc_draw_current(ctx, app);
+    }
+
     fn add_new_edit<F: Fn(&mut ControlTrafficSignal)>(
         &mut self,
         ctx: &mut EventCtx,
@@ -122,6 +133,14 @@ impl TrafficSignalEditor {
         bundle.apply(app);
 
         self.top_panel = make_top_panel(ctx, app, true, false);
+        // Replacing the entire signal might remove the plan being edited
+        let canonical_signal = app
+            .primary
+            .map
+            .get_traffic_signal(*self.members.iter().next().unwrap());
+        if self.plan >= canonical_signal.num_plans() {
+            self.plan = 0;
+        }
         self.change_stage(ctx, app, idx);
     }
 
@@ -129,12 +148,17 @@ impl TrafficSignalEditor {
         let mut batch = GeomBatch::new();
         let mut movements = Vec::new();
         for i in &self.members {
-            let stage = &app.primary.map.get_traffic_signal(*i).stages[self.current_stage];
+            let stage = &app
+                .primary
+                .map
+                .get_traffic_signal(*i)
+                .plan_stages(self.plan)[self.current_stage];
             for (m, draw) in DrawMovement::for_i(
                 ctx.prerender,
                 &app.primary.map,
                 &app.cs,
                 *i,
+                self.plan,
                 self.current_stage,
             ) {
                 if self
@@ -208,7 +232,7 @@ impl State<App> for TrafficSignalEditor {
             .primary
             .map
             .get_traffic_signal(*self.members.iter().next().unwrap());
-        let num_stages = canonical_signal.stages.len();
+        let num_stages = canonical_signal.plan_stages(self.plan).len();
 
         match self.side_panel.event(ctx) {
             Outcome::Clicked(x) => match x.as_ref() {
@@ -217,6 +241,7 @@ impl State<App> for TrafficSignalEditor {
                         ctx,
                         app,
                         canonical_signal.id,
+                        self.plan,
                         self.mode.clone(),
                         self.original.clone(),
                     ));
@@ -229,8 +254,9 @@ impl State<App> for TrafficSignalEditor {
                     ));
                 }
                 "Add a new stage" => {
+                    let plan = self.plan;
                     self.add_new_edit(ctx, app, num_stages, |ts| {
-                        ts.stages.push(Stage::new());
+                        ts.plan_stages_mut(plan).push(Stage::new());
                     });
                     return Transition::Keep;
                 }
@@ -239,14 +265,35 @@ impl State<App> for TrafficSignalEditor {
                         ctx,
                         app,
                         canonical_signal,
+                        self.plan,
                         self.current_stage,
                     ));
                 }
                 "delete stage" => {
+                    let plan = self.plan;
                     let idx = self.current_stage;
                     self.add_new_edit(ctx, app, 0, |ts| {
-                        ts.stages.remove(idx);
+                        ts.plan_stages_mut(plan).remove(idx);
+                    });
+                    return Transition::Keep;
+                }
+                "previous plan" => {
+                    self.change_plan(ctx, app, self.plan - 1);
+                    return Transition::Keep;
+                }
+                "next plan" => {
+                    self.change_plan(ctx, app, self.plan + 1);
+                    return Transition::Keep;
+                }
+                "add time-of-day plan" => {
+                    return Transition::Push(edits::add_plan(ctx, canonical_signal));
+                }
+                "remove plan" => {
+                    let plan = self.plan;
+                    self.add_new_edit(ctx, app, 0, |ts| {
+                        ts.remove_plan(plan);
                     });
+                    self.change_plan(ctx, app, plan - 1);
                     return Transition::Keep;
                 }
                 "previous stage" => {
@@ -268,8 +315,9 @@ impl State<App> for TrafficSignalEditor {
                 }
             },
             Outcome::DragDropReleased(_, old_idx, new_idx) => {
+                let plan = self.plan;
                 self.add_new_edit(ctx, app, new_idx, |ts| {
-                    ts.stages.swap(old_idx, new_idx);
+                    ts.plan_stages_mut(plan).swap(old_idx, new_idx);
                 });
             }
             _ => {}
@@ -365,6 +413,7 @@ impl State<App> for TrafficSignalEditor {
                         ctx,
                         app,
                         self.members.clone(),
+                        self.plan,
                         self.current_stage,
                     ));
                 }
@@ -373,7 +422,7 @@ impl State<App> for TrafficSignalEditor {
                         .push(BundleEdits::get_current(app, &self.members));
                     self.command_stack.pop().unwrap().apply(app);
                     self.top_panel = make_top_panel(ctx, app, !self.command_stack.is_empty(), true);
-                    self.change_stage(ctx, app, 0);
+                    self.change_plan(ctx, app, 0);
                     return Transition::Keep;
                 }
                 "redo" => {
@@ -381,7 +430,7 @@ impl State<App> for TrafficSignalEditor {
                         .push(BundleEdits::get_current(app, &self.members));
                     self.redo_stack.pop().unwrap().apply(app);
                     self.top_panel = make_top_panel(ctx, app, true, !self.redo_stack.is_empty());
-                    self.change_stage(ctx, app, 0);
+                    self.change_plan(ctx, app, 0);
                     return Transition::Keep;
                 }
                 _ => unreachable!(),
@@ -407,7 +456,7 @@ impl State<App> for TrafficSignalEditor {
                     let signal = app.primary.map.get_traffic_signal(m.id.parent);
                     let i = app.primary.map.get_i(signal.id);
                     if m.hitbox.contains_pt(pt) {
-                        let stage = &signal.stages[self.current_stage];
+                        let stage = &signal.plan_stages(self.plan)[self.current_stage];
                         let next_priority = match stage.get_priority_of_movement(m.id) {
                             TurnPriority::Banned => {
                                 if stage.could_be_protected(m.id, i) {
@@ -443,7 +492,8 @@ impl State<App> for TrafficSignalEditor {
             let mut txt = Text::new();
             txt.add_line(Line(format!(
                 "{} {}",
-                match signal.stages[self.current_stage].get_priority_of_movement(id) {
+                match signal.plan_stages(self.plan)[self.current_stage].get_priority_of_movement(id)
+                {
                     TurnPriority::Protected => "Protected",
                     TurnPriority::Yield => "Yielding",
                     TurnPriority::Banned => "Forbidden",
@@ -466,15 +516,16 @@ impl State<App> for TrafficSignalEditor {
                 ctx,
                 format!(
                     "toggle from {:?} to {:?}",
-                    signal.stages[self.current_stage].get_priority_of_movement(id),
+                    signal.plan_stages(self.plan)[self.current_stage].get_priority_of_movement(id),
                     pri
                 ),
             ) {
+                let plan = self.plan;
                 let idx = self.current_stage;
                 let movement = app.primary.map.get_i(id.parent).movements[&id].clone();
                 self.add_new_edit(ctx, app, idx, |ts| {
                     if ts.id == id.parent {
-                        ts.stages[idx].edit_movement(&movement, pri);
+                        ts.plan_stages_mut(plan)[idx].edit_movement(&movement, pri);
                     }
                 });
                 return Transition::KeepWithMouseover;
@@ -604,11 +655,13 @@ fn make_side_panel(
     ctx: &mut EventCtx,
     app: &App,
     members: &BTreeSet<IntersectionID>,
+    plan: usize,
     selected: usize,
 ) -> Panel {
     let map = &app.primary.map;
     // Use any member for stage duration
     let canonical_signal = map.get_traffic_signal(*members.iter().next().unwrap());
+    let stages = canonical_signal.plan_stages(plan);
 
     let mut txt = Text::new();
     if members.len() == 1 {
@@ -642,6 +695,43 @@ fn make_side_panel(
     }
     let mut col = vec![txt.into_widget(ctx)];
 
+    // Time-of-day plan controls
+    col.push(
+        Widget::row(vec![
+            ctx.style()
+                .btn_plain
+                .icon_bytes(include_labeled_bytes!(
+                    "../../../../widgetry/icons/arrow_left.svg"
+                ))
+                .disabled(plan == 0)
+                .build_widget(ctx, "previous plan"),
+            ctx.style()
+                .btn_plain
+                .icon_bytes(include_labeled_bytes!(
+                    "../../../../widgetry/icons/arrow_right.svg"
+                ))
+                .disabled(plan == canonical_signal.num_plans() - 1)
+                .build_widget(ctx, "next plan"),
+            describe_plan(canonical_signal, plan)
+                .text_widget(ctx)
+                .centered_vert(),
+            if plan != 0 {
+                ctx.style()
+                    .btn_solid_destructive
+                    .icon("system/assets/tools/trash.svg")
+                    .build_widget(ctx, "remove plan")
+            } else {
+                Widget::nothing()
+            },
+            ctx.style()
+                .btn_outline
+                .text("Add time-of-day plan")
+                .build_widget(ctx, "add time-of-day plan"),
+        ])
+        .padding(10)
+        .bg(app.cs.inner_panel_bg),
+    );
+
     // Stage controls
     col.push(
         Widget::row(vec![
@@ -657,14 +747,18 @@ fn make_side_panel(
                 .icon_bytes(include_labeled_bytes!(
                     "../../../../widgetry/icons/arrow_right.svg"
                 ))
-                .disabled(selected == canonical_signal.stages.len() - 1)
+                .disabled(selected == stages.len() - 1)
                 .build_widget(ctx, "next stage"),
-            match canonical_signal.stages[selected].stage_type {
+            match stages[selected].stage_type {
                 StageType::Fixed(d) => format!("Stage duration: {}", d),
                 StageType::Variable(min, delay, additional) => format!(
                     "Stage duration: {}, {}, {} (variable)",
                     min, delay, additional
                 ),
+                StageType::Actuated(ref a) => format!(
+                    "Stage duration: {} to {} (actuated)",
+                    a.min_green, a.max_green
+                ),
             }
             .text_widget(ctx)
             .centered_vert(),
@@ -673,7 +767,7 @@ fn make_side_panel(
                 .icon("system/assets/tools/pencil.svg")
                 .hotkey(Key::X)
                 .build_widget(ctx, "change duration"),
-            if canonical_signal.stages.len() > 1 {
+            if stages.len() > 1 {
                 ctx.style()
                     .btn_solid_destructive
                     .icon("system/assets/tools/trash.svg")
@@ -698,18 +792,19 @@ fn make_side_panel(
     );
 
     let mut drag_drop = DragDrop::new(ctx, "stage cards", StackAxis::Horizontal);
-    for idx in 0..canonical_signal.stages.len() {
+    for idx in 0..stages.len() {
         let mut stack = GeomBatchStack::vertical(vec![
             Text::from(Line(format!(
                 "Stage {}: {}",
                 idx + 1,
-                match canonical_signal.stages[idx].stage_type {
+                match stages[idx].stage_type {
                     StageType::Fixed(d) => format!("{}", d),
                     StageType::Variable(min, _, _) => format!("{} (v)", min),
+                    StageType::Actuated(ref a) => format!("{} (a)", a.min_green),
                 },
             )))
             .render(ctx),
-            draw_multiple_signals(ctx, app, members, idx, &translations),
+            draw_multiple_signals(ctx, app, members, plan, idx, &translations),
         ]);
         stack.set_spacing(10.0);
         let icon_batch = stack.batch();
@@ -745,7 +840,10 @@ fn make_side_panel(
         // TODO Say "normally" to account for variable stages?
         format!(
             "One full cycle lasts {}",
-            canonical_signal.simple_cycle_duration()
+            stages
+                .iter()
+                .map(|s| s.stage_type.simple_duration())
+                .sum::<Duration>()
         )
         .text_widget(ctx)
         .centered_vert(),
@@ -804,8 +902,9 @@ impl BundleEdits {
         BundleEdits { signals }
     }
 
-    // If the intersections haven't been edited together before, the number of stages and the
-    // durations might not match up. Just initially force them to align somehow.
+    // If the intersections haven't been edited together before, the time-of-day plans, the
+    // number of stages, and the durations might not match up. Just initially force them to align
+    // somehow.
     fn synchronize(app: &App, members: &BTreeSet<IntersectionID>) -> BundleEdits {
         let map = &app.primary.map;
         // Pick one of the members with the most stages as canonical.
@@ -815,15 +914,36 @@ impl BundleEdits {
                 .max_by_key(|i| map.get_traffic_signal(**i).stages.len())
                 .unwrap(),
         );
+        let start_times: Vec<Duration> = canonical
+            .time_of_day_plans
+            .iter()
+            .map(|p| p.start_time)
+            .collect();
 
         let mut signals = Vec::new();
         for i in members {
             let mut signal = map.get_traffic_signal(*i).clone();
-            for (idx, canonical_stage) in canonical.stages.iter().enumerate() {
-                if signal.stages.len() == idx {
-                    signal.stages.push(Stage::new());
+            signal
+                .time_of_day_plans
+                .retain(|p| start_times.contains(&p.start_time));
+            for start_time in &start_times {
+                if signal
+                    .time_of_day_plans
+                    .iter()
+                    .all(|p| p.start_time != *start_time)
+                {
+                    signal.add_plan(*start_time).unwrap();
+                }
+            }
+
+            for plan in 0..canonical.num_plans() {
+                let stages = signal.plan_stages_mut(plan);
+                for (idx, canonical_stage) in canonical.plan_stages(plan).iter().enumerate() {
+                    if stages.len() == idx {
+                        stages.push(Stage::new());
+                    }
+                    stages[idx].stage_type = canonical_stage.stage_type.clone();
                 }
-                signal.stages[idx].stage_type = canonical_stage.stage_type.clone();
             }
             signals.push(signal);
         }
@@ -848,21 +968,23 @@ fn check_for_missing_turns(app: &App, members: &BTreeSet<IntersectionID>) -> Opt
     }
 
     let mut bundle = BundleEdits::get_current(app, members);
-    // Stick all the missing turns in a new stage at the beginning.
+    // Stick all the missing turns in a new stage at the beginning of every plan.
     for signal in &mut bundle.signals {
-        let mut stage = Stage::new();
-        // TODO Could do this more efficiently
-        for m in &all_missing {
-            if m.parent != signal.id {
-                continue;
-            }
-            if m.crosswalk {
-                stage.protected_movements.insert(*m);
-            } else {
-                stage.yield_movements.insert(*m);
+        for plan in 0..signal.num_plans() {
+            let mut stage = Stage::new();
+            // TODO Could do this more efficiently
+            for m in &all_missing {
+                if m.parent != signal.id {
+                    continue;
+                }
+                if m.crosswalk {
+                    stage.protected_movements.insert(*m);
+                } else {
+                    stage.yield_movements.insert(*m);
+                }
             }
+            signal.plan_stages_mut(plan).insert(0, stage);
         }
-        signal.stages.insert(0, stage);
     }
     Some(bundle)
 }
@@ -871,6 +993,7 @@ fn draw_multiple_signals(
     ctx: &mut EventCtx,
     app: &App,
     members: &BTreeSet<IntersectionID>,
+    plan: usize,
     idx: usize,
     translations: &[(f64, f64)],
 ) -> GeomBatch {
@@ -883,7 +1006,7 @@ fn draw_multiple_signals(
         );
         traffic_signal::draw_signal_stage(
             ctx.prerender,
-            &app.primary.map.get_traffic_signal(*i).stages[idx],
+            &app.primary.map.get_traffic_signal(*i).plan_stages(plan)[idx],
             idx,
             *i,
             None,
@@ -902,6 +1025,15 @@ fn draw_multiple_signals(
     batch.scale(zoom)
 }
 
+fn describe_plan(signal: &ControlTrafficSignal, plan: usize) -> String {
+    let start = (Time::START_OF_DAY + signal.plan_start_time(plan)).ampm_tostring();
+    if signal.num_plans() == 1 {
+        "Same timing all day".to_string()
+    } else {
+        format!("Timing plan {} from {}", plan + 1, start)
+    }
+}
+
 // TODO Move to geom?
 fn squish_polygons_together(mut polygons: Vec<Polygon>) -> Vec<(f64, f64)> {
     if polygons.len() == 1 {
@@ -92,7 +92,9 @@ impl State<App> for ZoneEditor {
                         edits
                             .commands
                             .push(app.primary.map.edit_road_cmd(*r, |new| {
-                                new.access_restrictions = AccessRestrictions::new();
+                                // Conditional restrictions aren't edited here, so keep them
+                                new.access_restrictions.allow_through_traffic =
+                                    AccessRestrictions::new().allow_through_traffic;
                             }));
                     }
 
@@ -104,12 +106,13 @@ impl State<App> for ZoneEditor {
                     // The original allow_through_traffic always includes this, and there's no way
                     // to exclude it, so stay consistent.
                     allow_through_traffic.insert(PathConstraints::Train);
-                    let new_access_restrictions = AccessRestrictions {
-                        allow_through_traffic,
-                    };
                     for r in &self.selector.roads {
                         let old_access_restrictions =
                             app.primary.map.get_r(*r).access_restrictions.clone();
+                        let new_access_restrictions = AccessRestrictions {
+                            allow_through_traffic,
+                            conditional: old_access_restrictions.conditional.clone(),
+                        };
                         if old_access_restrictions != new_access_restrictions {
                             edits
                                 .commands
@@ -314,13 +314,27 @@ fn traffic_signal_body(ctx: &mut EventCtx, app: &App, id: IntersectionID) -> Wid
     let bbox = Polygon::rectangle(zoom * bounds.width(), zoom * bounds.height());
 
     let signal = app.primary.map.get_traffic_signal(id);
+    // Describe the time-of-day plan that's running right now
+    let plan = app.primary.sim.current_signal_plan(id);
+    let stages = signal.plan_stages(plan);
     {
         let mut txt = Text::new();
-        txt.add_line(Line(format!("{} stages", signal.stages.len())).small_heading());
-        txt.add_line(format!("Signal offset: {}", signal.offset));
+        txt.add_line(Line(format!("{} stages", stages.len())).small_heading());
+        if signal.num_plans() > 1 {
+            txt.add_line(format!(
+                "Timing plan {} of {}, starting at {}",
+                plan + 1,
+                signal.num_plans(),
+                (Time::START_OF_DAY + signal.plan_start_time(plan)).ampm_tostring()
+            ));
+        }
+        txt.add_line(format!("Signal offset: {}", signal.plan_offset(plan)));
+        if let Some(cycle) = signal.plan_cycle_length(plan) {
+            txt.add_line(format!("Coordinated with a {} cycle", cycle));
+        }
         {
             let mut total = Duration::ZERO;
-            for s in &signal.stages {
+            for s in stages {
                 total += s.stage_type.simple_duration();
             }
             // TODO Say "normally" or something?
@@ -329,7 +343,7 @@ fn traffic_signal_body(ctx: &mut EventCtx, app: &App, id: IntersectionID) -> Wid
         rows.push(txt.into_widget(ctx));
     }
 
-    for (idx, stage) in signal.stages.iter().enumerate() {
+    for (idx, stage) in stages.iter().enumerate() {
         rows.push(
             match stage.stage_type {
                 StageType::Fixed(d) => Line(format!("Stage {}: {}", idx + 1, d)),
@@ -340,6 +354,18 @@ fn traffic_signal_body(ctx: &mut EventCtx, app: &App, id: IntersectionID) -> Wid
                     delay,
                     additional
                 )),
+                StageType::Actuated(ref a) => Line(format!(
+                    "Stage {}: {} to {}, gap-out after {} (actuated{})",
+                    idx + 1,
+                    a.min_green,
+                    a.max_green,
+                    a.passage,
+                    if a.pedestrian_recall {
+                        ", pedestrian recall"
+                    } else {
+                        ""
+                    }
+                )),
             }
             .into_widget(ctx),
         );
@@ -445,6 +471,7 @@ fn header(
     let label = match i.intersection_type {
         IntersectionType::StopSign => format!("{} (Stop signs)", id),
         IntersectionType::TrafficSignal => format!("{} (Traffic signals)", id),
+        IntersectionType::Yield => format!("{} (Yield / roundabout)", id),
         IntersectionType::Border => format!("Border #{}", id.0),
         IntersectionType::Construction => format!("{} (under construction)", id),
     };
@@ -35,10 +35,39 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
             kv.push(("No through-traffic for", ban.join(", ")));
         }
     }
+    for c in &r.access_restrictions.conditional {
+        let modes = c
+            .modes
+            .iter()
+            .map(|p| format!("{:?}", p).to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join(", ");
+        kv.push((
+            if c.allow {
+                "Through-traffic allowed for"
+            } else {
+                "No through-traffic for"
+            },
+            format!(
+                "{} during {}{}",
+                modes,
+                c.times.describe(),
+                if c.direction.is_some() {
+                    " (one direction)"
+                } else {
+                    ""
+                }
+            ),
+        ));
+    }
 
-    if l.is_parking() {
+    if l.is_parking() || l.is_loading_zone() {
         kv.push((
-            "Parking",
+            if l.is_parking() {
+                "Parking"
+            } else {
+                "Loading zone"
+            },
             format!(
                 "{} / {} spots available",
                 app.primary.sim.get_free_onstreet_spots(l.id).len(),
@@ -53,7 +82,7 @@ fn info_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
 
     rows.extend(make_table(ctx, kv));
 
-    if l.is_parking() {
+    if l.is_parking() || l.is_loading_zone() {
         let capacity = l.number_parking_spots(app.primary.map.get_config());
         let mut series = vec![Series {
             label: format!("After \"{}\"", app.primary.map.get_edits().edits_name),
@@ -126,7 +155,7 @@ fn debug_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
         ));
     }
 
-    if let Some(types) = l.get_lane_level_turn_restrictions(r, false) {
+    if let Some(types) = l.get_lane_level_turn_restrictions(false) {
         kv.push((
             "Turn restrictions".to_string(),
             format!("{:?}", types.into_iter().collect::<Vec<_>>()),
@@ -138,6 +167,12 @@ fn debug_body(ctx: &EventCtx, app: &App, id: LaneID) -> Widget {
             format!("{:?}", restriction),
         ));
     }
+    for (restriction, to, times) in &r.conditional_turn_restrictions {
+        kv.push((
+            format!("Restriction from this road to {}", to),
+            format!("{:?} during {}", restriction, times.describe()),
+        ));
+    }
 
     // TODO Simplify and expose everywhere after there's better data
     kv.push((
@@ -275,7 +310,7 @@ fn header(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID, tab: Tab
 
     // tabs
     let mut tabs = vec![("Info", Tab::LaneInfo(id))];
-    if !l.is_parking() {
+    if !l.is_parking() && !l.is_loading_zone() {
         tabs.push(("Traffic", Tab::LaneTraffic(id, DataOptions::new())));
     }
     if app.opts.dev {
@@ -148,7 +148,9 @@ fn trips_body(
                     match trip.mode {
                         TripMode::Walk => "system/assets/meters/pedestrian.svg",
                         TripMode::Bike => "system/assets/meters/bike.svg",
-                        TripMode::Drive => "system/assets/meters/car.svg",
+                        TripMode::Drive | TripMode::RideHail | TripMode::Freight => {
+                            "system/assets/meters/car.svg"
+                        }
                         TripMode::Transit => "system/assets/meters/bus.svg",
                     },
                 )
@@ -554,17 +556,20 @@ fn parked_car_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: Car
     // TODO prev trips, next trips, etc
     let mut rows = vec![];
 
-    let p = app.primary.sim.get_owner_of_car(id).unwrap();
-    rows.push(
-        ctx.style()
-            .btn_outline
-            .text(format!("Owned by {}", p))
-            .build_def(ctx),
-    );
-    details.hyperlinks.insert(
-        format!("Owned by {}", p),
-        Tab::PersonTrips(p, BTreeMap::new()),
-    );
+    if let Some(p) = app.primary.sim.get_owner_of_car(id) {
+        rows.push(
+            ctx.style()
+                .btn_outline
+                .text(format!("Owned by {}", p))
+                .build_def(ctx),
+        );
+        details.hyperlinks.insert(
+            format!("Owned by {}", p),
+            Tab::PersonTrips(p, BTreeMap::new()),
+        );
+    } else {
+        rows.push("Part of the ride-hailing fleet".text_widget(ctx));
+    }
 
     if let Some(p) = app.primary.sim.lookup_parked_car(id) {
         match p.spot {
@@ -627,7 +632,9 @@ fn header(
                         ("walking", Some("system/assets/meters/pedestrian.svg"))
                     }
                     AgentID::Car(c) => match c.vehicle_type {
-                        VehicleType::Car => ("driving", Some("system/assets/meters/car.svg")),
+                        VehicleType::Car | VehicleType::Delivery | VehicleType::Truck => {
+                            ("driving", Some("system/assets/meters/car.svg"))
+                        }
                         VehicleType::Bike => ("biking", Some("system/assets/meters/bike.svg")),
                         VehicleType::Bus | VehicleType::Train => unreachable!(),
                     },
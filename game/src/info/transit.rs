@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
+
 use abstutil::{prettyprint_usize, Counter};
-use geom::{Circle, Distance, Time};
+use geom::{Circle, Distance, Duration, Percent, Time};
 use map_gui::tools::ColorNetwork;
 use map_gui::ID;
 use map_model::{PathStep, TransitRoute, TransitRouteID, TransitStopID};
@@ -50,6 +52,33 @@ fn stop_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: TransitSt
         } else {
             txt.add_line(Line("  No arrivals yet").secondary());
         }
+        if let Some(list) = sim.get_analytics().transit_lateness.get(&id) {
+            let lateness: Vec<Duration> = list
+                .iter()
+                .filter(|(_, route, _)| r.id == *route)
+                .map(|(_, _, lateness)| *lateness)
+                .collect();
+            if !lateness.is_empty() {
+                // A common definition of on-time: no more than 1 minute early or 5 minutes late
+                let num_on_time = lateness
+                    .iter()
+                    .filter(|x| **x >= -Duration::minutes(1) && **x <= Duration::minutes(5))
+                    .count();
+                let avg = lateness.iter().cloned().sum::<Duration>() / (lateness.len() as f64);
+                txt.add_line(
+                    Line(format!(
+                        "  {} on schedule, {} on average",
+                        Percent::of(num_on_time, lateness.len()),
+                        if avg < Duration::ZERO {
+                            format!("{} early", -avg)
+                        } else {
+                            format!("{} late", avg)
+                        }
+                    ))
+                    .secondary(),
+                );
+            }
+        }
         rows.push(txt.into_widget(ctx));
     }
 
@@ -219,7 +248,18 @@ fn route_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: TransitR
     let mut boardings: Counter<TransitStopID> = Counter::new();
     let mut alightings: Counter<TransitStopID> = Counter::new();
     let mut waiting: Counter<TransitStopID> = Counter::new();
+    // Average load factor when leaving each stop
+    let mut loads: BTreeMap<TransitStopID, (f64, usize)> = BTreeMap::new();
     for ts in &route.stops {
+        if let Some(list) = app.primary.sim.get_analytics().transit_loads.get(ts) {
+            for (_, r, load) in list {
+                if *r == id {
+                    let entry = loads.entry(*ts).or_insert((0.0, 0));
+                    entry.0 += *load;
+                    entry.1 += 1;
+                }
+            }
+        }
         if let Some(list) = app.primary.sim.get_analytics().passengers_boarding.get(ts) {
             for (_, r, _) in list {
                 if *r == id {
@@ -281,10 +321,18 @@ fn route_body(ctx: &mut EventCtx, app: &App, details: &mut Details, id: TransitR
             Text::from_all(vec![
                 Line(&ts.name),
                 Line(format!(
-                    ": {} boardings, {} alightings, {} currently waiting",
+                    ": {} boardings, {} alightings, {} currently waiting{}",
                     prettyprint_usize(boardings.get(ts.id)),
                     prettyprint_usize(alightings.get(ts.id)),
-                    prettyprint_usize(waiting.get(ts.id))
+                    prettyprint_usize(waiting.get(ts.id)),
+                    if let Some((sum, count)) = loads.get(&ts.id) {
+                        format!(
+                            ", {}% full leaving",
+                            (100.0 * sum / (*count as f64)).round()
+                        )
+                    } else {
+                        String::new()
+                    }
                 ))
                 .secondary(),
             ])
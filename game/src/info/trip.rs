@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 
 use maplit::btreemap;
 
-use geom::{Distance, Duration, Percent, Polygon, Pt2D, UnitFmt};
+use geom::{Circle, Distance, Duration, Percent, Polygon, Pt2D, UnitFmt};
 use map_gui::ID;
 use map_model::{Map, Path, PathStep, Traversable};
 use sim::{AgentID, Analytics, PersonID, Problem, TripID, TripInfo, TripPhase, TripPhaseType};
@@ -327,6 +327,27 @@ pub fn finished(
             waiting.to_string(&app.opts.units).text_widget(ctx),
         ]));
 
+        if let Some(cost) = app.primary.sim.trip_cost(
+            if open_trips[&id].show_after {
+                app.primary.sim.get_analytics()
+            } else {
+                app.prebaked()
+            },
+            id,
+        ) {
+            col.push(Widget::custom_row(vec![
+                Widget::custom_row(vec![Line("Generalized cost").secondary().into_widget(ctx)])
+                    .force_width_window_pct(ctx, col_width),
+                format!(
+                    "{:.2} ({:.2} paid, {:.2} for time)",
+                    cost.generalized(),
+                    cost.money(),
+                    cost.time
+                )
+                .text_widget(ctx),
+            ]));
+        }
+
         col.push(Widget::custom_row(vec![
             Widget::custom_row(vec![Line("Purpose").secondary().into_widget(ctx)])
                 .force_width_window_pct(ctx, col_width),
@@ -429,6 +450,7 @@ fn describe_problems(
         TripMode::Bike => {
             let mut count_complex_intersections = 0;
             let mut count_overtakes = 0;
+            let mut count_overtaken = 0;
             let empty = Vec::new();
             for (_, problem) in analytics.problems_per_trip.get(&id).unwrap_or(&empty) {
                 match problem {
@@ -438,8 +460,12 @@ fn describe_problems(
                     Problem::OvertakeDesired(_) => {
                         count_overtakes += 1;
                     }
+                    Problem::Overtaken(_) => {
+                        count_overtaken += 1;
+                    }
                     Problem::ArterialIntersectionCrossing(_) => {}
                     Problem::IntersectionDelay(_, _) => {}
+                    Problem::DeniedBoarding(_, _) => {}
                 }
             }
             let mut txt = Text::new();
@@ -461,6 +487,15 @@ fn describe_problems(
                 }
                 .secondary(),
             ]);
+            txt.add_appended(vec![
+                Line(count_overtaken.to_string()),
+                if count_overtaken == 1 {
+                    Line(" vehicle over-took")
+                } else {
+                    Line(" vehicles over-took")
+                }
+                .secondary(),
+            ]);
 
             Widget::custom_row(vec![
                 Line("Risk Exposure")
@@ -471,6 +506,34 @@ fn describe_problems(
                 txt.into_widget(ctx),
             ])
         }
+        TripMode::Transit => {
+            let mut count_denied_boardings = 0;
+            let empty = Vec::new();
+            for (_, problem) in analytics.problems_per_trip.get(&id).unwrap_or(&empty) {
+                if matches!(problem, Problem::DeniedBoarding(_, _)) {
+                    count_denied_boardings += 1;
+                }
+            }
+            let mut txt = Text::new();
+            txt.add_appended(vec![
+                Line(count_denied_boardings.to_string()),
+                if count_denied_boardings == 1 {
+                    Line(" vehicle was too full to board")
+                } else {
+                    Line(" vehicles were too full to board")
+                }
+                .secondary(),
+            ]);
+
+            Widget::custom_row(vec![
+                Line("Crowding")
+                    .secondary()
+                    .into_widget(ctx)
+                    .container()
+                    .force_width_window_pct(ctx, col_width),
+                txt.into_widget(ctx),
+            ])
+        }
         _ => Widget::nothing(),
     }
 }
@@ -541,7 +604,7 @@ fn draw_problems(
                     ]),
                 ));
             }
-            Problem::OvertakeDesired(on) => {
+            Problem::OvertakeDesired(on) | Problem::Overtaken(on) => {
                 let pt = on.get_polyline(map).middle();
                 details.draw_extra.unzoomed.append(
                     GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
@@ -559,7 +622,11 @@ fn draw_problems(
                         Traversable::Lane(l) => map.get_parent(*l).get_thick_polygon(),
                         Traversable::Turn(t) => map.get_i(t.parent).polygon.clone(),
                     },
-                    Text::from("A vehicle wanted to over-take this cyclist near here."),
+                    Text::from(if matches!(problem, Problem::Overtaken(_)) {
+                        "A vehicle over-took this one near here."
+                    } else {
+                        "A vehicle wanted to over-take this cyclist near here."
+                    }),
                 ));
             }
             Problem::ArterialIntersectionCrossing(t) => {
@@ -585,6 +652,29 @@ fn draw_problems(
                     ]),
                 ));
             }
+            Problem::DeniedBoarding(ts, r) => {
+                let ts = map.get_ts(*ts);
+                let pt = ts.sidewalk_pos.pt(map);
+                details.draw_extra.unzoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .centered_on(pt)
+                        .color(RewriteColor::ChangeAlpha(0.8)),
+                );
+                details.draw_extra.zoomed.append(
+                    GeomBatch::load_svg(ctx, "system/assets/tools/alert.svg")
+                        .scale(0.5)
+                        .color(RewriteColor::ChangeAlpha(0.5))
+                        .centered_on(pt),
+                );
+                details.tooltips.push((
+                    Circle::new(pt, Distance::meters(10.0)).to_polygon(),
+                    Text::from(format!(
+                        "A full {} vehicle left this person waiting at {}",
+                        map.get_tr(*r).short_name,
+                        ts.name
+                    )),
+                ));
+            }
         }
     }
 }
@@ -687,6 +777,11 @@ fn make_timeline(
                         "system/assets/timeline/waiting_for_bus.svg"
                     }
                     TripPhaseType::RidingBus(_, _, _) => "system/assets/timeline/riding_bus.svg",
+                    TripPhaseType::WaitingForRideHail => {
+                        "system/assets/timeline/waiting_for_bus.svg"
+                    }
+                    TripPhaseType::RidingRideHail(_) => "system/assets/timeline/driving.svg",
+                    TripPhaseType::Delivering(_) => "system/assets/timeline/parking.svg",
                     TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
                     TripPhaseType::DelayedStart => "system/assets/timeline/delayed_start.svg",
                 },
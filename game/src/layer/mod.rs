@@ -11,6 +11,7 @@ use crate::sandbox::dashboards;
 pub mod elevation;
 pub mod favorites;
 pub mod map;
+mod noise;
 mod pandemic;
 mod parking;
 mod population;
@@ -108,6 +109,7 @@ impl PickLayer {
                     btn("throughput", Key::T),
                     btn("traffic jams", Key::J),
                     btn("cycling activity", Key::B),
+                    btn("noise", Key::Q),
                 ]),
                 Widget::col(vec![
                     "Map".text_widget(ctx),
@@ -186,6 +188,9 @@ impl State<App> for PickLayer {
                 "map edits" => {
                     app.primary.layer = Some(Box::new(map::Static::edits(ctx, app)));
                 }
+                "noise" => {
+                    app.primary.layer = Some(Box::new(noise::Noise::new(ctx, app)));
+                }
                 "no sidewalks" => {
                     app.primary.layer = Some(Box::new(map::Static::no_sidewalks(ctx, app)));
                 }
@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Distance, Duration, FindClosest, Time};
+use map_gui::tools::{ColorLegend, ColorNetwork, DivergingScale};
+use map_gui::ID;
+use map_model::{BuildingID, Map, RoadID};
+use sim::{Analytics, VehicleType};
+use widgetry::mapspace::ToggleZoomed;
+use widgetry::{Color, EventCtx, GfxCtx, Line, Outcome, Panel, Text, TextExt, Toggle, Widget};
+
+use crate::app::App;
+use crate::layer::{header, Layer, LayerOutcome, PANEL_PLACEMENT};
+
+/// Only roads this close to a building contribute to its noise level
+const MAX_DISTANCE: Distance = Distance::const_meters(200.0);
+/// Levels are colored between these, in dB(A)
+const QUIETEST: f64 = 45.0;
+const LOUDEST: f64 = 75.0;
+/// The WHO recommends keeping Lden from road traffic below 53 dB. 65 dB is a common threshold for
+/// "severely affected".
+const LOUD: f64 = 65.0;
+/// Matching traffic to every building is slow, so don't redo it every time the simulation steps
+const RECALCULATE_EVERY: Duration = Duration::const_seconds(5.0 * 60.0);
+
+/// Estimates road traffic noise at each building's facade, from the simulated traffic volume and
+/// mix of vehicles on nearby roads. This is loosely based on the UK's Calculation of Road Traffic
+/// Noise (CRTN) method, ignoring screening by other buildings, road surfaces, and reflections.
+/// It's good for comparing before and after an edit, not for absolute numbers.
+pub struct Noise {
+    time: Time,
+    lden: bool,
+    compare: bool,
+    /// For each building, the nearby roads and the attenuation of sound energy from them
+    attenuation: BTreeMap<BuildingID, Vec<(RoadID, f64)>>,
+    levels: BTreeMap<BuildingID, f64>,
+    tooltip: Option<Text>,
+    draw: ToggleZoomed,
+    panel: Panel,
+}
+
+impl Layer for Noise {
+    fn name(&self) -> Option<&'static str> {
+        Some("noise")
+    }
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Option<LayerOutcome> {
+        let mut recalc_tooltip = false;
+        if needs_recalculate(self.time, app.primary.sim.time()) {
+            self.recalculate(ctx, app);
+            recalc_tooltip = true;
+        }
+
+        if ctx.redo_mouseover() || recalc_tooltip {
+            self.tooltip = None;
+            if let Some(ID::Building(b)) = app.primary.current_selection {
+                if let Some(level) = self.levels.get(&b) {
+                    self.tooltip = Some(Text::from(if self.compare {
+                        format!("{:+.1} dB", level)
+                    } else {
+                        format!("{:.1} dB", level)
+                    }));
+                }
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed(_) => {
+                self.lden = self.panel.is_checked("Show Lden");
+                self.compare = app.has_prebaked().is_some()
+                    && self.panel.is_checked("Compare before proposal");
+                self.recalculate(ctx, app);
+            }
+            _ => {}
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        self.draw.draw(g);
+        if let Some(ref txt) = self.tooltip {
+            g.draw_mouse_tooltip(txt.clone());
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw.unzoomed);
+    }
+}
+
+impl Noise {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Noise {
+        let mut layer = Noise {
+            time: app.primary.sim.time(),
+            lden: false,
+            compare: false,
+            // Matching every building to nearby roads is slow on large maps, but it only depends on
+            // the map, so do it once
+            attenuation: ctx.loading_screen("find roads near buildings", |_, timer| {
+                attenuation(&app.primary.map, timer)
+            }),
+            levels: BTreeMap::new(),
+            tooltip: None,
+            draw: ToggleZoomed::empty(ctx),
+            panel: Panel::empty(ctx),
+        };
+        layer.recalculate(ctx, app);
+        layer
+    }
+
+    fn recalculate(&mut self, ctx: &mut EventCtx, app: &App) {
+        let now = app.primary.sim.time();
+        let after = self.building_levels(source_levels(
+            &app.primary.map,
+            app.primary.sim.get_analytics(),
+            now,
+            self.lden,
+            true,
+        ));
+
+        let mut colorer = ColorNetwork::new(app);
+        let legend;
+        if self.compare {
+            let before = self.building_levels(source_levels(
+                &app.primary.map,
+                app.prebaked(),
+                now,
+                self.lden,
+                false,
+            ));
+            let scale =
+                DivergingScale::new(Color::hex("#5D9630"), Color::WHITE, Color::hex("#A32015"))
+                    .range(-6.0, 6.0)
+                    .ignore(-1.0, 1.0);
+            self.levels.clear();
+            for (b, after) in after {
+                // If there was no traffic nearby before, compare against the quietest level
+                let diff = after - before.get(&b).cloned().unwrap_or(QUIETEST).max(QUIETEST);
+                if let Some(c) = scale.eval(diff) {
+                    colorer.add_b(b, c);
+                }
+                self.levels.insert(b, diff);
+            }
+            for (b, before) in before {
+                if !self.levels.contains_key(&b) && before > QUIETEST {
+                    let diff = QUIETEST - before;
+                    if let Some(c) = scale.eval(diff) {
+                        colorer.add_b(b, c);
+                    }
+                    self.levels.insert(b, diff);
+                }
+            }
+            legend = scale.make_legend(ctx, vec!["quieter", "same", "louder"]);
+        } else {
+            for (b, level) in &after {
+                if *level >= QUIETEST {
+                    // Anything past LOUDEST gets the same color
+                    colorer.add_b(
+                        *b,
+                        app.cs
+                            .good_to_bad_red
+                            .eval(((level - QUIETEST) / (LOUDEST - QUIETEST)).min(1.0)),
+                    );
+                }
+            }
+            self.levels = after;
+            legend = ColorLegend::gradient(
+                ctx,
+                &app.cs.good_to_bad_red,
+                vec!["45 dB", "55", "65", "75+"],
+            );
+        }
+
+        let mut col = vec![
+            header(ctx, "Traffic noise"),
+            Text::from(
+                Line(if self.lden {
+                    "Day-evening-night level, only counting the hours simulated so far"
+                } else {
+                    "Equivalent level over the current hour"
+                })
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .into_widget(ctx),
+            Toggle::choice(ctx, "Show Lden", "Lden", "Leq", None, self.lden),
+        ];
+        if app.has_prebaked().is_some() {
+            col.push(Toggle::switch(
+                ctx,
+                "Compare before proposal",
+                None,
+                self.compare,
+            ));
+        }
+        if !self.compare {
+            col.push(
+                format!(
+                    "{} buildings above {} dB",
+                    prettyprint_usize(self.levels.values().filter(|x| **x >= LOUD).count()),
+                    LOUD
+                )
+                .text_widget(ctx),
+            );
+        }
+        col.push(legend);
+
+        self.time = now;
+        self.tooltip = None;
+        self.draw = colorer.build(ctx);
+        self.panel = Panel::new_builder(Widget::col(col))
+            .aligned_pair(PANEL_PLACEMENT)
+            .build(ctx);
+    }
+
+    /// Energy-sum the contribution of every nearby road
+    fn building_levels(&self, sources: BTreeMap<RoadID, f64>) -> BTreeMap<BuildingID, f64> {
+        let mut levels = BTreeMap::new();
+        for (b, roads) in &self.attenuation {
+            let energy: f64 = roads
+                .iter()
+                .filter_map(|(r, attenuation)| {
+                    sources.get(r).map(|level| attenuation * to_energy(*level))
+                })
+                .sum();
+            if energy > 0.0 {
+                levels.insert(*b, to_level(energy));
+            }
+        }
+        levels
+    }
+}
+
+/// Recalculate after enough time passes, when a new hour starts, or if the simulation is reset
+fn needs_recalculate(last: Time, now: Time) -> bool {
+    now < last || now - last >= RECALCULATE_EVERY || now.get_hours() != last.get_hours()
+}
+
+/// For every building, find nearby roads, and how much sound energy drops off over the distance
+/// from the road to the closest point of the facade.
+fn attenuation(map: &Map, timer: &mut Timer) -> BTreeMap<BuildingID, Vec<(RoadID, f64)>> {
+    let mut closest = FindClosest::new(map.get_bounds());
+    for r in map.all_roads() {
+        closest.add(r.id, r.center_pts.points());
+    }
+
+    let mut result = BTreeMap::new();
+    timer.start_iter("find roads near buildings", map.all_buildings().len());
+    for b in map.all_buildings() {
+        timer.next();
+        let mut per_road: BTreeMap<RoadID, Distance> = BTreeMap::new();
+        for pt in b.polygon.points() {
+            for (r, _, dist) in closest.all_close_pts(*pt, MAX_DISTANCE) {
+                // Measure from the edge of the road
+                let dist = (dist - map.get_r(r).get_half_width()).max(Distance::meters(1.0));
+                let entry = per_road.entry(r).or_insert(dist);
+                if dist < *entry {
+                    *entry = dist;
+                }
+            }
+        }
+        result.insert(
+            b.id,
+            per_road
+                .into_iter()
+                .map(|(r, dist)| (r, to_energy(distance_correction(dist))))
+                .collect(),
+        );
+    }
+    result
+}
+
+/// CRTN's distance correction, relative to the reference distance of 10m from the road edge. The
+/// source is 0.5m above the road and the receiver is about 4m above the ground.
+fn distance_correction(dist: Distance) -> f64 {
+    let slant = ((dist.inner_meters() + 3.5).powi(2) + 3.5f64.powi(2)).sqrt();
+    -10.0 * (slant / 13.5).log10()
+}
+
+/// The noise level 10m from each road, either as an equivalent level for the current hour or as
+/// Lden. The current hour is only partly simulated, so if `extrapolate` is true, the volume so far
+/// is scaled up to a full hour. Otherwise, the data is assumed to cover the whole hour already.
+fn source_levels(
+    map: &Map,
+    analytics: &Analytics,
+    now: Time,
+    lden: bool,
+    extrapolate: bool,
+) -> BTreeMap<RoadID, f64> {
+    let current_hour = now.get_hours();
+    let elapsed = (now - Time::START_OF_DAY).inner_seconds() - (current_hour as f64) * 3600.0;
+    let scale_current_hour = if extrapolate {
+        3600.0 / elapsed.max(60.0)
+    } else {
+        1.0
+    };
+
+    // (road, hour) -> (light vehicles, heavy vehicles) per hour
+    let mut flows: BTreeMap<(RoadID, usize), (f64, f64)> = BTreeMap::new();
+    for ((r, vehicle_type, hour), count) in &analytics.road_vehicle_thruput {
+        if *hour > current_hour || (!lden && *hour != current_hour) {
+            continue;
+        }
+        let count = if *hour == current_hour {
+            (*count as f64) * scale_current_hour
+        } else {
+            *count as f64
+        };
+        let entry = flows.entry((*r, *hour)).or_insert((0.0, 0.0));
+        match vehicle_type {
+            VehicleType::Car => {
+                entry.0 += count;
+            }
+            VehicleType::Bus | VehicleType::Train | VehicleType::Delivery | VehicleType::Truck => {
+                entry.1 += count;
+            }
+            // Quiet enough to ignore
+            VehicleType::Bike => {}
+        }
+    }
+
+    let mut energy_per_road: BTreeMap<RoadID, f64> = BTreeMap::new();
+    for ((r, hour), (light, heavy)) in flows {
+        if light + heavy == 0.0 {
+            continue;
+        }
+        let road = if let Some(road) = map.maybe_get_r(r) {
+            road
+        } else {
+            // Deleted by an edit
+            continue;
+        };
+        let level = hourly_level(
+            light + heavy,
+            100.0 * heavy / (light + heavy).max(1.0),
+            road.speed_limit.inner_meters_per_second() * 3.6,
+        );
+        // Lden penalizes evening and night noise
+        let penalty = if !lden || (7..19).contains(&(hour % 24)) {
+            0.0
+        } else if (19..23).contains(&(hour % 24)) {
+            5.0
+        } else {
+            10.0
+        };
+        *energy_per_road.entry(r).or_insert(0.0) += to_energy(level + penalty);
+    }
+
+    energy_per_road
+        .into_iter()
+        .filter(|(_, energy)| *energy > 0.0)
+        .map(|(r, energy)| {
+            // Lden averages over the entire day
+            (r, to_level(if lden { energy / 24.0 } else { energy }))
+        })
+        .collect()
+}
+
+/// The CRTN basic noise level for an hour, 10m from the road edge, converted from L10 to Leq by
+/// subtracting 3 dB.
+fn hourly_level(vehicles_per_hour: f64, percent_heavy: f64, kmph: f64) -> f64 {
+    let kmph = kmph.max(20.0);
+    let l10 = 42.2
+        + 10.0 * vehicles_per_hour.log10()
+        + 33.0 * (kmph + 40.0 + 500.0 / kmph).log10()
+        + 10.0 * (1.0 + 5.0 * percent_heavy / kmph).log10()
+        - 68.8;
+    l10 - 3.0
+}
+
+fn to_energy(level: f64) -> f64 {
+    10.0_f64.powf(level / 10.0)
+}
+
+fn to_level(energy: f64) -> f64 {
+    10.0 * energy.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx_eq(x: f64, y: f64) -> bool {
+        (x - y).abs() < 0.1
+    }
+
+    #[test]
+    fn test_hourly_level() {
+        // A worked CRTN example: 1000 light vehicles per hour at 75km/h has an L10 of about
+        // 72.2 dB
+        assert!(approx_eq(hourly_level(1000.0, 0.0, 75.0), 69.2));
+        // Doubling the traffic adds 3 dB
+        assert!(approx_eq(
+            hourly_level(2000.0, 0.0, 75.0) - hourly_level(1000.0, 0.0, 75.0),
+            3.0
+        ));
+        // Heavy vehicles are louder
+        assert!(hourly_level(1000.0, 20.0, 75.0) > hourly_level(1000.0, 0.0, 75.0) + 1.0);
+        // Below 20km/h, speed doesn't matter
+        assert_eq!(
+            hourly_level(100.0, 0.0, 5.0),
+            hourly_level(100.0, 0.0, 20.0)
+        );
+    }
+
+    #[test]
+    fn test_distance_correction() {
+        // Barely any correction at the reference distance; the receiver is a bit higher up
+        assert!(distance_correction(Distance::meters(10.0)).abs() < 0.2);
+        assert!(distance_correction(Distance::meters(1.0)) > 0.0);
+        assert!(distance_correction(Distance::meters(50.0)) < 0.0);
+        // Far from the road, doubling the distance loses about 3 dB
+        assert!(approx_eq(
+            distance_correction(Distance::meters(100.0))
+                - distance_correction(Distance::meters(200.0)),
+            3.0
+        ));
+    }
+
+    #[test]
+    fn test_energy() {
+        assert_eq!(to_energy(0.0), 1.0);
+        assert_eq!(to_energy(20.0), 100.0);
+        assert!(approx_eq(to_level(to_energy(63.7)), 63.7));
+        // Two equally loud sources are 3 dB louder than one
+        assert!(approx_eq(to_level(2.0 * to_energy(60.0)), 63.0));
+        // A much quieter source barely matters
+        assert!(approx_eq(to_level(to_energy(60.0) + to_energy(40.0)), 60.0));
+    }
+
+    #[test]
+    fn test_needs_recalculate() {
+        let at = |mins| Time::START_OF_DAY + Duration::minutes(mins);
+        assert!(!needs_recalculate(at(10), at(10)));
+        assert!(!needs_recalculate(at(10), at(14)));
+        assert!(needs_recalculate(at(10), at(15)));
+        // A new hour starts
+        assert!(needs_recalculate(at(58), at(60)));
+        // The simulation was reset
+        assert!(needs_recalculate(at(10), at(0)));
+    }
+}
@@ -67,10 +67,18 @@ impl ProblemMap {
                         | Problem::ComplexIntersectionCrossing(i) => {
                             app.primary.map.get_i(*i).polygon.center()
                         }
-                        Problem::OvertakeDesired(on) => on.get_polyline(&app.primary.map).middle(),
+                        Problem::OvertakeDesired(on) | Problem::Overtaken(on) => {
+                            on.get_polyline(&app.primary.map).middle()
+                        }
                         Problem::ArterialIntersectionCrossing(t) => {
                             app.primary.map.get_t(*t).geom.middle()
                         }
+                        Problem::DeniedBoarding(ts, _) => app
+                            .primary
+                            .map
+                            .get_ts(*ts)
+                            .sidewalk_pos
+                            .pt(&app.primary.map),
                     });
                 }
             }
@@ -132,6 +140,9 @@ impl ProblemMap {
             show_arterial_crossings: self
                 .panel
                 .is_checked("show where pedestrians cross arterial intersections"),
+            show_denied_boardings: self
+                .panel
+                .is_checked("show where full transit vehicles left people waiting"),
         }
     }
 }
@@ -147,6 +158,7 @@ pub struct Options {
     show_complex_crossings: bool,
     show_overtakes: bool,
     show_arterial_crossings: bool,
+    show_denied_boardings: bool,
     // TODO Time range
 }
 
@@ -161,6 +173,7 @@ impl Options {
             show_complex_crossings: true,
             show_overtakes: true,
             show_arterial_crossings: true,
+            show_denied_boardings: true,
         }
     }
 
@@ -171,8 +184,9 @@ impl Options {
         match problem {
             Problem::IntersectionDelay(_, _) => self.show_delays,
             Problem::ComplexIntersectionCrossing(_) => self.show_complex_crossings,
-            Problem::OvertakeDesired(_) => self.show_overtakes,
+            Problem::OvertakeDesired(_) | Problem::Overtaken(_) => self.show_overtakes,
             Problem::ArterialIntersectionCrossing(_) => self.show_arterial_crossings,
+            Problem::DeniedBoarding(_, _) => self.show_denied_boardings,
         }
     }
 }
@@ -236,6 +250,12 @@ fn make_controls(
         None,
         opts.show_arterial_crossings,
     ));
+    col.push(Toggle::checkbox(
+        ctx,
+        "show where full transit vehicles left people waiting",
+        None,
+        opts.show_denied_boardings,
+    ));
 
     col.push(Toggle::choice(
         ctx,
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use geom::Percent;
 use map_gui::load::MapLoader;
 use map_gui::tools::{open_browser, PopupMsg};
-use map_model::PermanentMapEdits;
+use map_model::{MigrationOutcome, PermanentMapEdits};
 use synthpop::Scenario;
 use widgetry::{EventCtx, Key, Line, Panel, SimpleState, State, Text, Widget};
 
@@ -149,21 +149,26 @@ fn launch(ctx: &mut EventCtx, app: &App, edits: PermanentMapEdits) -> Transition
         Box::new(move |ctx, app| {
             // Apply edits before setting up the sandbox, for simplicity
             let maybe_err = ctx.loading_screen("apply edits", |ctx, timer| {
-                match edits.into_edits(&app.primary.map) {
-                    Ok(edits) => {
-                        apply_map_edits(ctx, app, edits);
-                        app.primary.map.recalculate_pathfinding_after_edits(timer);
-                        None
-                    }
-                    Err(err) => Some(err),
+                // If the map was regenerated, some edits may have to be matched by location
+                let (edits, report) = edits.migrate(&app.primary.map);
+                if report
+                    .iter()
+                    .any(|item| matches!(item.outcome, MigrationOutcome::Dropped(_)))
+                {
+                    return Some(
+                        report
+                            .into_iter()
+                            .filter(|item| !matches!(item.outcome, MigrationOutcome::CarriedOver))
+                            .map(|item| item.describe())
+                            .collect::<Vec<_>>(),
+                    );
                 }
+                apply_map_edits(ctx, app, edits);
+                app.primary.map.recalculate_pathfinding_after_edits(timer);
+                None
             });
-            if let Some(err) = maybe_err {
-                Transition::Replace(PopupMsg::new_state(
-                    ctx,
-                    "Can't load proposal",
-                    vec![err.to_string()],
-                ))
+            if let Some(problems) = maybe_err {
+                Transition::Replace(PopupMsg::new_state(ctx, "Can't load proposal", problems))
             } else {
                 app.primary.layer = Some(Box::new(crate::layer::map::Static::edits(ctx, app)));
                 Transition::Replace(SandboxMode::simple_new(
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use map_model::RoadID;
+use sim::{Emissions, VehicleType};
+use widgetry::{EventCtx, GfxCtx, Line, Outcome, Panel, State, Text, TextExt, Widget};
+
+use crate::app::{App, Transition};
+use crate::info::Tab;
+use crate::sandbox::dashboards::DashTab;
+use crate::sandbox::SandboxMode;
+
+pub struct EmissionsSummary {
+    panel: Panel,
+}
+
+impl EmissionsSummary {
+    pub fn new_state(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let analytics = app.primary.sim.get_analytics();
+
+        let mut col = vec![
+            DashTab::Emissions.picker(ctx, app),
+            Line("Vehicle emissions and energy use")
+                .small_heading()
+                .into_widget(ctx),
+            "These are rough estimates from an average-speed model, using fleet-wide factors for \
+             each type of vehicle."
+                .text_widget(ctx),
+        ];
+
+        let after = total(&analytics.vehicle_type_emissions);
+        let before = app
+            .has_prebaked()
+            .map(|_| total(&app.prebaked().vehicle_type_emissions));
+        let mut txt = Text::new();
+        for (name, unit, after, before) in [
+            ("CO2", "g", after.co2_grams, before.map(|x| x.co2_grams)),
+            ("NOx", "g", after.nox_grams, before.map(|x| x.nox_grams)),
+            (
+                "Particulate matter",
+                "g",
+                after.pm_grams,
+                before.map(|x| x.pm_grams),
+            ),
+            (
+                "Energy",
+                "kWh",
+                after.energy_kwh,
+                before.map(|x| x.energy_kwh),
+            ),
+        ] {
+            txt.add_line(Line(format!("{}: {}", name, describe(after, unit))));
+            if let Some(before) = before {
+                txt.append(
+                    Line(format!(" (before changes: {})", describe(before, unit))).secondary(),
+                );
+            }
+        }
+        col.push(txt.into_widget(ctx).section(ctx));
+
+        let mut txt = Text::from(Line("By vehicle type").small_heading());
+        for (vehicle_type, emissions) in &analytics.vehicle_type_emissions {
+            txt.add_line(Line(format!(
+                "{}: {} CO2, {}",
+                vehicle_type,
+                describe(emissions.co2_grams, "g"),
+                describe(emissions.energy_kwh, "kWh")
+            )));
+        }
+        col.push(txt.into_widget(ctx).section(ctx));
+
+        let mut roads: Vec<(RoadID, f64)> = analytics
+            .road_emissions
+            .iter()
+            .filter(|(r, _)| app.primary.map.maybe_get_r(**r).is_some())
+            .map(|(r, e)| (*r, e.co2_grams))
+            .collect();
+        roads.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        roads.truncate(10);
+        col.push(
+            Line("Roads with the most CO2")
+                .small_heading()
+                .into_widget(ctx),
+        );
+        col.push(Widget::col(
+            roads
+                .into_iter()
+                .map(|(r, co2)| {
+                    Widget::row(vec![
+                        ctx.style()
+                            .btn_outline
+                            .text(
+                                app.primary
+                                    .map
+                                    .get_r(r)
+                                    .get_name(app.opts.language.as_ref()),
+                            )
+                            .build_widget(ctx, r.to_string()),
+                        describe(co2, "g").text_widget(ctx).centered_vert(),
+                    ])
+                })
+                .collect(),
+        ));
+
+        Box::new(EmissionsSummary {
+            panel: Panel::new_builder(Widget::col(col))
+                .exact_size_percent(90, 90)
+                .build(ctx),
+        })
+    }
+}
+
+impl State<App> for EmissionsSummary {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                let r = RoadID(x.strip_prefix("Road #").unwrap().parse::<usize>().unwrap());
+                let l = app.primary.map.get_r(r).lanes[0].id;
+                Transition::Multi(vec![
+                    Transition::Pop,
+                    Transition::ModifyState(Box::new(move |state, ctx, app| {
+                        let sandbox = state.downcast_mut::<SandboxMode>().unwrap();
+                        let mut actions = sandbox.contextual_actions();
+                        sandbox.controls.common.as_mut().unwrap().launch_info_panel(
+                            ctx,
+                            app,
+                            Tab::LaneInfo(l),
+                            &mut actions,
+                        )
+                    })),
+                ])
+            }
+            Outcome::Changed(_) => DashTab::Emissions
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _app: &App) {
+        self.panel.draw(g);
+    }
+}
+
+fn total(per_vehicle_type: &BTreeMap<VehicleType, Emissions>) -> Emissions {
+    let mut total = Emissions::default();
+    for x in per_vehicle_type.values() {
+        total += *x;
+    }
+    total
+}
+
+/// Switch grams to kilograms or tonnes when they get large
+fn describe(amount: f64, unit: &str) -> String {
+    if unit == "g" && amount >= 1_000_000.0 {
+        format!("{:.1} tonnes", amount / 1_000_000.0)
+    } else if unit == "g" && amount >= 1000.0 {
+        format!("{:.1} kg", amount / 1000.0)
+    } else {
+        format!("{:.1} {}", amount, unit)
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
+
 use abstutil::{prettyprint_usize, Counter};
 use geom::Time;
-use map_model::TransitRouteID;
+use map_model::{TransitRouteID, TransitStopID};
 use widgetry::{
     Autocomplete, EventCtx, GfxCtx, Image, Line, LinePlot, Outcome, Panel, PlotOptions, Series,
     State, TextExt, Widget,
@@ -137,6 +139,22 @@ impl TransitRoutes {
                 waiting.inc(*r);
             }
         }
+        // Average load factor when leaving each stop, then the most crowded stop per route
+        let mut loads: BTreeMap<(TransitRouteID, TransitStopID), (f64, usize)> = BTreeMap::new();
+        for (ts, list) in &app.primary.sim.get_analytics().transit_loads {
+            for (_, r, load) in list {
+                let entry = loads.entry((*r, *ts)).or_insert((0.0, 0));
+                entry.0 += *load;
+                entry.1 += 1;
+            }
+        }
+        let mut peak_load: BTreeMap<TransitRouteID, (f64, TransitStopID)> = BTreeMap::new();
+        for ((r, ts), (sum, count)) in loads {
+            let avg = sum / (count as f64);
+            if peak_load.get(&r).map(|(x, _)| avg > *x).unwrap_or(true) {
+                peak_load.insert(r, (avg, ts));
+            }
+        }
 
         // Sort descending by count, but ascending by name. Hence the funny negation.
         let mut routes: Vec<(isize, isize, isize, String, TransitRouteID)> = Vec::new();
@@ -174,18 +192,25 @@ impl TransitRoutes {
                 routes
                     .into_iter()
                     .map(|(boardings, alightings, waiting, name, id)| {
+                        let mut summary = format!(
+                            "{} boardings, {} alightings, {} currently waiting",
+                            prettyprint_usize(-boardings as usize),
+                            prettyprint_usize(-alightings as usize),
+                            prettyprint_usize(-waiting as usize)
+                        );
+                        if let Some((load, ts)) = peak_load.get(&id) {
+                            summary.push_str(&format!(
+                                ", most crowded leaving {} ({}% full on average)",
+                                app.primary.map.get_ts(*ts).name,
+                                (load * 100.0).round()
+                            ));
+                        }
                         Widget::row(vec![
                             ctx.style()
                                 .btn_outline
                                 .text(name)
                                 .build_widget(ctx, id.to_string()),
-                            format!(
-                                "{} boardings, {} alightings, {} currently waiting",
-                                prettyprint_usize(-boardings as usize),
-                                prettyprint_usize(-alightings as usize),
-                                prettyprint_usize(-waiting as usize)
-                            )
-                            .text_widget(ctx),
+                            summary.text_widget(ctx),
                         ])
                     })
                     .collect(),
@@ -120,6 +120,18 @@ impl RiskSummaries {
                             ),
                         ])
                         .section(ctx),
+                        Widget::col(vec![
+                            Line("Vehicles over-taking cyclists")
+                                .small_heading()
+                                .into_widget(ctx)
+                                .centered_horiz(),
+                            problem_matrix(
+                                ctx,
+                                app,
+                                bike_filter.trip_problems(app, ProblemType::Overtaken),
+                            ),
+                        ])
+                        .section(ctx),
                     ],
                 )
                 .margin_above(30),
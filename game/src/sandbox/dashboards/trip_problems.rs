@@ -17,7 +17,9 @@ pub enum ProblemType {
     IntersectionDelay,
     ComplexIntersectionCrossing,
     OvertakeDesired,
+    Overtaken,
     ArterialIntersectionCrossing,
+    DeniedBoarding,
 }
 
 impl From<&Problem> for ProblemType {
@@ -26,7 +28,9 @@ impl From<&Problem> for ProblemType {
             Problem::IntersectionDelay(_, _) => Self::IntersectionDelay,
             Problem::ComplexIntersectionCrossing(_) => Self::ComplexIntersectionCrossing,
             Problem::OvertakeDesired(_) => Self::OvertakeDesired,
+            Problem::Overtaken(_) => Self::Overtaken,
             Problem::ArterialIntersectionCrossing(_) => Self::ArterialIntersectionCrossing,
+            Problem::DeniedBoarding(_, _) => Self::DeniedBoarding,
         }
     }
 }
@@ -47,7 +51,9 @@ impl ProblemType {
             ProblemType::IntersectionDelay,
             ProblemType::ComplexIntersectionCrossing,
             ProblemType::OvertakeDesired,
+            ProblemType::Overtaken,
             ProblemType::ArterialIntersectionCrossing,
+            ProblemType::DeniedBoarding,
         ]
     }
 }
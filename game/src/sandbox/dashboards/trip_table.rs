@@ -221,6 +221,8 @@ struct FinishedTrip {
     duration_before: Duration,
     waiting: Duration,
     percent_waiting: usize,
+    cost_after: f64,
+    cost_before: f64,
 }
 
 struct CancelledTrip {
@@ -294,6 +296,17 @@ fn produce_raw_data(app: &App) -> (Vec<FinishedTrip>, Vec<CancelledTrip>) {
         };
 
         let (_, waiting, _) = sim.finished_trip_details(*id).unwrap();
+        let cost_after = sim
+            .trip_cost(sim.get_analytics(), *id)
+            .map(|c| c.generalized())
+            .unwrap_or(0.0);
+        let cost_before = if app.has_prebaked().is_some() {
+            sim.trip_cost(app.prebaked(), *id)
+                .map(|c| c.generalized())
+                .unwrap_or(0.0)
+        } else {
+            0.0
+        };
 
         let duration_after = maybe_duration_after.unwrap();
         finished.push(FinishedTrip {
@@ -307,6 +320,8 @@ fn produce_raw_data(app: &App) -> (Vec<FinishedTrip>, Vec<CancelledTrip>) {
             duration_before: duration_before.unwrap(),
             waiting,
             percent_waiting: (100.0 * waiting / duration_after) as usize,
+            cost_after,
+            cost_before,
         });
     }
 
@@ -498,6 +513,28 @@ fn make_table_finished_trips(app: &App) -> Table<App, FinishedTrip, Filters> {
         Box::new(|ctx, _, x| Text::from(x.percent_waiting.to_string()).render(ctx)),
         Col::Sortable(Box::new(|rows| rows.sort_by_key(|x| x.percent_waiting))),
     );
+    table.column(
+        "Generalized cost",
+        Box::new(|ctx, _, x| Text::from(format!("{:.2}", x.cost_after)).render(ctx)),
+        Col::Sortable(Box::new(|rows| {
+            rows.sort_by(|a, b| a.cost_after.partial_cmp(&b.cost_after).unwrap())
+        })),
+    );
+    if app.has_prebaked().is_some() {
+        table.column(
+            "Cost comparison",
+            Box::new(|ctx, _, x| {
+                Text::from(format!("{:+.2}", x.cost_after - x.cost_before)).render(ctx)
+            }),
+            Col::Sortable(Box::new(|rows| {
+                rows.sort_by(|a, b| {
+                    (a.cost_after - a.cost_before)
+                        .partial_cmp(&(b.cost_after - b.cost_before))
+                        .unwrap()
+                })
+            })),
+        );
+    }
 
     table
 }
@@ -253,6 +253,11 @@ fn make_agent_toggles(ctx: &mut EventCtx, app: &App, is_enabled: [bool; 4]) -> V
                 prettyprint_usize(counts.sov_drivers)
             ))
             .secondary(),
+            Line(format!(
+                "Delivery vans and trucks: {}",
+                prettyprint_usize(counts.freight_vehicles)
+            ))
+            .secondary(),
         ]);
         colored_checkbox(
             ctx,
@@ -260,7 +265,7 @@ fn make_agent_toggles(ctx: &mut EventCtx, app: &App, is_enabled: [bool; 4]) -> V
             is_car_enabled,
             app.cs.unzoomed_car,
             "system/assets/meters/car.svg",
-            &prettyprint_usize(counts.sov_drivers),
+            &prettyprint_usize(counts.sov_drivers + counts.freight_vehicles),
             tooltip,
         )
     };
@@ -314,6 +314,9 @@ impl ContextualActions for Actions {
                     {
                         actions.push((Key::E, "edit stop sign".to_string()));
                     }
+                    if app.primary.map.get_i(i).is_yield() && self.gameplay.can_edit_stop_signs() {
+                        actions.push((Key::E, "edit yield".to_string()));
+                    }
                     if app.opts.dev && app.primary.sim.num_recorded_trips().is_none() {
                         actions.push((Key::R, "record traffic here".to_string()));
                     }
@@ -361,7 +364,7 @@ impl ContextualActions for Actions {
                     self.gameplay.clone(),
                 )),
             ]),
-            (ID::Intersection(i), "edit stop sign") => Transition::Multi(vec![
+            (ID::Intersection(i), "edit stop sign" | "edit yield") => Transition::Multi(vec![
                 Transition::Push(EditMode::new_state(ctx, app, self.gameplay.clone())),
                 Transition::Push(StopSignEditor::new_state(
                     ctx,
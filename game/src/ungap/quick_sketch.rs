@@ -328,6 +328,7 @@ fn maybe_add_bike_lanes(
                 lt: LaneType::Biking,
                 dir,
                 width: LaneSpec::typical_lane_widths(LaneType::Biking, &dummy_tags)[0].0,
+                allowed_turns: None,
             };
             if let Some(buffer) = buffer_type {
                 side.insert(
@@ -338,6 +339,7 @@ fn maybe_add_bike_lanes(
                         width: LaneSpec::typical_lane_widths(LaneType::Buffer(buffer), &dummy_tags)
                             [0]
                         .0,
+                        allowed_turns: None,
                     },
                 );
             }
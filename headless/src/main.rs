@@ -8,6 +8,38 @@
 //! it's now 01:01:00.0
 //! > curl http://localhost:1234/data/get-road-thruput
 //! ... huge JSON blob
+//!
+//! The server can run many independent simulations at once. Each session has its own map, edits,
+//! and simulation. Every /sim, /traffic-signals, /data, and /map command takes an optional
+//! `session` parameter; without it, the session created at startup (0) is used.
+//!
+//! > curl http://localhost:1234/sessions/fork?session=0
+//! 1
+//! > curl http://localhost:1234/sim/goto-time?session=1&t=02:00:00
+//! it's now 02:00:00.0
+//! > curl http://localhost:1234/sessions/list
+//! > curl http://localhost:1234/sessions/delete?session=1
+//!
+//! To follow a simulation live, keep /sim/stream-events open while another request calls
+//! /sim/goto-time. Events are sent as newline-delimited JSON. By default, only trip phases,
+//! intersection delays, alerts, and bus arrivals are sent; pass `types=all` or a comma-separated
+//! list of event names to change this. `intersection`, `trip`, `person`, and `route` parameters
+//! only keep events about that one object.
+//!
+//! > curl -N http://localhost:1234/sim/stream-events?types=Alert,TripFinished&intersection=42
+//!
+//! Snapshots keep the state of a session in memory, so experiments can start from the middle of
+//! the day instead of re-simulating from midnight. A snapshot can be restored in the same session,
+//! or forked into a new session, optionally applying different map edits (as PermanentMapEdits in
+//! the POST body) at that moment.
+//!
+//! > curl http://localhost:1234/sim/goto-time?t=08:00:00
+//! > curl http://localhost:1234/sim/snapshot?name=morning
+//! > curl -X POST -d @edits.json http://localhost:1234/sessions/fork?session=0&snapshot=morning
+//! > curl http://localhost:1234/sim/restore?name=morning
+//!
+//! To try out a different signal timing from that moment, change it with
+//! `/traffic-signals/set?live=true`, which updates the running simulation immediately.
 
 #[macro_use]
 extern crate anyhow;
@@ -15,7 +47,7 @@ extern crate anyhow;
 extern crate log;
 
 use std::collections::{BTreeMap, BTreeSet, HashMap};
-use std::sync::RwLock;
+use std::sync::{Arc, Mutex, RwLock};
 
 use anyhow::Result;
 use hyper::{Body, Request, Response, Server, StatusCode};
@@ -29,27 +61,130 @@ use abstutil::{serialize_btreemap, Timer};
 use geom::{Distance, Duration, FindClosest, LonLat, Time};
 use map_model::{
     CompressedMovementID, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map,
-    MovementID, PermanentMapEdits, RoadID, TurnID,
+    MapEdits, MovementID, PermanentMapEdits, RoadID, TransitRouteID, Traversable, TurnID,
 };
 use sim::{
-    AgentID, AgentType, DelayCause, PersonID, Sim, SimFlags, SimOptions, TripID, VehicleType,
+    AgentID, AgentType, AlertLocation, DelayCause, Emissions, Event, PersonID, Sim, SimFlags,
+    SimOptions, TripCost, TripID, VehicleType,
 };
 use synthpop::{ExternalPerson, Scenario, ScenarioModifier, TripMode};
 
 lazy_static::lazy_static! {
-    static ref MAP: RwLock<Map> = RwLock::new(Map::blank());
-    static ref SIM: RwLock<Sim> = RwLock::new(Sim::new(&Map::blank(), SimOptions::new("tmp")));
-    static ref LOAD: RwLock<LoadSim> = RwLock::new({
-        LoadSim {
+    static ref SESSIONS: RwLock<Sessions> = RwLock::new(Sessions {
+        template: LoadSim {
             scenario: abstio::path_scenario(&MapName::seattle("montlake"), "weekday"),
             modifiers: Vec::new(),
             edits: None,
             rng_seed: SimFlags::RNG_SEED,
             opts: SimOptions::default(),
-        }
+        },
+        sessions: BTreeMap::new(),
+        next_id: 0,
     });
 }
 
+/// Every simulation the server is running.
+struct Sessions {
+    /// New sessions use these settings by default. The RNG seed and simulation options always come
+    /// from the command line.
+    template: LoadSim,
+    sessions: BTreeMap<SessionID, Arc<Mutex<Session>>>,
+    next_id: SessionID,
+}
+
+type SessionID = usize;
+
+/// While somebody is streaming events, advance the simulation in steps of this size, so they see
+/// events shortly after they happen.
+const STREAM_STEP: Duration = Duration::const_seconds(60.0);
+
+/// The events streamed when the client doesn't ask for specific types
+const DEFAULT_STREAM_EVENTS: [&str; 4] = [
+    "TripPhaseStarting",
+    "IntersectionDelayMeasured",
+    "Alert",
+    "BusArrivedAtStop",
+];
+
+/// One independent simulation, with its own map edits
+struct Session {
+    map: Map,
+    sim: Sim,
+    load: LoadSim,
+    subscribers: Vec<EventSubscriber>,
+    snapshots: BTreeMap<String, Snapshot>,
+}
+
+/// The state of a session at some moment
+#[derive(Clone)]
+struct Snapshot {
+    sim: Sim,
+    map_name: MapName,
+    edits: MapEdits,
+}
+
+impl Snapshot {
+    fn new(map: &Map, sim: &Sim) -> Snapshot {
+        let mut sim = sim.clone();
+        // Streaming clients are attached to the session, not the snapshot
+        sim.capture_events(false);
+        Snapshot {
+            sim,
+            map_name: map.get_name().clone(),
+            edits: map.get_edits().clone(),
+        }
+    }
+
+    /// Overwrite the map and simulation with this snapshot.
+    fn restore(&self, map: &mut Map, sim: &mut Sim, timer: &mut Timer) -> Result<()> {
+        if map.get_name() != &self.map_name {
+            bail!(
+                "This snapshot is from {}, but {} is loaded now",
+                self.map_name.describe(),
+                map.get_name().describe()
+            );
+        }
+        if map.get_edits() != &self.edits {
+            map.must_apply_edits(self.edits.clone(), timer);
+            map.recalculate_pathfinding_after_edits(timer);
+        }
+        *sim = self.sim.clone();
+        Ok(())
+    }
+}
+
+/// A client following the simulation through /sim/stream-events
+struct EventSubscriber {
+    filter: EventFilter,
+    // Dropping this ends the response
+    tx: futures_channel::mpsc::UnboundedSender<Result<String, std::io::Error>>,
+}
+
+struct EventFilter {
+    /// None means every type of event
+    types: Option<BTreeSet<String>>,
+    intersection: Option<IntersectionID>,
+    trip: Option<TripID>,
+    person: Option<PersonID>,
+    route: Option<TransitRouteID>,
+}
+
+impl Sessions {
+    fn insert(&mut self, session: Session) -> SessionID {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sessions.insert(id, Arc::new(Mutex::new(session)));
+        id
+    }
+
+    fn get(&self, id: SessionID) -> Result<Arc<Mutex<Session>>> {
+        self.sessions
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| anyhow!("no session {}", id))
+    }
+}
+
 #[derive(StructOpt)]
 #[structopt(
     name = "headless",
@@ -74,13 +209,19 @@ async fn main() {
     let args = Args::from_args();
 
     {
-        let mut load = LOAD.write().unwrap();
-        load.rng_seed = args.rng_seed;
-        load.opts = args.opts;
+        let mut sessions = SESSIONS.write().unwrap();
+        sessions.template.rng_seed = args.rng_seed;
+        sessions.template.opts = args.opts;
 
+        let load = sessions.template.clone();
         let (map, sim) = load.setup(&mut Timer::new("setup headless"));
-        *MAP.write().unwrap() = map;
-        *SIM.write().unwrap() = sim;
+        sessions.insert(Session {
+            map,
+            sim,
+            load,
+            subscribers: Vec::new(),
+            snapshots: BTreeMap::new(),
+        });
     }
 
     let addr = std::net::SocketAddr::from(([127, 0, 0, 1], args.port));
@@ -104,27 +245,319 @@ async fn serve_req(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
             .collect();
     let body = hyper::body::to_bytes(req).await?.to_vec();
     info!("Handling {}", path);
-    Ok(
-        match handle_command(
-            &path,
-            &params,
-            &body,
-            &mut SIM.write().unwrap(),
-            &mut MAP.write().unwrap(),
-            &mut LOAD.write().unwrap(),
-        ) {
-            Ok(resp) => Response::new(Body::from(resp)),
+    if path == "/sim/stream-events" {
+        // Unlike everything else, this response stays open. Subscribing waits for the session,
+        // which may be busy simulating, so that also happens on a blocking thread.
+        let result = tokio::task::spawn_blocking(move || subscribe(&params))
+            .await
+            .unwrap_or_else(|err| Err(anyhow!("{}", err)));
+        return Ok(match result {
+            Ok(body) => Response::builder()
+                .header("Content-Type", "application/x-ndjson")
+                .body(body)
+                .unwrap(),
+            Err(err) => bad_request(&path, err),
+        });
+    }
+    // Loading and simulating take a while and hold locks, so keep them off the async runtime's
+    // threads. Otherwise a few slow requests would stall everything else, including streams.
+    let result = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || handle_req(&path, &params, &body))
+            .await
+            .unwrap_or_else(|err| Err(anyhow!("{}", err)))
+    };
+    Ok(match result {
+        Ok(resp) => Response::new(Body::from(resp)),
+        Err(err) => bad_request(&path, err),
+    })
+}
+
+/// This blocks, so it shouldn't run on the async runtime.
+fn handle_req(path: &str, params: &HashMap<String, String>, body: &[u8]) -> Result<String> {
+    if path.starts_with("/sessions/") {
+        handle_session_command(path, params, body)
+    } else {
+        // Only lock this one session, so that requests for others can proceed in parallel
+        let session = get_session(params)?;
+        let mut session = session.lock().unwrap();
+        let Session {
+            map,
+            sim,
+            load,
+            subscribers,
+            snapshots,
+        } = &mut *session;
+        let result = handle_command(path, params, body, sim, map, load, subscribers, snapshots);
+        // Flush anything left over, and keep capturing events after a reset
+        publish_events(sim, subscribers);
+        result
+    }
+}
+
+fn bad_request(path: &str, err: anyhow::Error) -> Response<Body> {
+    error!("{}: {}", path, err);
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(Body::from(format!("Bad command {}: {}", path, err)))
+        .unwrap()
+}
+
+fn subscribe(params: &HashMap<String, String>) -> Result<Body> {
+    let filter = EventFilter::parse(params)?;
+    let session = get_session(params)?;
+    let mut session = session.lock().unwrap();
+    let (tx, rx) = futures_channel::mpsc::unbounded();
+    session.sim.capture_events(true);
+    session.subscribers.push(EventSubscriber { filter, tx });
+    Ok(Body::wrap_stream(rx))
+}
+
+/// Send everything that's happened since the last call to the clients who want it.
+fn publish_events(sim: &mut Sim, subscribers: &mut Vec<EventSubscriber>) {
+    for (time, ev) in sim.drain_captured_events() {
+        let json = match serde_json::to_value(&ev) {
+            Ok(json) => json,
             Err(err) => {
-                error!("{}: {}", path, err);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from(format!("Bad command {}: {}", path, err)))
+                warn!("Couldn't serialize {:?}: {}", ev, err);
+                continue;
+            }
+        };
+        // Every variant carries data, so serde represents it as a map with one key
+        let name = json
+            .as_object()
+            .and_then(|obj| obj.keys().next().cloned())
+            .unwrap_or_default();
+        let mut line = None;
+        for sub in subscribers.iter() {
+            if !sub.filter.matches(&name, &ev) {
+                continue;
+            }
+            if line.is_none() {
+                line = Some(format!(
+                    "{}\n",
+                    serde_json::to_string(&StreamedEvent {
+                        time,
+                        event: json.clone(),
+                    })
                     .unwrap()
+                ));
             }
-        },
-    )
+            // If the client went away, this fails, and the subscriber is cleaned up below
+            let _ = sub.tx.unbounded_send(Ok(line.clone().unwrap()));
+        }
+    }
+
+    subscribers.retain(|sub| !sub.tx.is_closed());
+    sim.capture_events(!subscribers.is_empty());
+}
+
+impl EventFilter {
+    fn parse(params: &HashMap<String, String>) -> Result<EventFilter> {
+        let types = match params.get("types").map(|x| x.as_str()) {
+            Some("all") => None,
+            Some(list) => Some(list.split(',').map(|x| x.to_string()).collect()),
+            None => Some(
+                DEFAULT_STREAM_EVENTS
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect(),
+            ),
+        };
+        let id = |key: &str| -> Result<Option<usize>> {
+            match params.get(key) {
+                Some(x) => Ok(Some(x.parse::<usize>()?)),
+                None => Ok(None),
+            }
+        };
+        Ok(EventFilter {
+            types,
+            intersection: id("intersection")?.map(IntersectionID),
+            trip: id("trip")?.map(TripID),
+            person: id("person")?.map(PersonID),
+            route: id("route")?.map(TransitRouteID),
+        })
+    }
+
+    fn matches(&self, name: &str, ev: &Event) -> bool {
+        if let Some(ref types) = self.types {
+            if !types.contains(name) {
+                return false;
+            }
+        }
+
+        let mut intersection = None;
+        let mut trip = None;
+        let mut person = None;
+        let mut route = None;
+        match ev {
+            Event::BusArrivedAtStop(_, r, _, _) | Event::BusDepartedFromStop(_, r, _, _, _) => {
+                route = Some(*r);
+            }
+            Event::PassengerBoardsTransit(p, _, r, _, _)
+            | Event::PassengerAlightsTransit(p, _, r, _) => {
+                person = Some(*p);
+                route = Some(*r);
+            }
+            Event::RideHailPickup(t, _, _)
+            | Event::ProblemEncountered(t, _)
+            | Event::TripCancelled(t, _)
+            | Event::TripFinished { trip: t, .. } => {
+                trip = Some(*t);
+            }
+            Event::PersonEntersBuilding(p, _) | Event::PersonLeavesBuilding(p, _) => {
+                person = Some(*p);
+            }
+            Event::PersonLeavesMap(p, _, i) | Event::PersonEntersMap(p, _, i) => {
+                person = Some(*p);
+                intersection = Some(*i);
+            }
+            Event::AgentEntersTraversable(_, t, on, _) => {
+                trip = *t;
+                if let Traversable::Turn(turn) = on {
+                    intersection = Some(turn.parent);
+                }
+            }
+            Event::IntersectionDelayMeasured(t, turn, _, _) => {
+                trip = Some(*t);
+                intersection = Some(turn.parent);
+            }
+            Event::TripPhaseStarting(t, p, _, _) => {
+                trip = Some(*t);
+                person = Some(*p);
+            }
+            Event::Alert(AlertLocation::Intersection(i), _) => {
+                intersection = Some(*i);
+            }
+            Event::Alert(AlertLocation::Person(p), _) => {
+                person = Some(*p);
+            }
+            _ => {}
+        }
+
+        (self.intersection.is_none() || self.intersection == intersection)
+            && (self.trip.is_none() || self.trip == trip)
+            && (self.person.is_none() || self.person == person)
+            && (self.route.is_none() || self.route == route)
+    }
+}
+
+fn get_session(params: &HashMap<String, String>) -> Result<Arc<Mutex<Session>>> {
+    let id = match params.get("session") {
+        Some(id) => id.parse::<SessionID>()?,
+        None => 0,
+    };
+    SESSIONS.read().unwrap().get(id)
+}
+
+fn handle_session_command(
+    path: &str,
+    params: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<String> {
+    let get_id = || -> Result<SessionID> {
+        Ok(params
+            .get("session")
+            .ok_or_else(|| anyhow!("missing GET parameter session"))?
+            .parse::<SessionID>()?)
+    };
+
+    match path {
+        "/sessions/list" => {
+            // Don't hold onto the global lock while waiting for busy sessions
+            let sessions: Vec<(SessionID, Arc<Mutex<Session>>)> = SESSIONS
+                .read()
+                .unwrap()
+                .sessions
+                .iter()
+                .map(|(id, session)| (*id, session.clone()))
+                .collect();
+            let mut list = Vec::new();
+            for (id, session) in sessions {
+                let session = session.lock().unwrap();
+                list.push(SessionInfo {
+                    id,
+                    map: session.map.get_name().path(),
+                    scenario: session.load.scenario.clone(),
+                    edits: session.map.get_edits().edits_name.clone(),
+                    time: session.sim.time(),
+                });
+            }
+            Ok(abstutil::to_json(&list))
+        }
+        "/sessions/create" => {
+            let mut load = SESSIONS.read().unwrap().template.clone();
+            if !body.is_empty() {
+                let args: LoadSim = abstutil::from_json(body)?;
+                load.scenario = args.scenario;
+                load.modifiers = args.modifiers;
+                load.edits = args.edits;
+            }
+            // Don't block other sessions while loading
+            let (map, sim) = load.setup(&mut Timer::new("create session"));
+            let id = SESSIONS.write().unwrap().insert(Session {
+                map,
+                sim,
+                load,
+                subscribers: Vec::new(),
+                snapshots: BTreeMap::new(),
+            });
+            Ok(id.to_string())
+        }
+        "/sessions/fork" => {
+            let session = SESSIONS.read().unwrap().get(get_id()?)?;
+            let mut copy = {
+                let session = session.lock().unwrap();
+                let mut copy = Session {
+                    map: session.map.clone(),
+                    sim: session.sim.clone(),
+                    load: session.load.clone(),
+                    subscribers: Vec::new(),
+                    // Share snapshots, so the fork can also rewind
+                    snapshots: session.snapshots.clone(),
+                };
+                if let Some(name) = params.get("snapshot") {
+                    session
+                        .snapshots
+                        .get(name)
+                        .ok_or_else(|| anyhow!("no snapshot {}", name))?
+                        .restore(&mut copy.map, &mut copy.sim, &mut Timer::throwaway())?;
+                }
+                copy
+            };
+            // Nobody's following the copy yet
+            copy.sim.capture_events(false);
+
+            // Optionally diverge from this moment with different edits
+            if !body.is_empty() {
+                let perma: PermanentMapEdits = abstutil::from_json(body)?;
+                let edits = perma.clone().into_edits(&copy.map)?;
+                apply_edits_live(&mut copy.map, &mut copy.sim, edits);
+
+                // The copy's map was edited in place, starting from whatever edits the session
+                // had. Resetting the fork later loads the map fresh with these edits, so make sure
+                // that gives the same map.
+                let mut timer = Timer::new("check forked edits");
+                let fresh = load_map(copy.map.get_name(), Some(perma.clone()), &mut timer)?;
+                check_same_map(&copy.map, &fresh)?;
+                copy.load.edits = Some(perma);
+            }
+
+            let id = SESSIONS.write().unwrap().insert(copy);
+            Ok(id.to_string())
+        }
+        "/sessions/delete" => {
+            let id = get_id()?;
+            if SESSIONS.write().unwrap().sessions.remove(&id).is_none() {
+                bail!("no session {}", id);
+            }
+            Ok(format!("session {} deleted", id))
+        }
+        _ => Err(anyhow!("Unknown command")),
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_command(
     path: &str,
     params: &HashMap<String, String>,
@@ -132,6 +565,8 @@ fn handle_command(
     sim: &mut Sim,
     map: &mut Map,
     load: &mut LoadSim,
+    subscribers: &mut Vec<EventSubscriber>,
+    snapshots: &mut BTreeMap<String, Snapshot>,
 ) -> Result<String> {
     let get = |key: &str| {
         params
@@ -173,11 +608,56 @@ fn handle_command(
             if t <= sim.time() {
                 bail!("{} is in the past. call /sim/reset first?", t)
             } else {
-                let dt = t - sim.time();
-                sim.timed_step(map, dt, &mut None, &mut Timer::new("goto-time"));
+                if subscribers.is_empty() {
+                    let dt = t - sim.time();
+                    sim.timed_step(map, dt, &mut None, &mut Timer::new("goto-time"));
+                } else {
+                    while sim.time() < t {
+                        let dt = STREAM_STEP.min(t - sim.time());
+                        sim.timed_step(map, dt, &mut None, &mut Timer::throwaway());
+                        publish_events(sim, subscribers);
+                    }
+                }
                 Ok(format!("it's now {}", t))
             }
         }
+        "/sim/snapshot" => {
+            let name = params
+                .get("name")
+                .cloned()
+                .unwrap_or_else(|| sim.time().to_string());
+            snapshots.insert(name.clone(), Snapshot::new(map, sim));
+            Ok(format!("saved snapshot {} at {}", name, sim.time()))
+        }
+        "/sim/list-snapshots" => Ok(abstutil::to_json(
+            &snapshots
+                .iter()
+                .map(|(name, snapshot)| SnapshotInfo {
+                    name: name.clone(),
+                    time: snapshot.sim.time(),
+                    edits: snapshot.edits.edits_name.clone(),
+                })
+                .collect::<Vec<_>>(),
+        )),
+        "/sim/restore" => {
+            let name = get("name")?;
+            snapshots
+                .get(name)
+                .ok_or_else(|| anyhow!("no snapshot {}", name))?
+                .restore(map, sim, &mut Timer::new("restore snapshot"))?;
+            Ok(format!(
+                "restored snapshot {}; it's now {}",
+                name,
+                sim.time()
+            ))
+        }
+        "/sim/delete-snapshot" => {
+            let name = get("name")?;
+            if snapshots.remove(name).is_none() {
+                bail!("no snapshot {}", name);
+            }
+            Ok(format!("deleted snapshot {}", name))
+        }
         "/sim/new-person" => {
             let input: ExternalPerson = abstutil::from_json(body)?;
             for trip in &input.trips {
@@ -220,8 +700,18 @@ fn handle_command(
                 old: map.get_i_edit(id),
                 new: EditIntersection::TrafficSignal(ts.export(map)),
             });
-            map.must_apply_edits(edits, &mut Timer::throwaway());
-            map.recalculate_pathfinding_after_edits(&mut Timer::throwaway());
+            // By default, the change only takes effect after /sim/reset. Pass live=true to also
+            // switch the running simulation over to the new timing right away.
+            let live = match params.get("live") {
+                Some(x) => x.parse::<bool>()?,
+                None => false,
+            };
+            if live {
+                apply_edits_live(map, sim, edits);
+            } else {
+                map.must_apply_edits(edits, &mut Timer::throwaway());
+                map.recalculate_pathfinding_after_edits(&mut Timer::throwaway());
+            }
 
             Ok(format!("{} has been updated", id))
         }
@@ -286,6 +776,7 @@ fn handle_command(
                 all_state.insert(
                     i.id,
                     TrafficSignalState {
+                        current_plan_idx: sim.current_signal_plan(i.id),
                         current_stage_idx,
                         remaining_time,
                         accepted: sim
@@ -314,6 +805,7 @@ fn handle_command(
                     duration: *maybe_duration,
                     distance_crossed,
                     mode: *mode,
+                    cost: sim.trip_cost(sim.get_analytics(), *id),
                 });
             }
             Ok(abstutil::to_json(&trips))
@@ -354,6 +846,44 @@ fn handle_command(
                 })
                 .collect(),
         })),
+        "/data/get-ride-hail-stats" => {
+            let (busy_vehicles, fleet_size, waiting_requests) = sim.get_ride_hail_fleet_status();
+            let analytics = sim.get_analytics();
+            let mut stats = RideHailStats {
+                fleet_size,
+                busy_vehicles,
+                waiting_requests,
+                wait_times: analytics
+                    .ride_hail_waits
+                    .iter()
+                    .map(|(_, trip, wait)| (*trip, *wait))
+                    .collect(),
+                occupied_distance: Distance::ZERO,
+                deadhead_distance: Distance::ZERO,
+            };
+            for (_, _, dist, with_passenger) in &analytics.ride_hail_legs {
+                if *with_passenger {
+                    stats.occupied_distance += *dist;
+                } else {
+                    stats.deadhead_distance += *dist;
+                }
+            }
+            Ok(abstutil::to_json(&stats))
+        }
+        "/data/get-emissions" => {
+            let analytics = sim.get_analytics();
+            let mut total = Emissions::default();
+            for x in analytics.vehicle_type_emissions.values() {
+                total += *x;
+            }
+            Ok(abstutil::to_json(&EmissionsSummary {
+                total,
+                per_vehicle_type: analytics.vehicle_type_emissions.clone(),
+                per_road: analytics.road_emissions.clone(),
+                per_intersection: analytics.intersection_emissions.clone(),
+                per_trip: analytics.trip_emissions.clone(),
+            }))
+        }
         "/data/trip-time-lower-bound" => {
             let id = TripID(get("id")?.parse::<usize>()?);
             let duration = sim.get_trip_time_lower_bound(map, id)?;
@@ -409,6 +939,16 @@ fn handle_command(
     }
 }
 
+/// Change the map in the middle of a simulation. Returns the number of (trips cancelled, parked
+/// cars displaced).
+fn apply_edits_live(map: &mut Map, sim: &mut Sim, edits: MapEdits) -> (usize, usize) {
+    let mut timer = Timer::new("apply edits live");
+    map.must_apply_edits(edits, &mut timer);
+    map.recalculate_pathfinding_after_edits(&mut timer);
+    sim.handle_live_edited_traffic_signals(map);
+    sim.handle_live_edits(map, &mut timer)
+}
+
 // TODO I think specifying the API with protobufs or similar will be a better idea.
 
 #[derive(Serialize)]
@@ -418,6 +958,8 @@ struct FinishedTrip {
     duration: Option<Duration>,
     distance_crossed: Distance,
     mode: TripMode,
+    /// None if cancelled
+    cost: Option<TripCost>,
 }
 
 #[derive(Serialize)]
@@ -463,6 +1005,31 @@ struct AgentPosition {
     distance_crossed: Distance,
 }
 
+#[derive(Serialize)]
+struct RideHailStats {
+    fleet_size: usize,
+    /// Vehicles dispatched to a pickup, carrying a passenger, or returning to their depot
+    busy_vehicles: usize,
+    /// Requests that haven't had a vehicle dispatched yet
+    waiting_requests: usize,
+    /// For every pickup so far, how long the passenger waited after requesting the ride
+    wait_times: Vec<(TripID, Duration)>,
+    /// Total distance driven with a passenger on board
+    occupied_distance: Distance,
+    /// Total distance driven without a passenger, to reach a pickup or return to a depot
+    deadhead_distance: Distance,
+}
+
+#[derive(Serialize)]
+struct EmissionsSummary {
+    total: Emissions,
+    per_vehicle_type: BTreeMap<VehicleType, Emissions>,
+    per_road: BTreeMap<RoadID, Emissions>,
+    /// From vehicles while turning
+    per_intersection: BTreeMap<IntersectionID, Emissions>,
+    per_trip: BTreeMap<TripID, Emissions>,
+}
+
 #[derive(Serialize)]
 struct RoadThroughput {
     // (road, agent type, hour since midnight, throughput for that one hour period)
@@ -471,6 +1038,8 @@ struct RoadThroughput {
 
 #[derive(Serialize)]
 struct TrafficSignalState {
+    // Which time-of-day plan is running; current_stage_idx refers to its stages
+    current_plan_idx: usize,
     current_stage_idx: usize,
     remaining_time: Duration,
     accepted: BTreeSet<AgentID>,
@@ -487,7 +1056,29 @@ struct BlockedByGraph {
     blocked_by: BTreeMap<AgentID, (Duration, DelayCause, Option<TripID>, Option<PersonID>)>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize)]
+struct StreamedEvent {
+    time: Time,
+    event: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct SnapshotInfo {
+    name: String,
+    time: Time,
+    edits: String,
+}
+
+#[derive(Serialize)]
+struct SessionInfo {
+    id: SessionID,
+    map: String,
+    scenario: String,
+    edits: String,
+    time: Time,
+}
+
+#[derive(Clone, Deserialize)]
 struct LoadSim {
     scenario: String,
     modifiers: Vec<ScenarioModifier>,
@@ -503,12 +1094,7 @@ impl LoadSim {
     fn setup(&self, timer: &mut Timer) -> (Map, Sim) {
         let mut scenario: Scenario = abstio::must_read_object(self.scenario.clone(), timer);
 
-        let mut map = Map::load_synchronously(scenario.map_name.path(), timer);
-        if let Some(perma) = self.edits.clone() {
-            let edits = perma.into_edits(&map).unwrap();
-            map.must_apply_edits(edits, timer);
-            map.recalculate_pathfinding_after_edits(timer);
-        }
+        let map = load_map(&scenario.map_name, self.edits.clone(), timer).unwrap();
 
         for m in &self.modifiers {
             scenario = m.apply(&map, scenario);
@@ -522,6 +1108,40 @@ impl LoadSim {
     }
 }
 
+fn load_map(name: &MapName, edits: Option<PermanentMapEdits>, timer: &mut Timer) -> Result<Map> {
+    let mut map = Map::load_synchronously(name.path(), timer);
+    if let Some(perma) = edits {
+        let edits = perma.into_edits(&map)?;
+        map.must_apply_edits(edits, timer);
+        map.recalculate_pathfinding_after_edits(timer);
+    }
+    Ok(map)
+}
+
+/// Fails if any road or intersection differs between the two maps, as far as edits are concerned.
+fn check_same_map(map1: &Map, map2: &Map) -> Result<()> {
+    if map1.all_roads().len() != map2.all_roads().len()
+        || map1.all_intersections().len() != map2.all_intersections().len()
+    {
+        bail!("The maps have different numbers of roads or intersections");
+    }
+    for r in map1.all_roads() {
+        if map1.get_r_edit(r.id) != map2.get_r_edit(r.id) {
+            bail!("{} differs from loading the map with the same edits", r.id);
+        }
+    }
+    for i in map1.all_intersections() {
+        let other = map2.get_i(i.id);
+        if i.intersection_type != other.intersection_type
+            || i.turns != other.turns
+            || (!i.is_border() && map1.get_i_edit(i.id) != map2.get_i_edit(i.id))
+        {
+            bail!("{} differs from loading the map with the same edits", i.id);
+        }
+    }
+    Ok(())
+}
+
 fn export_geometry(map: &Map, i: IntersectionID) -> geojson::GeoJson {
     use geojson::{Feature, FeatureCollection, GeoJson};
 
@@ -614,3 +1234,274 @@ fn export_all_geometry(map: &Map) -> geojson::GeoJson {
         foreign_members: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use geom::Speed;
+    use synthpop::{IndividTrip, PersonSpec, TripEndpoint, TripPurpose};
+
+    use super::*;
+
+    /// Start a session on a small test map, with cars crossing it between every pair of borders.
+    fn new_session() -> SessionID {
+        let mut timer = Timer::throwaway();
+        let path = abstio::path("../tests/input/lane_selection.osm");
+        let raw = convert_osm::convert(
+            path.clone(),
+            MapName::new("zz", "oneshot", &abstutil::basename(&path)),
+            None,
+            convert_osm::Options {
+                map_config: map_model::MapConfig {
+                    driving_side: map_model::DrivingSide::Right,
+                    bikes_can_use_bus_lanes: true,
+                    inferred_sidewalks: true,
+                    street_parking_spot_length: Distance::meters(8.0),
+                    turn_on_red: false,
+                },
+                onstreet_parking: convert_osm::OnstreetParking::JustOSM,
+                public_offstreet_parking: convert_osm::PublicOffstreetParking::None,
+                private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(0),
+                include_railroads: true,
+                extra_buildings: None,
+                skip_local_roads: false,
+                filter_crosswalks: false,
+                gtfs_url: None,
+                gtfs_service_date: None,
+                elevation: convert_osm::ElevationBackend::Skip,
+                osm_changes: Vec::new(),
+            },
+            &mut timer,
+        );
+        let map = Map::create_from_raw(raw, map_model::RawToMapOptions::default(), &mut timer);
+
+        let borders: Vec<IntersectionID> = map
+            .all_intersections()
+            .iter()
+            .filter(|i| i.is_border())
+            .map(|i| i.id)
+            .collect();
+        let mut scenario = Scenario::empty(&map, "headless_test");
+        let n = borders.len();
+        for idx in 0..100 {
+            let from = idx % n;
+            let to = (from + 1 + (idx / n) % (n - 1)) % n;
+            scenario.people.push(PersonSpec {
+                orig_id: None,
+                trips: vec![IndividTrip::new(
+                    Time::START_OF_DAY + Duration::seconds(5.0 * idx as f64),
+                    TripPurpose::Shopping,
+                    TripEndpoint::Border(borders[from]),
+                    TripEndpoint::Border(borders[to]),
+                    TripMode::Drive,
+                )],
+            });
+        }
+
+        let mut opts = SimOptions::new("headless_test");
+        opts.alerts = sim::AlertHandler::Silence;
+        let mut sim = Sim::new(&map, opts);
+        let mut rng = XorShiftRng::seed_from_u64(SimFlags::RNG_SEED);
+        sim.instantiate(&scenario, &map, &mut rng, &mut timer);
+
+        let load = SESSIONS.read().unwrap().template.clone();
+        SESSIONS.write().unwrap().insert(Session {
+            map,
+            sim,
+            load,
+            subscribers: Vec::new(),
+            snapshots: BTreeMap::new(),
+        })
+    }
+
+    fn request(path: &str, params: Vec<(&str, String)>) -> Result<String> {
+        let params = params
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        handle_req(path, &params, &[])
+    }
+
+    fn finished_trips(id: SessionID) -> usize {
+        let session = SESSIONS.read().unwrap().get(id).unwrap();
+        let session = session.lock().unwrap();
+        session.sim.get_analytics().finished_trips.len()
+    }
+
+    #[test]
+    fn test_independent_sessions() {
+        let id1 = new_session();
+        let id2 = new_session();
+
+        request(
+            "/sim/goto-time",
+            vec![("session", id1.to_string()), ("t", "00:10:00".to_string())],
+        )
+        .unwrap();
+        assert_eq!(
+            request("/sim/get-time", vec![("session", id1.to_string())]).unwrap(),
+            "00:10:00.0"
+        );
+        // The other session hasn't moved
+        assert_eq!(
+            request("/sim/get-time", vec![("session", id2.to_string())]).unwrap(),
+            "00:00:00.0"
+        );
+        assert!(finished_trips(id1) > 0);
+        assert_eq!(finished_trips(id2), 0);
+
+        // Stepping the second one to the same time gets the same results
+        request(
+            "/sim/goto-time",
+            vec![("session", id2.to_string()), ("t", "00:10:00".to_string())],
+        )
+        .unwrap();
+        assert_eq!(finished_trips(id1), finished_trips(id2));
+
+        for id in [id1, id2] {
+            request("/sessions/delete", vec![("session", id.to_string())]).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stream_events() {
+        let streamed = new_session();
+        let expected = new_session();
+
+        let body = subscribe(
+            &vec![
+                ("session".to_string(), streamed.to_string()),
+                ("types".to_string(), "all".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+        )
+        .unwrap();
+        request(
+            "/sim/goto-time",
+            vec![
+                ("session", streamed.to_string()),
+                ("t", "00:05:00".to_string()),
+            ],
+        )
+        .unwrap();
+        // Deleting the session closes the stream
+        request("/sessions/delete", vec![("session", streamed.to_string())]).unwrap();
+        let streamed = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(hyper::body::to_bytes(body))
+            .unwrap();
+        let streamed = String::from_utf8(streamed.to_vec()).unwrap();
+
+        // Simulate the same window without streaming, in one step
+        let expected = {
+            let session = SESSIONS.read().unwrap().get(expected).unwrap();
+            let mut session = session.lock().unwrap();
+            let Session { map, sim, .. } = &mut *session;
+            sim.capture_events(true);
+            sim.timed_step(
+                map,
+                Duration::minutes(5),
+                &mut None,
+                &mut Timer::throwaway(),
+            );
+            let mut lines = String::new();
+            for (time, ev) in sim.drain_captured_events() {
+                lines.push_str(
+                    &serde_json::to_string(&StreamedEvent {
+                        time,
+                        event: serde_json::to_value(&ev).unwrap(),
+                    })
+                    .unwrap(),
+                );
+                lines.push('\n');
+            }
+            lines
+        };
+
+        assert!(!expected.is_empty());
+        assert_eq!(streamed, expected);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let id = new_session();
+        let uninterrupted = new_session();
+        let goto = |id: SessionID, t: &str| {
+            request(
+                "/sim/goto-time",
+                vec![("session", id.to_string()), ("t", t.to_string())],
+            )
+            .unwrap();
+        };
+        // The order of agents doesn't matter
+        let positions = |id: SessionID| {
+            let json = request(
+                "/data/get-agent-positions",
+                vec![("session", id.to_string())],
+            )
+            .unwrap();
+            let mut agents: Vec<String> = serde_json::from_str::<serde_json::Value>(&json).unwrap()
+                ["agents"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|a| a.to_string())
+                .collect();
+            agents.sort();
+            agents
+        };
+
+        goto(id, "00:03:00");
+        request(
+            "/sim/snapshot",
+            vec![("session", id.to_string()), ("name", "a".to_string())],
+        )
+        .unwrap();
+        goto(id, "00:06:00");
+        let first_try = positions(id);
+        request(
+            "/sim/restore",
+            vec![("session", id.to_string()), ("name", "a".to_string())],
+        )
+        .unwrap();
+        assert_eq!(
+            request("/sim/get-time", vec![("session", id.to_string())]).unwrap(),
+            "00:03:00.0"
+        );
+        goto(id, "00:06:00");
+
+        goto(uninterrupted, "00:06:00");
+        assert_eq!(positions(id), positions(uninterrupted));
+        assert_eq!(first_try, positions(uninterrupted));
+        assert_eq!(finished_trips(id), finished_trips(uninterrupted));
+    }
+
+    #[test]
+    fn test_edits_in_place_match_fresh_map() {
+        let id = new_session();
+        let session = SESSIONS.read().unwrap().get(id).unwrap();
+        let original = session.lock().unwrap().map.clone();
+        let r1 = original.all_roads()[0].id;
+        let r2 = original.all_roads()[1].id;
+        let mut timer = Timer::throwaway();
+
+        // Edit one road, then switch to a different set of edits touching another
+        let mut live = original.clone();
+        let mut edits = live.get_edits().clone();
+        edits.commands.push(live.edit_road_cmd(r1, |new| {
+            new.speed_limit = Speed::miles_per_hour(5.0);
+        }));
+        live.must_apply_edits(edits, &mut timer);
+
+        let mut edits = original.get_edits().clone();
+        edits.commands.push(original.edit_road_cmd(r2, |new| {
+            new.speed_limit = Speed::miles_per_hour(10.0);
+        }));
+        live.must_apply_edits(edits.clone(), &mut timer);
+
+        let mut fresh = original.clone();
+        fresh.must_apply_edits(edits, &mut timer);
+        check_same_map(&live, &fresh).unwrap();
+        assert!(check_same_map(&live, &original).is_err());
+    }
+}
@@ -3,19 +3,25 @@ use serde::Deserialize;
 #[derive(Deserialize)]
 #[serde(default)]
 pub struct ImporterConfiguration {
-    pub osmconvert: String,
     pub unzip: String,
     pub gunzip: String,
     pub gunzip_args: String,
+    /// Where to look up elevation data. Defaults to running Docker; set to "LocalRasters" to read
+    /// DEM files from data/input/shared/elevation instead.
+    pub elevation: convert_osm::ElevationBackend,
+    /// If set, import GTFS schedules for this date, in YYYYMMDD format. Otherwise, transit
+    /// vehicles just run every 30 minutes.
+    pub gtfs_service_date: Option<String>,
 }
 
 impl Default for ImporterConfiguration {
     fn default() -> ImporterConfiguration {
         ImporterConfiguration {
-            osmconvert: String::from("osmconvert"),
             unzip: String::from("unzip"),
             gunzip: String::from("gunzip"),
             gunzip_args: String::from(""),
+            elevation: convert_osm::ElevationBackend::default(),
+            gtfs_service_date: None,
         }
     }
 }
@@ -14,14 +14,17 @@ use structopt::StructOpt;
 use abstio::{CityName, MapName};
 use abstutil::Timer;
 use geom::Distance;
+use map_model::raw::RawMap;
 use map_model::RawToMapOptions;
 
 use self::configuration::{load_configuration, ImporterConfiguration};
+use self::osm_changes::ChangeReport;
 pub use self::pick_geofabrik::pick_geofabrik;
 
 mod berlin;
 mod configuration;
 mod map_config;
+mod osm_changes;
 mod pick_geofabrik;
 mod seattle;
 mod soundcast;
@@ -45,6 +48,7 @@ pub async fn regenerate_everything(shard_num: usize, num_shards: usize) {
             scenario: false,
             city_overview: false,
             only_map: None,
+            osm_changes: Vec::new(),
             opts: RawToMapOptions::default(),
         };
         // Only some maps run extra tasks
@@ -125,6 +129,9 @@ pub async fn oneshot(
             skip_local_roads: false,
             filter_crosswalks,
             gtfs_url: None,
+            gtfs_service_date: None,
+            elevation: convert_osm::ElevationBackend::Docker,
+            osm_changes: Vec::new(),
         },
         &mut timer,
     );
@@ -168,6 +175,13 @@ pub struct Job {
     /// importer/config/$city/.
     #[structopt()]
     pub only_map: Option<String>,
+    /// With --raw, apply these OSM change files (.osc) on top of the downloaded OSM data, in
+    /// order. A report of the roads, intersections, and buildings that changed is written to
+    /// data/input/$city/osm_changes/. With --map, saved edits are also checked against the new
+    /// map, and if the changes only touch road tags and intersection controls, the existing map is
+    /// updated in place instead of being rebuilt.
+    #[structopt(long)]
+    pub osm_changes: Vec<String>,
 
     #[structopt(flatten)]
     pub opts: RawToMapOptions,
@@ -219,10 +233,26 @@ impl Job {
 
         for name in names {
             timer.start(name.describe());
+            let mut change_report = None;
+            let mut raw_before_changes = None;
             if self.osm_to_raw
                 && (!built_raw_huge_seattle || name != MapName::seattle("huge_seattle"))
             {
-                let raw = utils::osm_to_raw(name.clone(), timer, &config).await;
+                // Remember the previous RawMap, to report what the changes affect
+                let before = if self.osm_changes.is_empty() {
+                    None
+                } else {
+                    abstio::maybe_read_binary::<RawMap>(abstio::path_raw_map(&name), timer).ok()
+                };
+                let raw = utils::osm_to_raw(name.clone(), timer, &config, &self.osm_changes).await;
+                if !self.osm_changes.is_empty() {
+                    change_report = Some(ChangeReport::new(
+                        before.as_ref(),
+                        &raw,
+                        self.osm_changes.clone(),
+                    ));
+                }
+                raw_before_changes = before;
 
                 // The collision data will only cover one part of London, since we don't have a
                 // region-wide map there yet
@@ -239,6 +269,13 @@ impl Job {
                 let mut map = if built_map_huge_seattle && name == MapName::seattle("huge_seattle")
                 {
                     map_model::Map::load_synchronously(name.path(), timer)
+                } else if let Some(map) = raw_before_changes
+                    .and_then(|before| utils::update_map(&name, before, &self.opts, timer))
+                {
+                    if let Some(ref mut report) = change_report {
+                        report.updated_in_place = true;
+                    }
+                    map
                 } else {
                     utils::raw_to_map(&name, self.opts.clone(), timer)
                 };
@@ -256,6 +293,10 @@ impl Job {
                     ));
                 }
 
+                if let Some(ref mut report) = change_report {
+                    report.revalidate_edits(&map, timer);
+                }
+
                 Some(map)
             } else if self.scenario {
                 Some(map_model::Map::load_synchronously(name.path(), timer))
@@ -263,6 +304,11 @@ impl Job {
                 None
             };
 
+            if let Some(report) = change_report {
+                report.print_summary();
+                report.save();
+            }
+
             if self.scenario {
                 if self.city == CityName::seattle() {
                     timer.start(format!("scenario for {}", name.describe()));
@@ -80,5 +80,9 @@ pub fn config_for_map(name: &MapName) -> convert_osm::Options {
         } else {
             None
         },
+        // The importer overrides these from importer.json
+        gtfs_service_date: None,
+        elevation: convert_osm::ElevationBackend::default(),
+        osm_changes: Vec::new(),
     }
 }
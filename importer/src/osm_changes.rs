@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use abstio::MapName;
+use abstutil::{prettyprint_usize, Timer};
+use map_model::raw::{OriginalRoad, RawMap};
+use map_model::{osm, EditsValidation, Map, PermanentMapEdits};
+
+/// Describes what changed in a map after applying OSM change files, and which saved edits are
+/// affected.
+#[derive(Serialize)]
+pub struct ChangeReport {
+    pub map_name: MapName,
+    pub osm_changes: Vec<String>,
+
+    pub roads_added: BTreeSet<OriginalRoad>,
+    pub roads_removed: BTreeSet<OriginalRoad>,
+    pub roads_modified: BTreeSet<OriginalRoad>,
+    pub intersections_added: BTreeSet<osm::NodeID>,
+    pub intersections_removed: BTreeSet<osm::NodeID>,
+    pub intersections_modified: BTreeSet<osm::NodeID>,
+    pub buildings_added: BTreeSet<osm::OsmID>,
+    pub buildings_removed: BTreeSet<osm::OsmID>,
+    pub buildings_modified: BTreeSet<osm::OsmID>,
+
+    /// Only filled out once the new map is built
+    pub edits: Vec<EditsValidation>,
+    /// True if the existing map was updated in place, instead of being rebuilt
+    pub updated_in_place: bool,
+}
+
+impl ChangeReport {
+    /// Compares the RawMap from before the changes to the one after. If there was no RawMap
+    /// before, everything counts as added.
+    pub fn new(before: Option<&RawMap>, after: &RawMap, osm_changes: Vec<String>) -> ChangeReport {
+        let mut report = ChangeReport {
+            map_name: after.name.clone(),
+            osm_changes,
+
+            roads_added: BTreeSet::new(),
+            roads_removed: BTreeSet::new(),
+            roads_modified: BTreeSet::new(),
+            intersections_added: BTreeSet::new(),
+            intersections_removed: BTreeSet::new(),
+            intersections_modified: BTreeSet::new(),
+            buildings_added: BTreeSet::new(),
+            buildings_removed: BTreeSet::new(),
+            buildings_modified: BTreeSet::new(),
+
+            edits: Vec::new(),
+            updated_in_place: false,
+        };
+        let empty = RawMap::blank(after.name.clone());
+        let before = before.unwrap_or(&empty);
+
+        diff(
+            &before.roads,
+            &after.roads,
+            &mut report.roads_added,
+            &mut report.roads_removed,
+            &mut report.roads_modified,
+        );
+        diff(
+            &before.intersections,
+            &after.intersections,
+            &mut report.intersections_added,
+            &mut report.intersections_removed,
+            &mut report.intersections_modified,
+        );
+        diff(
+            &before.buildings,
+            &after.buildings,
+            &mut report.buildings_added,
+            &mut report.buildings_removed,
+            &mut report.buildings_modified,
+        );
+
+        report
+    }
+
+    /// Check all of the saved edits for this map against the rebuilt map. Anything touching a
+    /// road or intersection that changed is flagged for review.
+    pub fn revalidate_edits(&mut self, map: &Map, timer: &mut Timer) {
+        let changed_roads: BTreeSet<OriginalRoad> = self
+            .roads_removed
+            .iter()
+            .chain(self.roads_modified.iter())
+            .cloned()
+            .collect();
+        let changed_intersections: BTreeSet<osm::NodeID> = self
+            .intersections_removed
+            .iter()
+            .chain(self.intersections_modified.iter())
+            .cloned()
+            .collect();
+
+        self.edits.clear();
+        for path in abstio::list_dir(abstio::path_all_edits(map.get_name())) {
+            match PermanentMapEdits::load_from_file(map, path.clone(), timer) {
+                Ok(perma) => {
+                    self.edits
+                        .push(perma.revalidate(map, &changed_roads, &changed_intersections));
+                }
+                Err(err) => {
+                    warn!("Couldn't load {}: {}", path, err);
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let path = self
+            .map_name
+            .city
+            .input_path(format!("osm_changes/{}.json", self.map_name.map));
+        abstio::write_json(path.clone(), self);
+        println!("- Wrote report of OSM changes to {}", path);
+    }
+
+    pub fn print_summary(&self) {
+        println!(
+            "Applied {} OSM change files to {}",
+            self.osm_changes.len(),
+            self.map_name.describe()
+        );
+        for (objects, added, removed, modified) in [
+            (
+                "roads",
+                self.roads_added.len(),
+                self.roads_removed.len(),
+                self.roads_modified.len(),
+            ),
+            (
+                "intersections",
+                self.intersections_added.len(),
+                self.intersections_removed.len(),
+                self.intersections_modified.len(),
+            ),
+            (
+                "buildings",
+                self.buildings_added.len(),
+                self.buildings_removed.len(),
+                self.buildings_modified.len(),
+            ),
+        ] {
+            println!(
+                "- {}: {} added, {} removed, {} modified",
+                objects,
+                prettyprint_usize(added),
+                prettyprint_usize(removed),
+                prettyprint_usize(modified)
+            );
+        }
+        if self.updated_in_place {
+            println!("- Updated the existing map in place");
+        }
+        for edits in &self.edits {
+            if edits.broken.is_empty() && edits.needs_review.is_empty() {
+                continue;
+            }
+            println!(
+                "- Edits {}: {} commands broken, {} need review",
+                edits.edits_name,
+                edits.broken.len(),
+                edits.needs_review.len()
+            );
+            for cmd in &edits.broken {
+                println!("  - broken: {}", cmd);
+            }
+        }
+    }
+}
+
+/// Objects are compared by their serialized form, so any change to geometry, tags, or anything
+/// else counts.
+fn diff<K: Clone + Ord, V: Serialize>(
+    before: &BTreeMap<K, V>,
+    after: &BTreeMap<K, V>,
+    added: &mut BTreeSet<K>,
+    removed: &mut BTreeSet<K>,
+    modified: &mut BTreeSet<K>,
+) {
+    for (id, obj) in after {
+        if let Some(old) = before.get(id) {
+            if abstutil::to_json(old) != abstutil::to_json(obj) {
+                modified.insert(id.clone());
+            }
+        } else {
+            added.insert(id.clone());
+        }
+    }
+    for id in before.keys() {
+        if !after.contains_key(id) {
+            removed.insert(id.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstutil::Tags;
+    use geom::{Distance, Pt2D};
+    use map_model::raw::{RawIntersection, RawRoad};
+    use map_model::IntersectionType;
+
+    use super::*;
+
+    fn raw_map(roads: Vec<(i64, &str)>, signals: Vec<i64>) -> RawMap {
+        let mut map = RawMap::blank(MapName::new("zz", "test", "osm_changes"));
+        for i in 1..=3 {
+            map.intersections.insert(
+                osm::NodeID(i),
+                RawIntersection {
+                    point: Pt2D::new(i as f64 * 100.0, 0.0),
+                    intersection_type: if signals.contains(&i) {
+                        IntersectionType::TrafficSignal
+                    } else {
+                        IntersectionType::StopSign
+                    },
+                    elevation: Distance::ZERO,
+                    trim_roads_for_merging: BTreeMap::new(),
+                },
+            );
+        }
+        for (way, highway) in roads {
+            let (i1, i2) = (way - 100, way - 99);
+            let mut tags = Tags::empty();
+            tags.insert("highway", highway);
+            map.roads.insert(
+                OriginalRoad::new(way, (i1, i2)),
+                RawRoad {
+                    center_points: vec![
+                        Pt2D::new(i1 as f64 * 100.0, 0.0),
+                        Pt2D::new(i2 as f64 * 100.0, 0.0),
+                    ],
+                    osm_tags: tags,
+                    turn_restrictions: Vec::new(),
+                    complicated_turn_restrictions: Vec::new(),
+                    conditional_turn_restrictions: Vec::new(),
+                    lane_connectivity: Vec::new(),
+                    percent_incline: 0.0,
+                    crosswalk_forward: true,
+                    crosswalk_backward: true,
+                },
+            );
+        }
+        map
+    }
+
+    #[test]
+    fn test_change_report() {
+        let before = raw_map(vec![(101, "residential")], Vec::new());
+        let after = raw_map(vec![(101, "tertiary"), (102, "service")], vec![2]);
+        let report = ChangeReport::new(Some(&before), &after, vec!["x.osc".to_string()]);
+
+        assert_eq!(
+            report.roads_added,
+            vec![OriginalRoad::new(102, (2, 3))].into_iter().collect()
+        );
+        assert!(report.roads_removed.is_empty());
+        assert_eq!(
+            report.roads_modified,
+            vec![OriginalRoad::new(101, (1, 2))].into_iter().collect()
+        );
+        assert!(report.intersections_added.is_empty());
+        assert_eq!(
+            report.intersections_modified,
+            vec![osm::NodeID(2)].into_iter().collect()
+        );
+
+        // Without a RawMap from before, everything is new
+        let report = ChangeReport::new(None, &before, Vec::new());
+        assert_eq!(report.roads_added.len(), 1);
+        assert_eq!(report.intersections_added.len(), 3);
+        assert!(report.roads_modified.is_empty());
+
+        let report = ChangeReport::new(Some(&after), &before, Vec::new());
+        assert_eq!(
+            report.roads_removed,
+            vec![OriginalRoad::new(102, (2, 3))].into_iter().collect()
+        );
+    }
+}
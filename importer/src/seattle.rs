@@ -115,7 +115,7 @@ pub async fn ensure_popdat_exists(
     }
 
     if !abstio::file_exists(abstio::path_raw_map(&huge_name)) {
-        crate::utils::osm_to_raw(MapName::seattle("huge_seattle"), timer, config).await;
+        crate::utils::osm_to_raw(MapName::seattle("huge_seattle"), timer, config, &[]).await;
         *built_raw_huge_seattle = true;
     }
     let huge_map = if abstio::file_exists(huge_name.path()) {
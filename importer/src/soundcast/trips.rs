@@ -202,7 +202,8 @@ fn clip_trips(
                 borders.for_mode(orig.mode),
                 match orig.mode {
                     TripMode::Walk | TripMode::Transit => PathConstraints::Pedestrian,
-                    TripMode::Drive => PathConstraints::Car,
+                    TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
+                    TripMode::Freight => PathConstraints::Truck,
                     TripMode::Bike => PathConstraints::Bike,
                 },
                 maybe_huge_map.as_ref(),
@@ -83,45 +83,20 @@ pub async fn download_kml(
     fs_err::rename(tmp, output.replace(".bin", ".kml")).unwrap();
 }
 
-/// Uses osmconvert to clip the input .osm (or .pbf) against a polygon and produce some output.
-/// Skips if the output exists.
-fn osmconvert(
-    input: String,
-    clipping_polygon: String,
-    output: String,
-    config: &ImporterConfiguration,
-) {
-    let clipping_polygon = clipping_polygon;
-
-    if Path::new(&output).exists() {
-        println!("- {} already exists", output);
-        return;
-    }
-    // Create the output directory if needed
-    fs_err::create_dir_all(Path::new(&output).parent().unwrap())
-        .expect("Creating parent dir failed");
-
-    println!("- Clipping {} to {}", input, clipping_polygon);
-
-    must_run_cmd(
-        Command::new(&config.osmconvert)
-            .arg(input)
-            .arg(format!("-B={}", clipping_polygon))
-            .arg("--complete-ways")
-            .arg(format!("-o={}", output)),
-    );
-}
-
-/// Creates a RawMap from OSM and other input data.
+/// Creates a RawMap from OSM and other input data, applying any OSM change files on top.
 pub async fn osm_to_raw(
     name: MapName,
     timer: &mut abstutil::Timer<'_>,
     config: &ImporterConfiguration,
+    osm_changes: &[String],
 ) -> RawMap {
     if name.city == CityName::seattle() {
         crate::seattle::input(config, timer).await;
     }
-    let opts = crate::map_config::config_for_map(&name);
+    let mut opts = crate::map_config::config_for_map(&name);
+    opts.elevation = config.elevation.clone();
+    opts.gtfs_service_date = config.gtfs_service_date.clone();
+    opts.osm_changes = osm_changes.to_vec();
     if let Some(ref url) = opts.gtfs_url {
         download(config, name.city.input_path("gtfs/"), url).await;
     }
@@ -145,15 +120,9 @@ pub async fn osm_to_raw(
     ));
     download(config, local_osm_file.clone(), &osm_url).await;
 
-    osmconvert(
-        local_osm_file,
-        boundary_polygon.clone(),
-        name.city.input_path(format!("osm/{}.osm", name.map)),
-        config,
-    );
-
+    // The .osm.pbf is clipped to the boundary while reading it
     let map = convert_osm::convert(
-        name.city.input_path(format!("osm/{}.osm", name.map)),
+        local_osm_file,
         name.clone(),
         Some(boundary_polygon),
         opts,
@@ -173,6 +142,50 @@ pub fn raw_to_map(name: &MapName, opts: RawToMapOptions, timer: &mut Timer) -> m
     timer.stop("save map");
     timer.stop(format!("Raw->Map for {}", name.describe()));
 
+    write_city_manifest(name, &map, timer);
+    map
+}
+
+/// After applying OSM changes, try to update the existing map in place, instead of building it
+/// again from the RawMap. `before` is the RawMap the existing map was built from. Returns None if
+/// there's no existing map or the changes need a full rebuild.
+pub fn update_map(
+    name: &MapName,
+    before: RawMap,
+    opts: &RawToMapOptions,
+    timer: &mut Timer,
+) -> Option<map_model::Map> {
+    let mut map: map_model::Map = match abstio::maybe_read_binary(name.path(), timer) {
+        Ok(map) => map,
+        Err(err) => {
+            warn!("Can't update {} in place: {}", name.describe(), err);
+            return None;
+        }
+    };
+    map.map_loaded_directly(timer);
+
+    timer.start(format!("update Map for {}", name.describe()));
+    let after: RawMap = abstio::read_binary(abstio::path_raw_map(name), timer);
+    let result = map.update_from_raw(before, after, opts, timer);
+    timer.stop(format!("update Map for {}", name.describe()));
+    if let Err(err) = result {
+        warn!(
+            "Can't update {} in place, so rebuilding it: {}",
+            name.describe(),
+            err
+        );
+        return None;
+    }
+
+    timer.start("save map");
+    map.save();
+    timer.stop("save map");
+
+    write_city_manifest(name, &map, timer);
+    Some(map)
+}
+
+fn write_city_manifest(name: &MapName, map: &map_model::Map, timer: &mut Timer) {
     // TODO Just sticking this here for now
     if name.map == "huge_seattle" || name == &MapName::new("gb", "leeds", "huge") {
         timer.start("generating city manifest");
@@ -182,10 +195,8 @@ pub fn raw_to_map(name: &MapName, opts: RawToMapOptions, timer: &mut Timer) -> m
                 map.get_city_name().country,
                 map.get_city_name().city
             )),
-            &map_model::City::from_huge_map(&map),
+            &map_model::City::from_huge_map(map),
         );
         timer.stop("generating city manifest");
     }
-
-    map
 }
@@ -1,5 +1,6 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use geom::{Circle, Distance, Line};
@@ -178,6 +179,31 @@ impl DiagonalFilter {
         ]
     }
 
+    /// Recreate a filter from its intersection and two roads, checking they still make sense on
+    /// the current map
+    pub fn maybe_new(
+        map: &Map,
+        i: IntersectionID,
+        r1: RoadID,
+        r2: RoadID,
+    ) -> Result<DiagonalFilter> {
+        let roads = map.get_i(i).get_roads_sorted_by_incoming_angle(map);
+        if roads.len() != 4 {
+            bail!("{} has {} roads now, not 4", i, roads.len());
+        }
+        // r2 must come right after r1 in the clockwise ordering
+        match roads.iter().position(|r| *r == r1) {
+            Some(idx) if roads[(idx + 1) % roads.len()] == r2 => {}
+            _ => bail!("{} and {} aren't adjacent at {}", r1, r2, i),
+        }
+        Ok(DiagonalFilter::new(map, i, r1, r2))
+    }
+
+    /// The intersection and the two roads defining this filter
+    pub fn key(&self) -> (IntersectionID, RoadID, RoadID) {
+        (self.i, self.r1, self.r2)
+    }
+
     fn new(map: &Map, i: IntersectionID, r1: RoadID, r2: RoadID) -> DiagonalFilter {
         let mut roads = map.get_i(i).get_roads_sorted_by_incoming_angle(map);
         // Make self.r1 be the first entry
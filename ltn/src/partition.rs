@@ -134,6 +134,16 @@ impl Partitioning {
         p
     }
 
+    /// All roads that're part of any block
+    pub fn all_roads(&self) -> BTreeSet<RoadID> {
+        let mut roads = BTreeSet::new();
+        for block in &self.single_blocks {
+            roads.extend(block.perimeter.roads.iter().map(|id| id.road));
+            roads.extend(block.perimeter.interior.iter().cloned());
+        }
+        roads
+    }
+
     /// True if the coloring changed
     pub fn recalculate_coloring(&mut self) -> bool {
         let perims: Vec<Perimeter> = self
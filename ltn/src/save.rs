@@ -1,18 +1,25 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
 use abstutil::Timer;
 use map_gui::tools::{ChooseSomething, PopupMsg, PromptInput};
+use map_model::{
+    IntersectionID, IntersectionLocation, Map, MigrationItem, MigrationOutcome, RoadID,
+    RoadLocation,
+};
 use widgetry::{Choice, EventCtx, State, Transition};
 
-use crate::{App, BrowseNeighborhoods, ModalFilters, Partitioning};
+use crate::{App, BrowseNeighborhoods, DiagonalFilter, ModalFilters, Partitioning};
 
 /// Captures all of the edits somebody makes to a map in the LTN tool. Note this separate from
 /// `map_model::MapEdits`.
 ///
-/// TODO Note this format isn't future-proof at all. Changes to the LTN blockfinding algorithm or
-/// map data (like RoadIDs) will probably break someone's edits.
+/// This refers to RoadIDs and IntersectionIDs, which change when the map is regenerated, so the
+/// location of everything is saved too. When loading, anything that moved is matched again by
+/// location. Changes to the LTN blockfinding algorithm will still break the partitioning.
 #[derive(Serialize, Deserialize)]
 pub struct Proposal {
     pub map: MapName,
@@ -21,6 +28,87 @@ pub struct Proposal {
 
     pub partitioning: Partitioning,
     pub modal_filters: ModalFilters,
+
+    locations: Locations,
+}
+
+/// The format before locations were saved
+#[derive(Deserialize)]
+struct LegacyProposal {
+    map: MapName,
+    name: String,
+    abst_version: String,
+
+    partitioning: Partitioning,
+    modal_filters: ModalFilters,
+}
+
+/// Where the objects referenced by a proposal were when it was saved. Proposals saved before this
+/// existed have no locations. Their IDs are kept if they still exist, but flagged as unverified.
+#[derive(Default, Serialize, Deserialize)]
+struct Locations {
+    /// The middle of every road in the partitioning or a diagonal filter
+    roads: BTreeMap<RoadID, RoadLocation>,
+    /// The total number of roads in the map. If roads are added, the partitioning is invalid.
+    num_roads: usize,
+    /// The position of every filter along a road
+    road_filters: BTreeMap<RoadID, RoadLocation>,
+    /// The intersection of every diagonal filter
+    diagonal_filters: BTreeMap<IntersectionID, IntersectionLocation>,
+}
+
+impl Locations {
+    fn new(map: &Map, partitioning: &Partitioning, modal_filters: &ModalFilters) -> Locations {
+        let mut locations = Locations {
+            num_roads: map.all_roads().len(),
+            ..Default::default()
+        };
+        for r in partitioning.all_roads() {
+            locations.roads.insert(r, RoadLocation::middle(map, r));
+        }
+        for (r, dist) in &modal_filters.roads {
+            locations
+                .road_filters
+                .insert(*r, RoadLocation::new(map, *r, *dist));
+        }
+        for (i, filter) in &modal_filters.intersections {
+            let (_, r1, r2) = filter.key();
+            locations
+                .diagonal_filters
+                .insert(*i, IntersectionLocation::new(map, *i));
+            for r in [r1, r2] {
+                locations.roads.insert(r, RoadLocation::middle(map, r));
+            }
+        }
+        locations
+    }
+
+    /// Where is this road in the current map? Roads saved without a location are assumed not to
+    /// have moved.
+    fn relocate_road(&self, map: &Map, r: RoadID) -> Option<RoadID> {
+        match self.roads.get(&r) {
+            None => map.maybe_get_r(r).map(|_| r),
+            Some(loc) if loc.matches(map, r) => Some(r),
+            Some(loc) => loc.find(map).map(|(r, _)| r),
+        }
+    }
+
+    /// Where is this intersection in the current map? Intersections saved without a location are
+    /// assumed not to have moved.
+    fn relocate_intersection(&self, map: &Map, i: IntersectionID) -> Option<IntersectionID> {
+        match self.diagonal_filters.get(&i) {
+            None => map.maybe_get_i(i).map(|_| i),
+            Some(loc) if loc.matches(map, i) => Some(i),
+            Some(loc) => loc.find(map),
+        }
+    }
+
+    /// Do the saved locations cover everything this diagonal filter refers to?
+    fn has_diagonal_filter(&self, i: IntersectionID, r1: RoadID, r2: RoadID) -> bool {
+        self.diagonal_filters.contains_key(&i)
+            && self.roads.contains_key(&r1)
+            && self.roads.contains_key(&r2)
+    }
 }
 
 impl Proposal {
@@ -45,6 +133,11 @@ impl Proposal {
 
             partitioning: app.session.partitioning.clone(),
             modal_filters: app.session.modal_filters.clone(),
+            locations: Locations::new(
+                &app.map,
+                &app.session.partitioning,
+                &app.session.modal_filters,
+            ),
         };
         abstio::write_binary(path, &proposal);
     }
@@ -56,21 +149,37 @@ impl Proposal {
             Choice::strings(abstio::list_all_objects(abstio::path_all_ltn_proposals(
                 app.map.get_name(),
             ))),
-            Box::new(|name, ctx, app| {
-                Transition::Replace(match Self::load(ctx, app, &name) {
-                    Some(err_state) => err_state,
-                    None => BrowseNeighborhoods::new_state(ctx, app),
-                })
+            Box::new(|name, ctx, app| match Self::load(ctx, app, &name) {
+                Some(popup) => Transition::Multi(vec![
+                    Transition::Replace(BrowseNeighborhoods::new_state(ctx, app)),
+                    Transition::Push(popup),
+                ]),
+                None => Transition::Replace(BrowseNeighborhoods::new_state(ctx, app)),
             }),
         )
     }
 
-    /// Try to load a proposal. If it fails, returns a popup message state.
+    /// Try to load a proposal. If it fails, or if the map changed since the proposal was saved,
+    /// returns a popup message state.
     pub fn load(ctx: &mut EventCtx, app: &mut App, name: &str) -> Option<Box<dyn State<App>>> {
         ctx.loading_screen(
             "load existing proposal",
             |ctx, mut timer| match Self::inner_load(app, name, &mut timer) {
-                Ok(()) => None,
+                Ok(report) => {
+                    let changes: Vec<String> = report
+                        .into_iter()
+                        .filter(|item| !matches!(item.outcome, MigrationOutcome::CarriedOver))
+                        .map(|item| item.describe())
+                        .collect();
+                    if changes.is_empty() {
+                        None
+                    } else {
+                        let mut lines =
+                            vec![format!("{} doesn't exactly match the current map", name)];
+                        lines.extend(changes);
+                        Some(PopupMsg::new_state(ctx, "Check this proposal", lines))
+                    }
+                }
                 Err(err) => Some(PopupMsg::new_state(
                     ctx,
                     "Error",
@@ -80,12 +189,128 @@ impl Proposal {
         )
     }
 
-    fn inner_load(app: &mut App, name: &str, timer: &mut Timer) -> Result<()> {
-        let proposal: Proposal =
-            abstio::maybe_read_binary(abstio::path_ltn_proposals(app.map.get_name(), name), timer)?;
-        // TODO We could try to detect if the file still matches this version of the map or not
-        app.session.partitioning = proposal.partitioning;
-        app.session.modal_filters = proposal.modal_filters;
-        Ok(())
+    fn inner_load(app: &mut App, name: &str, timer: &mut Timer) -> Result<Vec<MigrationItem>> {
+        let path = abstio::path_ltn_proposals(app.map.get_name(), name);
+        let proposal = match abstio::maybe_read_binary::<Proposal>(path.clone(), timer) {
+            Ok(proposal) => proposal,
+            Err(_) => {
+                let legacy: LegacyProposal = abstio::maybe_read_binary(path, timer)?;
+                Proposal {
+                    map: legacy.map,
+                    name: legacy.name,
+                    abst_version: legacy.abst_version,
+                    partitioning: legacy.partitioning,
+                    modal_filters: legacy.modal_filters,
+                    locations: Locations::default(),
+                }
+            }
+        };
+        let (partitioning, modal_filters, report) = proposal.migrate(app, timer);
+        app.session.partitioning = partitioning;
+        app.session.modal_filters = modal_filters;
+        Ok(report)
+    }
+
+    /// Match everything in the proposal to the current map, finding things by location if their
+    /// IDs changed. Describes what happened to the partitioning and every filter.
+    fn migrate(
+        self,
+        app: &App,
+        timer: &mut Timer,
+    ) -> (Partitioning, ModalFilters, Vec<MigrationItem>) {
+        let map = &app.map;
+        let locations = self.locations;
+        let mut report = Vec::new();
+
+        // Blocks are traced from every road, so if any road changed, start over
+        let partitioning_roads = self.partitioning.all_roads();
+        let outcome = if partitioning_roads
+            .iter()
+            .any(|r| locations.relocate_road(map, *r) != Some(*r))
+        {
+            MigrationOutcome::Dropped(
+                "roads changed, so the default boundaries are used".to_string(),
+            )
+        } else if partitioning_roads
+            .iter()
+            .any(|r| !locations.roads.contains_key(r))
+        {
+            MigrationOutcome::Unverified
+        } else if locations.num_roads != map.all_roads().len() {
+            MigrationOutcome::Dropped(
+                "roads were added, so the default boundaries are used".to_string(),
+            )
+        } else {
+            MigrationOutcome::CarriedOver
+        };
+        let partitioning = if matches!(outcome, MigrationOutcome::Dropped(_)) {
+            Partitioning::seed_using_heuristics(app, timer)
+        } else {
+            self.partitioning
+        };
+        report.push(MigrationItem {
+            description: "neighborhood boundaries".to_string(),
+            outcome,
+        });
+
+        let mut modal_filters = ModalFilters::default();
+        for (r, dist) in self.modal_filters.roads {
+            let outcome = match locations.road_filters.get(&r) {
+                None if map.maybe_get_r(r).is_some() => {
+                    modal_filters.roads.insert(r, dist);
+                    MigrationOutcome::Unverified
+                }
+                None => MigrationOutcome::Dropped("the road is gone".to_string()),
+                Some(loc) if loc.matches(map, r) => {
+                    modal_filters.roads.insert(r, dist);
+                    MigrationOutcome::CarriedOver
+                }
+                Some(loc) => match loc.find(map) {
+                    Some((new_r, new_dist)) => {
+                        modal_filters.roads.insert(new_r, new_dist);
+                        MigrationOutcome::Moved(format!("{}", new_r))
+                    }
+                    None => MigrationOutcome::Dropped("nothing is at its old location".to_string()),
+                },
+            };
+            report.push(MigrationItem {
+                description: format!("filter on {}", r),
+                outcome,
+            });
+        }
+
+        for (i, filter) in self.modal_filters.intersections {
+            let (_, r1, r2) = filter.key();
+            let outcome = match (
+                locations.relocate_intersection(map, i),
+                locations.relocate_road(map, r1),
+                locations.relocate_road(map, r2),
+            ) {
+                (Some(new_i), Some(new_r1), Some(new_r2)) => {
+                    match DiagonalFilter::maybe_new(map, new_i, new_r1, new_r2) {
+                        Ok(filter) => {
+                            modal_filters.intersections.insert(new_i, filter);
+                            if !locations.has_diagonal_filter(i, r1, r2) {
+                                MigrationOutcome::Unverified
+                            } else if (new_i, new_r1, new_r2) == (i, r1, r2) {
+                                MigrationOutcome::CarriedOver
+                            } else {
+                                MigrationOutcome::Moved(format!("{}", new_i))
+                            }
+                        }
+                        Err(err) => MigrationOutcome::Dropped(err.to_string()),
+                    }
+                }
+                _ => {
+                    MigrationOutcome::Dropped("the intersection or its roads are gone".to_string())
+                }
+            };
+            report.push(MigrationItem {
+                description: format!("diagonal filter at {}", i),
+                outcome,
+            });
+        }
+
+        (partitioning, modal_filters, report)
     }
 }
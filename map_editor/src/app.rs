@@ -84,6 +84,9 @@ impl AppLike for App {
     ) -> (usize, geom::Duration) {
         unreachable!()
     }
+    fn current_signal_plan(&self, _: map_model::IntersectionID) -> usize {
+        unreachable!()
+    }
 }
 
 pub struct MainState {
@@ -290,7 +293,7 @@ impl State<App> for MainState {
                         txt.add_appended(vec![
                             Line("- Press "),
                             Key::T.txt(ctx),
-                            Line(" to toggle stop sign / traffic signal"),
+                            Line(" to toggle stop sign / yield / traffic signal"),
                         ]);
                         txt.add_appended(vec![
                             Line("- Press "),
@@ -150,6 +150,7 @@ impl Model {
         let color = match i.intersection_type {
             IntersectionType::TrafficSignal => Color::GREEN,
             IntersectionType::StopSign => Color::RED,
+            IntersectionType::Yield => Color::YELLOW,
             IntersectionType::Border => Color::BLUE,
             IntersectionType::Construction => Color::ORANGE,
         };
@@ -205,12 +206,14 @@ impl Model {
     pub fn toggle_i(&mut self, ctx: &EventCtx, id: osm::NodeID) {
         self.world.delete(ID::Intersection(id));
 
+        // Cycle between stop sign, yield, and traffic signal
         let i = self.map.intersections.get_mut(&id).unwrap();
-        if i.intersection_type == IntersectionType::TrafficSignal {
-            i.intersection_type = IntersectionType::StopSign;
-        } else if i.intersection_type == IntersectionType::StopSign {
-            i.intersection_type = IntersectionType::TrafficSignal;
-        }
+        i.intersection_type = match i.intersection_type {
+            IntersectionType::StopSign => IntersectionType::Yield,
+            IntersectionType::Yield => IntersectionType::TrafficSignal,
+            IntersectionType::TrafficSignal => IntersectionType::StopSign,
+            x => x,
+        };
 
         self.intersection_added(ctx, id);
     }
@@ -304,6 +307,8 @@ impl Model {
                 osm_tags,
                 turn_restrictions: Vec::new(),
                 complicated_turn_restrictions: Vec::new(),
+                conditional_turn_restrictions: Vec::new(),
+                lane_connectivity: Vec::new(),
                 percent_incline: 0.0,
                 crosswalk_forward: true,
                 crosswalk_backward: true,
@@ -83,6 +83,7 @@ pub struct ColorScheme {
     driving_lane: Color,
     bus_lane: Color,
     parking_lane: Color,
+    loading_zone: Color,
     bike_lane: Color,
     sidewalk: Color,
     pub sidewalk_lines: Color,
@@ -130,6 +131,8 @@ pub struct ColorScheme {
     pub unzoomed_bike: Color,
     pub unzoomed_bus: Color,
     pub unzoomed_pedestrian: Color,
+    pub unzoomed_ride_hail: Color,
+    pub unzoomed_freight: Color,
 
     // Agents
     agent_colors: Vec<Color>,
@@ -203,6 +206,7 @@ impl ColorScheme {
             driving_lane: Color::BLACK,
             bus_lane: Color::rgb(190, 74, 76),
             parking_lane: Color::grey(0.2),
+            loading_zone: Color::rgb(112, 86, 38),
             bike_lane: Color::rgb(15, 125, 75),
             sidewalk: Color::grey(0.8),
             sidewalk_lines: Color::grey(0.7),
@@ -250,6 +254,8 @@ impl ColorScheme {
             unzoomed_bike: hex("#90BE6D"),
             unzoomed_bus: hex("#FFD166"),
             unzoomed_pedestrian: hex("#457B9D"),
+            unzoomed_ride_hail: hex("#B56576"),
+            unzoomed_freight: hex("#8D6A9F"),
 
             // Agents
             agent_colors: vec![
@@ -401,6 +407,13 @@ impl ColorScheme {
             LaneType::Driving => main_asphalt,
             LaneType::Bus => self.bus_lane,
             LaneType::Parking => parking_asphalt,
+            LaneType::LoadingZone => {
+                if self.experiment {
+                    main_asphalt
+                } else {
+                    self.loading_zone
+                }
+            }
             LaneType::Sidewalk | LaneType::Shoulder => self.sidewalk,
             LaneType::Biking => self.bike_lane,
             LaneType::SharedLeftTurn => main_asphalt,
@@ -63,6 +63,9 @@ pub trait AppLike {
     fn current_stage_and_remaining_time(&self, id: IntersectionID) -> (usize, Duration) {
         self.sim().current_stage_and_remaining_time(id)
     }
+    fn current_signal_plan(&self, id: IntersectionID) -> usize {
+        self.sim().current_signal_plan(id)
+    }
 
     /// Change the color scheme. Idempotent. Return true if there was a change.
     fn change_color_scheme(&mut self, ctx: &mut EventCtx, cs: ColorSchemeChoice) -> bool {
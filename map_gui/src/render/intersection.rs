@@ -96,6 +96,27 @@ impl DrawIntersection {
                     }
                 }
             }
+            IntersectionType::Yield => {
+                for ss in map.get_stop_sign(i.id).roads.values() {
+                    if ss.must_stop {
+                        if let Some((octagon, pole, angle)) =
+                            DrawIntersection::stop_sign_geom(ss, map)
+                        {
+                            // Draw a give-way triangle in the same spot as a stop sign
+                            let center = octagon.center();
+                            default_geom.push(
+                                app.cs().stop_sign,
+                                make_triangle(center, Distance::meters(1.0), angle),
+                            );
+                            default_geom.push(
+                                Color::WHITE,
+                                make_triangle(center, Distance::meters(0.6), angle),
+                            );
+                            default_geom.push(app.cs().stop_sign_pole, pole);
+                        }
+                    }
+                }
+            }
             IntersectionType::Construction => {
                 // TODO Centering seems weird
                 default_geom.append(
@@ -218,10 +239,11 @@ impl Renderable for DrawIntersection {
                     .unwrap_or(true);
                 if recalc {
                     let (idx, remaining) = app.current_stage_and_remaining_time(self.id);
+                    let plan = app.current_signal_plan(self.id);
                     let mut batch = GeomBatch::new();
                     traffic_signal::draw_signal_stage(
                         g.prerender,
-                        &signal.stages[idx],
+                        &signal.plan_stages(plan)[idx],
                         idx,
                         self.id,
                         Some(remaining),
@@ -483,6 +505,16 @@ fn make_octagon(center: Pt2D, radius: Distance, facing: Angle) -> Polygon {
     .into_polygon()
 }
 
+// One corner points in the direction of travel
+fn make_triangle(center: Pt2D, radius: Distance, facing: Angle) -> Polygon {
+    Ring::must_new(
+        (0..=3)
+            .map(|i| center.project_away(radius, facing.rotate_degs(f64::from(i * 120))))
+            .collect(),
+    )
+    .into_polygon()
+}
+
 /// Draws both zebra crosswalks and unmarked crossings
 pub fn make_crosswalk(batch: &mut GeomBatch, turn: &Turn, map: &Map, cs: &ColorScheme) {
     if turn.turn_type == TurnType::UnmarkedCrossing {
@@ -65,7 +65,7 @@ impl DrawLane {
                     );
                 }
             }
-            LaneType::Parking => {
+            LaneType::Parking | LaneType::LoadingZone => {
                 batch.extend(general_road_marking, calculate_parking_lines(lane, map));
             }
             LaneType::Driving => {
@@ -248,12 +248,17 @@ fn calculate_parking_lines(lane: &Lane, map: &Map) -> Vec<Polygon> {
     let leg_length = Distance::meters(1.0);
 
     let mut result = Vec::new();
+    let spot_length = if let Some(x) = lane.curbside_spot_length(map.get_config()) {
+        x
+    } else {
+        return result;
+    };
     let num_spots = lane.number_parking_spots(map.get_config());
     if num_spots > 0 {
         for idx in 0..=num_spots {
-            let (pt, lane_angle) = lane
-                .lane_center_pts
-                .must_dist_along(map.get_config().street_parking_spot_length * (1.0 + idx as f64));
+            let (pt, lane_angle) = lane.lane_center_pts.must_dist_along(
+                map.get_config().street_parking_spot_length + spot_length * (idx as f64),
+            );
             let perp_angle = if map.get_config().driving_side == DrivingSide::Right {
                 lane_angle.rotate_degs(270.0)
             } else {
@@ -241,7 +241,7 @@ impl DrawMap {
             let zorder = 10 * i.get_zorder(map);
             unzoomed_pieces.push((
                 zorder,
-                if i.is_stop_sign() {
+                if i.is_stop_sign() || i.is_yield() {
                     if i.is_light_rail(map) {
                         cs.light_rail_track
                     } else if i.is_cycleway(map) {
@@ -347,6 +347,8 @@ pub fn color_for_mode(app: &dyn AppLike, m: TripMode) -> Color {
         TripMode::Bike => app.cs().unzoomed_bike,
         TripMode::Transit => app.cs().unzoomed_bus,
         TripMode::Drive => app.cs().unzoomed_car,
+        TripMode::RideHail => app.cs().unzoomed_ride_hail,
+        TripMode::Freight => app.cs().unzoomed_freight,
     }
 }
 
@@ -202,7 +202,12 @@ pub fn all_walking_costs_from(
                             PathConstraints::Pedestrian,
                             map,
                         )
-                    + zone_cost(turn.id.to_movement(map), PathConstraints::Pedestrian, map),
+                    + zone_cost(
+                        turn.id.to_movement(map),
+                        PathConstraints::Pedestrian,
+                        map.routing_params(),
+                        map,
+                    ),
                 node: WalkingNode::SidewalkEndpoint(
                     map.get_l(turn.id.dst).get_directed_parent(),
                     map.get_l(turn.id.dst).dst_i == turn.id.parent,
@@ -111,6 +111,27 @@ pub fn upgrade(mut value: Value, map: &Map) -> Result<PermanentMapEdits> {
             .unwrap()
             .insert("version".to_string(), Value::Number(11.into()));
     }
+    if value["version"] == Value::Number(11.into()) {
+        // The locations of referenced objects are optional, so nothing to do
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("version".to_string(), Value::Number(12.into()));
+    }
+    if value["version"] == Value::Number(12.into()) {
+        fix_turn_lanes(&mut value, map);
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("version".to_string(), Value::Number(13.into()));
+    }
+    if value["version"] == Value::Number(13.into()) {
+        fix_conditional_access(&mut value, map);
+        value
+            .as_object_mut()
+            .unwrap()
+            .insert("version".to_string(), Value::Number(14.into()));
+    }
 
     abstutil::from_json(&value.to_string().into_bytes())
 }
@@ -393,6 +414,7 @@ fn fix_lane_widths(value: &mut Value, map: &Map) -> Result<()> {
                         // Before this commit, lane widths weren't modifiable, so this lookup works
                         // for both "old" and "new".
                         width: road.lanes[idx].width,
+                        allowed_turns: None,
                     });
                 }
                 cmd[key]["lanes_ltr"] = serde_json::to_value(lanes_ltr).unwrap();
@@ -499,3 +521,75 @@ impl OriginalLane {
         Ok((r.id, l.offset))
     }
 }
+
+// Turn lane restrictions used to be read from OSM tags, even on edited roads. Now they're part of
+// each LaneSpec. Fill them in from OSM, as long as the edit didn't change the number of driving
+// and bus lanes in that direction. If the road can't be found, leave it for migration to handle.
+fn fix_turn_lanes(value: &mut Value, map: &Map) {
+    for orig in value.as_object_mut().unwrap()["commands"]
+        .as_array_mut()
+        .unwrap()
+    {
+        let cmd = orig.as_object_mut().unwrap();
+        if let Some(cmd) = cmd.get_mut("ChangeRoad") {
+            let road_id: OriginalRoad = serde_json::from_value(cmd["r"].clone()).unwrap();
+            let road = match map.find_r_by_osm_id(road_id) {
+                Ok(r) => map.get_r(r),
+                Err(_) => continue,
+            };
+            let from_osm = EditRoad::get_orig_from_osm(road, map.get_config()).lanes_ltr;
+            let cmd = cmd.as_object_mut().unwrap();
+
+            for key in ["old", "new"] {
+                let mut lanes_ltr: Vec<LaneSpec> =
+                    serde_json::from_value(cmd[key]["lanes_ltr"].clone()).unwrap();
+                for dir in [Direction::Fwd, Direction::Back] {
+                    let mut edited: Vec<&mut LaneSpec> = lanes_ltr
+                        .iter_mut()
+                        .filter(|spec| is_vehicle_lane(spec, dir))
+                        .collect();
+                    let osm: Vec<&LaneSpec> = from_osm
+                        .iter()
+                        .filter(|spec| is_vehicle_lane(spec, dir))
+                        .collect();
+                    if edited.len() == osm.len() {
+                        for (spec, osm_spec) in edited.iter_mut().zip(osm) {
+                            spec.allowed_turns = osm_spec.allowed_turns.clone();
+                        }
+                    }
+                }
+                cmd[key]["lanes_ltr"] = serde_json::to_value(lanes_ltr).unwrap();
+            }
+        }
+    }
+}
+
+// Conditional access restrictions are now parsed from OSM. Edits to a road's access restrictions
+// from before then would otherwise wipe them out.
+fn fix_conditional_access(value: &mut Value, map: &Map) {
+    for orig in value.as_object_mut().unwrap()["commands"]
+        .as_array_mut()
+        .unwrap()
+    {
+        let cmd = orig.as_object_mut().unwrap();
+        if let Some(cmd) = cmd.get_mut("ChangeRoad") {
+            let road_id: OriginalRoad = serde_json::from_value(cmd["r"].clone()).unwrap();
+            let road = match map.find_r_by_osm_id(road_id) {
+                Ok(r) => map.get_r(r),
+                Err(_) => continue,
+            };
+            let conditional =
+                serde_json::to_value(road.access_restrictions_from_osm().conditional).unwrap();
+            let cmd = cmd.as_object_mut().unwrap();
+            for key in ["old", "new"] {
+                if let Some(obj) = cmd[key]["access_restrictions"].as_object_mut() {
+                    obj.insert("conditional".to_string(), conditional.clone());
+                }
+            }
+        }
+    }
+}
+
+fn is_vehicle_lane(spec: &LaneSpec, dir: Direction) -> bool {
+    spec.dir == dir && (spec.lt == LaneType::Driving || spec.lt == LaneType::Bus)
+}
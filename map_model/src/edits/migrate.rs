@@ -0,0 +1,157 @@
+//! Saved edits refer to map objects by ID. When a map is regenerated from newer OSM data, those IDs
+//! can change, even if the road or intersection itself is still there -- a way gets split, or a
+//! node is replaced. This finds objects again by location.
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Angle, Distance, LonLat, Pt2D};
+
+use crate::{IntersectionID, Map, Road, RoadID};
+
+/// How far a road or intersection may move between map versions and still be matched
+const MAX_SHIFT: Distance = Distance::const_meters(10.0);
+/// How much the direction of a road may change and still be matched
+const MAX_BEARING_CHANGE_DEGREES: f64 = 30.0;
+
+/// A point along a road, remembered so the road can be found again if its IDs change.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoadLocation {
+    pt: LonLat,
+    /// The direction of the road's center line at `pt`
+    bearing: Angle,
+}
+
+impl RoadLocation {
+    pub fn new(map: &Map, r: RoadID, dist_along: Distance) -> RoadLocation {
+        let pl = &map.get_r(r).center_pts;
+        let (pt, bearing) = pl.must_dist_along(dist_along.max(Distance::ZERO).min(pl.length()));
+        RoadLocation {
+            pt: pt.to_gps(map.get_gps_bounds()),
+            bearing,
+        }
+    }
+
+    pub fn middle(map: &Map, r: RoadID) -> RoadLocation {
+        RoadLocation::new(map, r, map.get_r(r).center_pts.length() / 2.0)
+    }
+
+    /// Find the road passing closest to this location and pointing the same way, if there's one
+    /// nearby. Returns the road and the distance along it.
+    pub fn find(&self, map: &Map) -> Option<(RoadID, Distance)> {
+        // A location that was never filled in, or from some other map, matches nothing
+        if !map.get_gps_bounds().contains(self.pt) {
+            return None;
+        }
+        let pt = self.pt.to_pt(map.get_gps_bounds());
+        map.all_roads()
+            .iter()
+            .filter_map(|road| {
+                self.compare(road, pt)
+                    .map(|(shift, dist_along)| (road.id, dist_along, shift))
+            })
+            .min_by_key(|(_, _, shift)| *shift)
+            .map(|(r, dist_along, _)| (r, dist_along))
+    }
+
+    /// Is the road still at this location? This only checks the one road, so it's fast, but
+    /// there might be a closer match.
+    pub fn matches(&self, map: &Map, r: RoadID) -> bool {
+        map.maybe_get_r(r)
+            .and_then(|road| self.compare(road, self.pt.to_pt(map.get_gps_bounds())))
+            .is_some()
+    }
+
+    /// If the road passes near the point in the same direction, returns how far away it is and
+    /// the distance along the road.
+    fn compare(&self, road: &Road, pt: Pt2D) -> Option<(Distance, Distance)> {
+        let projected = road.center_pts.project_pt(pt);
+        let shift = projected.dist_to(pt);
+        if shift > MAX_SHIFT {
+            return None;
+        }
+        let (dist_along, angle) = road.center_pts.dist_along_of_point(projected)?;
+        if angle.approx_eq(self.bearing, MAX_BEARING_CHANGE_DEGREES) {
+            Some((shift, dist_along))
+        } else {
+            None
+        }
+    }
+}
+
+/// Remembers where an intersection was, so it can be found again if its IDs change.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntersectionLocation {
+    pt: LonLat,
+}
+
+impl IntersectionLocation {
+    pub fn new(map: &Map, i: IntersectionID) -> IntersectionLocation {
+        IntersectionLocation {
+            pt: map.get_i(i).polygon.center().to_gps(map.get_gps_bounds()),
+        }
+    }
+
+    /// Find the intersection closest to this location, if there's one nearby.
+    pub fn find(&self, map: &Map) -> Option<IntersectionID> {
+        if !map.get_gps_bounds().contains(self.pt) {
+            return None;
+        }
+        let pt = self.pt.to_pt(map.get_gps_bounds());
+        map.all_intersections()
+            .iter()
+            .map(|i| (i.id, i.polygon.center().dist_to(pt)))
+            .filter(|(_, dist)| *dist <= MAX_SHIFT)
+            .min_by_key(|(_, dist)| *dist)
+            .map(|(i, _)| i)
+    }
+
+    /// Is the intersection still at this location?
+    pub fn matches(&self, map: &Map, i: IntersectionID) -> bool {
+        map.maybe_get_i(i)
+            .map(|i| {
+                i.polygon
+                    .center()
+                    .dist_to(self.pt.to_pt(map.get_gps_bounds()))
+                    <= MAX_SHIFT
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// What happened to one saved item when loading it on a different version of the map.
+#[derive(Clone, Debug, Serialize)]
+pub enum MigrationOutcome {
+    /// Everything it refers to still exists
+    CarriedOver,
+    /// Something it refers to changed IDs, but was found again by location. Describes the new
+    /// objects.
+    Moved(String),
+    /// It couldn't be matched to the new map, for this reason
+    Dropped(String),
+    /// The IDs it refers to still exist, but it was saved without locations, so there's no way to
+    /// tell if they still mean the same thing. Kept as is, but somebody should check it.
+    Unverified,
+}
+
+/// One entry in a report of migrating saved edits to a new version of a map
+#[derive(Clone, Debug, Serialize)]
+pub struct MigrationItem {
+    pub description: String,
+    pub outcome: MigrationOutcome,
+}
+
+impl MigrationItem {
+    pub fn describe(&self) -> String {
+        match self.outcome {
+            MigrationOutcome::CarriedOver => format!("{}: carried over", self.description),
+            MigrationOutcome::Moved(ref to) => format!("{}: moved to {}", self.description, to),
+            MigrationOutcome::Dropped(ref reason) => {
+                format!("{}: dropped, {}", self.description, reason)
+            }
+            MigrationOutcome::Unverified => format!(
+                "{}: kept, but it was saved without locations, so check it",
+                self.description
+            ),
+        }
+    }
+}
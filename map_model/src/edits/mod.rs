@@ -10,7 +10,8 @@ use serde::{Deserialize, Serialize};
 use abstutil::Timer;
 use geom::{Distance, HashablePt2D, Line, Speed, Time};
 
-pub use self::perma::PermanentMapEdits;
+pub use self::migrate::{IntersectionLocation, MigrationItem, MigrationOutcome, RoadLocation};
+pub use self::perma::{EditsValidation, PermanentMapEdits};
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
 use crate::make::{match_points_to_lanes, snap_driveway, trim_path};
 use crate::{
@@ -20,6 +21,7 @@ use crate::{
 };
 
 mod compat;
+mod migrate;
 mod perma;
 
 /// Represents changes to a map. Note this isn't serializable -- that's what `PermanentMapEdits`
@@ -49,6 +51,9 @@ pub struct MapEdits {
 #[derive(Debug, Clone, PartialEq)]
 pub enum EditIntersection {
     StopSign(ControlStopSign),
+    /// A roundabout or give-way intersection. `must_stop` on a road means entering from it must
+    /// yield to everybody else.
+    Yield(ControlStopSign),
     // Don't keep ControlTrafficSignal here, because it contains movements that should be
     // generated after all lane edits are applied.
     TrafficSignal(traffic_signal_data::TrafficSignal),
@@ -130,6 +135,7 @@ impl EditRoad {
                     },
                     // Dummy
                     width: Distance::ZERO,
+                    allowed_turns: None,
                 })
                 .collect(),
             speed_limit: Speed::ZERO,
@@ -220,16 +226,7 @@ impl MapEdits {
     /// match the current map. If the resulting edits are totally empty, consider that a failure --
     /// the edits likely don't cover this map at all.
     pub fn load_from_file(map: &Map, path: String, timer: &mut Timer) -> Result<MapEdits> {
-        let perma = match abstio::maybe_read_json::<PermanentMapEdits>(path.clone(), timer) {
-            Ok(perma) => perma,
-            Err(_) => {
-                // The JSON format may have changed, so attempt backwards compatibility.
-                let bytes = abstio::slurp_file(path)?;
-                let contents = std::str::from_utf8(&bytes)?;
-                let value = serde_json::from_str(contents)?;
-                compat::upgrade(value, map)?
-            }
-        };
+        let perma = PermanentMapEdits::load_from_file(map, path, timer)?;
         let edits = perma.into_edits_permissive(map);
         if edits.commands.is_empty() {
             bail!("None of the edits apply to this map");
@@ -242,8 +239,8 @@ impl MapEdits {
     /// failure -- the edits likely don't cover this map at all.
     pub fn load_from_bytes(map: &Map, bytes: Vec<u8>) -> Result<MapEdits> {
         let perma = match abstutil::from_json::<PermanentMapEdits>(&bytes) {
-            Ok(perma) => perma,
-            Err(_) => {
+            Ok(perma) if perma.version == perma::VERSION => perma,
+            _ => {
                 // The JSON format may have changed, so attempt backwards compatibility.
                 let contents = std::str::from_utf8(&bytes)?;
                 let value = serde_json::from_str(contents)?;
@@ -391,6 +388,7 @@ impl EditCmd {
             // TODO Describe changes
             EditCmd::ChangeIntersection { i, new, .. } => match new {
                 EditIntersection::StopSign(_) => format!("stop sign #{}", i.0),
+                EditIntersection::Yield(_) => format!("yield #{}", i.0),
                 EditIntersection::TrafficSignal(_) => format!("traffic signal #{}", i.0),
                 EditIntersection::Closed => format!("close {}", i),
             },
@@ -451,6 +449,10 @@ impl EditCmd {
                         map.intersections[i.0].intersection_type = IntersectionType::StopSign;
                         map.stop_signs.insert(*i, ss.clone());
                     }
+                    EditIntersection::Yield(ref ss) => {
+                        map.intersections[i.0].intersection_type = IntersectionType::Yield;
+                        map.stop_signs.insert(*i, ss.clone());
+                    }
                     EditIntersection::TrafficSignal(ref raw_ts) => {
                         map.intersections[i.0].intersection_type = IntersectionType::TrafficSignal;
                         if old == &EditIntersection::Closed {
@@ -531,7 +533,7 @@ fn recalculate_turns(id: IntersectionID, map: &mut Map, effects: &mut EditEffect
     i.movements = movements;
 
     match i.intersection_type {
-        IntersectionType::StopSign => {
+        IntersectionType::StopSign | IntersectionType::Yield => {
             // Stop sign policy usually doesn't depend on incoming lane types, except when changing
             // to/from construction. To be safe, always regenerate. Edits to stop signs are rare
             // anyway. And when we're smarter about preserving traffic signal changes in the face
@@ -798,6 +800,7 @@ impl Map {
     pub fn get_i_edit(&self, i: IntersectionID) -> EditIntersection {
         match self.get_i(i).intersection_type {
             IntersectionType::StopSign => EditIntersection::StopSign(self.get_stop_sign(i).clone()),
+            IntersectionType::Yield => EditIntersection::Yield(self.get_stop_sign(i).clone()),
             IntersectionType::TrafficSignal => {
                 EditIntersection::TrafficSignal(self.get_traffic_signal(i).export(self))
             }
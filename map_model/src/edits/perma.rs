@@ -4,12 +4,19 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
 use abstio::MapName;
-use abstutil::{deserialize_btreemap, serialize_btreemap};
+use abstutil::{deserialize_btreemap, serialize_btreemap, Timer};
 use geom::Time;
 
-use crate::edits::{EditCmd, EditIntersection, EditRoad, MapEdits};
+use crate::edits::{
+    EditCmd, EditIntersection, EditRoad, IntersectionLocation, MapEdits, MigrationItem,
+    MigrationOutcome, RoadLocation,
+};
 use crate::raw::OriginalRoad;
-use crate::{osm, ControlStopSign, IntersectionID, Map};
+use crate::{osm, ControlStopSign, ControlTrafficSignal, IntersectionID, Map};
+
+/// The current version of the PermanentMapEdits format. Increase this every time there's a schema
+/// change, and add a transformation to `compat`.
+pub(crate) const VERSION: usize = 14;
 
 /// MapEdits are converted to this before serializing. Referencing things like LaneID in a Map won't
 /// work if the basemap is rebuilt from new OSM data, so instead we use stabler OSM IDs that're less
@@ -29,6 +36,32 @@ pub struct PermanentMapEdits {
     pub proposal_description: Vec<String>,
     /// The link is optional even for proposals
     pub proposal_link: Option<String>,
+
+    /// Where the roads and intersections referenced by commands were, so they can be found again
+    /// if the map is regenerated and their OSM IDs change. Older edits don't have these.
+    #[serde(
+        default,
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    road_locations: BTreeMap<OriginalRoad, RoadLocation>,
+    #[serde(
+        default,
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    intersection_locations: BTreeMap<osm::NodeID, IntersectionLocation>,
+}
+
+/// The result of checking saved edits against a basemap rebuilt from new OSM data
+#[derive(Serialize)]
+pub struct EditsValidation {
+    pub edits_name: String,
+    /// Commands that can't be applied anymore, and why
+    pub broken: Vec<String>,
+    /// Commands that still apply, but touch a road or intersection that changed, so the original
+    /// intent might be lost
+    pub needs_review: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -40,6 +73,13 @@ pub enum PermanentEditIntersection {
         )]
         must_stop: BTreeMap<OriginalRoad, bool>,
     },
+    Yield {
+        #[serde(
+            serialize_with = "serialize_btreemap",
+            deserialize_with = "deserialize_btreemap"
+        )]
+        must_stop: BTreeMap<OriginalRoad, bool>,
+    },
     TrafficSignal(traffic_signal_data::TrafficSignal),
     Closed,
 }
@@ -89,6 +129,46 @@ impl EditCmd {
 }
 
 impl PermanentEditCmd {
+    /// A short description, referring to OSM IDs
+    pub fn describe(&self) -> String {
+        match self {
+            PermanentEditCmd::ChangeRouteSchedule { .. } => {
+                format!("change schedule of {}", self.target())
+            }
+            _ => format!("change {}", self.target()),
+        }
+    }
+
+    /// What this command changes
+    fn target(&self) -> String {
+        match self {
+            PermanentEditCmd::ChangeRoad { r, .. } => format!("road {}", r),
+            PermanentEditCmd::ChangeIntersection { i, .. } => format!("intersection {}", i),
+            PermanentEditCmd::ChangeRouteSchedule { gtfs_id, .. } => format!("route {}", gtfs_id),
+        }
+    }
+
+    /// Does this command refer to any of these roads or intersections? Stop signs and traffic
+    /// signals refer to the roads meeting at the intersection.
+    fn touches(
+        &self,
+        roads: &BTreeSet<OriginalRoad>,
+        intersections: &BTreeSet<osm::NodeID>,
+    ) -> bool {
+        match self {
+            PermanentEditCmd::ChangeRoad { r, .. } => roads.contains(r),
+            PermanentEditCmd::ChangeIntersection { i, new, old } => {
+                intersections.contains(i)
+                    || new
+                        .roads()
+                        .into_iter()
+                        .chain(old.roads())
+                        .any(|r| roads.contains(&r))
+            }
+            PermanentEditCmd::ChangeRouteSchedule { .. } => false,
+        }
+    }
+
     pub fn into_cmd(self, map: &Map) -> Result<EditCmd> {
         match self {
             PermanentEditCmd::ChangeRoad { r, new, old } => {
@@ -131,32 +211,123 @@ impl PermanentEditCmd {
 impl MapEdits {
     /// Encode the edits in a permanent format, referring to more-stable OSM IDs.
     pub fn to_permanent(&self, map: &Map) -> PermanentMapEdits {
+        let mut road_locations = BTreeMap::new();
+        let mut intersection_locations = BTreeMap::new();
+        for cmd in &self.commands {
+            match cmd {
+                EditCmd::ChangeRoad { r, .. } => {
+                    road_locations.insert(map.get_r(*r).orig_id, RoadLocation::middle(map, *r));
+                }
+                EditCmd::ChangeIntersection { i, .. } => {
+                    let i = map.get_i(*i);
+                    intersection_locations.insert(i.orig_id, IntersectionLocation::new(map, i.id));
+                    // Stop signs refer to all of the roads
+                    for r in &i.roads {
+                        road_locations.insert(map.get_r(*r).orig_id, RoadLocation::middle(map, *r));
+                    }
+                }
+                EditCmd::ChangeRouteSchedule { .. } => {}
+            }
+        }
+
         PermanentMapEdits {
             map_name: map.get_name().clone(),
             edits_name: self.edits_name.clone(),
-            // Increase this every time there's a schema change
-            version: 11,
+            version: VERSION,
             proposal_description: self.proposal_description.clone(),
             proposal_link: self.proposal_link.clone(),
             commands: self.commands.iter().map(|cmd| cmd.to_perma(map)).collect(),
             merge_zones: self.merge_zones,
+            road_locations,
+            intersection_locations,
         }
     }
 }
 
 impl PermanentMapEdits {
-    /// Transform permanent edits to MapEdits, looking up the map IDs by the hopefully stabler OSM
-    /// IDs. Validate that the basemap hasn't changed in important ways.
-    pub fn into_edits(self, map: &Map) -> Result<MapEdits> {
+    /// Load edits from a file, upgrading old formats if needed. Commands aren't checked against
+    /// the map.
+    pub fn load_from_file(map: &Map, path: String, timer: &mut Timer) -> Result<PermanentMapEdits> {
+        match abstio::maybe_read_json::<PermanentMapEdits>(path.clone(), timer) {
+            Ok(perma) if perma.version == VERSION => Ok(perma),
+            _ => {
+                // The JSON format may have changed, so attempt backwards compatibility. Some
+                // changes are purely additive, so older files might parse but still need
+                // upgrading.
+                let bytes = abstio::slurp_file(path)?;
+                let contents = std::str::from_utf8(&bytes)?;
+                let value = serde_json::from_str(contents)?;
+                super::compat::upgrade(value, map)
+            }
+        }
+    }
+
+    /// Check every command against a map rebuilt from new OSM data, given the roads and
+    /// intersections that the new data changed.
+    pub fn revalidate(
+        &self,
+        map: &Map,
+        changed_roads: &BTreeSet<OriginalRoad>,
+        changed_intersections: &BTreeSet<osm::NodeID>,
+    ) -> EditsValidation {
+        let mut result = EditsValidation {
+            edits_name: self.edits_name.clone(),
+            broken: Vec::new(),
+            needs_review: Vec::new(),
+        };
+        for cmd in &self.commands {
+            let describe = cmd.describe();
+            if cmd.clone().into_cmd(map).is_ok() {
+                if cmd.touches(changed_roads, changed_intersections) {
+                    result.needs_review.push(describe);
+                }
+                continue;
+            }
+            match self.relocate(cmd, map) {
+                Ok((_, moved)) => {
+                    result
+                        .needs_review
+                        .push(format!("{}: moved to {}", describe, moved.target()));
+                }
+                Err(err) => {
+                    result.broken.push(format!("{}: {}", describe, err));
+                }
+            }
+        }
+        result
+    }
+
+    /// Transform permanent edits to MapEdits for a possibly different version of the map.
+    /// Commands referring to roads or intersections whose OSM IDs changed are matched again by
+    /// location. Commands that still don't apply are dropped. Describes what happened to every
+    /// command.
+    pub fn migrate(self, map: &Map) -> (MapEdits, Vec<MigrationItem>) {
+        let mut commands = Vec::new();
+        let mut report = Vec::new();
+        for cmd in &self.commands {
+            let outcome = if let Ok(cmd) = cmd.clone().into_cmd(map) {
+                commands.push(cmd);
+                MigrationOutcome::CarriedOver
+            } else {
+                match self.relocate(cmd, map) {
+                    Ok((cmd, moved)) => {
+                        commands.push(cmd);
+                        MigrationOutcome::Moved(moved.target())
+                    }
+                    Err(err) => MigrationOutcome::Dropped(err.to_string()),
+                }
+            };
+            report.push(MigrationItem {
+                description: cmd.describe(),
+                outcome,
+            });
+        }
+
         let mut edits = MapEdits {
             edits_name: self.edits_name,
             proposal_description: self.proposal_description,
             proposal_link: self.proposal_link,
-            commands: self
-                .commands
-                .into_iter()
-                .map(|cmd| cmd.into_cmd(map))
-                .collect::<Result<Vec<EditCmd>>>()?,
+            commands,
             merge_zones: self.merge_zones,
 
             changed_roads: BTreeSet::new(),
@@ -164,12 +335,63 @@ impl PermanentMapEdits {
             changed_routes: BTreeSet::new(),
         };
         edits.update_derived(map);
-        Ok(edits)
+        (edits, report)
+    }
+
+    /// Point a command that doesn't apply anymore at the roads and intersections now at the
+    /// saved locations.
+    fn relocate(&self, cmd: &PermanentEditCmd, map: &Map) -> Result<(EditCmd, PermanentEditCmd)> {
+        let moved = match cmd.clone() {
+            PermanentEditCmd::ChangeRoad { r, new, old } => PermanentEditCmd::ChangeRoad {
+                r: self.relocate_road(r, map)?,
+                new,
+                old,
+            },
+            PermanentEditCmd::ChangeIntersection { i, new, old } => {
+                PermanentEditCmd::ChangeIntersection {
+                    i: self.relocate_intersection(i, map)?,
+                    new: new.relocate(self, map)?,
+                    old: old.relocate(self, map)?,
+                }
+            }
+            PermanentEditCmd::ChangeRouteSchedule { gtfs_id, .. } => {
+                bail!("can't find {}", gtfs_id);
+            }
+        };
+        Ok((moved.clone().into_cmd(map)?, moved))
+    }
+
+    fn relocate_road(&self, r: OriginalRoad, map: &Map) -> Result<OriginalRoad> {
+        if map.find_r_by_osm_id(r).is_ok() {
+            return Ok(r);
+        }
+        let loc = self
+            .road_locations
+            .get(&r)
+            .ok_or_else(|| anyhow!("can't find {}, and its location wasn't saved", r))?;
+        let (found, _) = loc
+            .find(map)
+            .ok_or_else(|| anyhow!("can't find {} or any road at its old location", r))?;
+        Ok(map.get_r(found).orig_id)
+    }
+
+    fn relocate_intersection(&self, i: osm::NodeID, map: &Map) -> Result<osm::NodeID> {
+        if map.find_i_by_osm_id(i).is_ok() {
+            return Ok(i);
+        }
+        let loc = self
+            .intersection_locations
+            .get(&i)
+            .ok_or_else(|| anyhow!("can't find {}, and its location wasn't saved", i))?;
+        let found = loc
+            .find(map)
+            .ok_or_else(|| anyhow!("can't find {} or any intersection at its old location", i))?;
+        Ok(map.get_i(found).orig_id)
     }
 
     /// Transform permanent edits to MapEdits, looking up the map IDs by the hopefully stabler OSM
-    /// IDs. Strip out commands that're broken, but log warnings.
-    pub fn into_edits_permissive(self, map: &Map) -> MapEdits {
+    /// IDs. Validate that the basemap hasn't changed in important ways.
+    pub fn into_edits(self, map: &Map) -> Result<MapEdits> {
         let mut edits = MapEdits {
             edits_name: self.edits_name,
             proposal_description: self.proposal_description,
@@ -177,14 +399,8 @@ impl PermanentMapEdits {
             commands: self
                 .commands
                 .into_iter()
-                .filter_map(|cmd| match cmd.into_cmd(map) {
-                    Ok(cmd) => Some(cmd),
-                    Err(err) => {
-                        warn!("Skipping broken command: {}", err);
-                        None
-                    }
-                })
-                .collect(),
+                .map(|cmd| cmd.into_cmd(map))
+                .collect::<Result<Vec<EditCmd>>>()?,
             merge_zones: self.merge_zones,
 
             changed_roads: BTreeSet::new(),
@@ -192,6 +408,27 @@ impl PermanentMapEdits {
             changed_routes: BTreeSet::new(),
         };
         edits.update_derived(map);
+        Ok(edits)
+    }
+
+    /// Transform permanent edits to MapEdits, looking up the map IDs by the hopefully stabler OSM
+    /// IDs, or by location if those changed. Strip out commands that're broken, but log warnings.
+    pub fn into_edits_permissive(self, map: &Map) -> MapEdits {
+        let (edits, report) = self.migrate(map);
+        for item in report {
+            match item.outcome {
+                MigrationOutcome::CarriedOver => {}
+                MigrationOutcome::Moved(_) => {
+                    info!("{}", item.describe());
+                }
+                MigrationOutcome::Dropped(_) => {
+                    warn!("Skipping broken command: {}", item.describe());
+                }
+                MigrationOutcome::Unverified => {
+                    warn!("{}", item.describe());
+                }
+            }
+        }
         edits
     }
 
@@ -210,11 +447,10 @@ impl EditIntersection {
     fn to_permanent(&self, map: &Map) -> PermanentEditIntersection {
         match self {
             EditIntersection::StopSign(ref ss) => PermanentEditIntersection::StopSign {
-                must_stop: ss
-                    .roads
-                    .iter()
-                    .map(|(r, val)| (map.get_r(*r).orig_id, val.must_stop))
-                    .collect(),
+                must_stop: permanent_must_stop(ss, map),
+            },
+            EditIntersection::Yield(ref ss) => PermanentEditIntersection::Yield {
+                must_stop: permanent_must_stop(ss, map),
             },
             EditIntersection::TrafficSignal(ref raw_ts) => {
                 PermanentEditIntersection::TrafficSignal(raw_ts.clone())
@@ -225,35 +461,136 @@ impl EditIntersection {
 }
 
 impl PermanentEditIntersection {
-    fn with_permanent(self, i: IntersectionID, map: &Map) -> Result<EditIntersection> {
+    fn roads(&self) -> Vec<OriginalRoad> {
         match self {
-            PermanentEditIntersection::StopSign { must_stop } => {
-                let mut translated_must_stop = BTreeMap::new();
-                for (r, stop) in must_stop {
-                    translated_must_stop.insert(map.find_r_by_osm_id(r)?, stop);
-                }
+            PermanentEditIntersection::StopSign { must_stop }
+            | PermanentEditIntersection::Yield { must_stop } => must_stop.keys().cloned().collect(),
+            PermanentEditIntersection::TrafficSignal(ts) => signal_turns(ts)
+                .flat_map(|turn| [signal_road(&turn.from), signal_road(&turn.to)])
+                .collect(),
+            PermanentEditIntersection::Closed => Vec::new(),
+        }
+    }
 
-                // Make sure the roads exactly match up
-                let mut ss = ControlStopSign::new(map, i);
-                if translated_must_stop.len() != ss.roads.len() {
-                    bail!(
-                        "Stop sign has {} roads now, but {} from edits",
-                        ss.roads.len(),
-                        translated_must_stop.len()
-                    );
+    /// Point stop signs and traffic signals at the roads now at the saved locations
+    fn relocate(self, edits: &PermanentMapEdits, map: &Map) -> Result<PermanentEditIntersection> {
+        let relocate_must_stop = |must_stop: BTreeMap<OriginalRoad, bool>| {
+            must_stop
+                .into_iter()
+                .map(|(r, stop)| Ok((edits.relocate_road(r, map)?, stop)))
+                .collect::<Result<BTreeMap<_, _>>>()
+        };
+        Ok(match self {
+            PermanentEditIntersection::StopSign { must_stop } => {
+                PermanentEditIntersection::StopSign {
+                    must_stop: relocate_must_stop(must_stop)?,
                 }
-                for (r, stop) in translated_must_stop {
-                    if let Some(road) = ss.roads.get_mut(&r) {
-                        road.must_stop = stop;
-                    } else {
-                        bail!("{} doesn't connect to {}", i, r);
+            }
+            PermanentEditIntersection::Yield { must_stop } => PermanentEditIntersection::Yield {
+                must_stop: relocate_must_stop(must_stop)?,
+            },
+            PermanentEditIntersection::TrafficSignal(mut ts) => {
+                ts.intersection_osm_node_id = edits
+                    .relocate_intersection(osm::NodeID(ts.intersection_osm_node_id), map)?
+                    .0;
+                for plan in &mut ts.plans {
+                    for stage in &mut plan.stages {
+                        for turns in [&mut stage.protected_turns, &mut stage.permitted_turns] {
+                            *turns = std::mem::take(turns)
+                                .into_iter()
+                                .map(|turn| relocate_signal_turn(turn, edits, map))
+                                .collect::<Result<_>>()?;
+                        }
                     }
                 }
+                PermanentEditIntersection::TrafficSignal(ts)
+            }
+            PermanentEditIntersection::Closed => PermanentEditIntersection::Closed,
+        })
+    }
 
-                Ok(EditIntersection::StopSign(ss))
+    fn with_permanent(self, i: IntersectionID, map: &Map) -> Result<EditIntersection> {
+        match self {
+            PermanentEditIntersection::StopSign { must_stop } => Ok(EditIntersection::StopSign(
+                stop_sign_from_permanent(must_stop, i, map)?,
+            )),
+            PermanentEditIntersection::Yield { must_stop } => Ok(EditIntersection::Yield(
+                stop_sign_from_permanent(must_stop, i, map)?,
+            )),
+            PermanentEditIntersection::TrafficSignal(ts) => {
+                // Make sure all of the movements still exist
+                ControlTrafficSignal::import(ts.clone(), i, map)?;
+                Ok(EditIntersection::TrafficSignal(ts))
             }
-            PermanentEditIntersection::TrafficSignal(ts) => Ok(EditIntersection::TrafficSignal(ts)),
             PermanentEditIntersection::Closed => Ok(EditIntersection::Closed),
         }
     }
 }
+
+fn signal_turns(
+    ts: &traffic_signal_data::TrafficSignal,
+) -> impl Iterator<Item = &traffic_signal_data::Turn> {
+    ts.plans
+        .iter()
+        .flat_map(|plan| &plan.stages)
+        .flat_map(|stage| stage.protected_turns.iter().chain(&stage.permitted_turns))
+}
+
+fn signal_road(r: &traffic_signal_data::DirectedRoad) -> OriginalRoad {
+    OriginalRoad::new(r.osm_way_id, (r.osm_node1, r.osm_node2))
+}
+
+/// Point a traffic signal movement at the roads now at the saved locations. A road found again by
+/// location points the same way, so the direction of the movement along it doesn't change.
+fn relocate_signal_turn(
+    mut turn: traffic_signal_data::Turn,
+    edits: &PermanentMapEdits,
+    map: &Map,
+) -> Result<traffic_signal_data::Turn> {
+    for r in [&mut turn.from, &mut turn.to] {
+        let found = edits.relocate_road(signal_road(r), map)?;
+        r.osm_way_id = found.osm_way_id.0;
+        r.osm_node1 = found.i1.0;
+        r.osm_node2 = found.i2.0;
+    }
+    turn.intersection_osm_node_id = edits
+        .relocate_intersection(osm::NodeID(turn.intersection_osm_node_id), map)?
+        .0;
+    Ok(turn)
+}
+
+fn permanent_must_stop(ss: &ControlStopSign, map: &Map) -> BTreeMap<OriginalRoad, bool> {
+    ss.roads
+        .iter()
+        .map(|(r, val)| (map.get_r(*r).orig_id, val.must_stop))
+        .collect()
+}
+
+fn stop_sign_from_permanent(
+    must_stop: BTreeMap<OriginalRoad, bool>,
+    i: IntersectionID,
+    map: &Map,
+) -> Result<ControlStopSign> {
+    let mut translated_must_stop = BTreeMap::new();
+    for (r, stop) in must_stop {
+        translated_must_stop.insert(map.find_r_by_osm_id(r)?, stop);
+    }
+
+    // Make sure the roads exactly match up
+    let mut ss = ControlStopSign::new(map, i);
+    if translated_must_stop.len() != ss.roads.len() {
+        bail!(
+            "Stop sign has {} roads now, but {} from edits",
+            ss.roads.len(),
+            translated_must_stop.len()
+        );
+    }
+    for (r, stop) in translated_must_stop {
+        if let Some(road) = ss.roads.get_mut(&r) {
+            road.must_stop = stop;
+        } else {
+            bail!("{} doesn't connect to {}", i, r);
+        }
+    }
+    Ok(ss)
+}
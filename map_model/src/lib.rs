@@ -38,7 +38,8 @@ use geom::{Bounds, GPSBounds, Polygon};
 
 pub use crate::city::City;
 pub use crate::edits::{
-    EditCmd, EditEffects, EditIntersection, EditRoad, MapEdits, PermanentMapEdits,
+    EditCmd, EditEffects, EditIntersection, EditRoad, EditsValidation, IntersectionLocation,
+    MapEdits, MigrationItem, MigrationOutcome, PermanentMapEdits, RoadLocation,
 };
 pub use crate::make::RawToMapOptions;
 pub use crate::map::{DrivingSide, MapConfig};
@@ -49,17 +50,21 @@ pub use crate::objects::building::{
 };
 pub use crate::objects::intersection::{Intersection, IntersectionID, IntersectionType};
 pub use crate::objects::lane::{
-    BufferType, CommonEndpoint, Lane, LaneID, LaneSpec, LaneType, NORMAL_LANE_THICKNESS,
-    PARKING_LOT_SPOT_LENGTH, SIDEWALK_THICKNESS,
+    BufferType, CommonEndpoint, Lane, LaneID, LaneSpec, LaneType, LOADING_ZONE_SPOT_LENGTH,
+    NORMAL_LANE_THICKNESS, PARKING_LOT_SPOT_LENGTH, SIDEWALK_THICKNESS,
 };
 pub use crate::objects::movement::{CompressedMovementID, Movement, MovementID};
 pub use crate::objects::parking_lot::{ParkingLot, ParkingLotID};
-pub use crate::objects::road::{DirectedRoadID, Direction, Road, RoadID, RoadSideID, SideOfRoad};
+pub use crate::objects::road::{
+    DirectedRoadID, Direction, LaneConnectivity, Road, RoadID, RoadSideID, SideOfRoad,
+};
 pub use crate::objects::stop_signs::{ControlStopSign, RoadWithStopSign};
-pub use crate::objects::traffic_signals::{ControlTrafficSignal, Stage, StageType};
+pub use crate::objects::traffic_signals::{
+    Actuation, ControlTrafficSignal, Stage, StageType, TimingPlan,
+};
 pub use crate::objects::transit::{TransitRoute, TransitRouteID, TransitStop, TransitStopID};
 pub use crate::objects::turn::{Turn, TurnID, TurnPriority, TurnType};
-pub use crate::objects::zone::{AccessRestrictions, Zone};
+pub use crate::objects::zone::{AccessRestrictions, ConditionalAccess, TimeWindows, Zone};
 pub use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn};
 use crate::pathfind::Pathfinder;
 pub use crate::pathfind::{
@@ -48,6 +48,14 @@ fn should_collapse(r1: OriginalRoad, r2: OriginalRoad, raw: &RawMap) -> Result<(
     if !road2.turn_restrictions.is_empty() || !road2.complicated_turn_restrictions.is_empty() {
         bail!("one road has turn restrictions");
     }
+    if !road1.conditional_turn_restrictions.is_empty()
+        || !road2.conditional_turn_restrictions.is_empty()
+    {
+        bail!("one road has conditional turn restrictions");
+    }
+    if !road1.lane_connectivity.is_empty() || !road2.lane_connectivity.is_empty() {
+        bail!("one road has lane connectivity");
+    }
 
     // Avoid two one-ways that point at each other. https://www.openstreetmap.org/node/440979339 is
     // a bizarre example. These are actually blackholed, some problem with service roads.
@@ -229,6 +237,18 @@ pub fn collapse_intersection(raw: &mut RawMap, i: NodeID) {
                 *id2 = new_r1;
             }
         }
+
+        for (_, id, _) in &mut road.conditional_turn_restrictions {
+            if rewrite(id) {
+                *id = new_r1;
+            }
+        }
+
+        for (id, _) in &mut road.lane_connectivity {
+            if rewrite(id) {
+                *id = new_r1;
+            }
+        }
     }
 }
 
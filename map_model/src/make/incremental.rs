@@ -0,0 +1,173 @@
+//! Updates an existing Map after small OSM changes, instead of building everything again from the
+//! RawMap. Only tag changes on existing roads and control changes at existing intersections are
+//! handled here; anything else needs a full rebuild with `Map::create_from_raw`.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use abstutil::Timer;
+
+use super::RawToMapOptions;
+use crate::raw::RawMap;
+use crate::{
+    osm, ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, EditRoad,
+    IntersectionType, Map,
+};
+
+/// Changing any of these tags affects simplification, road geometry, or z-ordering, which only a
+/// full rebuild handles.
+const STRUCTURAL_TAGS: [&str; 6] = [
+    osm::HIGHWAY,
+    "junction",
+    "area",
+    "layer",
+    "bridge",
+    "tunnel",
+];
+
+impl Map {
+    /// `before` is the RawMap this map was built from, and `after` is the same RawMap with OSM
+    /// changes applied. If every difference can be applied in place, only the affected roads and
+    /// intersections (and their neighbors' turns, buildings, and parking lots) are regenerated,
+    /// the same way map edits are applied. Otherwise, returns an error without changing the map.
+    ///
+    /// Pairs of nearby traffic signals aren't re-synchronized like they are in a full rebuild.
+    pub fn update_from_raw(
+        &mut self,
+        mut before: RawMap,
+        mut after: RawMap,
+        opts: &RawToMapOptions,
+        timer: &mut Timer,
+    ) -> Result<()> {
+        if !self.edits.commands.is_empty() {
+            bail!("{} has edits applied", self.name.describe());
+        }
+
+        // The map is built from the simplified RawMap, and tag changes can affect what gets
+        // simplified, so compare the simplified versions.
+        timer.start("simplify RawMaps");
+        before.run_all_simplifications(opts.consolidate_all_intersections, timer);
+        after.run_all_simplifications(opts.consolidate_all_intersections, timer);
+        timer.stop("simplify RawMaps");
+
+        let before_roads = std::mem::take(&mut before.roads);
+        let after_roads = std::mem::take(&mut after.roads);
+        let before_intersections = std::mem::take(&mut before.intersections);
+        let after_intersections = std::mem::take(&mut after.intersections);
+        if !before_roads.keys().eq(after_roads.keys())
+            || !before_intersections.keys().eq(after_intersections.keys())
+        {
+            bail!("roads or intersections were added or removed");
+        }
+        if abstutil::to_json_terse(&before) != abstutil::to_json_terse(&after) {
+            bail!("something besides roads and intersections changed");
+        }
+
+        // Check everything before changing the map
+        let mut road_tags = BTreeMap::new();
+        for (id, after_road) in &after_roads {
+            let before_road = &before_roads[id];
+            if before_road == after_road {
+                continue;
+            }
+            let mut only_tags = before_road.clone();
+            only_tags.osm_tags = after_road.osm_tags.clone();
+            if &only_tags != after_road {
+                bail!("more than the tags of {} changed", id);
+            }
+            for key in STRUCTURAL_TAGS {
+                if before_road.osm_tags.get(key) != after_road.osm_tags.get(key) {
+                    bail!("{} changed on {}", key, id);
+                }
+            }
+
+            let r = self.find_r_by_osm_id(*id)?;
+            let road = self.get_r(r);
+            if road.osm_tags != before_road.osm_tags {
+                bail!("{} doesn't match the RawMap it was built from", id);
+            }
+            if !road.all_transit_stops().is_empty() {
+                bail!("{} has transit stops", id);
+            }
+            road_tags.insert(r, after_road.osm_tags.clone());
+        }
+
+        let mut intersection_types = BTreeMap::new();
+        for (id, after_i) in &after_intersections {
+            let before_i = &before_intersections[id];
+            if before_i == after_i {
+                continue;
+            }
+            let mut only_type = before_i.clone();
+            only_type.intersection_type = after_i.intersection_type;
+            if &only_type != after_i {
+                bail!("more than the type of {} changed", id);
+            }
+            if before_i.intersection_type == IntersectionType::Border
+                || after_i.intersection_type == IntersectionType::Border
+            {
+                bail!("{} changed to or from a border", id);
+            }
+
+            let i = self.find_i_by_osm_id(*id)?;
+            // Importing sometimes downgrades traffic signals
+            if self.get_i(i).intersection_type != before_i.intersection_type {
+                bail!("{} doesn't match the RawMap it was built from", id);
+            }
+            intersection_types.insert(i, after_i.intersection_type);
+        }
+
+        if road_tags.is_empty() && intersection_types.is_empty() {
+            return Ok(());
+        }
+
+        let label = format!(
+            "update {} roads and {} intersections",
+            road_tags.len(),
+            intersection_types.len()
+        );
+        timer.start(&label);
+        // Roads first, since the new intersection controls depend on their lanes
+        let mut edits = self.get_edits().clone();
+        for (r, tags) in road_tags {
+            self.roads[r.0].osm_tags = tags;
+            let old = self.get_r_edit(r);
+            let new = EditRoad::get_orig_from_osm(self.get_r(r), &self.config);
+            if old != new {
+                edits.commands.push(EditCmd::ChangeRoad { r, old, new });
+            }
+        }
+        self.must_apply_edits(edits.clone(), timer);
+
+        for (i, intersection_type) in intersection_types {
+            let new = match intersection_type {
+                IntersectionType::StopSign => {
+                    EditIntersection::StopSign(ControlStopSign::new(self, i))
+                }
+                IntersectionType::Yield => EditIntersection::Yield(ControlStopSign::new(self, i)),
+                // Like a full rebuild, signals without any movements become stop signs
+                IntersectionType::TrafficSignal if self.get_i(i).movements.is_empty() => {
+                    EditIntersection::StopSign(ControlStopSign::new(self, i))
+                }
+                IntersectionType::TrafficSignal => EditIntersection::TrafficSignal(
+                    ControlTrafficSignal::validating_new(self, i).export(self),
+                ),
+                IntersectionType::Construction => EditIntersection::Closed,
+                IntersectionType::Border => unreachable!(),
+            };
+            edits.commands.push(EditCmd::ChangeIntersection {
+                i,
+                old: self.get_i_edit(i),
+                new,
+            });
+        }
+        self.must_apply_edits(edits, timer);
+        self.recalculate_pathfinding_after_edits(timer);
+
+        // The changes are part of the map now, not edits on top of it
+        self.edits = self.new_edits();
+        timer.stop(&label);
+        Ok(())
+    }
+}
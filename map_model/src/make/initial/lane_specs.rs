@@ -1,20 +1,24 @@
 /// Purely from OSM tags, determine the lanes that a road segment has.
+use std::collections::BTreeSet;
 use std::iter;
 
 use abstutil::Tags;
 
-use crate::{osm, BufferType, Direction, DrivingSide, LaneSpec, LaneType, MapConfig};
+use crate::objects::lane::parse_turn_type_from_osm;
+use crate::{osm, BufferType, Direction, DrivingSide, LaneSpec, LaneType, MapConfig, TurnType};
 
 pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
     let fwd = |lt: LaneType| LaneSpec {
         lt,
         dir: Direction::Fwd,
         width: LaneSpec::typical_lane_widths(lt, tags)[0].0,
+        allowed_turns: None,
     };
     let back = |lt: LaneType| LaneSpec {
         lt,
         dir: Direction::Back,
         width: LaneSpec::typical_lane_widths(lt, tags)[0].0,
+        allowed_turns: None,
     };
 
     // Easy special cases first.
@@ -178,6 +182,22 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
         }
     }
 
+    // Turn lanes are only tagged on the last segment of a way before the intersection they apply
+    // to.
+    if tags.contains_key(osm::ENDPT_FWD) {
+        if let Some(spec) = tags
+            .get("turn:lanes:forward")
+            .or_else(|| tags.get("turn:lanes"))
+        {
+            assign_turn_lanes(&mut fwd_side, spec, cfg.driving_side);
+        }
+    }
+    if tags.contains_key(osm::ENDPT_BACK) {
+        if let Some(spec) = tags.get("turn:lanes:backward") {
+            assign_turn_lanes(&mut back_side, spec, cfg.driving_side);
+        }
+    }
+
     if tags.is_any("cycleway", vec!["lane", "track"]) {
         fwd_side.push(fwd(LaneType::Biking));
         if !back_side.is_empty() {
@@ -271,11 +291,24 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
             || tags.is_any(osm::PARKING_BOTH, has_parking.clone());
         let parking_lane_back = tags.is_any(osm::PARKING_LEFT, has_parking.clone())
             || tags.is_any(osm::PARKING_BOTH, has_parking);
+        // Curbside space restricted to loading and unloading becomes a loading zone
+        let loading_fwd = tags.is("parking:condition:right", "loading")
+            || tags.is("parking:condition:both", "loading");
+        let loading_back = tags.is("parking:condition:left", "loading")
+            || tags.is("parking:condition:both", "loading");
         if parking_lane_fwd {
-            fwd_side.push(fwd(LaneType::Parking));
+            fwd_side.push(fwd(if loading_fwd {
+                LaneType::LoadingZone
+            } else {
+                LaneType::Parking
+            }));
         }
         if parking_lane_back {
-            back_side.push(back(LaneType::Parking));
+            back_side.push(back(if loading_back {
+                LaneType::LoadingZone
+            } else {
+                LaneType::Parking
+            }));
         }
     }
 
@@ -358,6 +391,54 @@ fn assemble_ltr(
     }
 }
 
+/// Fills out `allowed_turns` for the driving and bus lanes on one side of the road, using a
+/// `turn:lanes` value. See <https://wiki.openstreetmap.org/wiki/Key:turn>.
+fn assign_turn_lanes(side: &mut [LaneSpec], spec: &str, driving_side: DrivingSide) {
+    let parts: Vec<&str> = spec.split('|').collect();
+    // The side is ordered from the road's center outwards, but turn:lanes is ordered from left to
+    // right in the direction of travel.
+    let mut lanes: Vec<&mut LaneSpec> = side
+        .iter_mut()
+        .filter(|spec| spec.lt == LaneType::Driving || spec.lt == LaneType::Bus)
+        .collect();
+    if driving_side == DrivingSide::Left {
+        lanes.reverse();
+    }
+    // This gets called repeatedly while building a map, so don't warn about mismatches here.
+    if parts.len() != lanes.len() {
+        return;
+    }
+
+    let all_explicit_types: BTreeSet<TurnType> = parts
+        .iter()
+        .flat_map(|part| part.split(';').flat_map(parse_turn_type_from_osm))
+        .collect();
+    for (lane, part) in lanes.into_iter().zip(parts) {
+        lane.allowed_turns = if part == "yes" || part == "psv" || part == "bus" {
+            // TODO Probably the target lane should get marked as LaneType::Bus
+            None
+        } else if part.is_empty() || part == "none" {
+            // These both mean that physically, there's no marking saying what turn is valid. In
+            // practice, this seems to imply straight is always fine, and right/left are fine
+            // unless covered by an explicit turn lane.
+            //
+            // If a multi-lane road lacks markings, this means that the rightmost lanes could turn
+            // left, which probably isn't great for people in the middle lanes going straight.
+            // Further filtering (in remove_merging_turns) will prune this out.
+            let mut implied = BTreeSet::new();
+            implied.insert(TurnType::Straight);
+            for tt in [TurnType::Left, TurnType::Right] {
+                if !all_explicit_types.contains(&tt) {
+                    implied.insert(tt);
+                }
+            }
+            Some(implied)
+        } else {
+            Some(part.split(';').flat_map(parse_turn_type_from_osm).collect())
+        };
+    }
+}
+
 // See https://wiki.openstreetmap.org/wiki/Proposed_features/cycleway:separation#Typical_values.
 // Lots of these mappings are pretty wacky right now. We need more BufferTypes.
 #[allow(clippy::ptr_arg)] // Can't chain with `tags.get("foo").and_then` otherwise
@@ -445,6 +526,19 @@ mod tests {
                 "spddddbbps",
                 "vvvv^^v^^^",
             ),
+            (
+                // I didn't look for a real example of this
+                "https://www.openstreetmap.org/way/353690151",
+                vec![
+                    "lanes=2",
+                    "sidewalk=both",
+                    "parking:lane:both=parallel",
+                    "parking:condition:right=loading",
+                ],
+                DrivingSide::Right,
+                "spddLs",
+                "vvv^^^",
+            ),
             (
                 "https://www.openstreetmap.org/way/389654080",
                 vec![
@@ -550,6 +644,7 @@ mod tests {
                 bikes_can_use_bus_lanes: true,
                 inferred_sidewalks: true,
                 street_parking_spot_length: geom::Distance::meters(8.0),
+                turn_on_red: true,
             };
             let actual = get_lane_specs_ltr(&tags(input.clone()), &cfg);
             let actual_lt: String = actual.iter().map(|s| s.lt.to_char()).collect();
@@ -574,4 +669,54 @@ mod tests {
         }
         assert!(ok);
     }
+
+    #[test]
+    fn test_turn_lanes() {
+        let input = tags(vec![
+            "lanes=3",
+            "oneway=yes",
+            "sidewalk=both",
+            "turn:lanes=left|none|through;right",
+            "abst:endpt_fwd=true",
+        ]);
+        // turn:lanes is always left to right in the direction of travel, which for this one-way
+        // road matches the lane order, no matter which side people drive on.
+        for driving_side in [DrivingSide::Right, DrivingSide::Left] {
+            let cfg = MapConfig {
+                driving_side,
+                bikes_can_use_bus_lanes: true,
+                inferred_sidewalks: true,
+                street_parking_spot_length: geom::Distance::meters(8.0),
+                turn_on_red: true,
+            };
+            let actual: Vec<Option<Vec<TurnType>>> = get_lane_specs_ltr(&input, &cfg)
+                .into_iter()
+                .filter(|spec| spec.lt == LaneType::Driving)
+                .map(|spec| spec.allowed_turns.map(|set| set.into_iter().collect()))
+                .collect();
+            assert_eq!(
+                actual,
+                vec![
+                    Some(vec![TurnType::Left]),
+                    // Left and right turns are both covered by explicit lanes
+                    Some(vec![TurnType::Straight]),
+                    Some(vec![TurnType::Straight, TurnType::Right]),
+                ]
+            );
+        }
+
+        // Turn lanes only apply at the end of the way
+        let mut input = input;
+        input.remove(osm::ENDPT_FWD);
+        let cfg = MapConfig {
+            driving_side: DrivingSide::Right,
+            bikes_can_use_bus_lanes: true,
+            inferred_sidewalks: true,
+            street_parking_spot_length: geom::Distance::meters(8.0),
+            turn_on_red: true,
+        };
+        assert!(get_lane_specs_ltr(&input, &cfg)
+            .into_iter()
+            .all(|spec| spec.allowed_turns.is_none()));
+    }
 }
@@ -12,14 +12,15 @@ pub use self::parking_lots::snap_driveway;
 use crate::pathfind::{CreateEngine, Pathfinder};
 use crate::raw::{OriginalRoad, RawMap};
 use crate::{
-    connectivity, osm, AccessRestrictions, Area, AreaID, ControlStopSign, ControlTrafficSignal,
-    Intersection, IntersectionID, IntersectionType, Lane, LaneID, Map, MapEdits, PathConstraints,
-    Position, Road, RoadID, RoutingParams, Zone,
+    connectivity, osm, AccessRestrictions, Area, AreaID, CommonEndpoint, ControlStopSign,
+    ControlTrafficSignal, Intersection, IntersectionID, IntersectionType, Lane, LaneConnectivity,
+    LaneID, Map, MapEdits, PathConstraints, Position, Road, RoadID, RoutingParams, Zone,
 };
 
 mod bridges;
 mod buildings;
 pub mod collapse_intersections;
+mod incremental;
 pub mod initial;
 pub mod merge_intersections;
 mod parking_lots;
@@ -143,6 +144,15 @@ impl Map {
                         }
                     })
                     .collect(),
+                conditional_turn_restrictions: raw_road
+                    .conditional_turn_restrictions
+                    .iter()
+                    .filter_map(|(rt, to, times)| {
+                        road_id_mapping.get(to).map(|to| (*rt, *to, times.clone()))
+                    })
+                    .collect(),
+                // Filled out below, once all of the roads exist
+                lane_connectivity: Vec::new(),
                 orig_id: r.id,
                 lanes: Vec::new(),
                 center_pts: r.trimmed_center_pts,
@@ -168,6 +178,50 @@ impl Map {
             map.roads.push(road);
         }
 
+        let mut lane_connectivity = Vec::new();
+        for road in &map.roads {
+            for (to, lanes) in &raw.roads[&road.orig_id].lane_connectivity {
+                // Missing roads are filtered or clipped out
+                let to = match road_id_mapping.get(to) {
+                    Some(to) => &map.roads[to.0],
+                    None => continue,
+                };
+                let i = match road.common_endpoint(to) {
+                    CommonEndpoint::One(i) => i,
+                    _ => {
+                        warn!(
+                            "Lane connectivity from {} to {} is ambiguous",
+                            road.orig_id, to.orig_id
+                        );
+                        continue;
+                    }
+                };
+                let num_from_lanes = road.vehicle_lanes(i, true).len();
+                let num_to_lanes = to.vehicle_lanes(i, false).len();
+                if !lanes.iter().all(|(from_lane, to_lanes)| {
+                    *from_lane < num_from_lanes && to_lanes.iter().all(|l| *l < num_to_lanes)
+                }) {
+                    warn!(
+                        "Lane connectivity from {} to {} doesn't match the lanes",
+                        road.orig_id, to.orig_id
+                    );
+                    continue;
+                }
+                lane_connectivity.push((
+                    road.id,
+                    LaneConnectivity {
+                        to: to.id,
+                        lanes: lanes.clone(),
+                        num_from_lanes,
+                        num_to_lanes,
+                    },
+                ));
+            }
+        }
+        for (r, connectivity) in lane_connectivity {
+            map.roads[r.0].lane_connectivity.push(connectivity);
+        }
+
         for i in map.intersections.iter_mut() {
             if i.is_border() && i.roads.len() != 1 {
                 panic!(
@@ -259,15 +313,17 @@ impl Map {
 
         let mut stop_signs: BTreeMap<IntersectionID, ControlStopSign> = BTreeMap::new();
         let mut traffic_signals: BTreeMap<IntersectionID, ControlTrafficSignal> = BTreeMap::new();
+        let mut downgraded_signals = Vec::new();
         for i in &map.intersections {
             match i.intersection_type {
-                IntersectionType::StopSign => {
+                IntersectionType::StopSign | IntersectionType::Yield => {
                     stop_signs.insert(i.id, ControlStopSign::new(&map, i.id));
                 }
                 IntersectionType::TrafficSignal => {
                     if i.movements.is_empty() {
                         error!("Traffic signal at {} downgraded to stop sign, because it has no movements -- probably roads under construction", i.orig_id);
                         stop_signs.insert(i.id, ControlStopSign::new(&map, i.id));
+                        downgraded_signals.push(i.id);
                     } else {
                         traffic_signals
                             .insert(i.id, ControlTrafficSignal::validating_new(&map, i.id));
@@ -279,7 +335,7 @@ impl Map {
         map.stop_signs = stop_signs;
         map.traffic_signals = traffic_signals;
         // Fix up the type for any problematic traffic signals
-        for i in map.stop_signs.keys() {
+        for i in downgraded_signals {
             map.intersections[i.0].intersection_type = IntersectionType::StopSign;
         }
 
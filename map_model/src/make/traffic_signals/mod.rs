@@ -107,6 +107,8 @@ fn new(id: IntersectionID) -> ControlTrafficSignal {
         id,
         stages: Vec::new(),
         offset: Duration::ZERO,
+        cycle_length: None,
+        time_of_day_plans: Vec::new(),
     }
 }
 
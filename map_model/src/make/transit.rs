@@ -144,6 +144,11 @@ fn create_route(
     snapper: &BorderSnapper,
 ) -> Result<()> {
     // TODO At least warn about stops that failed to snap
+    let keep: Vec<bool> = route
+        .stops
+        .iter()
+        .map(|gtfs_id| gtfs_to_stop_id.contains_key(gtfs_id))
+        .collect();
     let stops: Vec<TransitStopID> = route
         .stops
         .iter()
@@ -208,12 +213,19 @@ fn create_route(
         }
     };
 
-    // TODO This'll come from the RawTransitRoute eventually. For now, every 30 minutes.
-    let spawn_times: Vec<Time> = (0..48)
-        .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
+    // Keep the schedule lined up with the stops that snapped
+    let scheduled_arrivals: Vec<Vec<Time>> = route
+        .schedule
+        .iter()
+        .map(|times| {
+            times
+                .iter()
+                .zip(keep.iter())
+                .filter_map(|(t, keep)| if *keep { Some(*t) } else { None })
+                .collect()
+        })
         .collect();
-
-    let result = TransitRoute {
+    let mut result = TransitRoute {
         id: TransitRouteID(map.transit_routes.len()),
         long_name: route.long_name.clone(),
         short_name: route.short_name.clone(),
@@ -222,8 +234,10 @@ fn create_route(
         start,
         end_border,
         route_type: route.route_type,
-        spawn_times: spawn_times.clone(),
-        orig_spawn_times: spawn_times,
+        // Filled out below
+        spawn_times: Vec::new(),
+        orig_spawn_times: Vec::new(),
+        scheduled_arrivals,
     };
 
     // Check that the paths are valid
@@ -239,6 +253,26 @@ fn create_route(
         }
     }
 
+    // Vehicles should reach the first stop when scheduled, so start them earlier by how long it
+    // takes to get there from the start without any traffic. Without a schedule, just run every
+    // 30 minutes.
+    let spawn_times: Vec<Time> = if result.scheduled_arrivals.is_empty() {
+        (0..48)
+            .map(|i| Time::START_OF_DAY + (i as f64) * Duration::minutes(30))
+            .collect()
+    } else {
+        let approach = map
+            .pathfind(result.all_path_requests(map).remove(0))?
+            .estimate_duration(map, None);
+        result
+            .scheduled_arrivals
+            .iter()
+            .map(|times| times[0].clamped_sub(approach))
+            .collect()
+    };
+    result.spawn_times = spawn_times.clone();
+    result.orig_spawn_times = spawn_times;
+
     map.transit_routes.push(result);
     Ok(())
 }
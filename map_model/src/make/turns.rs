@@ -25,11 +25,11 @@ pub fn make_all_turns(map: &Map, i: &Intersection) -> Vec<Turn> {
         .filter(|t| t.permitted_by_road(i, map))
         .collect();
 
-    // Try to use turn lane tags...
+    // Try to use turn lane tags and lane connectivity...
     let filtered_turns: Vec<Turn> = all_turns
         .clone()
         .into_iter()
-        .filter(|t| t.permitted_by_lane(map))
+        .filter(|t| t.permitted_by_lane(map) && t.permitted_by_connectivity(map))
         .collect();
     // And remove merging left or right turns. If we wanted to remove the "lane-changing at
     // intersections" behavior, we could do this for TurnType::Straight too.
@@ -42,7 +42,7 @@ pub fn make_all_turns(map: &Map, i: &Intersection) -> Vec<Turn> {
                 // U-turns at divided highways are sometimes legal (and a common movement --
                 // https://www.openstreetmap.org/way/361443212), so let OSM turn:lanes override.
                 if src_lane
-                    .get_lane_level_turn_restrictions(map.get_r(src_lane.id.road), false)
+                    .get_lane_level_turn_restrictions(false)
                     .map(|set| !set.contains(&TurnType::UTurn))
                     .unwrap_or(true)
                 {
@@ -349,7 +349,8 @@ fn remove_merging_turns(map: &Map, input: Vec<Turn>, turn_type: TurnType) -> Vec
         // lanes. For now, just give up figuring this out, and allow all combinations.
         //
         // TODO https://wiki.openstreetmap.org/wiki/Relation:connectivity may have hints about a
-        // better algorithm.
+        // better algorithm. When those relations are mapped, make_all_turns already filters using
+        // them.
         if num_src_lanes < num_dst_lanes {
             turns.extend(group);
             continue;
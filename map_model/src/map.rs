@@ -10,7 +10,7 @@ use abstio::{CityName, MapName};
 use abstutil::{prettyprint_usize, serialized_size_bytes, MultiMap, Tags, Timer};
 use geom::{Bounds, Distance, Duration, GPSBounds, Polygon, Pt2D, Ring, Time};
 
-use crate::raw::{OriginalRoad, RawMap};
+use crate::raw::{OriginalRoad, RawMap, RestrictionType};
 use crate::{
     osm, Area, AreaID, AreaType, Building, BuildingID, BuildingType, CommonEndpoint,
     CompressedMovementID, ControlStopSign, ControlTrafficSignal, DirectedRoadID, Direction,
@@ -594,6 +594,23 @@ impl Map {
             .pathfind_with_params(req.clone(), params, cache_custom, self)
             .ok_or_else(|| anyhow!("can't fulfill {}", req))
     }
+    /// Like `pathfind`, but also respects conditional restrictions in effect at this time of day.
+    /// While any restriction is in effect, the first call is much slower, until a pathfinder for
+    /// those restrictions is cached.
+    pub fn pathfind_at(&self, req: PathRequest, time: Time) -> Result<Path> {
+        self.pathfind_v2_at(req, time)?.into_v1(self)
+    }
+    pub fn pathfind_v2_at(&self, req: PathRequest, time: Time) -> Result<PathV2> {
+        // The walking graph doesn't use RoutingParams at all
+        if req.constraints == PathConstraints::Pedestrian {
+            return self.pathfind_v2(req);
+        }
+        self.pathfind_v2_with_params(
+            req,
+            &self.routing_params_at(time),
+            PathfinderCaching::CacheCH,
+        )
+    }
     pub fn should_use_transit(
         &self,
         start: Position,
@@ -689,6 +706,8 @@ impl Map {
     pub fn hack_override_orig_spawn_times(&mut self, br: TransitRouteID, times: Vec<Time>) {
         self.transit_routes[br.0].orig_spawn_times = times.clone();
         self.transit_routes[br.0].spawn_times = times;
+        // The old schedule no longer matches
+        self.transit_routes[br.0].scheduled_arrivals.clear();
     }
 
     pub fn hack_add_area(&mut self, area_type: AreaType, polygon: Polygon, osm_tags: Tags) {
@@ -814,6 +833,44 @@ impl Map {
         &self.routing_params
     }
 
+    /// Returns the routing params baked into the map, plus any conditional access and turn
+    /// restrictions in effect at this time of day. When nothing is in effect, this is equal to
+    /// `routing_params()`.
+    pub fn routing_params_at(&self, time: Time) -> RoutingParams {
+        let mut params = self.routing_params.clone();
+        for r in &self.roads {
+            if !r.access_restrictions.conditional.is_empty() {
+                for dr in r.id.both_directions() {
+                    let allow = r.access_restrictions.allow_through_traffic_at(dr.dir, time);
+                    if allow != r.access_restrictions.allow_through_traffic {
+                        params.conditional_access.insert(dr, allow);
+                    }
+                }
+            }
+
+            for (rt, to, times) in &r.conditional_turn_restrictions {
+                if !times.contains(time) {
+                    continue;
+                }
+                match rt {
+                    RestrictionType::BanTurns => {
+                        params.avoid_movements_between.insert((r.id, *to));
+                    }
+                    RestrictionType::OnlyAllowTurns => {
+                        if let CommonEndpoint::One(i) = r.common_endpoint(self.get_r(*to)) {
+                            for other in &self.get_i(i).roads {
+                                if *other != r.id && other != to {
+                                    params.avoid_movements_between.insert((r.id, *other));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        params
+    }
+
     pub fn road_to_buildings(&self, r: RoadID) -> &BTreeSet<BuildingID> {
         self.road_to_buildings.get(r)
     }
@@ -872,7 +929,7 @@ impl Map {
         turn_type == unprotected_turn_type
             && from.get_detailed_rank() < to.get_detailed_rank()
             && match from.common_endpoint(to) {
-                CommonEndpoint::One(i) => self.get_i(i).is_stop_sign(),
+                CommonEndpoint::One(i) => self.get_i(i).is_stop_sign() || self.get_i(i).is_yield(),
                 _ => false,
             }
     }
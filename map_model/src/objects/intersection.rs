@@ -33,6 +33,10 @@ pub enum IntersectionType {
     TrafficSignal,
     Border,
     Construction,
+    /// A roundabout or give-way junction. Traffic on priority roads (like the circulating lanes
+    /// of a roundabout) goes freely; everybody else enters without stopping, but only when they
+    /// can find a gap. Uses the same `ControlStopSign` to record which roads have priority.
+    Yield,
 }
 
 /// An intersection connects roads. Most have >2 roads and are controlled by stop signs or traffic
@@ -86,6 +90,10 @@ impl Intersection {
         self.intersection_type == IntersectionType::StopSign
     }
 
+    pub fn is_yield(&self) -> bool {
+        self.intersection_type == IntersectionType::Yield
+    }
+
     pub fn is_traffic_signal(&self) -> bool {
         self.intersection_type == IntersectionType::TrafficSignal
     }
@@ -7,13 +7,15 @@ use abstutil::Tags;
 use geom::{Distance, Line, PolyLine, Polygon, Pt2D};
 
 use crate::{
-    osm, DirectedRoadID, Direction, DrivingSide, IntersectionID, Map, MapConfig, Road, RoadID,
+    osm, DirectedRoadID, Direction, DrivingSide, IntersectionID, Map, MapConfig, RoadID,
     RoadSideID, SideOfRoad, TransitStopID, TurnType,
 };
 
 /// From some manually audited cases in Seattle, the length of parallel street parking spots is a
 /// bit different than the length in parking lots, so set a different value here.
 pub const PARKING_LOT_SPOT_LENGTH: Distance = Distance::const_meters(6.4);
+/// Loading zones need to fit a truck, so each bay is much longer than a street parking spot.
+pub const LOADING_ZONE_SPOT_LENGTH: Distance = Distance::const_meters(15.0);
 
 pub const NORMAL_LANE_THICKNESS: Distance = Distance::const_meters(2.5);
 const SERVICE_ROAD_LANE_THICKNESS: Distance = Distance::const_meters(1.5);
@@ -82,6 +84,8 @@ impl<'de> Deserialize<'de> for LaneID {
 pub enum LaneType {
     Driving,
     Parking,
+    /// Curbside space reserved for delivery vans and trucks while they load and unload
+    LoadingZone,
     Sidewalk,
     // Walkable like a Sidewalk, but very narrow. Used to model pedestrians walking on roads
     // without sidewalks.
@@ -115,6 +119,7 @@ impl LaneType {
             LaneType::Biking => true,
             LaneType::Bus => true,
             LaneType::Parking => false,
+            LaneType::LoadingZone => false,
             LaneType::Sidewalk => false,
             LaneType::Shoulder => false,
             LaneType::SharedLeftTurn => false,
@@ -130,6 +135,7 @@ impl LaneType {
             LaneType::Biking => true,
             LaneType::Bus => true,
             LaneType::Parking => false,
+            LaneType::LoadingZone => false,
             LaneType::Sidewalk => true,
             LaneType::Shoulder => true,
             LaneType::SharedLeftTurn => false,
@@ -149,6 +155,7 @@ impl LaneType {
             LaneType::Biking => "a protected bike lane",
             LaneType::Bus => "a bus-only lane",
             LaneType::Parking => "an on-street parking lane",
+            LaneType::LoadingZone => "a curbside loading zone",
             LaneType::Sidewalk => "a sidewalk",
             LaneType::Shoulder => "a shoulder",
             LaneType::SharedLeftTurn => "a shared left-turn lane",
@@ -168,6 +175,7 @@ impl LaneType {
             LaneType::Biking => "bike lane",
             LaneType::Bus => "bus lane",
             LaneType::Parking => "parking lane",
+            LaneType::LoadingZone => "loading zone",
             LaneType::Sidewalk => "sidewalk",
             LaneType::Shoulder => "shoulder",
             LaneType::SharedLeftTurn => "left-turn lane",
@@ -187,6 +195,7 @@ impl LaneType {
             "bike lane" => Some(LaneType::Biking),
             "bus lane" => Some(LaneType::Bus),
             "parking lane" => Some(LaneType::Parking),
+            "loading zone" => Some(LaneType::LoadingZone),
             "sidewalk" => Some(LaneType::Sidewalk),
             "shoulder" => Some(LaneType::Shoulder),
             "left-turn lane" => Some(LaneType::SharedLeftTurn),
@@ -208,6 +217,7 @@ impl LaneType {
             LaneType::Biking => 'b',
             LaneType::Bus => 'B',
             LaneType::Parking => 'p',
+            LaneType::LoadingZone => 'L',
             LaneType::Sidewalk => 's',
             LaneType::Shoulder => 'S',
             LaneType::SharedLeftTurn => 'C',
@@ -224,6 +234,7 @@ impl LaneType {
             'b' => LaneType::Biking,
             'B' => LaneType::Bus,
             'p' => LaneType::Parking,
+            'L' => LaneType::LoadingZone,
             's' => LaneType::Sidewalk,
             'S' => LaneType::Shoulder,
             'C' => LaneType::SharedLeftTurn,
@@ -254,6 +265,10 @@ pub struct Lane {
     /// graph, because this is near a border.
     pub driving_blackhole: bool,
     pub biking_blackhole: bool,
+
+    /// Which turns vehicles may make from the end of this lane, usually from turn lane markings.
+    /// `None` means any turn is allowed.
+    pub allowed_turns: Option<BTreeSet<TurnType>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -261,6 +276,10 @@ pub struct LaneSpec {
     pub lt: LaneType,
     pub dir: Direction,
     pub width: Distance,
+    /// Which turns vehicles may make from the end of this lane. `None` means any turn is allowed.
+    /// Only meaningful for driving and bus lanes.
+    #[serde(default)]
+    pub allowed_turns: Option<BTreeSet<TurnType>>,
 }
 
 impl Lane {
@@ -316,10 +335,15 @@ impl Lane {
 
     // TODO different types for each lane type might be reasonable
 
+    /// Works for parking lanes and loading zones. Other lanes have no spots.
     pub fn number_parking_spots(&self, cfg: &MapConfig) -> usize {
-        assert_eq!(self.lane_type, LaneType::Parking);
-        // No spots next to intersections
-        let spots = (self.length() / cfg.street_parking_spot_length).floor() - 2.0;
+        let spot_length = if let Some(x) = self.curbside_spot_length(cfg) {
+            x
+        } else {
+            return 0;
+        };
+        // No spots next to intersections. Both ends keep clear one street parking spot's worth.
+        let spots = ((self.length() - cfg.street_parking_spot_length * 2.0) / spot_length).floor();
         if spots >= 1.0 {
             spots as usize
         } else {
@@ -327,6 +351,15 @@ impl Lane {
         }
     }
 
+    /// The length of one spot along a parking lane or loading zone. None for other lanes.
+    pub fn curbside_spot_length(&self, cfg: &MapConfig) -> Option<Distance> {
+        match self.lane_type {
+            LaneType::Parking => Some(cfg.street_parking_spot_length),
+            LaneType::LoadingZone => Some(LOADING_ZONE_SPOT_LENGTH),
+            _ => None,
+        }
+    }
+
     pub fn is_driving(&self) -> bool {
         self.lane_type == LaneType::Driving
     }
@@ -355,6 +388,10 @@ impl Lane {
         self.lane_type == LaneType::Parking
     }
 
+    pub fn is_loading_zone(&self) -> bool {
+        self.lane_type == LaneType::LoadingZone
+    }
+
     pub fn is_light_rail(&self) -> bool {
         self.lane_type == LaneType::LightRail
     }
@@ -388,67 +425,11 @@ impl Lane {
     /// This will return `None` for bus lanes, unless `force_bus` is true. OSM turn restrictions on
     /// bus lanes usually apply to regular vehicles, not the buses. When generating the turns for
     /// buses, we probably don't want to use the restrictions.
-    pub fn get_lane_level_turn_restrictions(
-        &self,
-        road: &Road,
-        force_bus: bool,
-    ) -> Option<BTreeSet<TurnType>> {
+    pub fn get_lane_level_turn_restrictions(&self, force_bus: bool) -> Option<BTreeSet<TurnType>> {
         if !self.is_driving() && (!force_bus || !self.is_bus()) {
             return None;
         }
-
-        let all = if self.dir == Direction::Fwd && road.osm_tags.contains_key(osm::ENDPT_FWD) {
-            road.osm_tags
-                .get("turn:lanes:forward")
-                .or_else(|| road.osm_tags.get("turn:lanes"))?
-        } else if self.dir == Direction::Back && road.osm_tags.contains_key(osm::ENDPT_BACK) {
-            road.osm_tags.get("turn:lanes:backward")?
-        } else {
-            return None;
-        };
-        let parts: Vec<&str> = all.split('|').collect();
-        // Verify the number of parts matches the road's lanes
-        let lanes: Vec<LaneID> = road
-            .children(self.dir)
-            .into_iter()
-            .filter(|(_, lt)| *lt == LaneType::Driving || *lt == LaneType::Bus)
-            .map(|(id, _)| id)
-            .collect();
-        if parts.len() != lanes.len() {
-            warn!("{}'s turn restrictions don't match the lanes", road.orig_id);
-            return None;
-        }
-        // TODO More warnings if this fails
-        let part = parts[lanes.iter().position(|l| *l == self.id)?];
-
-        // TODO Probably the target lane should get marked as LaneType::Bus
-        if part == "yes" || part == "psv" || part == "bus" {
-            return None;
-        }
-
-        // These both mean that physically, there's no marking saying what turn is valid. In
-        // practice, this seems to imply straight is always fine, and right/left are fine unless
-        // covered by an explicit turn lane.
-        //
-        // If a multi-lane road lacks markings, just listening to this function will mean that the
-        // rightmos lanes could turn left, which probably isn't great for people in the middle
-        // lanes going straight. Further filtering (in remove_merging_turns) will prune this out.
-        if part.is_empty() || part == "none" {
-            let all_explicit_types: BTreeSet<TurnType> = parts
-                .iter()
-                .flat_map(|part| part.split(';').flat_map(parse_turn_type_from_osm))
-                .collect();
-            let mut implied = BTreeSet::new();
-            implied.insert(TurnType::Straight);
-            for tt in [TurnType::Left, TurnType::Right] {
-                if !all_explicit_types.contains(&tt) {
-                    implied.insert(tt);
-                }
-            }
-            return Some(implied);
-        }
-
-        Some(part.split(';').flat_map(parse_turn_type_from_osm).collect())
+        self.allowed_turns.clone()
     }
 
     pub fn common_endpoint(&self, other: &Lane) -> CommonEndpoint {
@@ -522,7 +503,7 @@ impl LaneSpec {
                 (Distance::feet(10.0), "minimum"),
             ],
             // https://nacto.org/publication/urban-street-design-guide/street-design-elements/lane-width/
-            LaneType::Parking => {
+            LaneType::Parking | LaneType::LoadingZone => {
                 let mut choices = vec![
                     (Distance::feet(7.0), "narrow"),
                     (SERVICE_ROAD_LANE_THICKNESS, "alley"),
@@ -563,7 +544,7 @@ impl LaneSpec {
 }
 
 // See https://wiki.openstreetmap.org/wiki/Key:turn
-fn parse_turn_type_from_osm(x: &str) -> Vec<TurnType> {
+pub(crate) fn parse_turn_type_from_osm(x: &str) -> Vec<TurnType> {
     match x {
         "left" => vec![TurnType::Left],
         "right" => vec![TurnType::Right],
@@ -10,8 +10,8 @@ use geom::{Distance, PolyLine, Polygon, Speed};
 
 use crate::raw::{OriginalRoad, RestrictionType};
 use crate::{
-    osm, AccessRestrictions, CommonEndpoint, DrivingSide, IntersectionID, Lane, LaneID, LaneSpec,
-    LaneType, Map, PathConstraints, TransitStopID, Zone,
+    osm, AccessRestrictions, CommonEndpoint, ConditionalAccess, DrivingSide, IntersectionID, Lane,
+    LaneID, LaneSpec, LaneType, Map, PathConstraints, TimeWindows, TransitStopID, Zone,
 };
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -164,6 +164,20 @@ impl RoadSideID {
     }
 }
 
+/// Which vehicle lanes lead to which, going from one road to another. Comes from OSM
+/// `type=connectivity` relations. See <https://wiki.openstreetmap.org/wiki/Relation:connectivity>.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LaneConnectivity {
+    pub to: RoadID,
+    /// (from lane, to lanes). Lanes are counted left to right in the direction of travel, only
+    /// including driving and bus lanes, starting from 0.
+    pub lanes: Vec<(usize, Vec<usize>)>,
+    /// How many driving and bus lanes both roads had when the map was imported. If the lanes are
+    /// edited later, the lane numbers no longer mean anything, so this is ignored.
+    pub num_from_lanes: usize,
+    pub num_to_lanes: usize,
+}
+
 /// A Road represents a segment between exactly two Intersections. It contains Lanes as children.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Road {
@@ -173,6 +187,11 @@ pub struct Road {
     pub turn_restrictions: Vec<(RestrictionType, RoadID)>,
     /// self is 'from'. (via, to). Only BanTurns.
     pub complicated_turn_restrictions: Vec<(RoadID, RoadID)>,
+    /// Simple turn restrictions that only apply during some times of day. These aren't used when
+    /// generating turns; pathfinding respects them when a departure time is known.
+    pub conditional_turn_restrictions: Vec<(RestrictionType, RoadID, TimeWindows)>,
+    /// self is 'from'
+    pub lane_connectivity: Vec<LaneConnectivity>,
     pub orig_id: OriginalRoad,
     pub speed_limit: Speed,
     pub access_restrictions: AccessRestrictions,
@@ -206,6 +225,7 @@ impl Road {
                 lt: l.lane_type,
                 dir: l.dir,
                 width: l.width,
+                allowed_turns: l.allowed_turns.clone(),
             })
             .collect()
     }
@@ -474,7 +494,7 @@ impl Road {
     }
 
     pub fn is_private(&self) -> bool {
-        self.access_restrictions != AccessRestrictions::new() && !self.is_light_rail()
+        self.access_restrictions.allow_through_traffic != EnumSet::all() && !self.is_light_rail()
     }
 
     pub(crate) fn access_restrictions_from_osm(&self) -> AccessRestrictions {
@@ -489,8 +509,82 @@ impl Road {
         } else {
             EnumSet::all()
         };
+
+        let mut conditional = Vec::new();
+        // From general to specific, so more specific exceptions win
+        let all_but_train = EnumSet::all() - PathConstraints::Train;
+        let motor_vehicles = if self.osm_tags.is("psv", "yes") || self.osm_tags.is("bus", "yes") {
+            PathConstraints::Car | PathConstraints::Truck
+        } else {
+            PathConstraints::Car | PathConstraints::Bus | PathConstraints::Truck
+        };
+        for (key, modes) in [
+            ("access:conditional", all_but_train),
+            (
+                "vehicle:conditional",
+                all_but_train - PathConstraints::Pedestrian,
+            ),
+            ("motor_vehicle:conditional", motor_vehicles),
+            ("motorcar:conditional", EnumSet::only(PathConstraints::Car)),
+            ("hgv:conditional", EnumSet::only(PathConstraints::Truck)),
+            ("psv:conditional", EnumSet::only(PathConstraints::Bus)),
+            ("bus:conditional", EnumSet::only(PathConstraints::Bus)),
+            ("bicycle:conditional", EnumSet::only(PathConstraints::Bike)),
+            (
+                "foot:conditional",
+                EnumSet::only(PathConstraints::Pedestrian),
+            ),
+        ] {
+            if let Some(value) = self.osm_tags.get(key) {
+                for (value, times) in TimeWindows::parse_conditional(value) {
+                    let (modes, allow) = match value.as_str() {
+                        "yes" | "permissive" | "designated" => (modes, true),
+                        "no" | "private" | "destination" | "permit" => (modes, false),
+                        // Delivery vehicles (trucks) can still get through
+                        "delivery" => (modes - PathConstraints::Truck, false),
+                        _ => {
+                            continue;
+                        }
+                    };
+                    if modes.is_empty() {
+                        continue;
+                    }
+                    conditional.push(ConditionalAccess {
+                        times,
+                        modes,
+                        allow,
+                        direction: None,
+                    });
+                }
+            }
+        }
+
+        if let Some(value) = self.osm_tags.get("oneway:conditional") {
+            let mut modes = PathConstraints::Car | PathConstraints::Bus | PathConstraints::Truck;
+            if !self.osm_tags.is("oneway:bicycle", "no") {
+                modes |= PathConstraints::Bike;
+            }
+            for (value, times) in TimeWindows::parse_conditional(value) {
+                // TODO Conditionally opening up a one-way needs lanes that don't exist
+                let direction = match value.as_str() {
+                    "yes" => Direction::Back,
+                    "-1" => Direction::Fwd,
+                    _ => {
+                        continue;
+                    }
+                };
+                conditional.push(ConditionalAccess {
+                    times,
+                    modes,
+                    allow: false,
+                    direction: Some(direction),
+                });
+            }
+        }
+
         AccessRestrictions {
             allow_through_traffic,
+            conditional,
         }
     }
 
@@ -588,10 +682,27 @@ impl Road {
                 transit_stops: BTreeSet::new(),
                 driving_blackhole: false,
                 biking_blackhole: false,
+                allowed_turns: lane.allowed_turns,
             });
         }
     }
 
+    /// Returns the driving and bus lanes leading towards or away from an intersection, left to
+    /// right in the direction of travel. This is how OSM numbers lanes in `turn:lanes` and
+    /// connectivity relations.
+    pub(crate) fn vehicle_lanes(&self, i: IntersectionID, towards: bool) -> Vec<LaneID> {
+        let dir = if (self.dst_i == i) == towards {
+            Direction::Fwd
+        } else {
+            Direction::Back
+        };
+        self.children(dir)
+            .into_iter()
+            .filter(|(_, lt)| *lt == LaneType::Driving || *lt == LaneType::Bus)
+            .map(|(l, _)| l)
+            .collect()
+    }
+
     /// Returns all lanes located between l1 and l2, exclusive.
     pub fn get_lanes_between(&self, l1: LaneID, l2: LaneID) -> Vec<LaneID> {
         let mut results = Vec::new();
@@ -47,6 +47,8 @@ pub struct ControlStopSign {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RoadWithStopSign {
     pub lane_closest_to_edge: LaneID,
+    /// At yield intersections, vehicles from this road don't stop, but they must give way to
+    /// everybody else.
     pub must_stop: bool,
 }
 
@@ -93,20 +95,42 @@ impl ControlStopSign {
             }
         }
 
-        // Degenerate roads and deadends don't need any stop signs. But be careful with
-        // roundabouts; we want it to be lower priority to enter a roundabout than continue through
-        // it.
-        if ss.roads.len() <= 2
-            && ss
-                .roads
-                .keys()
-                .all(|r| !map.get_r(*r).osm_tags.is("junction", "roundabout"))
-        {
-            return ss;
-        }
-        if map.get_i(id).is_cycleway(map) {
-            // Two cyclepaths intersecting can just yield.
-            return ss;
+        if map.get_i(id).is_yield() {
+            // Use give-way signs tagged on the approaches, if there are any
+            let mut tagged = false;
+            for (r, cfg) in ss.roads.iter_mut() {
+                let r = map.get_r(*r);
+                let tag = if r.dst_i == id {
+                    osm::GIVE_WAY_FWD
+                } else {
+                    osm::GIVE_WAY_BACK
+                };
+                if r.osm_tags.is(tag, "true") {
+                    cfg.must_stop = true;
+                    tagged = true;
+                }
+            }
+            if tagged {
+                return ss;
+            }
+            // Otherwise rank the roads below. Unlike stop signs, even degenerate and cycleway
+            // junctions need somebody to give way.
+        } else {
+            // Degenerate roads and deadends don't need any stop signs. But be careful with
+            // roundabouts; we want it to be lower priority to enter a roundabout than continue
+            // through it.
+            if ss.roads.len() <= 2
+                && ss
+                    .roads
+                    .keys()
+                    .all(|r| !map.get_r(*r).osm_tags.is("junction", "roundabout"))
+            {
+                return ss;
+            }
+            if map.get_i(id).is_cycleway(map) {
+                // Two cyclepaths intersecting can just yield.
+                return ss;
+            }
         }
 
         // Rank each road based on OSM highway type, and additionally:
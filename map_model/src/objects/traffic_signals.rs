@@ -3,7 +3,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use geom::{Distance, Duration, Speed};
+use geom::{Distance, Duration, Speed, Time};
 
 use crate::make::traffic_signals::get_possible_policies;
 use crate::raw::OriginalRoad;
@@ -24,6 +24,23 @@ pub struct ControlTrafficSignal {
     pub id: IntersectionID,
     pub stages: Vec<Stage>,
     pub offset: Duration,
+    /// If present, coordinate with other signals along a corridor that share this cycle length.
+    /// The first stage absorbs time that actuated stages don't use.
+    pub cycle_length: Option<Duration>,
+    /// Alternate plans for other times of day, sorted by start time.
+    pub time_of_day_plans: Vec<TimingPlan>,
+}
+
+/// The stages, offset, and cycle length directly in `ControlTrafficSignal` are the plan used from
+/// midnight. Other plans take over at different times of day, like the AM and PM peaks.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TimingPlan {
+    /// When this plan takes effect, measured from midnight. The plan lasts until the next one
+    /// starts.
+    pub start_time: Duration,
+    pub stages: Vec<Stage>,
+    pub offset: Duration,
+    pub cycle_length: Option<Duration>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -42,6 +59,35 @@ pub enum StageType {
     /// Delay is the elapsed time with no demand that ends a cycle.
     /// Additional is the additional duration for an extended cycle.
     Variable(Duration, Duration, Duration),
+    /// Driven by virtual loop detectors on the approach lanes.
+    Actuated(Actuation),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Actuation {
+    /// The stage always lasts at least this long once it starts.
+    pub min_green: Duration,
+    /// The stage ends after this long, even if vehicles keep arriving ("max-out").
+    pub max_green: Duration,
+    /// After the minimum green, the stage ends once no vehicle is detected for this long
+    /// ("gap-out").
+    pub passage: Duration,
+    /// Serve the stage every cycle for pedestrians. Otherwise, skip it when there's no demand.
+    pub pedestrian_recall: bool,
+    /// Vehicles this close to the stop line place a call.
+    pub detector_length: Distance,
+}
+
+impl Actuation {
+    pub fn new(min_green: Duration, max_green: Duration) -> Actuation {
+        Actuation {
+            min_green,
+            max_green,
+            passage: Duration::seconds(3.0),
+            pedestrian_recall: false,
+            detector_length: Distance::meters(30.0),
+        }
+    }
 }
 
 impl StageType {
@@ -50,6 +96,7 @@ impl StageType {
         match self {
             StageType::Fixed(d) => *d,
             StageType::Variable(duration, _, _) => *duration,
+            StageType::Actuated(ref a) => a.min_green,
         }
     }
 }
@@ -83,23 +130,32 @@ impl ControlTrafficSignal {
     }
 
     pub fn get_min_crossing_time(&self, idx: usize, i: &Intersection) -> Duration {
-        let mut max_distance = Distance::meters(0.0);
-        for movement in &self.stages[idx].protected_movements {
-            if movement.crosswalk {
-                max_distance = max_distance.max(i.movements[movement].geom.length());
+        self.stages[idx].min_crossing_time(i)
+    }
+
+    pub fn validate(&self, i: &Intersection) -> Result<()> {
+        self.validate_stages(&self.stages, i)?;
+        let mut last_start = Duration::ZERO;
+        for plan in &self.time_of_day_plans {
+            if plan.start_time <= last_start || plan.start_time >= Duration::hours(24) {
+                bail!(
+                    "Traffic signal plans for {} must start in order during the day, but one \
+                     starts at {}",
+                    self.id,
+                    plan.start_time
+                );
             }
+            last_start = plan.start_time;
+            self.validate_stages(&plan.stages, i)?;
         }
-        let time = max_distance / CROSSWALK_PACE;
-        assert!(time >= Duration::ZERO);
-        // Round up because it is converted to a usize elsewhere
-        Duration::seconds(time.inner_seconds().ceil())
+        Ok(())
     }
 
-    pub fn validate(&self, i: &Intersection) -> Result<()> {
+    fn validate_stages(&self, stages: &[Stage], i: &Intersection) -> Result<()> {
         // Does the assignment cover the correct set of movements?
         let expected_movements: BTreeSet<MovementID> = i.movements.keys().cloned().collect();
         let mut actual_movements: BTreeSet<MovementID> = BTreeSet::new();
-        for stage in &self.stages {
+        for stage in stages {
             actual_movements.extend(stage.protected_movements.iter());
             actual_movements.extend(stage.yield_movements.iter());
         }
@@ -117,7 +173,7 @@ impl ControlTrafficSignal {
                     .collect::<Vec<_>>()
             );
         }
-        for (stage_index, stage) in self.stages.iter().enumerate() {
+        for (stage_index, stage) in stages.iter().enumerate() {
             // Do any of the priority movements in one stage conflict?
             for m1 in stage.protected_movements.iter().map(|m| &i.movements[m]) {
                 for m2 in stage.protected_movements.iter().map(|m| &i.movements[m]) {
@@ -137,8 +193,19 @@ impl ControlTrafficSignal {
                 // TODO Maybe make UnmarkedCrossing yield
                 assert!(!m.turn_type.pedestrian_crossing())
             }
+            if let StageType::Actuated(ref a) = stage.stage_type {
+                if a.max_green < a.min_green {
+                    bail!(
+                        "Traffic signal stage {} has a max green {} shorter than the min green {}",
+                        stage_index,
+                        a.max_green,
+                        a.min_green
+                    );
+                }
+            }
+
             // Is there enough time in each stage to walk across the crosswalk
-            let min_crossing_time = self.get_min_crossing_time(stage_index, i);
+            let min_crossing_time = stage.min_crossing_time(i);
             if stage.stage_type.simple_duration() < min_crossing_time {
                 bail!(
                     "Traffic signal does not allow enough time in stage to complete the \
@@ -271,9 +338,18 @@ impl ControlTrafficSignal {
         Ok(())
     }
 
+    /// Movements that aren't served by some stage of one of the plans.
     pub fn missing_turns(&self, i: &Intersection) -> BTreeSet<MovementID> {
+        let mut all_missing = BTreeSet::new();
+        for plan in 0..self.num_plans() {
+            all_missing.extend(self.missing_turns_in_plan(plan, i));
+        }
+        all_missing
+    }
+
+    pub fn missing_turns_in_plan(&self, plan: usize, i: &Intersection) -> BTreeSet<MovementID> {
         let mut missing: BTreeSet<MovementID> = i.movements.keys().cloned().collect();
-        for stage in &self.stages {
+        for stage in self.plan_stages(plan) {
             for m in &stage.protected_movements {
                 missing.remove(m);
             }
@@ -292,6 +368,107 @@ impl ControlTrafficSignal {
         }
         total
     }
+
+    // Plan 0 is the one starting at midnight, stored directly in this struct. The rest index into
+    // time_of_day_plans.
+
+    pub fn num_plans(&self) -> usize {
+        1 + self.time_of_day_plans.len()
+    }
+
+    /// Which plan should be active at some time? Plans repeat every day.
+    pub fn plan_at(&self, time: Time) -> usize {
+        let time_of_day = (time - Time::START_OF_DAY) % Duration::hours(24);
+        let mut plan = 0;
+        for (idx, p) in self.time_of_day_plans.iter().enumerate() {
+            if p.start_time <= time_of_day {
+                plan = idx + 1;
+            }
+        }
+        plan
+    }
+
+    pub fn plan_start_time(&self, plan: usize) -> Duration {
+        if plan == 0 {
+            Duration::ZERO
+        } else {
+            self.time_of_day_plans[plan - 1].start_time
+        }
+    }
+
+    pub fn plan_stages(&self, plan: usize) -> &Vec<Stage> {
+        if plan == 0 {
+            &self.stages
+        } else {
+            &self.time_of_day_plans[plan - 1].stages
+        }
+    }
+
+    pub fn plan_stages_mut(&mut self, plan: usize) -> &mut Vec<Stage> {
+        if plan == 0 {
+            &mut self.stages
+        } else {
+            &mut self.time_of_day_plans[plan - 1].stages
+        }
+    }
+
+    pub fn plan_offset(&self, plan: usize) -> Duration {
+        if plan == 0 {
+            self.offset
+        } else {
+            self.time_of_day_plans[plan - 1].offset
+        }
+    }
+
+    pub fn plan_cycle_length(&self, plan: usize) -> Option<Duration> {
+        if plan == 0 {
+            self.cycle_length
+        } else {
+            self.time_of_day_plans[plan - 1].cycle_length
+        }
+    }
+
+    pub fn plan_cycle_length_mut(&mut self, plan: usize) -> &mut Option<Duration> {
+        if plan == 0 {
+            &mut self.cycle_length
+        } else {
+            &mut self.time_of_day_plans[plan - 1].cycle_length
+        }
+    }
+
+    /// Adds a new plan starting at some time of day, copying the stages of whatever plan is
+    /// active then. Returns the index of the new plan.
+    pub fn add_plan(&mut self, start_time: Duration) -> Result<usize> {
+        if start_time <= Duration::ZERO || start_time >= Duration::hours(24) {
+            bail!(
+                "A plan must start sometime during the day, not at {}",
+                start_time
+            );
+        }
+        if self
+            .time_of_day_plans
+            .iter()
+            .any(|p| p.start_time == start_time)
+        {
+            bail!("There's already a plan starting at {}", start_time);
+        }
+        let copy = self.plan_at(Time::START_OF_DAY + start_time);
+        let plan = TimingPlan {
+            start_time,
+            stages: self.plan_stages(copy).clone(),
+            offset: self.plan_offset(copy),
+            cycle_length: self.plan_cycle_length(copy),
+        };
+        self.time_of_day_plans.push(plan);
+        self.time_of_day_plans.sort_by_key(|p| p.start_time);
+        Ok(self.plan_at(Time::START_OF_DAY + start_time))
+    }
+
+    /// Removes one of the time-of-day plans. The plan starting at midnight can't be removed.
+    pub fn remove_plan(&mut self, plan: usize) {
+        assert_ne!(plan, 0);
+        self.time_of_day_plans.remove(plan - 1);
+    }
 }
 
 impl Stage {
@@ -353,10 +530,29 @@ impl Stage {
                 StageType::Variable(_, delay, additional) => {
                     StageType::Variable(time, delay, additional)
                 }
+                StageType::Actuated(ref a) => StageType::Actuated(Actuation {
+                    min_green: time,
+                    max_green: a.max_green.max(time),
+                    ..a.clone()
+                }),
             };
         }
     }
 
+    /// How long the stage must last for pedestrians to finish crossing.
+    pub fn min_crossing_time(&self, i: &Intersection) -> Duration {
+        let mut max_distance = Distance::meters(0.0);
+        for movement in &self.protected_movements {
+            if movement.crosswalk {
+                max_distance = max_distance.max(i.movements[movement].geom.length());
+            }
+        }
+        let time = max_distance / CROSSWALK_PACE;
+        assert!(time >= Duration::ZERO);
+        // Round up because it is converted to a usize elsewhere
+        Duration::seconds(time.inner_seconds().ceil())
+    }
+
     // A trivial function that returns max crosswalk time if the stage is just crosswalks.
     pub fn max_crosswalk_time(&self, i: &Intersection) -> Option<Duration> {
         let mut max_distance = Distance::const_meters(0.0);
@@ -382,104 +578,154 @@ impl ControlTrafficSignal {
     pub fn export(&self, map: &Map) -> traffic_signal_data::TrafficSignal {
         traffic_signal_data::TrafficSignal {
             intersection_osm_node_id: map.get_i(self.id).orig_id.0,
-            plans: vec![traffic_signal_data::Plan {
-                start_time_seconds: 0,
-                stages: self
-                    .stages
-                    .iter()
-                    .map(|s| traffic_signal_data::Stage {
-                        protected_turns: s
-                            .protected_movements
-                            .iter()
-                            .map(|t| export_movement(t, map))
-                            .collect(),
-                        permitted_turns: s
-                            .yield_movements
-                            .iter()
-                            .map(|t| export_movement(t, map))
-                            .collect(),
-                        stage_type: match s.stage_type {
-                            StageType::Fixed(d) => {
-                                traffic_signal_data::StageType::Fixed(d.inner_seconds() as usize)
-                            }
-                            StageType::Variable(min, delay, additional) => {
-                                traffic_signal_data::StageType::Variable(
-                                    min.inner_seconds() as usize,
-                                    delay.inner_seconds() as usize,
-                                    additional.inner_seconds() as usize,
-                                )
-                            }
-                        },
-                    })
-                    .collect(),
-                offset_seconds: self.offset.inner_seconds() as usize,
-            }],
+            plans: (0..self.num_plans())
+                .map(|plan| traffic_signal_data::Plan {
+                    start_time_seconds: self.plan_start_time(plan).inner_seconds() as usize,
+                    stages: self
+                        .plan_stages(plan)
+                        .iter()
+                        .map(|s| export_stage(s, map))
+                        .collect(),
+                    offset_seconds: self.plan_offset(plan).inner_seconds() as usize,
+                    cycle_length_seconds: self
+                        .plan_cycle_length(plan)
+                        .map(|d| d.inner_seconds() as usize),
+                })
+                .collect(),
         }
     }
 
     pub(crate) fn import(
-        mut raw: traffic_signal_data::TrafficSignal,
+        raw: traffic_signal_data::TrafficSignal,
         id: IntersectionID,
         map: &Map,
     ) -> Result<ControlTrafficSignal> {
-        // TODO Only import the first plan. Will import all of them later.
-        let plan = raw.plans.remove(0);
-        let mut stages = Vec::new();
-        for s in plan.stages {
-            let mut errors = Vec::new();
-            let mut protected_movements = BTreeSet::new();
-            for t in s.protected_turns {
-                match import_movement(t, map) {
-                    Ok(mvmnt) => {
-                        protected_movements.insert(mvmnt);
-                    }
-                    Err(err) => {
-                        errors.push(err.to_string());
-                    }
-                }
-            }
-            let mut permitted_movements = BTreeSet::new();
-            for t in s.permitted_turns {
-                match import_movement(t, map) {
-                    Ok(mvmnt) => {
-                        permitted_movements.insert(mvmnt);
-                    }
-                    Err(err) => {
-                        errors.push(err.to_string());
-                    }
-                }
-            }
-            if errors.is_empty() {
-                stages.push(Stage {
-                    protected_movements,
-                    yield_movements: permitted_movements,
-                    stage_type: match s.stage_type {
-                        traffic_signal_data::StageType::Fixed(d) => {
-                            StageType::Fixed(Duration::seconds(d as f64))
-                        }
-                        traffic_signal_data::StageType::Variable(min, delay, additional) => {
-                            StageType::Variable(
-                                Duration::seconds(min as f64),
-                                Duration::seconds(delay as f64),
-                                Duration::seconds(additional as f64),
-                            )
-                        }
-                    },
-                });
-            } else {
-                bail!("{}", errors.join("; "));
+        let mut plans = Vec::new();
+        for plan in raw.plans {
+            let mut stages = Vec::new();
+            for s in plan.stages {
+                stages.push(import_stage(s, map)?);
             }
+            plans.push(TimingPlan {
+                start_time: Duration::seconds(plan.start_time_seconds as f64),
+                stages,
+                offset: Duration::seconds(plan.offset_seconds as f64),
+                cycle_length: plan
+                    .cycle_length_seconds
+                    .map(|secs| Duration::seconds(secs as f64)),
+            });
+        }
+        if plans.is_empty() {
+            bail!("Traffic signal for {} has no plans", id);
         }
+        if plans[0].start_time != Duration::ZERO {
+            // The plans repeat every day, so the last one keeps running past midnight until the
+            // first one starts
+            let mut overnight = plans.last().unwrap().clone();
+            overnight.start_time = Duration::ZERO;
+            plans.insert(0, overnight);
+        }
+        let first = plans.remove(0);
         let ts = ControlTrafficSignal {
             id,
-            stages,
-            offset: Duration::seconds(plan.offset_seconds as f64),
+            stages: first.stages,
+            offset: first.offset,
+            cycle_length: first.cycle_length,
+            time_of_day_plans: plans,
         };
         ts.validate(map.get_i(id))?;
         Ok(ts)
     }
 }
 
+fn export_stage(s: &Stage, map: &Map) -> traffic_signal_data::Stage {
+    traffic_signal_data::Stage {
+        protected_turns: s
+            .protected_movements
+            .iter()
+            .map(|t| export_movement(t, map))
+            .collect(),
+        permitted_turns: s
+            .yield_movements
+            .iter()
+            .map(|t| export_movement(t, map))
+            .collect(),
+        stage_type: match s.stage_type {
+            StageType::Fixed(d) => {
+                traffic_signal_data::StageType::Fixed(d.inner_seconds() as usize)
+            }
+            StageType::Variable(min, delay, additional) => {
+                traffic_signal_data::StageType::Variable(
+                    min.inner_seconds() as usize,
+                    delay.inner_seconds() as usize,
+                    additional.inner_seconds() as usize,
+                )
+            }
+            StageType::Actuated(ref a) => {
+                traffic_signal_data::StageType::Actuated(traffic_signal_data::Actuation {
+                    min_green_seconds: a.min_green.inner_seconds() as usize,
+                    max_green_seconds: a.max_green.inner_seconds() as usize,
+                    passage_seconds: a.passage.inner_seconds() as usize,
+                    pedestrian_recall: a.pedestrian_recall,
+                    detector_length_meters: a.detector_length.inner_meters() as usize,
+                })
+            }
+        },
+    }
+}
+
+fn import_stage(s: traffic_signal_data::Stage, map: &Map) -> Result<Stage> {
+    let mut errors = Vec::new();
+    let mut protected_movements = BTreeSet::new();
+    for t in s.protected_turns {
+        match import_movement(t, map) {
+            Ok(mvmnt) => {
+                protected_movements.insert(mvmnt);
+            }
+            Err(err) => {
+                errors.push(err.to_string());
+            }
+        }
+    }
+    let mut permitted_movements = BTreeSet::new();
+    for t in s.permitted_turns {
+        match import_movement(t, map) {
+            Ok(mvmnt) => {
+                permitted_movements.insert(mvmnt);
+            }
+            Err(err) => {
+                errors.push(err.to_string());
+            }
+        }
+    }
+    if !errors.is_empty() {
+        bail!("{}", errors.join("; "));
+    }
+    Ok(Stage {
+        protected_movements,
+        yield_movements: permitted_movements,
+        stage_type: match s.stage_type {
+            traffic_signal_data::StageType::Fixed(d) => {
+                StageType::Fixed(Duration::seconds(d as f64))
+            }
+            traffic_signal_data::StageType::Variable(min, delay, additional) => {
+                StageType::Variable(
+                    Duration::seconds(min as f64),
+                    Duration::seconds(delay as f64),
+                    Duration::seconds(additional as f64),
+                )
+            }
+            traffic_signal_data::StageType::Actuated(a) => StageType::Actuated(Actuation {
+                min_green: Duration::seconds(a.min_green_seconds as f64),
+                max_green: Duration::seconds(a.max_green_seconds as f64),
+                passage: Duration::seconds(a.passage_seconds as f64),
+                pedestrian_recall: a.pedestrian_recall,
+                detector_length: Distance::meters(a.detector_length_meters as f64),
+            }),
+        },
+    })
+}
+
 fn export_movement(id: &MovementID, map: &Map) -> traffic_signal_data::Turn {
     let from = map.get_r(id.from.road).orig_id;
     let to = map.get_r(id.to.road).orig_id;
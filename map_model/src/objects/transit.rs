@@ -68,6 +68,9 @@ pub struct TransitRoute {
     /// Explicitly store whatever the original was, since this can't be reconstructed without side
     /// input.
     pub orig_spawn_times: Vec<Time>,
+    /// For each of `orig_spawn_times`, when the vehicle is scheduled to arrive at each of `stops`.
+    /// Empty if the schedule isn't known.
+    pub scheduled_arrivals: Vec<Vec<Time>>,
 }
 
 impl TransitRoute {
@@ -224,7 +224,7 @@ impl Turn {
     pub(crate) fn permitted_by_lane(&self, map: &Map) -> bool {
         if let Some(types) = map
             .get_l(self.id.src)
-            .get_lane_level_turn_restrictions(map.get_parent(self.id.src), false)
+            .get_lane_level_turn_restrictions(false)
         {
             types.contains(&self.turn_type)
         } else {
@@ -232,6 +232,40 @@ impl Turn {
         }
     }
 
+    /// Is this turn legal, according to lane connectivity between the two roads?
+    pub(crate) fn permitted_by_connectivity(&self, map: &Map) -> bool {
+        let src = map.get_parent(self.id.src);
+        let dst = map.get_parent(self.id.dst);
+        let connectivity = match src.lane_connectivity.iter().find(|c| c.to == dst.id) {
+            Some(c) => c,
+            None => {
+                return true;
+            }
+        };
+        let from_lanes = src.vehicle_lanes(self.id.parent, true);
+        let to_lanes = dst.vehicle_lanes(self.id.parent, false);
+        // If the lanes have been edited, the lane numbers don't mean anything anymore
+        if from_lanes.len() != connectivity.num_from_lanes
+            || to_lanes.len() != connectivity.num_to_lanes
+        {
+            return true;
+        }
+        // Bike lanes and such aren't covered
+        let (from_idx, to_idx) = match (
+            from_lanes.iter().position(|l| *l == self.id.src),
+            to_lanes.iter().position(|l| *l == self.id.dst),
+        ) {
+            (Some(from_idx), Some(to_idx)) => (from_idx, to_idx),
+            _ => {
+                return true;
+            }
+        };
+        connectivity
+            .lanes
+            .iter()
+            .any(|(from, to)| *from == from_idx && to.contains(&to_idx))
+    }
+
     /// Is this turn legal, according to turn restrictions defined between road segments?
     pub(crate) fn permitted_by_road(&self, i: &Intersection, map: &Map) -> bool {
         if self.between_sidewalks() {
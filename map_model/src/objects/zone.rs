@@ -4,25 +4,219 @@
 //! 2) Stay Healthy Streets, where most car traffic is banned, except for trips beginning/ending in
 //!    the zone
 //! 3) Congestion capping, where only so many cars per hour can enter the zone
+//! 4) School streets, peak-hour bus gates, and delivery windows, where the restrictions only apply
+//!    at some times of day
 
 use std::collections::BTreeSet;
 
 use enumset::EnumSet;
 use serde::{Deserialize, Serialize};
 
-use crate::{CommonEndpoint, IntersectionID, Map, PathConstraints, RoadID};
+use geom::{Duration, Time};
+
+use crate::{CommonEndpoint, Direction, IntersectionID, Map, PathConstraints, RoadID};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct AccessRestrictions {
     pub allow_through_traffic: EnumSet<PathConstraints>,
+    /// Changes to `allow_through_traffic` during some times of day, applied in order.
+    #[serde(default)]
+    pub conditional: Vec<ConditionalAccess>,
 }
 
 impl AccessRestrictions {
     pub fn new() -> AccessRestrictions {
         AccessRestrictions {
             allow_through_traffic: EnumSet::all(),
+            conditional: Vec::new(),
         }
     }
+
+    /// Which modes may pass through this road in some direction at this time of day.
+    pub fn allow_through_traffic_at(&self, dir: Direction, time: Time) -> EnumSet<PathConstraints> {
+        let mut allow = self.allow_through_traffic;
+        for c in &self.conditional {
+            if c.direction.map(|d| d == dir).unwrap_or(true) && c.times.contains(time) {
+                if c.allow {
+                    allow |= c.modes;
+                } else {
+                    allow -= c.modes;
+                }
+            }
+        }
+        allow
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ConditionalAccess {
+    pub times: TimeWindows,
+    pub modes: EnumSet<PathConstraints>,
+    /// Are these modes allowed through or banned during the time windows?
+    pub allow: bool,
+    /// Only applies to traffic moving in this direction along the road. This is used for
+    /// conditional one-ways.
+    pub direction: Option<Direction>,
+}
+
+/// Daily periods of time, like "07:00-09:00 and 16:00-18:00". The simulation only models one
+/// typical weekday, so no day of the week is stored.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TimeWindows {
+    /// Each window starts and ends at some offset from midnight. If the end is before the start,
+    /// the window wraps around midnight.
+    pub windows: Vec<(Duration, Duration)>,
+}
+
+impl TimeWindows {
+    /// Understands a subset of the OSM opening_hours syntax, like "Mo-Fr 07:00-09:00,15:00-16:00;
+    /// Sa 10:00-12:00". Only rules covering some weekday are kept. Returns `None` if anything
+    /// isn't understood, or if no rule applies on a weekday.
+    pub fn parse(opening_hours: &str) -> Option<TimeWindows> {
+        let mut windows = Vec::new();
+        for rule in opening_hours.split(';') {
+            let rule = rule.trim();
+            if rule.is_empty() {
+                continue;
+            }
+
+            let mut on_weekday = true;
+            let mut times = Vec::new();
+            let mut off = false;
+            for part in rule.split_whitespace() {
+                if part == "off" {
+                    off = true;
+                } else if let Some(weekday) = parse_days(part) {
+                    on_weekday = weekday;
+                } else {
+                    for range in part.split(',').filter(|x| !x.is_empty()) {
+                        let (start, end) = range.split_once('-')?;
+                        times.push((parse_hhmm(start)?, parse_hhmm(end)?));
+                    }
+                }
+            }
+
+            if !on_weekday {
+                continue;
+            }
+            if off {
+                // Subtracting from other rules isn't supported
+                return None;
+            }
+            if times.is_empty() {
+                times.push((Duration::ZERO, Duration::hours(24)));
+            }
+            windows.extend(times);
+        }
+
+        if windows.is_empty() {
+            return None;
+        }
+        Some(TimeWindows { windows })
+    }
+
+    /// Splits an OSM conditional restriction, like
+    /// "no @ (Mo-Fr 07:00-09:00); delivery @ (06:00-11:00)", into values and their time windows.
+    /// Conditions that aren't purely about time, like "weight>7.5" or "wet", are skipped.
+    pub fn parse_conditional(value: &str) -> Vec<(String, TimeWindows)> {
+        let mut results = Vec::new();
+        for part in split_top_level(value) {
+            let (value, condition) = if let Some(pair) = part.split_once('@') {
+                pair
+            } else {
+                continue;
+            };
+            let condition = condition
+                .trim()
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .trim();
+            if condition.contains(" AND ") {
+                continue;
+            }
+            if let Some(times) = TimeWindows::parse(condition) {
+                results.push((value.trim().to_string(), times));
+            }
+        }
+        results
+    }
+
+    /// Does this time of day fall into any window? Times past the first day wrap around.
+    pub fn contains(&self, time: Time) -> bool {
+        let t = Duration::seconds(time.inner_seconds() % Duration::hours(24).inner_seconds());
+        self.windows.iter().any(|(start, end)| {
+            if start <= end {
+                *start <= t && t < *end
+            } else {
+                *start <= t || t < *end
+            }
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        self.windows
+            .iter()
+            .map(|(start, end)| format!("{}-{}", format_hhmm(*start), format_hhmm(*end)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Returns true if a day selector like "Mo-Fr", "Sa,Su", or "PH" covers any weekday, false if it
+/// only covers weekends or holidays, and `None` if the input isn't a day selector.
+fn parse_days(input: &str) -> Option<bool> {
+    const DAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+    let day = |x: &str| DAYS.iter().position(|d| *d == x);
+
+    let mut weekday = false;
+    for item in input.split(',').filter(|x| !x.is_empty()) {
+        if item == "PH" || item == "SH" {
+            continue;
+        }
+        if let Some((start, end)) = item.split_once('-') {
+            let (start, end) = (day(start)?, day(end)?);
+            // Ranges like Sa-Mo wrap around the week
+            weekday |= start < 5 || end < 5 || start > end;
+        } else {
+            weekday |= day(item)? < 5;
+        }
+    }
+    Some(weekday)
+}
+
+fn parse_hhmm(input: &str) -> Option<Duration> {
+    let (hours, mins) = input.split_once(':')?;
+    let hours = hours.parse::<usize>().ok()?;
+    let mins = mins.parse::<usize>().ok()?;
+    if hours > 24 || mins >= 60 {
+        return None;
+    }
+    Some(Duration::hours(hours) + Duration::minutes(mins))
+}
+
+fn format_hhmm(d: Duration) -> String {
+    let mins = (d.inner_seconds() / 60.0).round() as usize;
+    format!("{:02}:{:02}", mins / 60, mins % 60)
+}
+
+/// Splits on semicolons that aren't inside parentheses.
+fn split_top_level(input: &str) -> Vec<&str> {
+    let mut results = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ';' if depth == 0 => {
+                results.push(&input[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    results.push(&input[start..]);
+    results
 }
 
 /// A contiguous set of roads with access restrictions. This is derived from all the map's roads and
@@ -91,3 +285,32 @@ fn floodfill(map: &Map, start: RoadID) -> Zone {
         restrictions: match_constraints,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conditional() {
+        let parsed = TimeWindows::parse_conditional(
+            "no @ (Mo-Fr 07:00-09:00,15:00-16:00; PH off); delivery @ (Sa 06:00-11:00); \
+             no @ (weight>7.5)",
+        );
+        assert_eq!(parsed.len(), 1);
+        let (value, times) = &parsed[0];
+        assert_eq!(value, "no");
+        assert_eq!(times.describe(), "07:00-09:00, 15:00-16:00");
+        assert!(times.contains(Time::START_OF_DAY + Duration::hours(8)));
+        assert!(!times.contains(Time::START_OF_DAY + Duration::hours(12)));
+        // The next day
+        assert!(times.contains(Time::START_OF_DAY + Duration::hours(24 + 15)));
+
+        let overnight = TimeWindows::parse("22:00-06:00").unwrap();
+        assert!(overnight.contains(Time::START_OF_DAY + Duration::hours(2)));
+        assert!(overnight.contains(Time::START_OF_DAY + Duration::hours(23)));
+        assert!(!overnight.contains(Time::START_OF_DAY + Duration::hours(12)));
+
+        assert!(TimeWindows::parse("Sa,Su").is_none());
+        assert!(TimeWindows::parse("sunrise-sunset").is_none());
+    }
+}
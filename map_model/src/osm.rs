@@ -30,6 +30,9 @@ pub const ENDPT_BACK: &str = "abst:endpt_back";
 // Any roads might have these.
 pub const INFERRED_PARKING: &str = "abst:parking_inferred";
 pub const INFERRED_SIDEWALKS: &str = "abst:sidewalks_inferred";
+// Traffic moving in this direction along the road has to give way where the road ends.
+pub const GIVE_WAY_FWD: &str = "abst:give_way_fwd";
+pub const GIVE_WAY_BACK: &str = "abst:give_way_back";
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
 pub enum RoadRank {
@@ -1,8 +1,8 @@
 //! Everything related to pathfinding through a map for different types of agents.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
-use enumset::EnumSetType;
+use enumset::{EnumSet, EnumSetType};
 use serde::{Deserialize, Serialize};
 
 use geom::Duration;
@@ -13,7 +13,7 @@ pub use self::v1::{Path, PathRequest, PathStep};
 pub use self::v2::{PathStepV2, PathV2};
 pub use self::vehicles::vehicle_cost;
 pub use self::walking::WalkingNode;
-use crate::{osm, Lane, LaneID, LaneType, Map, MovementID, Road, RoadID, TurnType};
+use crate::{osm, DirectedRoadID, Lane, LaneID, LaneType, Map, MovementID, Road, RoadID, TurnType};
 
 mod engine;
 mod node_map;
@@ -34,6 +34,9 @@ pub enum PathConstraints {
     Bike,
     Bus,
     Train,
+    /// Heavy goods vehicles. They can use the same lanes as cars, except on roads tagged as
+    /// closed to them.
+    Truck,
 }
 
 impl PathConstraints {
@@ -44,6 +47,7 @@ impl PathConstraints {
             PathConstraints::Bike,
             PathConstraints::Bus,
             PathConstraints::Train,
+            PathConstraints::Truck,
         ]
     }
 
@@ -87,11 +91,14 @@ impl PathConstraints {
             PathConstraints::Train => {
                 return lane.is_light_rail();
             }
+            PathConstraints::Truck => {
+                lane.is_driving() && !map.get_r(lane.id.road).osm_tags.is("hgv", "no")
+            }
         };
         if result {
             return true;
         }
-        // Second chance for cars and bikes trying to use a bus-only lane that also happens to be a
+        // Second chance for cars, trucks, and bikes trying to use a bus-only lane that also happens to be a
         // turn lane.
         //
         // TODO This check could be made stricter in two ways:
@@ -104,9 +111,7 @@ impl PathConstraints {
         //    practice this isn't an issue; a bus lane often leads to another one, but the next bus
         //    lane won't also be an exclusive turn lane.
         if lane.is_bus() {
-            if let Some(types) =
-                lane.get_lane_level_turn_restrictions(map.get_r(lane.id.road), true)
-            {
+            if let Some(types) = lane.get_lane_level_turn_restrictions(true) {
                 if types.contains(&TurnType::Right) || types.contains(&TurnType::Left) {
                     return true;
                 }
@@ -139,17 +144,18 @@ impl PathConstraints {
 }
 
 /// Heavily penalize crossing into an access-restricted zone that doesn't allow this mode.
-pub(crate) fn zone_cost(mvmnt: MovementID, constraints: PathConstraints, map: &Map) -> Duration {
+pub(crate) fn zone_cost(
+    mvmnt: MovementID,
+    constraints: PathConstraints,
+    params: &RoutingParams,
+    map: &Map,
+) -> Duration {
     // Detect when we cross into a new zone that doesn't allow constraints.
-    if map
-        .get_r(mvmnt.from.road)
-        .access_restrictions
-        .allow_through_traffic
+    if params
+        .allow_through_traffic(mvmnt.from, map)
         .contains(constraints)
-        && !map
-            .get_r(mvmnt.to.road)
-            .access_restrictions
-            .allow_through_traffic
+        && !params
+            .allow_through_traffic(mvmnt.to, map)
             .contains(constraints)
     {
         // This should be high enough to achieve the desired effect of somebody not entering
@@ -200,6 +206,11 @@ pub struct RoutingParams {
     /// pedestrian.
     #[serde(skip_serializing, skip_deserializing)]
     pub avoid_movements_between: BTreeSet<(RoadID, RoadID)>,
+
+    /// Overrides which modes may pass through a road in some direction, used for conditional
+    /// access restrictions in effect at some time. See `Map::routing_params_at`.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub conditional_access: BTreeMap<DirectedRoadID, EnumSet<PathConstraints>>,
 }
 
 impl Default for RoutingParams {
@@ -220,10 +231,20 @@ impl Default for RoutingParams {
 
             avoid_roads: BTreeSet::new(),
             avoid_movements_between: BTreeSet::new(),
+            conditional_access: BTreeMap::new(),
         }
     }
 }
 
+impl RoutingParams {
+    fn allow_through_traffic(&self, dr: DirectedRoadID, map: &Map) -> EnumSet<PathConstraints> {
+        self.conditional_access
+            .get(&dr)
+            .cloned()
+            .unwrap_or_else(|| map.get_r(dr.road).access_restrictions.allow_through_traffic)
+    }
+}
+
 pub fn round(cost: Duration) -> usize {
     // Round up! 0 cost edges are ignored
     (cost.inner_seconds().round() as usize).max(1)
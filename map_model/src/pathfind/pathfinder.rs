@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 use serde::{Deserialize, Serialize};
 use thread_local::ThreadLocal;
@@ -21,6 +22,9 @@ pub struct Pathfinder {
     bike_graph: VehiclePathfinder,
     bus_graph: VehiclePathfinder,
     train_graph: VehiclePathfinder,
+    // Most scenarios have no freight, so this is only prepared when the first truck needs a path
+    #[serde(skip_serializing, skip_deserializing)]
+    truck_graph: OnceLock<VehiclePathfinder>,
     walking_graph: SidewalkPathfinder,
     walking_with_transit_graph: SidewalkPathfinder,
 
@@ -55,6 +59,7 @@ impl Clone for Pathfinder {
             bike_graph: self.bike_graph.clone(),
             bus_graph: self.bus_graph.clone(),
             train_graph: self.train_graph.clone(),
+            truck_graph: self.truck_graph.clone(),
             walking_graph: self.walking_graph.clone(),
             walking_with_transit_graph: self.walking_with_transit_graph.clone(),
             params: self.params.clone(),
@@ -72,6 +77,7 @@ impl Pathfinder {
             bike_graph: VehiclePathfinder::empty(),
             bus_graph: VehiclePathfinder::empty(),
             train_graph: VehiclePathfinder::empty(),
+            truck_graph: OnceLock::new(),
             walking_graph: SidewalkPathfinder::empty(),
             walking_with_transit_graph: SidewalkPathfinder::empty(),
             params: RoutingParams::default(),
@@ -129,6 +135,7 @@ impl Pathfinder {
             bike_graph,
             bus_graph,
             train_graph,
+            truck_graph: OnceLock::new(),
             walking_graph,
             walking_with_transit_graph,
 
@@ -164,6 +171,10 @@ impl Pathfinder {
                 PathConstraints::Train => {
                     p.train_graph = VehiclePathfinder::new(map, constraints, &params, &engine);
                 }
+                PathConstraints::Truck => {
+                    p.truck_graph =
+                        OnceLock::from(VehiclePathfinder::new(map, constraints, &params, &engine));
+                }
             }
             timer.stop(format!("prepare pathfinding for just {:?}", constraints));
         }
@@ -194,6 +205,7 @@ impl Pathfinder {
                 PathConstraints::Bike => self.bike_graph.pathfind(req, map),
                 PathConstraints::Bus => self.bus_graph.pathfind(req, map),
                 PathConstraints::Train => self.train_graph.pathfind(req, map),
+                PathConstraints::Truck => self.truck_graph(map).pathfind(req, map),
             };
         }
 
@@ -234,6 +246,19 @@ impl Pathfinder {
         result
     }
 
+    /// Trucks use nearly the same roads as cars, so the node ordering can be reused.
+    fn truck_graph(&self, map: &Map) -> &VehiclePathfinder {
+        self.truck_graph.get_or_init(|| {
+            let _timer = Timer::new("prepare pathfinding for trucks");
+            VehiclePathfinder::new(
+                map,
+                PathConstraints::Truck,
+                &self.params,
+                &self.car_graph.engine.reuse_ordering(),
+            )
+        })
+    }
+
     pub fn clear_custom_pathfinder_cache(&self) {
         self.cached_alternatives
             .get_or(|| RefCell::new(VecMap::new()))
@@ -251,6 +276,7 @@ impl Pathfinder {
             PathConstraints::Pedestrian => self.walking_graph.all_costs_from(req.start, map),
             PathConstraints::Car => self.car_graph.all_costs_from(req.start, map),
             PathConstraints::Bike => self.bike_graph.all_costs_from(req.start, map),
+            PathConstraints::Truck => self.truck_graph(map).all_costs_from(req.start, map),
             PathConstraints::Bus | PathConstraints::Train => unreachable!(),
         };
         Some((req_cost, all_costs))
@@ -284,6 +310,12 @@ impl Pathfinder {
         self.train_graph.apply_edits(map);
         timer.stop("apply edits to train pathfinding");
 
+        if let Some(truck_graph) = self.truck_graph.get_mut() {
+            timer.start("apply edits to truck pathfinding");
+            truck_graph.apply_edits(map);
+            timer.stop("apply edits to truck pathfinding");
+        }
+
         timer.start("apply edits to pedestrian pathfinding");
         self.walking_graph.apply_edits(map, None);
         timer.stop("apply edits to pedestrian pathfinding");
@@ -580,7 +580,7 @@ impl PathRequest {
         let (start, end) = match constraints {
             PathConstraints::Pedestrian => (from.sidewalk_pos, to.sidewalk_pos),
             PathConstraints::Bike => (from.biking_connection(map)?.0, to.biking_connection(map)?.0),
-            PathConstraints::Car => (
+            PathConstraints::Car | PathConstraints::Truck => (
                 from.driving_connection(map)?.0,
                 to.driving_connection(map)?.0,
             ),
@@ -589,7 +589,7 @@ impl PathRequest {
             // train to travel between buildings.
             PathConstraints::Bus | PathConstraints::Train => unimplemented!(),
         };
-        if constraints == PathConstraints::Car {
+        if constraints == PathConstraints::Car || constraints == PathConstraints::Truck {
             Some(PathRequest::leave_from_driveway(
                 start,
                 end,
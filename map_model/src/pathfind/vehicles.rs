@@ -289,7 +289,10 @@ pub fn vehicle_cost(
     let road = map.get_r(dr.road);
     let movement = &map.get_i(mvmnt.parent).movements[&mvmnt];
     let max_speed = match constraints {
-        PathConstraints::Car | PathConstraints::Bus | PathConstraints::Train => None,
+        PathConstraints::Car
+        | PathConstraints::Bus
+        | PathConstraints::Train
+        | PathConstraints::Truck => None,
         PathConstraints::Bike => Some(crate::MAX_BIKE_SPEED),
         PathConstraints::Pedestrian => unreachable!(),
     };
@@ -299,7 +302,7 @@ pub fn vehicle_cost(
         / Traversable::max_speed_along_movement(mvmnt, max_speed, constraints, map);
 
     let base = match constraints {
-        PathConstraints::Car | PathConstraints::Train => t1 + t2,
+        PathConstraints::Car | PathConstraints::Train | PathConstraints::Truck => t1 + t2,
         PathConstraints::Bike => {
             // TODO If we're on a driving lane, higher speed limit is worse.
             // TODO Bike lanes next to parking is dangerous.
@@ -356,7 +359,7 @@ pub fn vehicle_cost(
         return None;
     }
 
-    let mut extra = zone_cost(mvmnt, constraints, map);
+    let mut extra = zone_cost(mvmnt, constraints, params, map);
     // Penalize unprotected turns at a stop sign from smaller to larger roads.
     if map.is_unprotected_turn(dr.road, mvmnt.to.road, movement.turn_type) {
         extra += params.unprotected_turn_penalty
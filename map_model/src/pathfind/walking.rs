@@ -309,7 +309,12 @@ fn make_input_graph(
 
             let mut cost = t.geom.length()
                 / PathStep::Turn(t.id).max_speed_along(max_speed, PathConstraints::Pedestrian, map)
-                + zone_cost(t.id.to_movement(map), PathConstraints::Pedestrian, map);
+                + zone_cost(
+                    t.id.to_movement(map),
+                    PathConstraints::Pedestrian,
+                    map.routing_params(),
+                    map,
+                );
 
             if t.turn_type == TurnType::UnmarkedCrossing {
                 // TODO Add to RoutingParams
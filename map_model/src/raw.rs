@@ -11,12 +11,12 @@ use serde::{Deserialize, Serialize};
 
 use abstio::{CityName, MapName};
 use abstutil::{deserialize_btreemap, serialize_btreemap, Tags, Timer};
-use geom::{Distance, GPSBounds, PolyLine, Polygon, Pt2D};
+use geom::{Distance, GPSBounds, PolyLine, Polygon, Pt2D, Time};
 
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
 use crate::{
     osm, Amenity, AreaType, Direction, DrivingSide, IntersectionType, LaneType, MapConfig,
-    PathConstraints,
+    PathConstraints, TimeWindows,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -478,6 +478,30 @@ impl RawMap {
             road.turn_restrictions.extend(add);
         }
 
+        // Conditional restrictions into the deleted road are dropped, not redirected to the
+        // successors like simple restrictions. Restrictions into roads that changed IDs just need
+        // updating.
+        for road in self.roads.values_mut() {
+            road.conditional_turn_restrictions
+                .retain(|(_, to, _)| *to != short);
+            for (_, to, _) in &mut road.conditional_turn_restrictions {
+                if let Some(new_id) = old_to_new.get(to) {
+                    *to = *new_id;
+                }
+            }
+        }
+
+        // Lane connectivity into the deleted road no longer applies. Connectivity into roads that
+        // changed IDs just needs updating.
+        for road in self.roads.values_mut() {
+            road.lane_connectivity.retain(|(to, _)| *to != short);
+            for (to, _) in &mut road.lane_connectivity {
+                if let Some(new_id) = old_to_new.get(to) {
+                    *to = *new_id;
+                }
+            }
+        }
+
         Ok((i1, i2, deleted, created))
     }
 
@@ -567,6 +591,11 @@ pub struct RawRoad {
     pub turn_restrictions: Vec<(RestrictionType, OriginalRoad)>,
     /// (via, to). For turn restrictions where 'via' is an entire road. Only BanTurns.
     pub complicated_turn_restrictions: Vec<(OriginalRoad, OriginalRoad)>,
+    /// Simple turn restrictions that only apply during some times of day
+    pub conditional_turn_restrictions: Vec<(RestrictionType, OriginalRoad, TimeWindows)>,
+    /// (to, [(from lane, [to lanes])]), from connectivity relations. Lanes are counted left to
+    /// right in the direction of travel, only including driving and bus lanes, starting from 0.
+    pub lane_connectivity: Vec<(OriginalRoad, Vec<(usize, Vec<usize>)>)>,
     pub percent_incline: f64,
     /// Is there a tagged crosswalk near each end of the road?
     pub crosswalk_forward: bool,
@@ -714,8 +743,8 @@ pub struct TurnRestriction(pub OriginalRoad, pub RestrictionType, pub OriginalRo
 
 impl RestrictionType {
     pub fn new(restriction: &str) -> Option<RestrictionType> {
-        // TODO There's a huge space of things not represented yet: time conditions, bus-only, no
-        // right turn on red...
+        // TODO There's a huge space of things not represented yet: bus-only, no right turn on
+        // red...
 
         // There are so many possibilities:
         // https://taginfo.openstreetmap.org/keys/restriction#values
@@ -740,7 +769,9 @@ pub struct RawTransitRoute {
     /// Entries into transit_stops
     pub stops: Vec<String>,
     pub route_type: PathConstraints,
-    // TODO Schedule
+    /// Each entry is one run of a vehicle, listing the scheduled arrival time at each of `stops`.
+    /// Empty if the schedule isn't known.
+    pub schedule: Vec<Vec<Time>>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
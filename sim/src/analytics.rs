@@ -6,14 +6,17 @@ use fs_err::File;
 use serde::{Deserialize, Serialize};
 
 use abstutil::Counter;
-use geom::{Duration, Time};
+use geom::{Distance, Duration, Time};
 use map_model::{
-    CompressedMovementID, IntersectionID, LaneID, Map, MovementID, ParkingLotID, Path, PathRequest,
-    RoadID, TransitRouteID, TransitStopID, Traversable, TurnID,
+    BuildingID, CompressedMovementID, IntersectionID, LaneID, Map, MovementID, ParkingLotID, Path,
+    PathRequest, RoadID, TransitRouteID, TransitStopID, Traversable, TurnID,
 };
 use synthpop::TripMode;
 
-use crate::{AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID, TripPhaseType};
+use crate::{
+    AgentID, AgentType, AlertLocation, CarID, Emissions, Event, ParkingSpot, TripID, TripPhaseType,
+    TripUsage, VehicleType,
+};
 
 /// As a simulation runs, different pieces emit Events. The Analytics object listens to these,
 /// organizing and storing some information from them. The UI queries Analytics to draw time-series
@@ -26,6 +29,9 @@ use crate::{AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Analytics {
     pub road_thruput: TimeSeriesCount<RoadID>,
+    /// (road, vehicle type, hour) -> number of vehicles entering the road. Unlike road_thruput,
+    /// this distinguishes freight from cars.
+    pub road_vehicle_thruput: BTreeMap<(RoadID, VehicleType, usize), usize>,
     pub intersection_thruput: TimeSeriesCount<IntersectionID>,
     // TODO For traffic signals, intersection_thruput could theoretically use this. But that
     // requires occasionally expensive or complicated summing or merging over all directions of an
@@ -41,6 +47,22 @@ pub struct Analytics {
     /// For each passenger boarding, how long did they wait at the stop?
     pub passengers_boarding: BTreeMap<TransitStopID, Vec<(Time, TransitRouteID, Duration)>>,
     pub passengers_alighting: BTreeMap<TransitStopID, Vec<(Time, TransitRouteID)>>,
+    /// For each scheduled transit vehicle arriving at a stop, how late was it? Negative if early.
+    pub transit_lateness: BTreeMap<TransitStopID, Vec<(Time, TransitRouteID, Duration)>>,
+    /// For each transit vehicle departing a stop, what fraction of its capacity is filled?
+    pub transit_loads: BTreeMap<TransitStopID, Vec<(Time, TransitRouteID, f64)>>,
+
+    /// For each ride-hail pickup, how long did the passenger wait after requesting the ride?
+    pub ride_hail_waits: Vec<(Time, TripID, Duration)>,
+    /// Every time a ride-hail vehicle finishes driving somewhere, how far did it go, and was a
+    /// passenger on board? Distance without a passenger is deadheading.
+    pub ride_hail_legs: Vec<(Time, CarID, Distance, bool)>,
+    /// Whenever the number of busy ride-hail vehicles changes, (busy vehicles, fleet size)
+    pub ride_hail_utilization: Vec<(Time, usize, usize)>,
+
+    /// For each freight delivery stop, did the vehicle find a curbside spot (true) or double-park
+    /// (false)?
+    pub freight_stops: Vec<(Time, CarID, BuildingID, bool)>,
 
     pub started_trips: BTreeMap<TripID, Time>,
     /// Finish time, ID, mode, trip duration if successful (or None if cancelled)
@@ -48,6 +70,9 @@ pub struct Analytics {
 
     /// Record different problems that each trip encounters.
     pub problems_per_trip: BTreeMap<TripID, Vec<(Time, Problem)>>,
+    /// What each started or finished trip used, for calculating its cost. Cancelled trips aren't
+    /// included.
+    pub trip_usage: BTreeMap<TripID, TripUsage>,
 
     // TODO This subsumes finished_trips
     pub trip_log: Vec<(Time, TripID, Option<PathRequest>, TripPhaseType)>,
@@ -60,8 +85,20 @@ pub struct Analytics {
     pub parking_lane_changes: BTreeMap<LaneID, Vec<(Time, bool)>>,
     pub parking_lot_changes: BTreeMap<ParkingLotID, Vec<(Time, bool)>>,
 
+    /// Emissions and energy used by vehicles, totalled in different ways. Vehicles on a turn count
+    /// towards the intersection.
+    pub road_emissions: BTreeMap<RoadID, Emissions>,
+    pub intersection_emissions: BTreeMap<IntersectionID, Emissions>,
+    pub trip_emissions: BTreeMap<TripID, Emissions>,
+    pub vehicle_type_emissions: BTreeMap<VehicleType, Emissions>,
+
     pub(crate) alerts: Vec<(Time, AlertLocation, String)>,
 
+    /// If present, every event is also copied here, so that consumers outside the simulation can
+    /// follow along as it runs.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) captured_events: Option<Vec<(Time, Event)>>,
+
     /// For benchmarking, we may want to disable collecting data.
     record_anything: bool,
 }
@@ -76,40 +113,98 @@ pub enum Problem {
     ArterialIntersectionCrossing(TurnID),
     /// Another vehicle wanted to over-take this cyclist somewhere on this lane or turn.
     OvertakeDesired(Traversable),
+    /// Another vehicle over-took this one on this lane, either by changing lanes or by using the
+    /// oncoming lane.
+    Overtaken(Traversable),
+    /// A transit vehicle on this route arrived at this stop, but was too full to board.
+    DeniedBoarding(TransitStopID, TransitRouteID),
 }
 
 impl Analytics {
     pub fn new(record_anything: bool) -> Analytics {
         Analytics {
             road_thruput: TimeSeriesCount::new(),
+            road_vehicle_thruput: BTreeMap::new(),
             intersection_thruput: TimeSeriesCount::new(),
             traffic_signal_thruput: TimeSeriesCount::new(),
             demand: BTreeMap::new(),
             bus_arrivals: Vec::new(),
             passengers_boarding: BTreeMap::new(),
             passengers_alighting: BTreeMap::new(),
+            transit_lateness: BTreeMap::new(),
+            transit_loads: BTreeMap::new(),
+            ride_hail_waits: Vec::new(),
+            ride_hail_legs: Vec::new(),
+            ride_hail_utilization: Vec::new(),
+            freight_stops: Vec::new(),
             started_trips: BTreeMap::new(),
             finished_trips: Vec::new(),
             problems_per_trip: BTreeMap::new(),
+            trip_usage: BTreeMap::new(),
             trip_log: Vec::new(),
             intersection_delays: BTreeMap::new(),
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
+            road_emissions: BTreeMap::new(),
+            intersection_emissions: BTreeMap::new(),
+            trip_emissions: BTreeMap::new(),
+            vehicle_type_emissions: BTreeMap::new(),
             alerts: Vec::new(),
+            captured_events: None,
             record_anything,
         }
     }
 
     pub fn event(&mut self, ev: Event, time: Time, map: &Map) {
+        if let Some(ref mut events) = self.captured_events {
+            events.push((time, ev.clone()));
+        }
         if !self.record_anything {
             return;
         }
 
+        // Emissions
+        if let Event::VehicleEmissions(car, trip, on, emissions) = ev {
+            self.record_emissions(car, trip, on, emissions);
+        }
+
+        // Trip usage
+        match ev {
+            Event::TripPhaseStarting(id, _, _, phase_type) => {
+                self.trip_usage
+                    .entry(id)
+                    .or_insert_with(TripUsage::default)
+                    .phase_starting(time, phase_type);
+            }
+            Event::AgentEntersTraversable(AgentID::Car(_), Some(trip), Traversable::Lane(l), _) => {
+                if let (Some(usage), Some(lane)) =
+                    (self.trip_usage.get_mut(&trip), map.maybe_get_l(l))
+                {
+                    usage.distance_driven += lane.length();
+                }
+            }
+            Event::TripFinished { trip, .. } => {
+                if let Some(usage) = self.trip_usage.get_mut(&trip) {
+                    usage.end_phase(time);
+                }
+            }
+            Event::TripCancelled(id, _) => {
+                self.trip_usage.remove(&id);
+            }
+            _ => {}
+        }
+
         // Throughput
         if let Event::AgentEntersTraversable(a, _, to, passengers) = ev {
             match to {
                 Traversable::Lane(l) => {
                     self.road_thruput.record(time, l.road, a.to_type(), 1);
+                    if let Some(vt) = a.to_vehicle_type() {
+                        *self
+                            .road_vehicle_thruput
+                            .entry((l.road, vt, time.get_hours()))
+                            .or_insert(0) += 1;
+                    }
                     if let Some(n) = passengers {
                         self.road_thruput
                             .record(time, l.road, AgentType::TransitRider, n);
@@ -155,8 +250,14 @@ impl Analytics {
         }
 
         // Bus arrivals
-        if let Event::BusArrivedAtStop(bus, route, stop) = ev {
+        if let Event::BusArrivedAtStop(bus, route, stop, lateness) = ev {
             self.bus_arrivals.push((time, bus, route, stop));
+            if let Some(lateness) = lateness {
+                self.transit_lateness
+                    .entry(stop)
+                    .or_insert_with(Vec::new)
+                    .push((time, route, lateness));
+            }
         }
 
         // Passengers boarding/alighting
@@ -173,6 +274,31 @@ impl Analytics {
                 .push((time, route));
         }
 
+        if let Event::BusDepartedFromStop(_, route, stop, passengers, capacity) = ev {
+            self.transit_loads
+                .entry(stop)
+                .or_insert_with(Vec::new)
+                .push((time, route, (passengers as f64) / (capacity.max(1) as f64)));
+        }
+
+        // Ride-hailing
+        match ev {
+            Event::RideHailPickup(trip, _, wait) => {
+                self.ride_hail_waits.push((time, trip, wait));
+            }
+            Event::RideHailLegFinished(car, dist, with_passenger) => {
+                self.ride_hail_legs.push((time, car, dist, with_passenger));
+            }
+            Event::RideHailFleetUtilization(busy, total) => {
+                self.ride_hail_utilization.push((time, busy, total));
+            }
+            _ => {}
+        }
+
+        if let Event::FreightStopReached(car, b, spot) = ev {
+            self.freight_stops.push((time, car, b, spot.is_some()));
+        }
+
         // Started trips
         if let Event::TripPhaseStarting(id, _, _, _) = ev {
             self.started_trips.entry(id).or_insert(time);
@@ -298,6 +424,31 @@ impl Analytics {
         }
     }
 
+    fn record_emissions(
+        &mut self,
+        car: CarID,
+        trip: Option<TripID>,
+        on: Traversable,
+        emissions: Emissions,
+    ) {
+        // Live map edits might've deleted the lane or turn, but never the road or intersection
+        match on {
+            Traversable::Lane(l) => {
+                *self.road_emissions.entry(l.road).or_default() += emissions;
+            }
+            Traversable::Turn(t) => {
+                *self.intersection_emissions.entry(t.parent).or_default() += emissions;
+            }
+        }
+        if let Some(trip) = trip {
+            *self.trip_emissions.entry(trip).or_default() += emissions;
+        }
+        *self
+            .vehicle_type_emissions
+            .entry(car.vehicle_type)
+            .or_default() += emissions;
+    }
+
     pub fn record_demand(&mut self, path: &Path, map: &Map) {
         for step in path.get_steps() {
             if let Traversable::Turn(t) = step.as_traversable() {
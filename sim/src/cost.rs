@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Time};
+use synthpop::TripMode;
+
+use crate::TripPhaseType;
+
+/// What a trip used along the way -- distance driven, vehicles boarded, time spent in different
+/// ways. A `TripCostModel` turns this into a cost.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TripUsage {
+    /// Distance covered by a car, bike, or ride-hail while on this trip
+    pub distance_driven: Distance,
+    /// How many transit vehicles or ride-hails were boarded
+    pub boardings: usize,
+    /// Did the trip park a car?
+    pub parked: bool,
+    /// Driving, biking, riding, or looking for parking
+    pub in_vehicle_time: Duration,
+    /// Waiting at a transit stop or for a ride-hail pickup
+    pub waiting_time: Duration,
+    pub walking_time: Duration,
+    /// The phase in progress and when it started
+    current_phase: Option<(Time, TripPhaseType)>,
+}
+
+impl TripUsage {
+    pub(crate) fn phase_starting(&mut self, now: Time, phase: TripPhaseType) {
+        if let Some((_, prev)) = self.current_phase {
+            // Walking away from a car means it was parked. Freight vehicles stop at the curb to
+            // deliver instead.
+            if matches!(prev, TripPhaseType::Driving | TripPhaseType::Parking)
+                && phase == TripPhaseType::Walking
+            {
+                self.parked = true;
+            }
+        }
+        if matches!(
+            phase,
+            TripPhaseType::RidingBus(_, _, _) | TripPhaseType::RidingRideHail(_)
+        ) {
+            self.boardings += 1;
+        }
+        self.end_phase(now);
+        self.current_phase = Some((now, phase));
+    }
+
+    pub(crate) fn end_phase(&mut self, now: Time) {
+        if let Some((start, phase)) = self.current_phase.take() {
+            let dt = now - start;
+            match phase {
+                TripPhaseType::Driving
+                | TripPhaseType::Biking
+                | TripPhaseType::Parking
+                | TripPhaseType::RidingBus(_, _, _)
+                | TripPhaseType::RidingRideHail(_) => {
+                    self.in_vehicle_time += dt;
+                }
+                TripPhaseType::WaitingForBus(_, _) | TripPhaseType::WaitingForRideHail => {
+                    self.waiting_time += dt;
+                }
+                TripPhaseType::Walking => {
+                    self.walking_time += dt;
+                }
+                // Loading a freight vehicle is work, and a delayed start is the fault of the
+                // previous trip
+                TripPhaseType::Delivering(_)
+                | TripPhaseType::DelayedStart
+                | TripPhaseType::Cancelled
+                | TripPhaseType::Finished => {}
+            }
+        }
+    }
+}
+
+/// Prices and the value of time for one mode. Money is in whatever currency the user wants, as
+/// long as it's consistent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModeCosts {
+    /// Paid per kilometer driven. For drivers this is fuel and wear; for ride-hail passengers,
+    /// the distance part of the fare.
+    pub per_km: f64,
+    /// Paid once when a trip parks a car
+    pub parking_fee: f64,
+    /// Paid every time a transit vehicle or ride-hail is boarded
+    pub fare: f64,
+    /// How much an hour spent in a vehicle is worth
+    pub value_of_time_per_hour: f64,
+    /// Time spent waiting or walking usually feels worse than time spent riding. The value of
+    /// time is multiplied by these.
+    pub waiting_weight: f64,
+    pub walking_weight: f64,
+}
+
+/// How to turn what a trip used into a generalized cost, per mode. The defaults are rough US
+/// values from 2021.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TripCostModel {
+    pub modes: BTreeMap<TripMode, ModeCosts>,
+}
+
+impl Default for TripCostModel {
+    fn default() -> TripCostModel {
+        let costs = |per_km, parking_fee, fare| ModeCosts {
+            per_km,
+            parking_fee,
+            fare,
+            value_of_time_per_hour: 15.0,
+            waiting_weight: 2.0,
+            walking_weight: 1.5,
+        };
+        let mut modes = BTreeMap::new();
+        modes.insert(TripMode::Walk, costs(0.0, 0.0, 0.0));
+        modes.insert(TripMode::Bike, costs(0.0, 0.0, 0.0));
+        modes.insert(TripMode::Transit, costs(0.0, 0.0, 2.75));
+        modes.insert(TripMode::Drive, costs(0.12, 5.0, 0.0));
+        modes.insert(TripMode::RideHail, costs(1.1, 0.0, 2.5));
+        // Paid by a business, but it's still useful to see
+        modes.insert(TripMode::Freight, costs(0.35, 0.0, 0.0));
+        TripCostModel { modes }
+    }
+}
+
+impl TripCostModel {
+    /// Loads a model from a JSON file. Modes that aren't specified cost nothing.
+    pub fn load(path: &str) -> Result<TripCostModel> {
+        abstio::maybe_read_json(path.to_string(), &mut abstutil::Timer::throwaway())
+    }
+
+    pub fn cost(&self, mode: TripMode, usage: &TripUsage) -> TripCost {
+        let costs = if let Some(c) = self.modes.get(&mode) {
+            c
+        } else {
+            return TripCost::default();
+        };
+        let hours = |dt: Duration| dt.inner_seconds() / 3600.0;
+        TripCost {
+            distance: costs.per_km * usage.distance_driven.inner_meters() / 1000.0,
+            parking: if usage.parked { costs.parking_fee } else { 0.0 },
+            fares: costs.fare * (usage.boardings as f64),
+            time: costs.value_of_time_per_hour
+                * (hours(usage.in_vehicle_time)
+                    + costs.waiting_weight * hours(usage.waiting_time)
+                    + costs.walking_weight * hours(usage.walking_time)),
+        }
+    }
+}
+
+/// The cost of one trip, broken down
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TripCost {
+    /// Fuel, or distance-based fares
+    pub distance: f64,
+    pub parking: f64,
+    pub fares: f64,
+    /// The value of time spent on the trip, weighted by how it was spent
+    pub time: f64,
+}
+
+impl TripCost {
+    /// What was actually paid
+    pub fn money(&self) -> f64 {
+        self.distance + self.parking + self.fares
+    }
+
+    /// Money plus the value of time
+    pub fn generalized(&self) -> f64 {
+        self.money() + self.time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use map_model::{TransitRouteID, TransitStopID};
+
+    use super::*;
+    use crate::{CarID, VehicleType};
+
+    fn mins(x: usize) -> Duration {
+        Duration::minutes(x)
+    }
+
+    /// Run through some phases, each lasting a few minutes, then finish the trip.
+    fn usage(phases: Vec<(TripPhaseType, usize)>) -> TripUsage {
+        let mut usage = TripUsage::default();
+        let mut now = Time::START_OF_DAY;
+        for (phase, duration) in phases {
+            usage.phase_starting(now, phase);
+            now += mins(duration);
+        }
+        usage.end_phase(now);
+        usage
+    }
+
+    #[test]
+    fn test_phase_accounting() {
+        let car = CarID {
+            id: 1,
+            vehicle_type: VehicleType::Car,
+        };
+        let drive = usage(vec![
+            (TripPhaseType::DelayedStart, 3),
+            (TripPhaseType::Walking, 2),
+            (TripPhaseType::Driving, 15),
+            (TripPhaseType::Parking, 4),
+            (TripPhaseType::Walking, 5),
+        ]);
+        assert_eq!(drive.walking_time, mins(7));
+        assert_eq!(drive.in_vehicle_time, mins(19));
+        assert_eq!(drive.waiting_time, Duration::ZERO);
+        assert!(drive.parked);
+        assert_eq!(drive.boardings, 0);
+
+        // Stops can't be constructed outside of map_model
+        let stop: TransitStopID = abstutil::from_json(br#"{"sidewalk": 0, "idx": 0}"#).unwrap();
+        let bus = CarID {
+            id: 2,
+            vehicle_type: VehicleType::Bus,
+        };
+        let route = TransitRouteID(0);
+        let transit = usage(vec![
+            (TripPhaseType::Walking, 4),
+            (TripPhaseType::WaitingForBus(route, stop), 6),
+            (TripPhaseType::RidingBus(route, stop, bus), 20),
+            (TripPhaseType::Walking, 1),
+            (TripPhaseType::WaitingForRideHail, 3),
+            (TripPhaseType::RidingRideHail(car), 10),
+        ]);
+        assert_eq!(transit.walking_time, mins(5));
+        assert_eq!(transit.waiting_time, mins(9));
+        assert_eq!(transit.in_vehicle_time, mins(30));
+        assert!(!transit.parked);
+        assert_eq!(transit.boardings, 2);
+
+        // Every phase is counted exactly once, except for the delayed start
+        let total = |u: &TripUsage| u.walking_time + u.waiting_time + u.in_vehicle_time;
+        assert_eq!(total(&drive), mins(29) - mins(3));
+        assert_eq!(total(&transit), mins(44));
+    }
+
+    #[test]
+    fn test_cost() {
+        let mut model = TripCostModel::default();
+        model.modes.insert(
+            TripMode::Drive,
+            ModeCosts {
+                per_km: 0.5,
+                parking_fee: 3.0,
+                fare: 2.0,
+                value_of_time_per_hour: 10.0,
+                waiting_weight: 2.0,
+                walking_weight: 3.0,
+            },
+        );
+        let usage = TripUsage {
+            distance_driven: Distance::meters(8000.0),
+            boardings: 2,
+            parked: true,
+            in_vehicle_time: mins(60),
+            waiting_time: mins(30),
+            walking_time: mins(12),
+            current_phase: None,
+        };
+
+        let cost = model.cost(TripMode::Drive, &usage);
+        assert_eq!(
+            cost,
+            TripCost {
+                distance: 4.0,
+                parking: 3.0,
+                fares: 4.0,
+                // 10 * (1 + 2 * 0.5 + 3 * 0.2)
+                time: 26.0,
+            }
+        );
+        assert_eq!(cost.money(), 11.0);
+        assert_eq!(cost.generalized(), 37.0);
+
+        // Not parking means no fee
+        let cost = model.cost(
+            TripMode::Drive,
+            &TripUsage {
+                parked: false,
+                ..usage.clone()
+            },
+        );
+        assert_eq!(cost.parking, 0.0);
+
+        // Modes missing from the model are free
+        model.modes.remove(&TripMode::Bike);
+        assert_eq!(model.cost(TripMode::Bike, &usage), TripCost::default());
+    }
+}
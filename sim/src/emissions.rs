@@ -0,0 +1,241 @@
+use std::ops::{AddAssign, Mul};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Speed};
+use map_model::{Direction, Map, Traversable};
+
+use crate::VehicleType;
+
+/// Tailpipe (and for particulates, brake and tire wear) emissions, and energy used, by vehicles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Emissions {
+    pub co2_grams: f64,
+    pub nox_grams: f64,
+    pub pm_grams: f64,
+    pub energy_kwh: f64,
+}
+
+impl AddAssign for Emissions {
+    fn add_assign(&mut self, other: Emissions) {
+        self.co2_grams += other.co2_grams;
+        self.nox_grams += other.nox_grams;
+        self.pm_grams += other.pm_grams;
+        self.energy_kwh += other.energy_kwh;
+    }
+}
+
+impl Mul<f64> for Emissions {
+    type Output = Emissions;
+
+    fn mul(self, factor: f64) -> Emissions {
+        Emissions {
+            co2_grams: self.co2_grams * factor,
+            nox_grams: self.nox_grams * factor,
+            pm_grams: self.pm_grams * factor,
+            energy_kwh: self.energy_kwh * factor,
+        }
+    }
+}
+
+/// Average emission factors for one type of vehicle. These're rough fleet averages, not
+/// calibrated for any particular place.
+struct Factors {
+    /// Per kilometer, when cruising at an efficient speed on flat ground
+    per_km: Emissions,
+    /// Per second, while stopped with the engine running
+    idling_per_second: Emissions,
+}
+
+fn factors(vehicle_type: VehicleType) -> Option<Factors> {
+    // (CO2 g/km, NOx g/km, PM g/km, kWh/km), then the same per second of idling
+    let (per_km, idling) = match vehicle_type {
+        VehicleType::Car => ((170.0, 0.3, 0.03, 0.65), (0.4, 0.0003, 0.00001, 0.0015)),
+        VehicleType::Delivery => ((250.0, 0.8, 0.05, 0.95), (0.6, 0.0008, 0.00002, 0.0022)),
+        VehicleType::Truck => ((800.0, 3.5, 0.1, 3.0), (2.0, 0.004, 0.0001, 0.0075)),
+        VehicleType::Bus => ((1100.0, 5.0, 0.12, 4.2), (2.5, 0.005, 0.00015, 0.009)),
+        // Electric, so no tailpipe emissions, just wear
+        VehicleType::Train => ((0.0, 0.0, 0.02, 5.0), (0.0, 0.0, 0.0, 0.01)),
+        VehicleType::Bike => {
+            return None;
+        }
+    };
+    let emissions =
+        |(co2_grams, nox_grams, pm_grams, energy_kwh): (f64, f64, f64, f64)| Emissions {
+            co2_grams,
+            nox_grams,
+            pm_grams,
+            energy_kwh,
+        };
+    Some(Factors {
+        per_km: emissions(per_km),
+        idling_per_second: emissions(idling),
+    })
+}
+
+/// Calculate the emissions of a vehicle moving along part of a lane or turn. `speed_at` gives the
+/// vehicle's speed some time after it started this movement; the emissions are integrated over
+/// that speed. Emissions per kilometer are higher at low speeds and very high speeds, and uphill.
+pub fn moving_emissions<F: Fn(Duration) -> Speed>(
+    vehicle_type: VehicleType,
+    on: Traversable,
+    duration: Duration,
+    speed_at: F,
+    map: &Map,
+) -> Emissions {
+    let factors = if let Some(f) = factors(vehicle_type) {
+        f
+    } else {
+        return Emissions::default();
+    };
+    let percent_incline = match on {
+        Traversable::Lane(l) => {
+            let incline = map.get_r(l.road).percent_incline;
+            if map.get_l(l).dir == Direction::Fwd {
+                incline
+            } else {
+                -incline
+            }
+        }
+        Traversable::Turn(_) => 0.0,
+    };
+    integrate(&factors, percent_incline, duration, speed_at)
+}
+
+/// Calculate the emissions of a vehicle stopped with the engine running.
+pub fn idling_emissions(vehicle_type: VehicleType, duration: Duration) -> Emissions {
+    if let Some(factors) = factors(vehicle_type) {
+        factors.idling_per_second * duration.inner_seconds()
+    } else {
+        Emissions::default()
+    }
+}
+
+/// Sample the speed this often
+const INTEGRATION_STEP: Duration = Duration::const_seconds(1.0);
+/// Slower than this counts as idling
+const MIN_MOVING_SPEED: Speed = Speed::const_meters_per_second(0.1);
+
+fn integrate<F: Fn(Duration) -> Speed>(
+    factors: &Factors,
+    percent_incline: f64,
+    duration: Duration,
+    speed_at: F,
+) -> Emissions {
+    let incline_factor = (1.0 + 10.0 * percent_incline).max(0.2);
+    let mut total = Emissions::default();
+    let mut t = Duration::ZERO;
+    while t < duration {
+        let dt = INTEGRATION_STEP.min(duration - t);
+        // Use the speed in the middle of each step
+        let speed = speed_at(t + dt / 2.0);
+        if speed < MIN_MOVING_SPEED {
+            total += factors.idling_per_second * dt.inner_seconds();
+        } else {
+            let km = (speed * dt) / Distance::meters(1000.0);
+            total += factors.per_km * (speed_factor(speed) * incline_factor * km);
+        }
+        t += dt;
+    }
+    total
+}
+
+/// A U-shaped curve, lowest around 50km/h.
+fn speed_factor(speed: Speed) -> f64 {
+    let kmph = (speed.inner_meters_per_second() * 3.6).clamp(5.0, 130.0);
+    0.6 + 18.0 / kmph + (kmph / 110.0).powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn car() -> Factors {
+        factors(VehicleType::Car).unwrap()
+    }
+
+    fn kmph(x: f64) -> Speed {
+        Speed::km_per_hour(x)
+    }
+
+    #[test]
+    fn test_idling() {
+        let emissions = idling_emissions(VehicleType::Car, Duration::seconds(30.0));
+        assert_close(
+            emissions.co2_grams,
+            30.0 * car().idling_per_second.co2_grams,
+        );
+        assert_close(
+            emissions.energy_kwh,
+            30.0 * car().idling_per_second.energy_kwh,
+        );
+
+        assert_eq!(
+            idling_emissions(VehicleType::Bike, Duration::seconds(30.0)),
+            Emissions::default()
+        );
+    }
+
+    #[test]
+    fn test_speed_factor() {
+        let best = speed_factor(kmph(48.0));
+        assert!(speed_factor(kmph(40.0)) > best);
+        assert!(speed_factor(kmph(60.0)) > best);
+        assert!(speed_factor(kmph(10.0)) > 2.0 * best);
+        assert!(speed_factor(kmph(130.0)) > best);
+        // Crawling is capped
+        assert_close(speed_factor(kmph(1.0)), speed_factor(kmph(5.0)));
+    }
+
+    #[test]
+    fn test_constant_speed() {
+        // 1km at a steady 36km/h takes 100s
+        let emissions = integrate(&car(), 0.0, Duration::seconds(100.0), |_| kmph(36.0));
+        assert_close(
+            emissions.co2_grams,
+            speed_factor(kmph(36.0)) * car().per_km.co2_grams,
+        );
+
+        // Stopped the whole time
+        let emissions = integrate(&car(), 0.0, Duration::seconds(100.0), |_| Speed::ZERO);
+        assert_close(
+            emissions.co2_grams,
+            100.0 * car().idling_per_second.co2_grams,
+        );
+    }
+
+    #[test]
+    fn test_stop_and_go() {
+        // Cover the same 1km in 100s, but alternate between stopping and going twice as fast
+        let steady = integrate(&car(), 0.0, Duration::seconds(100.0), |_| kmph(36.0));
+        let stop_and_go = integrate(&car(), 0.0, Duration::seconds(100.0), |t| {
+            if (t.inner_seconds() / 5.0) as usize % 2 == 0 {
+                Speed::ZERO
+            } else {
+                kmph(72.0)
+            }
+        });
+        assert!(stop_and_go.co2_grams > steady.co2_grams);
+        assert!(stop_and_go.energy_kwh > steady.energy_kwh);
+    }
+
+    #[test]
+    fn test_incline() {
+        let speed = |_| kmph(50.0);
+        let flat = integrate(&car(), 0.0, Duration::seconds(60.0), speed);
+        let uphill = integrate(&car(), 0.05, Duration::seconds(60.0), speed);
+        let steep_downhill = integrate(&car(), -0.2, Duration::seconds(60.0), speed);
+        assert_close(uphill.co2_grams, 1.5 * flat.co2_grams);
+        // Going downhill still costs something
+        assert_close(steep_downhill.co2_grams, 0.2 * flat.co2_grams);
+    }
+}
@@ -1,13 +1,13 @@
 use serde::{Deserialize, Serialize};
 
-use geom::Duration;
+use geom::{Distance, Duration};
 use map_model::{
     BuildingID, IntersectionID, LaneID, Map, Path, PathRequest, TransitRouteID, TransitStopID,
     Traversable, TurnID,
 };
 use synthpop::TripMode;
 
-use crate::{AgentID, CarID, ParkingSpot, PedestrianID, PersonID, Problem, TripID};
+use crate::{AgentID, CarID, Emissions, ParkingSpot, PedestrianID, PersonID, Problem, TripID};
 
 /// As a simulation runs, different systems emit Events. This cleanly separates the internal
 /// mechanics of the simulation from consumers that just want to know what's happening.
@@ -22,12 +22,26 @@ pub enum Event {
     CarReachedParkingSpot(CarID, ParkingSpot),
     CarLeftParkingSpot(CarID, ParkingSpot),
 
-    BusArrivedAtStop(CarID, TransitRouteID, TransitStopID),
-    BusDepartedFromStop(CarID, TransitRouteID, TransitStopID),
+    /// If the vehicle follows a schedule, also how late it is (negative if early)
+    BusArrivedAtStop(CarID, TransitRouteID, TransitStopID, Option<Duration>),
+    /// Also the number of passengers on board when departing and the vehicle's capacity
+    BusDepartedFromStop(CarID, TransitRouteID, TransitStopID, usize, usize),
     /// How long waiting at the stop?
     PassengerBoardsTransit(PersonID, CarID, TransitRouteID, TransitStopID, Duration),
     PassengerAlightsTransit(PersonID, CarID, TransitRouteID, TransitStopID),
 
+    /// How long did the passenger wait for the ride-hail vehicle after requesting it?
+    RideHailPickup(TripID, CarID, Duration),
+    /// A ride-hail vehicle finished driving somewhere. True if a passenger was on board; false for
+    /// deadheading to a pickup or back to a depot.
+    RideHailLegFinished(CarID, Distance, bool),
+    /// How many ride-hail vehicles are busy, out of the entire fleet?
+    RideHailFleetUtilization(usize, usize),
+
+    /// A freight vehicle stopped to load or unload at a building. The spot is None if nothing was
+    /// free at the curb, so the vehicle double-parked in the travel lane.
+    FreightStopReached(CarID, BuildingID, Option<ParkingSpot>),
+
     PersonEntersBuilding(PersonID, BuildingID),
     PersonLeavesBuilding(PersonID, BuildingID),
     /// None if cancelled
@@ -43,6 +57,9 @@ pub enum Event {
     /// If the agent is a transit vehicle, then include a count of how many passengers are on
     /// board.
     AgentEntersTraversable(AgentID, Option<TripID>, Traversable, Option<usize>),
+    /// How much a vehicle emitted while its front was on something. Recorded when the front
+    /// leaves, or when the vehicle vanishes.
+    VehicleEmissions(CarID, Option<TripID>, Traversable, Emissions),
     /// TripID, TurnID (Where the delay was encountered), Time spent waiting at that turn
     IntersectionDelayMeasured(TripID, TurnID, AgentID, Duration),
 
@@ -79,6 +96,10 @@ pub enum TripPhaseType {
     WaitingForBus(TransitRouteID, TransitStopID),
     /// What stop did they board at?
     RidingBus(TransitRouteID, TransitStopID, CarID),
+    WaitingForRideHail,
+    RidingRideHail(CarID),
+    /// Loading or unloading a freight vehicle at this building
+    Delivering(BuildingID),
     Cancelled,
     Finished,
     DelayedStart,
@@ -97,6 +118,9 @@ impl TripPhaseType {
             TripPhaseType::RidingBus(r, _, _) => {
                 format!("Riding route {}", map.get_tr(r).long_name)
             }
+            TripPhaseType::WaitingForRideHail => "Waiting for a ride-hail pickup".to_string(),
+            TripPhaseType::RidingRideHail(_) => "Riding in a ride-hail".to_string(),
+            TripPhaseType::Delivering(b) => format!("Delivering to {}", map.get_b(b).address),
             TripPhaseType::Cancelled => "Trip was cancelled due to some bug".to_string(),
             TripPhaseType::Finished => "Trip finished".to_string(),
             TripPhaseType::DelayedStart => "Delayed by a previous trip taking too long".to_string(),
@@ -35,8 +35,9 @@ pub use crate::render::{
 };
 
 pub use self::analytics::{Analytics, Problem, SlidingWindow, TripPhase};
-pub(crate) use self::events::Event;
-pub use self::events::{AlertLocation, TripPhaseType};
+pub use self::cost::{ModeCosts, TripCost, TripCostModel, TripUsage};
+pub use self::emissions::Emissions;
+pub use self::events::{AlertLocation, Event, TripPhaseType};
 pub use self::make::{fork_rng, BorderSpawnOverTime, ScenarioGenerator, SimFlags, SpawnOverTime};
 pub(crate) use self::make::{StartTripArgs, TripSpec};
 pub(crate) use self::mechanics::{
@@ -44,26 +45,36 @@ pub(crate) use self::mechanics::{
 };
 pub(crate) use self::pandemic::PandemicModel;
 pub(crate) use self::recorder::TrafficRecorder;
+pub(crate) use self::ridehail::{
+    CurbArrival, RideHailSimState, RIDE_HAIL_DROPOFF_TIME, RIDE_HAIL_PICKUP_TIME,
+};
 pub(crate) use self::router::{ActionAtEnd, Router};
 pub(crate) use self::scheduler::{Command, Scheduler};
 pub use self::sim::{
     count_parked_cars_per_bldg, rand_dist, AgentProperties, AlertHandler, DelayCause, Sim,
     SimCallback, SimOptions,
 };
+pub use self::tables::{ColumnType, Table, TableFormat, Value};
+pub use self::trace::{Divergence, SimTrace, TraceStep};
 pub(crate) use self::transit::TransitSimState;
 pub use self::trips::{CommutersVehiclesCounts, Person, PersonState, TripInfo, TripResult};
 pub(crate) use self::trips::{TripLeg, TripManager};
 
 mod analytics;
+mod cost;
+mod emissions;
 mod events;
 mod make;
 mod mechanics;
 mod pandemic;
 mod recorder;
 mod render;
+mod ridehail;
 mod router;
 mod scheduler;
 mod sim;
+mod tables;
+mod trace;
 mod transit;
 mod trips;
 
@@ -74,6 +85,26 @@ pub(crate) const MAX_CAR_LENGTH: Distance = Distance::const_meters(6.5);
 // Note this is more than MAX_CAR_LENGTH
 pub(crate) const BUS_LENGTH: Distance = Distance::const_meters(12.5);
 pub(crate) const LIGHT_RAIL_LENGTH: Distance = Distance::const_meters(60.0);
+// A panel van
+pub(crate) const DELIVERY_VAN_LENGTH: Distance = Distance::const_meters(6.5);
+// A rigid box truck. Note this won't fit in a street parking spot, only a loading zone.
+pub(crate) const TRUCK_LENGTH: Distance = Distance::const_meters(10.0);
+// Seated plus standing passengers
+pub(crate) const BUS_CAPACITY: usize = 80;
+pub(crate) const LIGHT_RAIL_CAPACITY: usize = 200;
+
+// Comfortable rates of speeding up and braking, in meters per second squared. These're only used
+// when SimOptions::kinematic_model is enabled.
+pub(crate) const CAR_ACCEL: f64 = 2.5;
+pub(crate) const CAR_DECEL: f64 = 3.5;
+pub(crate) const BIKE_ACCEL: f64 = 1.0;
+pub(crate) const BIKE_DECEL: f64 = 2.0;
+pub(crate) const BUS_ACCEL: f64 = 1.2;
+pub(crate) const BUS_DECEL: f64 = 1.5;
+pub(crate) const TRAIN_ACCEL: f64 = 1.0;
+pub(crate) const TRAIN_DECEL: f64 = 1.3;
+pub(crate) const TRUCK_ACCEL: f64 = 1.0;
+pub(crate) const TRUCK_DECEL: f64 = 2.0;
 
 /// At all speeds (including at rest), cars must be at least this far apart, measured from front of
 /// one car to the back of the other.
@@ -103,6 +134,8 @@ impl fmt::Display for CarID {
             VehicleType::Bus => write!(f, "Bus #{}", self.id),
             VehicleType::Train => write!(f, "Train #{}", self.id),
             VehicleType::Bike => write!(f, "Bike #{}", self.id),
+            VehicleType::Delivery => write!(f, "Delivery van #{}", self.id),
+            VehicleType::Truck => write!(f, "Truck #{}", self.id),
         }
     }
 }
@@ -141,7 +174,8 @@ impl AgentID {
     pub fn to_type(self) -> AgentType {
         match self {
             AgentID::Car(c) => match c.vehicle_type {
-                VehicleType::Car => AgentType::Car,
+                // Freight vehicles share the road like cars do
+                VehicleType::Car | VehicleType::Delivery | VehicleType::Truck => AgentType::Car,
                 VehicleType::Bike => AgentType::Bike,
                 VehicleType::Bus => AgentType::Bus,
                 VehicleType::Train => AgentType::Train,
@@ -272,6 +306,10 @@ pub enum VehicleType {
     Bus,
     Train,
     Bike,
+    /// A van making deliveries. It can go anywhere a car can.
+    Delivery,
+    /// A heavy goods vehicle, banned from some roads.
+    Truck,
 }
 
 impl fmt::Display for VehicleType {
@@ -281,6 +319,8 @@ impl fmt::Display for VehicleType {
             VehicleType::Bus => write!(f, "bus"),
             VehicleType::Train => write!(f, "train"),
             VehicleType::Bike => write!(f, "bike"),
+            VehicleType::Delivery => write!(f, "delivery van"),
+            VehicleType::Truck => write!(f, "truck"),
         }
     }
 }
@@ -288,10 +328,31 @@ impl fmt::Display for VehicleType {
 impl VehicleType {
     pub fn to_constraints(self) -> PathConstraints {
         match self {
-            VehicleType::Car => PathConstraints::Car,
+            VehicleType::Car | VehicleType::Delivery => PathConstraints::Car,
             VehicleType::Bus => PathConstraints::Bus,
             VehicleType::Train => PathConstraints::Train,
             VehicleType::Bike => PathConstraints::Bike,
+            VehicleType::Truck => PathConstraints::Truck,
+        }
+    }
+
+    /// The default max acceleration and deceleration, in meters per second squared.
+    pub(crate) fn default_accel_decel(self) -> (f64, f64) {
+        match self {
+            VehicleType::Car | VehicleType::Delivery => (CAR_ACCEL, CAR_DECEL),
+            VehicleType::Bus => (BUS_ACCEL, BUS_DECEL),
+            VehicleType::Train => (TRAIN_ACCEL, TRAIN_DECEL),
+            VehicleType::Bike => (BIKE_ACCEL, BIKE_DECEL),
+            VehicleType::Truck => (TRUCK_ACCEL, TRUCK_DECEL),
+        }
+    }
+
+    /// How many passengers a transit vehicle holds, unless its route overrides this.
+    pub(crate) fn default_passenger_capacity(self) -> usize {
+        match self {
+            VehicleType::Bus => BUS_CAPACITY,
+            VehicleType::Train => LIGHT_RAIL_CAPACITY,
+            VehicleType::Car | VehicleType::Bike | VehicleType::Delivery | VehicleType::Truck => 0,
         }
     }
 
@@ -301,8 +362,15 @@ impl VehicleType {
             VehicleType::Bus => true,
             VehicleType::Train => true,
             VehicleType::Bike => false,
+            VehicleType::Delivery => false,
+            VehicleType::Truck => false,
         }
     }
+
+    /// Delivery vans and trucks may use loading zones.
+    pub fn is_freight(self) -> bool {
+        matches!(self, VehicleType::Delivery | VehicleType::Truck)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -312,6 +380,10 @@ pub struct Vehicle {
     pub vehicle_type: VehicleType,
     pub length: Distance,
     pub max_speed: Option<Speed>,
+    /// In meters per second squared. Only used by the kinematic model.
+    pub max_accel: f64,
+    /// In meters per second squared, as a positive number. Only used by the kinematic model.
+    pub max_decel: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -319,6 +391,10 @@ pub struct VehicleSpec {
     pub vehicle_type: VehicleType,
     pub length: Distance,
     pub max_speed: Option<Speed>,
+    /// In meters per second squared. Only used by the kinematic model.
+    pub max_accel: f64,
+    /// In meters per second squared, as a positive number. Only used by the kinematic model.
+    pub max_decel: f64,
 }
 
 impl VehicleSpec {
@@ -330,6 +406,8 @@ impl VehicleSpec {
             vehicle_type: self.vehicle_type,
             length: self.length,
             max_speed: self.max_speed,
+            max_accel: self.max_accel,
+            max_decel: self.max_decel,
         }
     }
 }
@@ -360,7 +438,7 @@ impl DrivingGoal {
     pub fn goal_pos(&self, constraints: PathConstraints, map: &Map) -> Option<Position> {
         match self {
             DrivingGoal::ParkNear(b) => match constraints {
-                PathConstraints::Car => {
+                PathConstraints::Car | PathConstraints::Truck => {
                     let driving_lane = map.find_driving_lane_near_building(*b);
                     let sidewalk_pos = map.get_b(*b).sidewalk_pos;
                     if driving_lane.road == sidewalk_pos.lane().road {
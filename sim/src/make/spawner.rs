@@ -4,10 +4,13 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use map_model::{BuildingID, Map, PathConstraints, Position, TransitRouteID, TransitStopID};
-use synthpop::{TripEndpoint, TripMode};
+use geom::Duration;
+use map_model::{
+    BuildingID, IntersectionID, Map, PathConstraints, Position, TransitRouteID, TransitStopID,
+};
+use synthpop::{FreightTour, TripEndpoint, TripMode};
 
-use crate::{CarID, DrivingGoal, SidewalkSpot, TripLeg, VehicleType, SPAWN_DIST};
+use crate::{CarID, DrivingGoal, SidewalkSpot, TripLeg, SPAWN_DIST};
 
 /// We need to remember a few things from scenario instantiation that're used for starting the
 /// trip.
@@ -55,6 +58,20 @@ pub(crate) enum TripSpec {
         stop1: TransitStopID,
         maybe_stop2: Option<TransitStopID>,
     },
+    /// Wait inside the building for a vehicle from the ride-hailing fleet, which drops the person
+    /// off at the curb in front of the goal.
+    UsingRideHail { start: BuildingID, goal: BuildingID },
+    /// A delivery van or truck appears, stops at each building in order, then either leaves
+    /// through a border or returns to a depot. The depot is the last stop, with no dwell time.
+    Freight {
+        start_pos: Position,
+        stops: Vec<(BuildingID, Duration)>,
+        /// Only set when the tour ends at a border
+        exit: Option<DrivingGoal>,
+        /// This must be a currently off-map vehicle owned by the person.
+        use_vehicle: CarID,
+        retry_if_no_room: bool,
+    },
 }
 
 impl TripSpec {
@@ -82,11 +99,7 @@ impl TripSpec {
                     }
                 }
 
-                let constraints = if use_vehicle.vehicle_type == VehicleType::Bike {
-                    PathConstraints::Bike
-                } else {
-                    PathConstraints::Car
-                };
+                let constraints = use_vehicle.vehicle_type.to_constraints();
 
                 legs.push(TripLeg::Drive(*use_vehicle, goal.clone()));
                 if let DrivingGoal::ParkNear(b) = goal {
@@ -201,6 +214,31 @@ impl TripSpec {
                     legs = vec![TripLeg::Walk(walk_to), TripLeg::RideBus(*route, None)];
                 }
             }
+            TripSpec::UsingRideHail { goal, .. } => {
+                legs.push(TripLeg::RideHail(*goal));
+            }
+            TripSpec::Freight {
+                start_pos,
+                stops,
+                exit,
+                use_vehicle,
+                ..
+            } => {
+                if start_pos.dist_along() >= map.get_l(start_pos.lane()).length() {
+                    return TripSpec::SpawningFailure {
+                        use_vehicle: Some(*use_vehicle),
+                        error: format!("can't start freight tour at {}", start_pos),
+                    }
+                    .into_plan(map);
+                }
+
+                for (b, dwell) in stops {
+                    legs.push(TripLeg::Deliver(*use_vehicle, *b, *dwell));
+                }
+                if let Some(goal) = exit {
+                    legs.push(TripLeg::Drive(*use_vehicle, goal.clone()));
+                }
+            }
         };
 
         (self, legs)
@@ -212,6 +250,7 @@ impl TripSpec {
         from: TripEndpoint,
         to: TripEndpoint,
         mode: TripMode,
+        freight: Option<&FreightTour>,
         use_vehicle: Option<CarID>,
         retry_if_no_room: bool,
         map: &Map,
@@ -240,23 +279,12 @@ impl TripSpec {
                             }
                         }
                     }
-                    TripEndpoint::Border(i) => {
-                        let start_lane = map
-                            .get_i(i)
-                            .some_outgoing_road(map)
-                            // TODO Since we're now doing this right when the trip is starting,
-                            // pick the least loaded lane or similar.
-                            .and_then(|dr| dr.lanes(constraints, map).pop())
-                            .ok_or_else(|| {
-                                anyhow!("can't start a {} trip from {}", mode.ongoing_verb(), i)
-                            })?;
-                        TripSpec::VehicleAppearing {
-                            start_pos: Position::new(start_lane, SPAWN_DIST),
-                            goal,
-                            use_vehicle: use_vehicle.unwrap(),
-                            retry_if_no_room,
-                        }
-                    }
+                    TripEndpoint::Border(i) => TripSpec::VehicleAppearing {
+                        start_pos: start_at_border(i, mode, constraints, map)?,
+                        goal,
+                        use_vehicle: use_vehicle.unwrap(),
+                        retry_if_no_room,
+                    },
                     TripEndpoint::SuddenlyAppear(start_pos) => TripSpec::VehicleAppearing {
                         start_pos,
                         goal,
@@ -288,10 +316,65 @@ impl TripSpec {
                     TripSpec::JustWalking { start, goal }
                 }
             }
+            TripMode::RideHail => match (from, to) {
+                (TripEndpoint::Building(start), TripEndpoint::Building(goal)) => {
+                    TripSpec::UsingRideHail { start, goal }
+                }
+                // Sim::instantiate cancels these up-front, but trips can be spawned other ways
+                _ => bail!("ride-hail trips must start and end at buildings"),
+            },
+            TripMode::Freight => {
+                let tour = freight.ok_or_else(|| anyhow!("freight trip has no tour"))?;
+                let use_vehicle = use_vehicle.unwrap();
+                let constraints = use_vehicle.vehicle_type.to_constraints();
+                let start_pos = match from {
+                    // Leaving the depot
+                    TripEndpoint::Building(b) => DrivingGoal::ParkNear(b)
+                        .goal_pos(constraints, map)
+                        .ok_or_else(|| anyhow!("can't start a freight tour from {}", b))?,
+                    TripEndpoint::Border(i) => start_at_border(i, mode, constraints, map)?,
+                    TripEndpoint::SuddenlyAppear(pos) => pos,
+                };
+                let mut stops: Vec<(BuildingID, Duration)> = tour
+                    .stops
+                    .iter()
+                    .map(|stop| (stop.building, stop.dwell))
+                    .collect();
+                let exit = match to {
+                    TripEndpoint::Building(b) => {
+                        stops.push((b, Duration::ZERO));
+                        None
+                    }
+                    _ => Some(driving_goal(to, constraints, map)?),
+                };
+                TripSpec::Freight {
+                    start_pos,
+                    stops,
+                    exit,
+                    use_vehicle,
+                    retry_if_no_room,
+                }
+            }
         })
     }
 }
 
+fn start_at_border(
+    i: IntersectionID,
+    mode: TripMode,
+    constraints: PathConstraints,
+    map: &Map,
+) -> Result<Position> {
+    let start_lane = map
+        .get_i(i)
+        .some_outgoing_road(map)
+        // TODO Since we're now doing this right when the trip is starting, pick the least loaded
+        // lane or similar.
+        .and_then(|dr| dr.lanes(constraints, map).pop())
+        .ok_or_else(|| anyhow!("can't start a {} trip from {}", mode.ongoing_verb(), i))?;
+    Ok(Position::new(start_lane, SPAWN_DIST))
+}
+
 fn start_sidewalk_spot(endpt: TripEndpoint, map: &Map) -> Result<SidewalkSpot> {
     match endpt {
         TripEndpoint::Building(b) => Ok(SidewalkSpot::building(b, map)),
@@ -2,12 +2,14 @@ use std::collections::{BTreeSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Distance, Duration, PolyLine, Time, EPSILON_DIST};
-use map_model::{Direction, LaneID, Map, Traversable};
+use geom::{Distance, Duration, PolyLine, Speed, Time, EPSILON_DIST};
+use map_model::{Direction, LaneID, Map, Traversable, TurnPriority};
 
+use crate::emissions::{idling_emissions, moving_emissions};
+use crate::mechanics::kinematics::SpeedProfile;
 use crate::{
-    CarID, CarStatus, DistanceInterval, DrawCarInput, Intent, ParkingSpot, PersonID, Router,
-    TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
+    CarID, CarStatus, DistanceInterval, DrawCarInput, Emissions, Intent, ParkingSpot, PersonID,
+    Router, TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
 };
 
 /// Represents a single vehicle. Note "car" is a misnomer; it could also be a bus or bike.
@@ -26,14 +28,111 @@ pub(crate) struct Car {
     /// vehicle.length.
     pub last_steps: VecDeque<Traversable>,
 
-    /// Since lane over-taking isn't implemented yet, a vehicle tends to be stuck behind a slow
-    /// leader for a while. Avoid duplicate events.
+    /// A vehicle may be stuck behind a slow leader for a while before there's a chance to
+    /// over-take. Avoid duplicate events.
     pub wants_to_overtake: BTreeSet<CarID>,
+
+    /// None when `SimOptions::kinematic_model` is disabled and vehicles instantly move at their
+    /// max speed. Otherwise, the speed this vehicle had at the end of its most recent Crossing
+    /// state.
+    pub exit_speed: Option<Speed>,
+
+    /// Emissions so far on the current step of the path, integrated up to `emissions_updated`.
+    pub emissions: Emissions,
+    pub emissions_updated: Time,
 }
 
 impl Car {
-    /// Assumes the current head of the path is the thing to cross.
-    pub fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> CarState {
+    /// Add the emissions from the current state since the last update. This must be called before
+    /// the state changes. A vehicle moving without the kinematic model might be held up behind a
+    /// leader before its Crossing state ends; that isn't captured here.
+    pub fn update_emissions(&mut self, now: Time, map: &Map) {
+        let since = self.emissions_updated;
+        self.emissions_updated = now;
+        if now <= since {
+            return;
+        }
+        let on = self.router.head();
+        // Live map edits might've removed what the vehicle was on
+        let exists = match on {
+            Traversable::Lane(l) => map.maybe_get_l(l).is_some(),
+            Traversable::Turn(t) => map.maybe_get_t(t).is_some(),
+        };
+        if !exists {
+            return;
+        }
+        let vehicle_type = self.vehicle.vehicle_type;
+
+        // The planned movement, and either how the speed changes or a constant speed
+        let (time_int, profile, constant_speed) = match self.state {
+            CarState::Crossing {
+                time_int,
+                dist_int,
+                ref profile,
+                ..
+            }
+            | CarState::ChangingLanes {
+                new_time: time_int,
+                new_dist: dist_int,
+                new_profile: ref profile,
+                ..
+            } => {
+                let speed = if time_int.end > time_int.start {
+                    Speed::from_dist_time(
+                        dist_int.end - dist_int.start,
+                        time_int.end - time_int.start,
+                    )
+                } else {
+                    Speed::ZERO
+                };
+                (time_int, profile.as_ref(), speed)
+            }
+            CarState::Overtaking { time_int, .. } => {
+                // The pass happens at the vehicle's full speed
+                let speed = self.router.get_path().current_step().max_speed_along(
+                    self.vehicle.max_speed,
+                    vehicle_type.to_constraints(),
+                    map,
+                );
+                (time_int, None, speed)
+            }
+            CarState::Queued { .. }
+            | CarState::WaitingToAdvance { .. }
+            | CarState::Unparking { .. }
+            | CarState::Parking(_, _, _)
+            | CarState::IdlingAtStop(_, _) => {
+                self.emissions += idling_emissions(vehicle_type, now - since);
+                return;
+            }
+        };
+
+        // Any time outside the planned movement is spent stopped
+        let start = since.max(time_int.start).min(now);
+        let end = now.min(time_int.end).max(start);
+        let mut emissions = moving_emissions(
+            vehicle_type,
+            on,
+            end - start,
+            |dt| match profile {
+                Some(profile) => profile.speed_at(start + dt - time_int.start),
+                None => constant_speed,
+            },
+            map,
+        );
+        emissions += idling_emissions(vehicle_type, (now - since) - (end - start));
+        self.emissions += emissions;
+    }
+
+    /// Assumes the current head of the path is the thing to cross. With the kinematic model,
+    /// `must_stop` means the vehicle should brake to a stop at the end, because of a red light or
+    /// a stopped leader.
+    pub fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        must_stop: bool,
+        map: &Map,
+    ) -> CarState {
         let dist_int = DistanceInterval::new_driving(
             start_dist,
             if self.router.last_step() {
@@ -42,13 +141,14 @@ impl Car {
                 self.router.head().get_polyline(map).length()
             },
         );
-        self.crossing_state_with_end_dist(dist_int, start_time, map)
+        self.crossing_state_with_end_dist(dist_int, start_time, must_stop, map)
     }
 
     pub fn crossing_state_with_end_dist(
         &self,
         dist_int: DistanceInterval,
         start_time: Time,
+        must_stop: bool,
         map: &Map,
     ) -> CarState {
         let (speed, percent_incline) = self
@@ -60,12 +160,115 @@ impl Car {
                 self.vehicle.vehicle_type.to_constraints(),
                 map,
             );
-        let dt = (dist_int.end - dist_int.start) / speed;
+        let steep_uphill = percent_incline >= 0.08;
+
+        if self.exit_speed.is_none() {
+            let dt = (dist_int.end - dist_int.start) / speed;
+            return CarState::Crossing {
+                time_int: TimeInterval::new(start_time, start_time + dt),
+                dist_int,
+                steep_uphill,
+                profile: None,
+            };
+        }
+
+        let profile = SpeedProfile::new(
+            dist_int.end - dist_int.start,
+            self.initial_speed(start_time),
+            speed,
+            self.planned_final_speed(dist_int, speed, must_stop, map),
+            self.vehicle.max_accel,
+            self.vehicle.max_decel,
+        );
         CarState::Crossing {
-            time_int: TimeInterval::new(start_time, start_time + dt),
+            time_int: TimeInterval::new(start_time, start_time + profile.total_time()),
             dist_int,
-            steep_uphill: percent_incline >= 0.08,
+            steep_uphill,
+            profile: Some(profile),
+        }
+    }
+
+    /// With the kinematic model, how fast is this vehicle going when it starts a new Crossing
+    /// state right now?
+    fn initial_speed(&self, now: Time) -> Speed {
+        match self.state {
+            // Recalculating in the middle of crossing something
+            CarState::Crossing {
+                ref time_int,
+                profile: Some(ref profile),
+                ..
+            }
+            | CarState::ChangingLanes {
+                new_time: ref time_int,
+                new_profile: Some(ref profile),
+                ..
+            } => profile.speed_at(now - time_int.start),
+            // If the vehicle finished crossing and immediately continues, it keeps its speed. If it
+            // had to wait for a red light or a leader, it starts from a stop.
+            CarState::Queued { blocked_since, .. }
+            | CarState::WaitingToAdvance { blocked_since }
+                if blocked_since == now =>
+            {
+                self.exit_speed.unwrap_or(Speed::ZERO)
+            }
+            _ => Speed::ZERO,
+        }
+    }
+
+    /// With the kinematic model, is this vehicle stopped, or braking to a stop at the end of what
+    /// it's crossing? Followers use this to brake behind it.
+    pub fn stopped_or_stopping(&self) -> bool {
+        match self.state {
+            CarState::Crossing { ref profile, .. }
+            | CarState::ChangingLanes {
+                new_profile: ref profile,
+                ..
+            } => profile
+                .as_ref()
+                .map(|p| p.final_speed() == Speed::ZERO)
+                .unwrap_or(false),
+            CarState::Overtaking { .. } => false,
+            CarState::Queued { .. }
+            | CarState::WaitingToAdvance { .. }
+            | CarState::Unparking { .. }
+            | CarState::Parking(_, _, _)
+            | CarState::IdlingAtStop(_, _) => true,
+        }
+    }
+
+    /// With the kinematic model, how fast should this vehicle be going at the end of a crossing?
+    /// Vehicles plan to stop at the end of their path, at stop signs, and when `must_stop` says
+    /// there's a red light or a stopped leader ahead. Otherwise they keep going, limited by the
+    /// speed of their next step.
+    fn planned_final_speed(
+        &self,
+        dist_int: DistanceInterval,
+        cruise: Speed,
+        must_stop: bool,
+        map: &Map,
+    ) -> Speed {
+        if self.router.last_step() {
+            return Speed::ZERO;
+        }
+        // Not crossing all the way to the end, so just keep going
+        if dist_int.end < self.router.head().get_polyline(map).length() {
+            return cruise;
         }
+        if must_stop {
+            return Speed::ZERO;
+        }
+        if let Traversable::Turn(t) = self.router.next() {
+            if let Some(sign) = map.maybe_get_stop_sign(t.parent) {
+                if sign.get_priority(t, map) == TurnPriority::Yield {
+                    return Speed::ZERO;
+                }
+            }
+        }
+        cruise.min(self.router.get_path().next_step().max_speed_along(
+            self.vehicle.max_speed,
+            self.vehicle.vehicle_type.to_constraints(),
+            map,
+        ))
     }
 
     pub fn get_draw_car(
@@ -168,6 +371,26 @@ impl Car {
                     }
                 }
             }
+            CarState::Overtaking { oncoming, .. } => {
+                // The car's body is already in front of the leader, so just shift it over to the
+                // oncoming lane for the whole pass
+                let current = self.router.head().as_lane();
+                let mut diff = (oncoming.offset as isize) - (current.offset as isize);
+                if map.get_l(current).dir == Direction::Back {
+                    diff *= -1;
+                }
+                let width = map.get_l(oncoming).width * (diff as f64);
+                match raw_body.shift_right(width) {
+                    Ok(pl) => pl,
+                    Err(err) => {
+                        println!(
+                            "Body for overtaking {} at {} broken: {}",
+                            self.vehicle.id, now, err
+                        );
+                        raw_body
+                    }
+                }
+            }
             CarState::Unparking {
                 ref spot,
                 ref time_int,
@@ -272,6 +495,7 @@ impl Car {
                 CarState::Crossing { .. } => CarStatus::Moving,
                 CarState::ChangingLanes { .. } => CarStatus::Moving,
                 CarState::Unparking { .. } => CarStatus::Moving,
+                CarState::Overtaking { .. } => CarStatus::Moving,
                 CarState::Parking(_, _, _) => CarStatus::Moving,
                 // Changing color for idling buses is helpful
                 CarState::IdlingAtStop(_, _) => CarStatus::Parked,
@@ -320,6 +544,9 @@ pub(crate) enum CarState {
         time_int: TimeInterval,
         dist_int: DistanceInterval,
         steep_uphill: bool,
+        /// Only present with the kinematic model. Otherwise, the vehicle moves at a constant
+        /// speed.
+        profile: Option<SpeedProfile>,
     },
     ChangingLanes {
         from: LaneID,
@@ -327,6 +554,7 @@ pub(crate) enum CarState {
         // For the most part, act just like a Crossing state with these intervals
         new_time: TimeInterval,
         new_dist: DistanceInterval,
+        new_profile: Option<SpeedProfile>,
         // How long does the lane-changing itself last? This must end before new_time_int does.
         lc_time: TimeInterval,
     },
@@ -344,6 +572,13 @@ pub(crate) enum CarState {
         time_int: TimeInterval,
         blocked_starts: Vec<LaneID>,
     },
+    /// Passing a slower leader using the oncoming lane. The vehicle is already in front of the
+    /// leader in the queue, and its front stays at the position it'll reach after the pass.
+    Overtaking {
+        front: Distance,
+        oncoming: LaneID,
+        time_int: TimeInterval,
+    },
     Parking(Distance, ParkingSpot, TimeInterval),
     IdlingAtStop(Distance, TimeInterval),
 }
@@ -357,11 +592,43 @@ impl CarState {
             // Note this state lasts for lc_time, NOT for new_time.
             CarState::ChangingLanes { ref lc_time, .. } => lc_time.end,
             CarState::Unparking { ref time_int, .. } => time_int.end,
+            CarState::Overtaking { ref time_int, .. } => time_int.end,
             CarState::Parking(_, _, ref time_int) => time_int.end,
             CarState::IdlingAtStop(_, ref time_int) => time_int.end,
         }
     }
 
+    /// Where's the front of a Crossing or ChangingLanes vehicle at some time?
+    pub fn dist_along_crossing(&self, now: Time) -> Distance {
+        let (time_int, dist_int, profile) = match *self {
+            CarState::Crossing {
+                ref time_int,
+                ref dist_int,
+                ref profile,
+                ..
+            } => (time_int, dist_int, profile),
+            CarState::ChangingLanes {
+                ref new_time,
+                ref new_dist,
+                ref new_profile,
+                ..
+            } => (new_time, new_dist, new_profile),
+            _ => unreachable!(),
+        };
+        // TODO Why percent_clamp_end? We process car updates in any order, so we might
+        // calculate this before moving this car from Crossing to another state.
+        match profile {
+            Some(profile) => {
+                if now >= time_int.end {
+                    dist_int.end
+                } else {
+                    (dist_int.start + profile.dist_at(now - time_int.start)).min(dist_int.end)
+                }
+            }
+            None => dist_int.lerp(time_int.percent_clamp_end(now)),
+        }
+    }
+
     pub fn time_spent_waiting(&self, now: Time) -> Duration {
         match self {
             CarState::Queued { blocked_since, .. }
@@ -3,21 +3,26 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_hashmap, serialize_hashmap, FixedMap, IndexableKey};
-use geom::{Distance, Duration, PolyLine, Time};
-use map_model::{DrivingSide, IntersectionID, LaneID, Map, Path, PathStep, Position, Traversable};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
+use map_model::{
+    DrivingSide, IntersectionID, LaneID, LaneType, Map, Path, PathStep, Position, Traversable,
+};
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::queue::{Queue, QueueEntry, Queued};
 use crate::sim::Ctx;
 use crate::{
-    ActionAtEnd, AgentID, AgentProperties, CarID, CarStatus, Command, CreateCar, DelayCause,
-    DistanceInterval, DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSim,
-    ParkingSpot, PersonID, Problem, SimOptions, TimeInterval, TransitSimState, TripID, TripManager,
-    UnzoomedAgent, Vehicle, VehicleType, WalkingSimState, FOLLOWING_DISTANCE, MAX_CAR_LENGTH,
+    ActionAtEnd, AgentID, AgentProperties, CarID, CarStatus, Command, CreateCar, CurbArrival,
+    DelayCause, DistanceInterval, DrawCarInput, Emissions, Event, IntersectionSimState, ParkedCar,
+    ParkingSim, ParkingSpot, PersonID, Problem, SimOptions, TimeInterval, TransitSimState, TripID,
+    TripManager, UnzoomedAgent, Vehicle, VehicleType, WalkingSimState, FOLLOWING_DISTANCE,
+    MAX_CAR_LENGTH, RIDE_HAIL_DROPOFF_TIME, RIDE_HAIL_PICKUP_TIME,
 };
 
-const TIME_TO_WAIT_AT_BUS_STOP: Duration = Duration::const_seconds(10.0);
 const TIME_TO_CHANGE_LANES: Duration = Duration::const_seconds(1.0);
+/// When overtaking using the oncoming lane, oncoming traffic must be at least this much farther
+/// away than the pass takes.
+const ONCOMING_GAP: Duration = Duration::const_seconds(3.0);
 
 // TODO Do something else.
 pub const BLIND_RETRY_TO_CREEP_FORWARDS: Duration = Duration::const_seconds(0.1);
@@ -45,6 +50,8 @@ pub(crate) struct DrivingSimState {
 
     recalc_lanechanging: bool,
     handle_uber_turns: bool,
+    kinematic_model: bool,
+    overtake_using_oncoming_lanes: bool,
 
     time_to_unpark_onstreet: Duration,
     time_to_park_onstreet: Duration,
@@ -61,6 +68,8 @@ impl DrivingSimState {
             events: Vec::new(),
             recalc_lanechanging: !opts.dont_recalc_lanechanging,
             handle_uber_turns: !opts.dont_handle_uber_turns,
+            kinematic_model: opts.kinematic_model,
+            overtake_using_oncoming_lanes: opts.overtake_using_oncoming_lanes,
             waiting_to_spawn: BTreeMap::new(),
 
             time_to_unpark_onstreet: Duration::seconds(10.0),
@@ -191,6 +200,13 @@ impl DrivingSimState {
                 total_blocked_time: Duration::ZERO,
                 trip_and_person: params.trip_and_person,
                 wants_to_overtake: BTreeSet::new(),
+                exit_speed: if self.kinematic_model {
+                    Some(Speed::ZERO)
+                } else {
+                    None
+                },
+                emissions: Emissions::default(),
+                emissions_updated: now,
             };
             if let Some(p) = params.maybe_parked_car {
                 let delay = match p.spot {
@@ -265,7 +281,8 @@ impl DrivingSimState {
                     }
                 }
 
-                car.state = car.crossing_state(start_dist, now, ctx.map);
+                car.state =
+                    car.crossing_state(start_dist, now, self.must_stop_ahead(&car, ctx), ctx.map);
             }
             ctx.scheduler
                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -274,6 +291,7 @@ impl DrivingSimState {
                 .unwrap()
                 .insert_car_at_idx(idx, &car);
             self.waiting_to_spawn.remove(&car.vehicle.id);
+            ctx.intersections.vehicle_replanned(&car, ctx.map);
             self.cars.insert(car.vehicle.id, car);
             return None;
         }
@@ -290,7 +308,7 @@ impl DrivingSimState {
     ///
     /// Crossing -> Queued or WaitingToAdvance
     /// Unparking -> Crossing
-    /// IdlingAtStop -> Crossing
+    /// IdlingAtStop -> Crossing, or IdlingAtStop again if passengers boarded late
     /// Queued -> last step handling (Parking or done)
     /// WaitingToAdvance -> try to advance to the next step of the path
     /// Parking -> done
@@ -315,6 +333,10 @@ impl DrivingSimState {
         transit: &mut TransitSimState,
         walking: &mut WalkingSimState,
     ) {
+        self.cars
+            .get_mut(&id)
+            .unwrap()
+            .update_emissions(now, ctx.map);
         let mut need_distances = {
             let car = &self.cars[&id];
             match car.state {
@@ -358,6 +380,9 @@ impl DrivingSimState {
                 self.delete_car_internal(&mut car, dists, idx, now, ctx);
             }
         }
+        if let Some(car) = self.cars.get(&id) {
+            ctx.intersections.vehicle_replanned(car, ctx.map);
+        }
     }
 
     // If this returns true, we need to immediately run update_car_with_distances. If we don't,
@@ -371,7 +396,10 @@ impl DrivingSimState {
         transit: &mut TransitSimState,
     ) -> bool {
         match car.state {
-            CarState::Crossing { .. } => {
+            CarState::Crossing { ref profile, .. } => {
+                if let Some(profile) = profile {
+                    car.exit_speed = Some(profile.final_speed());
+                }
                 car.state = CarState::Queued {
                     blocked_since: now,
                     want_to_change_lanes: None,
@@ -458,7 +486,7 @@ impl DrivingSimState {
                         &mut self.events,
                     );
                 }
-                car.state = car.crossing_state(front, now, ctx.map);
+                car.state = car.crossing_state(front, now, self.must_stop_ahead(car, ctx), ctx.map);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
             }
@@ -503,6 +531,7 @@ impl DrivingSimState {
                 // We do NOT need to update the follower. If they were Queued, they'll remain that
                 // way, until laggy_head is None.
 
+                self.record_emissions(car, now, ctx.map);
                 let last_step = car.router.advance(
                     &car.vehicle,
                     ctx.parking,
@@ -511,7 +540,12 @@ impl DrivingSimState {
                     &mut self.events,
                 );
                 car.total_blocked_time += now - blocked_since;
-                car.state = car.crossing_state(Distance::ZERO, now, ctx.map);
+                car.state = car.crossing_state(
+                    Distance::ZERO,
+                    now,
+                    self.must_stop_ahead(car, ctx),
+                    ctx.map,
+                );
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.events.push(Event::AgentEntersTraversable(
@@ -538,6 +572,7 @@ impl DrivingSimState {
                             car.vehicle.length + FOLLOWING_DISTANCE,
                         ),
                         now,
+                        false,
                         ctx.map,
                     )
                     .get_end_time(),
@@ -553,6 +588,7 @@ impl DrivingSimState {
                 from,
                 new_time,
                 new_dist,
+                ref new_profile,
                 ..
             } => {
                 // The car is already in the target queue. Just set them in the crossing state; we
@@ -561,6 +597,7 @@ impl DrivingSimState {
                     time_int: new_time,
                     dist_int: new_dist,
                     steep_uphill: false,
+                    profile: new_profile.clone(),
                 };
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -580,6 +617,15 @@ impl DrivingSimState {
                     .unwrap()
                     .clear_dynamic_blockage(car.vehicle.id, idx);
             }
+            CarState::Overtaking {
+                front, oncoming, ..
+            } => {
+                // The pass is done; pull back in and keep going
+                self.clear_oncoming_blockage(car.vehicle.id, oncoming, now, ctx);
+                car.state = car.crossing_state(front, now, self.must_stop_ahead(car, ctx), ctx.map);
+                ctx.scheduler
+                    .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+            }
             CarState::Queued { .. } => unreachable!(),
             CarState::Parking(_, _, _) => unreachable!(),
             CarState::IdlingAtStop(_, _) => unreachable!(),
@@ -605,7 +651,8 @@ impl DrivingSimState {
             CarState::Crossing { .. }
             | CarState::Unparking { .. }
             | CarState::WaitingToAdvance { .. }
-            | CarState::ChangingLanes { .. } => unreachable!(),
+            | CarState::ChangingLanes { .. }
+            | CarState::Overtaking { .. } => unreachable!(),
             CarState::Queued {
                 blocked_since,
                 want_to_change_lanes,
@@ -613,7 +660,20 @@ impl DrivingSimState {
                 // Two totally different reasons we'll wind up here: we want to lane-change, and
                 // we're on our last step.
                 if let Some(target_lane) = want_to_change_lanes {
-                    self.try_start_lc(car, our_dist, idx, target_lane, now, ctx);
+                    if ctx.map.get_l(target_lane).dir
+                        == ctx.map.get_l(car.router.head().as_lane()).dir
+                    {
+                        self.try_start_lc(car, our_dist, idx, target_lane, now, ctx);
+                    } else {
+                        self.try_overtake_using_oncoming_lane(
+                            car,
+                            dists,
+                            idx,
+                            target_lane,
+                            now,
+                            ctx,
+                        );
+                    }
                     return true;
                 }
 
@@ -673,7 +733,12 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::GotoLaneEnd) => {
                         car.total_blocked_time += now - blocked_since;
-                        car.state = car.crossing_state(our_dist, now, ctx.map);
+                        car.state = car.crossing_state(
+                            our_dist,
+                            now,
+                            self.must_stop_ahead(car, ctx),
+                            ctx.map,
+                        );
                         ctx.scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                         true
@@ -692,10 +757,12 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::BusAtStop) => {
                         car.total_blocked_time += now - blocked_since;
-                        if transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx) {
+                        if let Some(dwell_time) =
+                            transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx)
+                        {
                             car.state = CarState::IdlingAtStop(
                                 our_dist,
-                                TimeInterval::new(now, now + TIME_TO_WAIT_AT_BUS_STOP),
+                                TimeInterval::new(now, now + dwell_time),
                             );
                             ctx.scheduler
                                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -705,6 +772,64 @@ impl DrivingSimState {
                             false
                         }
                     }
+                    Some(ActionAtEnd::DoublePark(dwell_time)) => {
+                        car.total_blocked_time += now - blocked_since;
+                        trips.freight_double_parked(car.vehicle.id);
+                        // Stay in the queue, blocking anybody behind
+                        car.state = CarState::IdlingAtStop(
+                            our_dist,
+                            TimeInterval::new(now, now + dwell_time),
+                        );
+                        ctx.scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        true
+                    }
+                    Some(ActionAtEnd::RideHailAtCurb) => {
+                        car.total_blocked_time += now - blocked_since;
+                        let dwell_time = match ctx.ridehail.vehicle_arrived(
+                            now,
+                            car.vehicle.id,
+                            car.router.get_path().total_length(),
+                            ctx.map,
+                            ctx.restrictions,
+                            ctx.scheduler,
+                        ) {
+                            CurbArrival::PickUp(trip, req) => {
+                                trips.ride_hail_picked_up(trip, car.vehicle.id, req);
+                                // Only count delay while the passenger is on board
+                                car.total_blocked_time = Duration::ZERO;
+                                RIDE_HAIL_PICKUP_TIME
+                            }
+                            CurbArrival::DropOff => {
+                                trips.ride_hail_dropped_off(
+                                    now,
+                                    car.vehicle.id,
+                                    car.total_blocked_time,
+                                    car.router.get_path().total_length(),
+                                    ctx,
+                                );
+                                RIDE_HAIL_DROPOFF_TIME
+                            }
+                            CurbArrival::CantReachDropoff(trip, err) => {
+                                trips.cancel_trip(now, trip, err, None, ctx);
+                                Duration::ZERO
+                            }
+                            CurbArrival::Nothing => Duration::ZERO,
+                            CurbArrival::ReachedDepot(failed) => {
+                                for (trip, err) in failed {
+                                    trips.cancel_trip(now, trip, err, None, ctx);
+                                }
+                                return false;
+                            }
+                        };
+                        car.state = CarState::IdlingAtStop(
+                            our_dist,
+                            TimeInterval::new(now, now + dwell_time),
+                        );
+                        ctx.scheduler
+                            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                        true
+                    }
                     None => {
                         ctx.scheduler.push(
                             now + BLIND_RETRY_TO_REACH_END_DIST,
@@ -751,10 +876,45 @@ impl DrivingSimState {
                 false
             }
             CarState::IdlingAtStop(dist, _) => {
-                car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
+                if let Some(dwell_time) = transit.extra_dwell_time(car.vehicle.id) {
+                    car.state =
+                        CarState::IdlingAtStop(dist, TimeInterval::new(now, now + dwell_time));
+                    ctx.scheduler
+                        .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                    return true;
+                }
+                car.router = if ctx.ridehail.is_fleet_vehicle(car.vehicle.id) {
+                    ctx.ridehail.vehicle_departed(
+                        now,
+                        car.vehicle.id,
+                        Position::new(car.router.head().as_lane(), dist),
+                        ctx.map,
+                        ctx.restrictions,
+                    )
+                } else if car.vehicle.vehicle_type.is_freight() {
+                    match trips.freight_departed_double_park(
+                        now,
+                        car.vehicle.id,
+                        Position::new(car.router.head().as_lane(), dist),
+                        car.total_blocked_time,
+                        car.router.get_path().total_length(),
+                        ctx,
+                    ) {
+                        Some(router) => {
+                            car.total_blocked_time = Duration::ZERO;
+                            router
+                        }
+                        // Back at the depot, or the trip was cancelled
+                        None => {
+                            return false;
+                        }
+                    }
+                } else {
+                    transit.bus_departed_from_stop(car.vehicle.id, ctx.map)
+                };
                 self.events
                     .push(Event::PathAmended(car.router.get_path().clone()));
-                car.state = car.crossing_state(dist, now, ctx.map);
+                car.state = car.crossing_state(dist, now, self.must_stop_ahead(car, ctx), ctx.map);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
 
@@ -800,6 +960,9 @@ impl DrivingSimState {
         if car.router.last_step() {
             ctx.parking.unreserve_spot(c);
         }
+        if let CarState::Overtaking { oncoming, .. } = car.state {
+            self.clear_oncoming_blockage(c, oncoming, now, ctx);
+        }
 
         self.delete_car_internal(&mut car, dists, idx, now, ctx);
         // delete_car_internal cancels UpdateLaggyHead
@@ -815,6 +978,7 @@ impl DrivingSimState {
         now: Time,
         ctx: &mut Ctx,
     ) {
+        self.record_emissions(car, now, ctx.map);
         {
             let queue = self.queues.get_mut(&car.router.head()).unwrap();
             queue.remove_car_from_idx(car.vehicle.id, idx);
@@ -842,6 +1006,29 @@ impl DrivingSimState {
         self.update_follower(idx, &dists, now, ctx);
     }
 
+    /// With the kinematic model, should this vehicle plan to brake to a stop at the end of its
+    /// current step, because the next turn has a red light or the leader in the same queue is
+    /// stopped (or braking to a stop)? Stop signs and the end of the path are handled by the Car.
+    /// The light is only checked when the vehicle starts crossing, so it might brake for a light
+    /// that turns green before it arrives.
+    fn must_stop_ahead(&self, car: &Car, ctx: &Ctx) -> bool {
+        if !self.kinematic_model || car.router.last_step() {
+            return false;
+        }
+        if let Traversable::Turn(t) = car.router.next() {
+            if ctx.intersections.red_light(t, ctx.map) {
+                return true;
+            }
+        }
+        self.queues
+            .get(&car.router.head())
+            .and_then(|queue| queue.get_leader(car.vehicle.id))
+            // The leader might be in the middle of updating, temporarily removed from self.cars
+            .and_then(|leader| self.cars.get(&leader))
+            .map(|leader| leader.stopped_or_stopping())
+            .unwrap_or(false)
+    }
+
     /// After a leader (maybe an active vehicle, maybe a static blockage) gets out of the way,
     /// update the follower so that they don't suddenly jump forwards.
     fn update_follower(
@@ -866,7 +1053,9 @@ impl DrivingSimState {
                 }
             }
 
+            let must_stop = self.must_stop_ahead(&self.cars[&follower_id], ctx);
             let mut follower = self.cars.get_mut(&follower_id).unwrap();
+            follower.update_emissions(now, ctx.map);
             // TODO If the leader vanished at a border node, this still jumps a bit -- the lead
             // car's back is still sticking out. Need to still be bound by them, even though they
             // don't exist! If the leader just parked, then we're fine.
@@ -877,7 +1066,8 @@ impl DrivingSimState {
 
                     // Prevent them from jumping forwards.
                     follower.total_blocked_time += now - blocked_since;
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state =
+                        follower.crossing_state(follower_dist, now, must_stop, ctx.map);
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -887,7 +1077,8 @@ impl DrivingSimState {
                     // If the follower was still Crossing, they might not've been blocked by the
                     // leader yet. But recalculating their Crossing state isn't necessarily a no-op
                     // -- this could prevent them from suddenly warping past a blockage.
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state =
+                        follower.crossing_state(follower_dist, now, must_stop, ctx.map);
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -903,14 +1094,22 @@ impl DrivingSimState {
                     // finish before the new time interval, because there's no possible way
                     // recalculating this crossing state here will speed things up from the
                     // original estimate.
-                    let (new_time, new_dist) = match follower.crossing_state_with_end_dist(
-                        DistanceInterval::new_driving(follower_dist, ctx.map.get_l(to).length()),
-                        now,
-                        ctx.map,
-                    ) {
+                    let (new_time, new_dist, new_profile) = match follower
+                        .crossing_state_with_end_dist(
+                            DistanceInterval::new_driving(
+                                follower_dist,
+                                ctx.map.get_l(to).length(),
+                            ),
+                            now,
+                            must_stop,
+                            ctx.map,
+                        ) {
                         CarState::Crossing {
-                            time_int, dist_int, ..
-                        } => (time_int, dist_int),
+                            time_int,
+                            dist_int,
+                            profile,
+                            ..
+                        } => (time_int, dist_int, profile),
                         _ => unreachable!(),
                     };
                     assert!(new_time.end >= lc_time.end);
@@ -919,15 +1118,18 @@ impl DrivingSimState {
                         to,
                         new_time,
                         new_dist,
+                        new_profile,
                         lc_time,
                     };
                 }
                 // They weren't blocked
                 CarState::Unparking { .. }
+                | CarState::Overtaking { .. }
                 | CarState::Parking(_, _, _)
                 | CarState::IdlingAtStop(_, _) => {}
                 CarState::WaitingToAdvance { .. } => unreachable!(),
             }
+            ctx.intersections.vehicle_replanned(follower, ctx.map);
         }
     }
 
@@ -977,6 +1179,7 @@ impl DrivingSimState {
                         self.cars[&id].vehicle.length + FOLLOWING_DISTANCE,
                     ),
                     now,
+                    false,
                     ctx.map,
                 )
                 .get_end_time();
@@ -1044,6 +1247,7 @@ impl DrivingSimState {
                                     // The follower has been smoothly following while the laggy head
                                     // gets out of the way. So immediately promote them to
                                     // WaitingToAdvance.
+                                    follower.update_emissions(now, ctx.map);
                                     follower.state = CarState::WaitingToAdvance { blocked_since };
                                     if self.recalc_lanechanging && ctx.handling_live_edits.is_none()
                                     {
@@ -1063,6 +1267,7 @@ impl DrivingSimState {
                             CarState::Crossing { .. }
                             | CarState::ChangingLanes { .. }
                             | CarState::Unparking { .. }
+                            | CarState::Overtaking { .. }
                             | CarState::Parking(_, _, _)
                             | CarState::IdlingAtStop(_, _) => {}
                         }
@@ -1077,8 +1282,10 @@ impl DrivingSimState {
     }
 
     /// If the car wants to over-take somebody, what adjacent lane should they use?
-    /// - The lane must be in the same direction as the current; no support for crossing the road's
-    ///   yellow line yet.
+    /// - Prefer a lane in the same direction as the current one. The vehicle will lane-change
+    ///   into it.
+    /// - If there isn't one and the simulation allows it, use the oncoming lane just across the
+    ///   road's yellow line. The vehicle will briefly pop into it to pass.
     /// - Prefer passing on the left (for DrivingSide::Right)
     /// For now, just pick one candidate lane, even if both might be usable.
     fn pick_overtaking_lane(&self, car: &Car, map: &Map) -> Option<LaneID> {
@@ -1098,10 +1305,17 @@ impl DrivingSimState {
             candidates.reverse();
         }
 
+        let mut oncoming = None;
         for l in candidates {
             let target_lane = map.get_l(l);
-            // Must be the same direction -- no crossing into oncoming traffic yet
             if current_lane.dir != target_lane.dir {
+                // Only pop into a regular oncoming lane, not a bus or bike lane
+                if self.overtake_using_oncoming_lanes
+                    && oncoming.is_none()
+                    && target_lane.lane_type == LaneType::Driving
+                {
+                    oncoming = Some(target_lane.id);
+                }
                 continue;
             }
             // The lane types can differ, as long as the vehicle can use the target. Imagine
@@ -1125,7 +1339,7 @@ impl DrivingSimState {
             return Some(target_lane.id);
         }
 
-        None
+        oncoming
     }
 
     fn try_start_lc(
@@ -1154,14 +1368,18 @@ impl DrivingSimState {
 
         // Calculate the crossing state in the target queue. Pass in the DistanceInterval
         // explicitly, because we haven't modified the route yet.
-        let (new_time, new_dist) = match car.crossing_state_with_end_dist(
+        let (new_time, new_dist, new_profile) = match car.crossing_state_with_end_dist(
             DistanceInterval::new_driving(front_target_queue, ctx.map.get_l(target_lane).length()),
             now,
+            self.must_stop_ahead(car, ctx),
             ctx.map,
         ) {
             CarState::Crossing {
-                time_int, dist_int, ..
-            } => (time_int, dist_int),
+                time_int,
+                dist_int,
+                profile,
+                ..
+            } => (time_int, dist_int, profile),
             _ => unreachable!(),
         };
 
@@ -1192,6 +1410,16 @@ impl DrivingSimState {
                 );
             }
 
+            // Lane-changing doesn't always mean passing somebody. Only count it if the leader is
+            // slower, and we'll reach the end of the lane before they do.
+            if let Some(leader) = self.wants_to_overtake(car) {
+                if let CarState::Crossing { ref time_int, .. } = self.cars[&leader].state {
+                    if new_time.end < time_int.end {
+                        self.record_overtake(leader, car.router.head());
+                    }
+                }
+            }
+
             // Exit the old queue (leaving a dynamic blockage in place)
             self.queues
                 .get_mut(&car.router.head())
@@ -1213,6 +1441,7 @@ impl DrivingSimState {
                 to: target_lane,
                 new_time,
                 new_dist,
+                new_profile,
                 lc_time,
             };
             ctx.scheduler
@@ -1220,6 +1449,185 @@ impl DrivingSimState {
         }
     }
 
+    /// Pass the leader using the oncoming lane, if there's a big enough gap in oncoming traffic.
+    /// The vehicle and its leader swap places in the queue right away, but the vehicle holds its
+    /// final position and blocks the stretch of the oncoming lane it's using until the pass would
+    /// be finished.
+    fn try_overtake_using_oncoming_lane(
+        &mut self,
+        car: &mut Car,
+        dists: &[QueueEntry],
+        idx: usize,
+        oncoming: LaneID,
+        now: Time,
+        ctx: &mut Ctx,
+    ) {
+        // Same as lane-changing: don't start if our back is still sticking out somewhere else.
+        if !car.last_steps.is_empty() || idx == 0 {
+            return;
+        }
+        let leader = match dists[idx - 1].member {
+            Queued::Vehicle(id) => id,
+            _ => {
+                return;
+            }
+        };
+        let queue = &self.queues[&car.router.head()];
+
+        // How long does it take to pass at the difference in speeds, and where will we be after?
+        // The leader keeps moving, so they wind up FOLLOWING_DISTANCE behind us.
+        let step = car.router.get_path().current_step();
+        let our_speed = step.max_speed_along(
+            car.vehicle.max_speed,
+            car.vehicle.vehicle_type.to_constraints(),
+            ctx.map,
+        );
+        let leader_vehicle = &self.cars[&leader].vehicle;
+        let leader_speed = step.max_speed_along(
+            leader_vehicle.max_speed,
+            leader_vehicle.vehicle_type.to_constraints(),
+            ctx.map,
+        );
+        if our_speed <= leader_speed {
+            return;
+        }
+        let pass_time = (dists[idx - 1].front + FOLLOWING_DISTANCE + car.vehicle.length
+            - dists[idx].front)
+            / (our_speed - leader_speed);
+        let new_front = dists[idx].front + our_speed * pass_time;
+
+        // Is there room in front of the leader?
+        let bound = if idx >= 2 {
+            dists[idx - 2].back - FOLLOWING_DISTANCE
+        } else if queue.laggy_head.is_some() {
+            // We don't know exactly where they are, so be conservative
+            return;
+        } else {
+            queue.geom_len
+        };
+        if new_front > bound {
+            return;
+        }
+        if car.router.last_step() && new_front > car.router.get_end_dist() {
+            return;
+        }
+
+        // The oncoming lane points the other way, so flip the part of our lane the pass covers.
+        let our_len = queue.geom_len;
+        let oncoming_queue = &self.queues[&Traversable::Lane(oncoming)];
+        let oncoming_len = oncoming_queue.geom_len;
+        let blockage_back = (oncoming_len * (1.0 - new_front / our_len)).max(FOLLOWING_DISTANCE);
+        let blockage_front = oncoming_len * (1.0 - dists[idx].back / our_len);
+        if blockage_front <= blockage_back {
+            return;
+        }
+
+        // Gap acceptance: anything on the oncoming lane driving at the speed limit must not be
+        // able to reach the part we're using before the pass is done, with some margin to spare.
+        let safe_dist = ctx.map.get_r(oncoming.road).speed_limit * (pass_time + ONCOMING_GAP);
+        if oncoming_queue
+            .get_car_positions(now, &self.cars, &self.queues)
+            .into_iter()
+            .any(|entry| entry.back < blockage_front && entry.front > blockage_back - safe_dist)
+        {
+            return;
+        }
+        // Somebody about to enter the oncoming lane could reach us too
+        if blockage_back <= safe_dist
+            && (oncoming_queue.laggy_head.is_some()
+                || !ctx
+                    .intersections
+                    .nobody_headed_towards(oncoming, ctx.map.get_l(oncoming).src_i))
+        {
+            return;
+        }
+        let oncoming_idx = match oncoming_queue.can_block_from_driveway(
+            &Position::new(oncoming, blockage_front),
+            blockage_front - blockage_back,
+            now,
+            &self.cars,
+            &self.queues,
+        ) {
+            Some(idx) => idx,
+            None => {
+                return;
+            }
+        };
+
+        self.queues
+            .get_mut(&Traversable::Lane(oncoming))
+            .unwrap()
+            .add_static_blockage(car.vehicle.id, blockage_front, blockage_back, oncoming_idx);
+        self.queues
+            .get_mut(&car.router.head())
+            .unwrap()
+            .swap_with_leader(car.vehicle.id, idx);
+        self.record_overtake(leader, car.router.head());
+
+        car.state = CarState::Overtaking {
+            front: new_front,
+            oncoming,
+            time_int: TimeInterval::new(now, now + pass_time),
+        };
+        ctx.scheduler
+            .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+
+        // Whoever was following us is now following the slow leader, who's farther ahead, so
+        // they might be able to move again.
+        self.update_follower(idx, dists, now, ctx);
+    }
+
+    /// After a pass is done (or the vehicle is deleted in the middle of one), stop blocking the
+    /// oncoming lane.
+    fn clear_oncoming_blockage(&mut self, id: CarID, oncoming: LaneID, now: Time, ctx: &mut Ctx) {
+        // Like unparking, it's fine that the car isn't currently in self.cars.
+        let dists = match self.queues.get(&Traversable::Lane(oncoming)) {
+            Some(queue) => queue.get_car_positions(now, &self.cars, &self.queues),
+            None => Vec::new(),
+        };
+        let idx = match dists.iter().position(
+            |entry| matches!(entry.member, Queued::StaticBlockage { cause, ..} if cause == id),
+        ) {
+            Some(idx) => idx,
+            None => {
+                // Live edits may have deleted or recreated the oncoming lane since the pass began
+                warn!(
+                    "{} finished overtaking, but isn't blocking {} anymore",
+                    id, oncoming
+                );
+                return;
+            }
+        };
+        self.update_follower(idx, &dists, now, ctx);
+
+        self.queues
+            .get_mut(&Traversable::Lane(oncoming))
+            .unwrap()
+            .clear_static_blockage(id, idx);
+    }
+
+    /// The vehicle's front is leaving the current step of its path, or the vehicle is vanishing.
+    fn record_emissions(&mut self, car: &mut Car, now: Time, map: &Map) {
+        car.update_emissions(now, map);
+        let emissions = std::mem::take(&mut car.emissions);
+        if emissions != Emissions::default() {
+            self.events.push(Event::VehicleEmissions(
+                car.vehicle.id,
+                car.trip_and_person.map(|(t, _)| t),
+                car.router.head(),
+                emissions,
+            ));
+        }
+    }
+
+    /// Record that some vehicle just passed `overtaken`.
+    fn record_overtake(&mut self, overtaken: CarID, on: Traversable) {
+        if let Some((trip, _)) = self.cars[&overtaken].trip_and_person {
+            self.events
+                .push(Event::ProblemEncountered(trip, Problem::Overtaken(on)));
+        }
+    }
+
     pub fn collect_events(&mut self) -> Vec<Event> {
         std::mem::take(&mut self.events)
     }
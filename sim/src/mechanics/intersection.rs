@@ -3,10 +3,10 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, prettyprint_usize, serialize_btreemap, FixedMap};
-use geom::{Duration, Time};
+use geom::{Distance, Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, StageType,
-    Traversable, TurnID, TurnPriority, TurnType, UberTurn,
+    Actuation, ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map,
+    Stage, StageType, Traversable, TurnID, TurnPriority, TurnType, UberTurn,
 };
 
 use crate::mechanics::car::{Car, CarState};
@@ -17,10 +17,17 @@ use crate::{
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+/// At a yield intersection or roundabout, don't enter if a vehicle with priority will reach the
+/// intersection sooner than this.
+const CRITICAL_GAP_AT_YIELD: Duration = Duration::const_seconds(4.0);
+/// After switching to a coordinated time-of-day plan, a signal gets in step with the new cycle over
+/// a few cycles, by stretching or shrinking its coordinated stage by at most this fraction of the
+/// cycle each time.
+const MAX_TRANSITION_CORRECTION: f64 = 0.2;
 
 /// Manages conflicts at intersections. When an agent has reached the end of a lane, they call
 /// maybe_start_turn to make a Request. Based on the intersection type (stop sign, traffic signal,
-/// or a "freeform policy"), the Request gets queued or immediately accepted. When agents finish
+/// yield, or a "freeform policy"), the Request gets queued or immediately accepted. When agents finish
 /// turns or when some time passes (for traffic signals), the intersection also gets a chance to
 /// react, maybe granting one of the pending requests.
 ///
@@ -37,6 +44,12 @@ pub(crate) struct IntersectionSimState {
     // (x, y) means x is blocked by y. It's a many-to-many relationship. TODO Better data
     // structure.
     blocked_by: BTreeSet<(CarID, CarID)>,
+    // Which traffic signal each vehicle is approaching, if any. See SignalState::approaching.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    approaching_signal: BTreeMap<CarID, IntersectionID>,
     events: Vec<Event>,
 
     // Count how many calls to maybe_start_turn there are aside from the initial call. Break down
@@ -72,12 +85,26 @@ struct State {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct SignalState {
-    // The current stage of the signal, zero based
+    // Which of the signal's time-of-day plans is running
+    plan: usize,
+    // The current stage of the signal's plan, zero based
     current_stage: usize,
     // The time when the signal is checked for advancing
     stage_ends_at: Time,
+    // When the current stage began, for enforcing an actuated stage's max green
+    stage_started_at: Time,
     // The number of times a variable signal has been extended during the current stage.
     extensions_count: usize,
+    // Still getting in step with the cycle of a plan that just started
+    transitioning: bool,
+    // The most recently planned movement of every vehicle on a lane leading here. Virtual loop
+    // detectors use this to find vehicles near the stop line, without calculating the exact
+    // position of everybody in the queues.
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    approaching: BTreeMap<CarID, (LaneID, CarState)>,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Debug)]
@@ -97,6 +124,7 @@ impl IntersectionSimState {
             handle_uber_turns: !opts.dont_handle_uber_turns,
             disable_turn_conflicts: opts.disable_turn_conflicts,
             blocked_by: BTreeSet::new(),
+            approaching_signal: BTreeMap::new(),
             events: Vec::new(),
 
             total_repeat_requests: 0,
@@ -184,6 +212,51 @@ impl IntersectionSimState {
     /// turn.
     pub fn vehicle_gone(&mut self, car: CarID) {
         self.blocked_by.retain(|(c1, c2)| *c1 != car && *c2 != car);
+        self.stop_approaching_signal(car);
+    }
+
+    /// The driving sim calls this after a vehicle's movement might've been replanned, so actuated
+    /// signals can keep track of vehicles approaching them.
+    pub fn vehicle_replanned(&mut self, car: &Car, map: &Map) {
+        let id = car.vehicle.id;
+        let (i, lane) = match car.router.head() {
+            Traversable::Lane(l) => (map.get_l(l).dst_i, l),
+            Traversable::Turn(_) => {
+                self.stop_approaching_signal(id);
+                return;
+            }
+        };
+        if self.state[&i].signal.is_none() {
+            self.stop_approaching_signal(id);
+            return;
+        }
+        if matches!(
+            car.state,
+            CarState::Queued { .. } | CarState::WaitingToAdvance { .. }
+        ) {
+            // Stopped wherever the last movement on this lane ended
+            return;
+        }
+        if self.approaching_signal.get(&id) != Some(&i) {
+            self.stop_approaching_signal(id);
+            self.approaching_signal.insert(id, i);
+        }
+        self.state
+            .get_mut(&i)
+            .unwrap()
+            .signal
+            .as_mut()
+            .unwrap()
+            .approaching
+            .insert(id, (lane, car.state.clone()));
+    }
+
+    fn stop_approaching_signal(&mut self, car: CarID) {
+        if let Some(i) = self.approaching_signal.remove(&car) {
+            if let Some(ref mut signal_state) = self.state.get_mut(&i).unwrap().signal {
+                signal_state.approaching.remove(&car);
+            }
+        }
     }
 
     pub fn agent_deleted_mid_turn(&mut self, agent: AgentID, turn: TurnID) {
@@ -219,8 +292,8 @@ impl IntersectionSimState {
                 protected.push(req);
             }
         } else if let Some(signal) = map.maybe_get_traffic_signal(i) {
-            let current_stage = self.state[&i].signal.as_ref().unwrap().current_stage;
-            let stage = &signal.stages[current_stage];
+            let signal_state = self.state[&i].signal.as_ref().unwrap();
+            let stage = &signal.plan_stages(signal_state.plan)[signal_state.current_stage];
             let reserved = &self.state[&i].reserved;
             let i = map.get_i(i);
             for (req, _, _) in all {
@@ -281,45 +354,83 @@ impl IntersectionSimState {
         scheduler: &mut Scheduler,
     ) {
         let i = map.get_i(id);
+        let signal = map.get_traffic_signal(id);
+
+        // Which actuated stages have somebody detected or waiting? Stages without demand get
+        // skipped.
+        let signal_state = self.state[&id].signal.as_ref().unwrap();
+        let plan = signal_state.plan;
+        let demand: Vec<bool> = signal
+            .plan_stages(plan)
+            .iter()
+            .map(|stage| match stage.stage_type {
+                StageType::Actuated(ref a) => {
+                    a.pedestrian_recall
+                        || self.state[&id].waiting.keys().any(|req| {
+                            matches!(req.agent, AgentID::Pedestrian(_))
+                                && stage.get_priority_of_turn(req.turn, i)
+                                    == TurnPriority::Protected
+                        })
+                        || vehicle_call(
+                            stage,
+                            a.detector_length,
+                            i,
+                            now,
+                            &signal_state.approaching,
+                            map,
+                        )
+                }
+                _ => true,
+            })
+            .collect();
 
-        // trivial function that advances the signal stage and returns duration
+        // trivial function that advances the signal stage
         fn advance(
             signal_state: &mut SignalState,
             signal: &ControlTrafficSignal,
             i: &Intersection,
             allow_crosswalk_skip: bool,
-        ) -> Duration {
-            signal_state.current_stage = (signal_state.current_stage + 1) % signal.stages.len();
-            let stage = &signal.stages[signal_state.current_stage];
+            demand: &[bool],
+            now: Time,
+        ) {
+            let stages = signal.plan_stages(signal_state.plan);
+            let num_stages = stages.len();
+            signal_state.current_stage = (signal_state.current_stage + 1) % num_stages;
+            let stage = &stages[signal_state.current_stage];
             // only skip for variable all-walk crosswalk
             if let StageType::Variable(_, _, _) = stage.stage_type {
                 if allow_crosswalk_skip && stage.max_crosswalk_time(i).is_some() {
                     // we can skip this stage, as its all walk and we're allowed to skip (no
                     // pedestrian waiting).
-                    signal_state.current_stage =
-                        (signal_state.current_stage + 1) % signal.stages.len();
+                    signal_state.current_stage = (signal_state.current_stage + 1) % num_stages;
+                }
+            }
+            // Skip actuated stages that nobody is waiting for. If nobody wants any other stage,
+            // this winds up serving the same stage again.
+            for _ in 1..num_stages {
+                if demand[signal_state.current_stage] {
+                    break;
                 }
+                signal_state.current_stage = (signal_state.current_stage + 1) % num_stages;
             }
-            signal.stages[signal_state.current_stage]
-                .stage_type
-                .simple_duration()
+            signal_state.stage_started_at = now;
         }
         let state = self.state.get_mut(&id).unwrap();
         let signal_state = state.signal.as_mut().unwrap();
-        let signal = map.get_traffic_signal(id);
         let ped_waiting = state.waiting.keys().any(|req| {
             if let AgentID::Pedestrian(_) = req.agent {
                 return true;
             }
             false
         });
-        let duration: Duration;
+        let mut duration: Duration;
         // Switch to a new stage?
         assert_eq!(now, signal_state.stage_ends_at);
-        let old_stage = &signal.stages[signal_state.current_stage];
+        let old_stage = &signal.plan_stages(signal_state.plan)[signal_state.current_stage];
         match old_stage.stage_type {
             StageType::Fixed(_) => {
-                duration = advance(signal_state, signal, i, !ped_waiting);
+                advance(signal_state, signal, i, !ped_waiting, &demand, now);
+                duration = signal_state.stage_duration(signal, i, now);
             }
             StageType::Variable(min, delay, additional) => {
                 // test if anyone is waiting in current stage, and if so, extend the signal cycle.
@@ -337,7 +448,8 @@ impl IntersectionSimState {
                             min, delay, additional, signal_state.extensions_count
                         ),
                     ));
-                    duration = advance(signal_state, signal, i, !ped_waiting);
+                    advance(signal_state, signal, i, !ped_waiting, &demand, now);
+                    duration = signal_state.stage_duration(signal, i, now);
                     signal_state.extensions_count = 0;
                 } else if state.waiting.keys().all(|req| {
                     if let AgentID::Pedestrian(_) = req.agent {
@@ -348,7 +460,8 @@ impl IntersectionSimState {
                     old_stage.get_priority_of_turn(req.turn, i) != TurnPriority::Protected
                 }) {
                     signal_state.extensions_count = 0;
-                    duration = advance(signal_state, signal, i, !ped_waiting);
+                    advance(signal_state, signal, i, !ped_waiting, &demand, now);
+                    duration = signal_state.stage_duration(signal, i, now);
                 } else {
                     signal_state.extensions_count += 1;
                     duration = delay;
@@ -361,6 +474,50 @@ impl IntersectionSimState {
                     ));
                 }
             }
+            StageType::Actuated(ref a) => {
+                // The minimum green has passed.
+                let green_so_far = now - signal_state.stage_started_at;
+                if let Some(extension) = actuated_extension(a, green_so_far, || {
+                    vehicle_call(
+                        old_stage,
+                        a.detector_length,
+                        i,
+                        now,
+                        &signal_state.approaching,
+                        map,
+                    )
+                }) {
+                    duration = extension;
+                } else {
+                    if green_so_far >= a.max_green {
+                        self.events.push(Event::Alert(
+                            AlertLocation::Intersection(id),
+                            format!("actuated stage maxed out after {}", green_so_far),
+                        ));
+                    }
+                    advance(signal_state, signal, i, !ped_waiting, &demand, now);
+                    duration = signal_state.stage_duration(signal, i, now);
+                }
+            }
+        }
+
+        // Time to switch to a different time-of-day plan? Only do this when the stage would
+        // change anyway. The new plan starts from its first stage. If the plan is coordinated, the
+        // signal gets in step with the new cycle over the next few cycles.
+        let new_plan = signal.plan_at(now);
+        if new_plan != signal_state.plan && signal_state.stage_started_at == now {
+            self.events.push(Event::Alert(
+                AlertLocation::Intersection(id),
+                format!(
+                    "switching from timing plan {} to {}",
+                    signal_state.plan, new_plan
+                ),
+            ));
+            signal_state.plan = new_plan;
+            signal_state.current_stage = 0;
+            signal_state.extensions_count = 0;
+            signal_state.transitioning = true;
+            duration = signal_state.stage_duration(signal, i, now);
         }
 
         signal_state.stage_ends_at = now + duration;
@@ -451,7 +608,11 @@ impl IntersectionSimState {
         } else if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
             self.traffic_signal_policy(&req, map, signal, speed, now, Some(scheduler))
         } else if let Some(sign) = map.maybe_get_stop_sign(turn.parent) {
-            self.stop_sign_policy(&req, map, sign, now, scheduler)
+            if map.get_i(turn.parent).is_yield() {
+                self.yield_policy(&req, map, sign, now, scheduler, readonly_pair)
+            } else {
+                self.stop_sign_policy(&req, map, sign, now, scheduler)
+            }
         } else {
             unreachable!()
         };
@@ -585,7 +746,17 @@ impl IntersectionSimState {
                 state.signal.as_mut(),
             ) {
                 (Some(ts), Some(signal_state)) => {
-                    if signal_state.current_stage >= ts.stages.len() {
+                    if signal_state.plan >= ts.num_plans() {
+                        signal_state.plan = ts.plan_at(now);
+                        signal_state.current_stage = 0;
+                        signal_state.transitioning = true;
+                        println!(
+                            "WARNING: Traffic signal {} had its running plan removed, so \
+                             jumping to the first stage of another",
+                            state.id
+                        );
+                    }
+                    if signal_state.current_stage >= ts.plan_stages(signal_state.plan).len() {
                         // Just jump back to the first one. Shrug.
                         signal_state.current_stage = 0;
                         println!(
@@ -716,6 +887,24 @@ impl IntersectionSimState {
         (state.current_stage, state.stage_ends_at - now)
     }
 
+    /// Does the current stage of a traffic signal ban this turn? False for other intersections.
+    pub fn red_light(&self, turn: TurnID, map: &Map) -> bool {
+        let signal_state = match self.state.get(&turn.parent).and_then(|s| s.signal.as_ref()) {
+            Some(x) => x,
+            None => {
+                return false;
+            }
+        };
+        let signal = map.get_traffic_signal(turn.parent);
+        let stage = &signal.plan_stages(signal_state.plan)[signal_state.current_stage];
+        stage.get_priority_of_turn(turn, map.get_i(turn.parent)) == TurnPriority::Banned
+    }
+
+    /// Which of the signal's time-of-day plans is currently running
+    pub fn current_signal_plan(&self, i: IntersectionID) -> usize {
+        self.state[&i].signal.as_ref().unwrap().plan
+    }
+
     pub fn describe_stats(&self) -> Vec<String> {
         vec![
             "intersection stats".to_string(),
@@ -844,6 +1033,78 @@ impl IntersectionSimState {
         true
     }
 
+    /// Roundabouts and give-way junctions. Vehicles from priority roads (usually circulating
+    /// around a roundabout) go freely. Everybody else doesn't need to stop, but only enters when
+    /// there's a big enough gap before the next priority vehicle arrives.
+    fn yield_policy(
+        &mut self,
+        req: &Request,
+        map: &Map,
+        sign: &ControlStopSign,
+        now: Time,
+        scheduler: &mut Scheduler,
+        cars_and_queues: Option<(&FixedMap<CarID, Car>, &HashMap<Traversable, Queue>)>,
+    ) -> bool {
+        let our_priority = sign.get_priority(req.turn, map);
+        assert!(our_priority != TurnPriority::Banned);
+        if our_priority == TurnPriority::Protected {
+            return true;
+        }
+        // Pedestrians don't look for gaps
+        let (cars, queues) = match cars_and_queues {
+            Some(pair) => pair,
+            None => {
+                return true;
+            }
+        };
+
+        // Look at the lead vehicle on every approach with priority, and find when the ones about
+        // to make a conflicting turn will arrive.
+        let our_turn = map.get_t(req.turn);
+        let mut arrivals = Vec::new();
+        for l in &map.get_i(req.turn.parent).incoming_lanes {
+            if l.road == req.turn.src.road
+                || sign
+                    .roads
+                    .get(&l.road)
+                    .map(|cfg| cfg.must_stop)
+                    .unwrap_or(true)
+            {
+                continue;
+            }
+            let queue = match queues.get(&Traversable::Lane(*l)) {
+                Some(q) => q,
+                None => continue,
+            };
+            let leader = match queue.get_active_cars().get(0) {
+                Some(c) => &cars[c],
+                None => continue,
+            };
+            let arrival = match leader.state {
+                CarState::Crossing { ref time_int, .. } => time_int.end,
+                // If they were waiting and could've started by now, they would have. Just
+                // like the stop sign policy, assume they're blocked.
+                _ => continue,
+            };
+            let conflicts = match leader.router.maybe_next() {
+                Some(Traversable::Turn(t)) => map.get_t(t).conflicts_with(our_turn),
+                _ => false,
+            };
+            if conflicts {
+                arrivals.push(arrival);
+            }
+        }
+
+        if let Some(t) = wait_for_gap(now, arrivals) {
+            // Since we have "ownership" of scheduling for req.agent, don't need to use
+            // scheduler.update.
+            scheduler.push(t, Command::update_agent(req.agent));
+            return false;
+        }
+
+        true
+    }
+
     fn traffic_signal_policy(
         &mut self,
         req: &Request,
@@ -857,7 +1118,7 @@ impl IntersectionSimState {
 
         let state = &self.state[&req.turn.parent];
         let signal_state = state.signal.as_ref().unwrap();
-        let stage = &signal.stages[signal_state.current_stage];
+        let stage = &signal.plan_stages(signal_state.plan)[signal_state.current_stage];
         let full_stage_duration = stage.stage_type.simple_duration();
         let remaining_stage_time = signal_state.stage_ends_at - now;
         let (our_time, _) = state.waiting[req];
@@ -1034,23 +1295,26 @@ impl IntersectionSimState {
 
 impl SignalState {
     fn new(id: IntersectionID, now: Time, map: &Map, scheduler: &mut Scheduler) -> SignalState {
+        let signal = map.get_traffic_signal(id);
         let mut state = SignalState {
+            plan: signal.plan_at(now),
             current_stage: 0,
             stage_ends_at: now,
+            stage_started_at: now,
             extensions_count: 0,
+            transitioning: false,
+            approaching: BTreeMap::new(),
         };
 
-        let signal = map.get_traffic_signal(id);
         // What stage are we starting with?
-        let mut offset = (now - Time::START_OF_DAY) + signal.offset;
+        let stages = signal.plan_stages(state.plan);
+        let mut offset = (now - Time::START_OF_DAY) + signal.plan_offset(state.plan);
         loop {
-            let dt = signal.stages[state.current_stage]
-                .stage_type
-                .simple_duration();
+            let dt = stages[state.current_stage].stage_type.simple_duration();
             if offset >= dt {
                 offset -= dt;
                 state.current_stage += 1;
-                if state.current_stage == signal.stages.len() {
+                if state.current_stage == stages.len() {
                     state.current_stage = 0;
                 }
             } else {
@@ -1061,6 +1325,129 @@ impl SignalState {
         scheduler.push(state.stage_ends_at, Command::UpdateIntersection(id));
         state
     }
+
+    /// How long the current stage should last, just after starting it. The first stage of a
+    /// coordinated signal stretches or shrinks to line up with the corridor's common cycle.
+    fn stage_duration(
+        &mut self,
+        signal: &ControlTrafficSignal,
+        i: &Intersection,
+        now: Time,
+    ) -> Duration {
+        let stages = signal.plan_stages(self.plan);
+        let stage_type = &stages[self.current_stage].stage_type;
+        let mut duration = stage_type.simple_duration();
+        if let StageType::Actuated(_) = stage_type {
+            // Don't re-check detectors in a tight loop if there's no minimum green
+            duration = duration.max(Duration::const_seconds(1.0));
+        }
+        let cycle = match signal.plan_cycle_length(self.plan) {
+            Some(cycle) if self.current_stage == 0 => cycle,
+            _ => {
+                return duration;
+            }
+        };
+        // Just after switching plans, the signal may be far out of step. Jumping straight to the
+        // new offset would badly starve or stretch some movements, so correct a bit each cycle.
+        let max_correction = if self.transitioning {
+            cycle * MAX_TRANSITION_CORRECTION
+        } else {
+            cycle
+        };
+        let (duration, in_step) = coordinated_duration(
+            duration,
+            now,
+            signal.plan_offset(self.plan),
+            cycle,
+            max_correction,
+            stages[0]
+                .min_crossing_time(i)
+                .max(Duration::const_seconds(1.0)),
+        );
+        if in_step {
+            self.transitioning = false;
+        }
+        duration
+    }
+}
+
+/// Stretch or shrink a coordinated stage starting now, so that the next cycle starts on time. The
+/// stage normally lasts `duration`, but can't be corrected by more than `max_correction` or cut
+/// shorter than `min_duration`. Also returns true if the stage completely catches up.
+fn coordinated_duration(
+    duration: Duration,
+    now: Time,
+    offset: Duration,
+    cycle: Duration,
+    max_correction: Duration,
+    min_duration: Duration,
+) -> (Duration, bool) {
+    // Ideally the coordinated stage starts exactly at the cycle boundary.
+    let into_cycle = ((now - Time::START_OF_DAY) + offset) % cycle;
+    if into_cycle * 2.0 >= cycle {
+        // Other stages gapped out early, so hold this green until the boundary.
+        let correction = cycle - into_cycle;
+        (
+            duration + correction.min(max_correction),
+            correction <= max_correction,
+        )
+    } else {
+        // Other stages maxed out, so cut this green short to catch up, but still let people
+        // cross.
+        (
+            (duration - into_cycle.min(max_correction)).max(min_duration),
+            into_cycle <= max_correction,
+        )
+    }
+}
+
+/// After an actuated stage's minimum green, keep extending it by the passage time while vehicles
+/// are still detected. None means the stage should end, because it maxed out or gapped out.
+fn actuated_extension<F: FnOnce() -> bool>(
+    actuation: &Actuation,
+    green_so_far: Duration,
+    vehicle_detected: F,
+) -> Option<Duration> {
+    if green_so_far >= actuation.max_green || !vehicle_detected() {
+        return None;
+    }
+    let passage = std::cmp::max(Duration::const_seconds(1.0), actuation.passage);
+    Some(passage.min(actuation.max_green - green_so_far))
+}
+
+/// Virtual loop detectors: is any vehicle close to the stop line on a lane feeding one of the
+/// stage's protected movements? Vehicles are assumed to move as planned. If one is actually stuck
+/// behind a leader, then the leader is even closer to the stop line, and probably detected anyway.
+fn vehicle_call(
+    stage: &Stage,
+    detector_length: Distance,
+    i: &Intersection,
+    now: Time,
+    approaching: &BTreeMap<CarID, (LaneID, CarState)>,
+    map: &Map,
+) -> bool {
+    let mut lanes = BTreeSet::new();
+    for m in &stage.protected_movements {
+        if !m.crosswalk {
+            lanes.extend(i.movements[m].members.iter().map(|t| t.src));
+        }
+    }
+    approaching.values().any(|(lane, state)| {
+        if !lanes.contains(lane) {
+            return false;
+        }
+        let front = match state {
+            CarState::Crossing { .. } | CarState::ChangingLanes { .. } => {
+                state.dist_along_crossing(now)
+            }
+            CarState::Unparking { front, .. }
+            | CarState::Overtaking { front, .. }
+            | CarState::Parking(front, _, _)
+            | CarState::IdlingAtStop(front, _) => *front,
+            CarState::Queued { .. } | CarState::WaitingToAdvance { .. } => unreachable!(),
+        };
+        front >= map.get_l(*lane).length() - detector_length
+    })
 }
 
 fn allow_block_the_box(i: &Intersection) -> bool {
@@ -1102,3 +1489,148 @@ fn allow_block_the_box(i: &Intersection) -> bool {
     }
     false
 }
+
+/// Given when conflicting vehicles with priority will reach the intersection, decide if there's a
+/// big enough gap to enter now. If not, returns when to check again -- just after the next
+/// vehicle arrives.
+fn wait_for_gap(now: Time, arrivals: Vec<Time>) -> Option<Time> {
+    let next = arrivals
+        .into_iter()
+        .filter(|t| *t - now < CRITICAL_GAP_AT_YIELD)
+        .min()?;
+    Some(next.max(now) + Duration::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(x: f64) -> Duration {
+        Duration::seconds(x)
+    }
+
+    #[test]
+    fn test_actuated_extension() {
+        let a = Actuation::new(secs(10.0), secs(40.0));
+        // Keep extending by the passage time while vehicles are detected
+        assert_eq!(actuated_extension(&a, secs(15.0), || true), Some(secs(3.0)));
+        // Gap out
+        assert_eq!(actuated_extension(&a, secs(15.0), || false), None);
+
+        let mut no_passage = a.clone();
+        no_passage.passage = Duration::ZERO;
+        assert_eq!(
+            actuated_extension(&no_passage, secs(15.0), || true),
+            Some(secs(1.0))
+        );
+    }
+
+    #[test]
+    fn test_actuated_max_out() {
+        let a = Actuation::new(secs(10.0), secs(40.0));
+        // The last extension doesn't go past the maximum green
+        assert_eq!(actuated_extension(&a, secs(38.0), || true), Some(secs(2.0)));
+        // Once maxed out, the detectors don't matter
+        assert_eq!(
+            actuated_extension(&a, secs(40.0), || panic!("detectors checked after max-out")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_coordination_offsets() {
+        let cycle = secs(90.0);
+        let min = secs(5.0);
+        let at = |x| Time::START_OF_DAY + secs(x);
+
+        // Other stages gapped out early, so hold the coordinated green until the boundary
+        assert_eq!(
+            coordinated_duration(secs(30.0), at(70.0), Duration::ZERO, cycle, cycle, min),
+            (secs(50.0), true)
+        );
+        // Other stages maxed out, so cut it short
+        assert_eq!(
+            coordinated_duration(secs(30.0), at(100.0), Duration::ZERO, cycle, cycle, min),
+            (secs(20.0), true)
+        );
+        // ...but not below the minimum
+        assert_eq!(
+            coordinated_duration(secs(30.0), at(130.0), Duration::ZERO, cycle, cycle, min),
+            (min, true)
+        );
+        // The offset shifts the cycle boundary
+        assert_eq!(
+            coordinated_duration(secs(30.0), at(70.0), secs(10.0), cycle, cycle, min),
+            (secs(40.0), true)
+        );
+        assert_eq!(
+            coordinated_duration(secs(30.0), at(80.0), secs(10.0), cycle, cycle, min),
+            (secs(30.0), true)
+        );
+    }
+
+    #[test]
+    fn test_coordination_transition() {
+        let cycle = secs(90.0);
+        let max_correction = cycle * MAX_TRANSITION_CORRECTION;
+        let min = secs(5.0);
+        let at = |x| Time::START_OF_DAY + secs(x);
+
+        // Far out of step after switching plans, so only correct part of the way
+        assert_eq!(
+            coordinated_duration(
+                secs(30.0),
+                at(45.0),
+                Duration::ZERO,
+                cycle,
+                max_correction,
+                min
+            ),
+            (secs(48.0), false)
+        );
+        assert_eq!(
+            coordinated_duration(
+                secs(30.0),
+                at(120.0),
+                Duration::ZERO,
+                cycle,
+                max_correction,
+                min
+            ),
+            (secs(12.0), false)
+        );
+        // Close enough to catch up completely
+        assert_eq!(
+            coordinated_duration(
+                secs(30.0),
+                at(100.0),
+                Duration::ZERO,
+                cycle,
+                max_correction,
+                min
+            ),
+            (secs(20.0), true)
+        );
+    }
+
+    #[test]
+    fn test_gap_acceptance() {
+        let now = Time::START_OF_DAY + secs(100.0);
+        let at = |x| now + secs(x);
+
+        // Nobody's coming
+        assert_eq!(wait_for_gap(now, Vec::new()), None);
+        // Everybody's far enough away
+        assert_eq!(wait_for_gap(now, vec![at(4.0), at(10.0)]), None);
+        // Wait for the closest vehicle to arrive, then look again
+        assert_eq!(
+            wait_for_gap(now, vec![at(10.0), at(3.0), at(1.0)]),
+            Some(at(1.0) + Duration::EPSILON)
+        );
+        // Somebody should've already arrived but hasn't yet, so just check again soon
+        assert_eq!(
+            wait_for_gap(now, vec![at(-1.0)]),
+            Some(now + Duration::EPSILON)
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, Speed};
+
+/// When `SimOptions::kinematic_model` is enabled, this describes how a vehicle speeds up and slows
+/// down while crossing some distance. There are up to three phases: changing from the initial
+/// speed to a peak speed with constant acceleration, cruising at that peak, then changing to the
+/// final speed with constant deceleration.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct SpeedProfile {
+    initial_speed: Speed,
+    peak_speed: Speed,
+    final_speed: Speed,
+    speed_up_time: Duration,
+    cruise_time: Duration,
+    slow_down_time: Duration,
+}
+
+impl SpeedProfile {
+    /// Plan how to cross `dist`, starting at `initial_speed`, never exceeding `max_speed`, and
+    /// trying to finish at `final_speed`. `accel` and `decel` are both positive, in meters per
+    /// second squared. If the distance is too short to brake down to `final_speed`, the vehicle
+    /// brakes harder than `decel`. If it's too short to speed up to `final_speed`, the vehicle
+    /// finishes more slowly.
+    pub fn new(
+        dist: Distance,
+        initial_speed: Speed,
+        max_speed: Speed,
+        final_speed: Speed,
+        accel: f64,
+        decel: f64,
+    ) -> SpeedProfile {
+        let d = dist.inner_meters();
+        let vmax = max_speed.inner_meters_per_second();
+        let v0 = initial_speed.inner_meters_per_second().min(vmax);
+        let vf = final_speed.inner_meters_per_second().min(vmax);
+
+        if d <= 0.0 {
+            return SpeedProfile::constant(Speed::meters_per_second(v0), Duration::ZERO);
+        }
+
+        // Too close to brake normally; brake as hard as needed the whole way.
+        if v0 > vf && (v0 * v0 - vf * vf) / (2.0 * decel) >= d {
+            return SpeedProfile {
+                initial_speed: Speed::meters_per_second(v0),
+                peak_speed: Speed::meters_per_second(v0),
+                final_speed: Speed::meters_per_second(vf),
+                speed_up_time: Duration::ZERO,
+                cruise_time: Duration::ZERO,
+                slow_down_time: Duration::seconds(2.0 * d / (v0 + vf)),
+            };
+        }
+        // Too close to reach the final speed; speed up the whole way.
+        if vf > v0 && (vf * vf - v0 * v0) / (2.0 * accel) >= d {
+            let v_end = (v0 * v0 + 2.0 * accel * d).sqrt();
+            return SpeedProfile {
+                initial_speed: Speed::meters_per_second(v0),
+                peak_speed: Speed::meters_per_second(v_end),
+                final_speed: Speed::meters_per_second(v_end),
+                speed_up_time: Duration::seconds(2.0 * d / (v0 + v_end)),
+                cruise_time: Duration::ZERO,
+                slow_down_time: Duration::ZERO,
+            };
+        }
+
+        let dist_speeding_up = (vmax * vmax - v0 * v0) / (2.0 * accel);
+        let dist_slowing_down = (vmax * vmax - vf * vf) / (2.0 * decel);
+        let (peak, cruise_time) = if dist_speeding_up + dist_slowing_down <= d {
+            (vmax, (d - dist_speeding_up - dist_slowing_down) / vmax)
+        } else {
+            // A triangular profile; never reach the max speed. The checks above guarantee the
+            // peak is at least the initial and final speed.
+            let peak = ((2.0 * accel * decel * d + decel * v0 * v0 + accel * vf * vf)
+                / (accel + decel))
+                .sqrt();
+            (peak.max(v0).max(vf), 0.0)
+        };
+        SpeedProfile {
+            initial_speed: Speed::meters_per_second(v0),
+            peak_speed: Speed::meters_per_second(peak),
+            final_speed: Speed::meters_per_second(vf),
+            speed_up_time: Duration::seconds((peak - v0) / accel),
+            cruise_time: Duration::seconds(cruise_time),
+            slow_down_time: Duration::seconds((peak - vf) / decel),
+        }
+    }
+
+    fn constant(speed: Speed, time: Duration) -> SpeedProfile {
+        SpeedProfile {
+            initial_speed: speed,
+            peak_speed: speed,
+            final_speed: speed,
+            speed_up_time: Duration::ZERO,
+            cruise_time: time,
+            slow_down_time: Duration::ZERO,
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.speed_up_time + self.cruise_time + self.slow_down_time
+    }
+
+    pub fn final_speed(&self) -> Speed {
+        self.final_speed
+    }
+
+    fn phases(&self) -> [(f64, f64, f64); 3] {
+        let v0 = self.initial_speed.inner_meters_per_second();
+        let peak = self.peak_speed.inner_meters_per_second();
+        let vf = self.final_speed.inner_meters_per_second();
+        [
+            (v0, peak, self.speed_up_time.inner_seconds()),
+            (peak, peak, self.cruise_time.inner_seconds()),
+            (peak, vf, self.slow_down_time.inner_seconds()),
+        ]
+    }
+
+    /// How far has the vehicle traveled some time after starting this profile?
+    pub fn dist_at(&self, elapsed: Duration) -> Distance {
+        let mut t = elapsed.inner_seconds().max(0.0);
+        let mut dist = 0.0;
+        for (from, to, duration) in self.phases() {
+            if t <= duration {
+                if duration > 0.0 {
+                    dist += from * t + (to - from) / (2.0 * duration) * t * t;
+                }
+                return Distance::meters(dist);
+            }
+            dist += (from + to) / 2.0 * duration;
+            t -= duration;
+        }
+        Distance::meters(dist)
+    }
+
+    /// How fast is the vehicle going some time after starting this profile?
+    pub fn speed_at(&self, elapsed: Duration) -> Speed {
+        let mut t = elapsed.inner_seconds().max(0.0);
+        for (from, to, duration) in self.phases() {
+            if t <= duration {
+                if duration == 0.0 {
+                    return Speed::meters_per_second(to);
+                }
+                return Speed::meters_per_second(from + (to - from) * t / duration);
+            }
+            t -= duration;
+        }
+        self.final_speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // geom rounds distances, durations, and speeds to a few decimal places
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_accelerate_cruise_brake() {
+        // Start and end at a stop
+        let profile = SpeedProfile::new(
+            Distance::meters(200.0),
+            Speed::ZERO,
+            Speed::meters_per_second(10.0),
+            Speed::ZERO,
+            2.0,
+            4.0,
+        );
+        // 5s and 25m to speed up, 2.5s and 12.5m to brake, and cruise for the remaining 162.5m
+        assert_close(profile.speed_up_time.inner_seconds(), 5.0);
+        assert_close(profile.cruise_time.inner_seconds(), 16.25);
+        assert_close(profile.slow_down_time.inner_seconds(), 2.5);
+        let total = profile.total_time();
+        assert_close(total.inner_seconds(), 23.75);
+
+        assert_close(profile.dist_at(Duration::seconds(5.0)).inner_meters(), 25.0);
+        assert_close(
+            profile
+                .speed_at(Duration::seconds(2.5))
+                .inner_meters_per_second(),
+            5.0,
+        );
+        assert_close(
+            profile
+                .speed_at(Duration::seconds(10.0))
+                .inner_meters_per_second(),
+            10.0,
+        );
+        assert_close(
+            profile.dist_at(Duration::seconds(21.25)).inner_meters(),
+            187.5,
+        );
+        assert_close(profile.dist_at(total).inner_meters(), 200.0);
+        assert_close(profile.speed_at(total).inner_meters_per_second(), 0.0);
+        // Past the end, the vehicle stays put
+        assert_close(
+            profile
+                .dist_at(total + Duration::seconds(1.0))
+                .inner_meters(),
+            200.0,
+        );
+    }
+
+    #[test]
+    fn test_never_reach_max_speed() {
+        // Not enough room to reach 10m/s and still stop
+        let profile = SpeedProfile::new(
+            Distance::meters(20.0),
+            Speed::ZERO,
+            Speed::meters_per_second(10.0),
+            Speed::ZERO,
+            2.0,
+            2.0,
+        );
+        let peak = 40.0_f64.sqrt();
+        assert_close(profile.peak_speed.inner_meters_per_second(), peak);
+        assert_close(profile.cruise_time.inner_seconds(), 0.0);
+        assert_close(profile.total_time().inner_seconds(), peak);
+        assert_close(profile.dist_at(profile.total_time()).inner_meters(), 20.0);
+    }
+
+    #[test]
+    fn test_brake_to_stop_line() {
+        // Braking normally at 4m/s^2 from 10m/s needs 12.5m, but the stop line is 10m away
+        let profile = SpeedProfile::new(
+            Distance::meters(10.0),
+            Speed::meters_per_second(10.0),
+            Speed::meters_per_second(10.0),
+            Speed::ZERO,
+            2.0,
+            4.0,
+        );
+        assert_close(profile.total_time().inner_seconds(), 2.0);
+        assert_close(profile.dist_at(Duration::seconds(1.0)).inner_meters(), 7.5);
+        assert_close(profile.dist_at(Duration::seconds(2.0)).inner_meters(), 10.0);
+        assert_close(profile.final_speed().inner_meters_per_second(), 0.0);
+
+        // With plenty of room, cruise and then brake at the normal rate
+        let profile = SpeedProfile::new(
+            Distance::meters(32.5),
+            Speed::meters_per_second(10.0),
+            Speed::meters_per_second(10.0),
+            Speed::ZERO,
+            2.0,
+            4.0,
+        );
+        assert_close(profile.cruise_time.inner_seconds(), 2.0);
+        assert_close(profile.slow_down_time.inner_seconds(), 2.5);
+        assert_close(
+            profile
+                .speed_at(Duration::seconds(3.0))
+                .inner_meters_per_second(),
+            6.0,
+        );
+    }
+
+    #[test]
+    fn test_speed_up_the_whole_way() {
+        // Too short to reach the final speed of 10m/s
+        let profile = SpeedProfile::new(
+            Distance::meters(9.0),
+            Speed::ZERO,
+            Speed::meters_per_second(10.0),
+            Speed::meters_per_second(10.0),
+            2.0,
+            4.0,
+        );
+        assert_close(profile.final_speed().inner_meters_per_second(), 6.0);
+        assert_close(profile.total_time().inner_seconds(), 3.0);
+    }
+}
@@ -7,6 +7,7 @@ pub(crate) use self::walking::WalkingSimState;
 mod car;
 mod driving;
 mod intersection;
+mod kinematics;
 mod parking;
 mod queue;
 mod walking;
@@ -364,8 +364,7 @@ impl ParkingSim for NormalParkingSimState {
         let p = self.parked_cars.get(&id)?;
         match p.spot {
             ParkingSpot::Onstreet(lane, idx) => {
-                let front_dist =
-                    self.onstreet_lanes[&lane].dist_along_for_car(idx, &p.vehicle, map);
+                let front_dist = self.onstreet_lanes[&lane].dist_along_for_car(idx, &p.vehicle);
                 Some(DrawCarInput {
                     id: p.vehicle.id,
                     waiting_for_turn: None,
@@ -451,7 +450,11 @@ impl ParkingSim for NormalParkingSimState {
         let mut candidates = Vec::new();
 
         for l in self.driving_to_parking_lanes.get(driving_pos.lane()) {
-            for spot in self.onstreet_lanes[l].spots() {
+            let lane = &self.onstreet_lanes[l];
+            if !lane.fits(vehicle) {
+                continue;
+            }
+            for spot in lane.spots() {
                 if self.is_free(spot)
                     && driving_pos.dist_along()
                         <= self.spot_to_driving_pos(spot, vehicle, map).dist_along()
@@ -499,8 +502,11 @@ impl ParkingSim for NormalParkingSimState {
         match spot {
             ParkingSpot::Onstreet(l, idx) => {
                 let lane = &self.onstreet_lanes[&l];
-                Position::new(l, lane.dist_along_for_car(idx, vehicle, map))
-                    .equiv_pos_for_long_object(lane.driving_lane, vehicle.length, map)
+                Position::new(l, lane.dist_along_for_car(idx, vehicle)).equiv_pos_for_long_object(
+                    lane.driving_lane,
+                    vehicle.length,
+                    map,
+                )
             }
             ParkingSpot::Offstreet(b, _) => map.get_b(b).driving_connection(map).unwrap().0,
             ParkingSpot::Lot(pl, _) => map.get_pl(pl).driving_pos,
@@ -512,11 +518,8 @@ impl ParkingSim for NormalParkingSimState {
             ParkingSpot::Onstreet(l, idx) => {
                 let lane = &self.onstreet_lanes[&l];
                 // Always centered in the entire parking spot
-                Position::new(
-                    l,
-                    lane.spot_dist_along[idx] - (map.get_config().street_parking_spot_length / 2.0),
-                )
-                .equiv_pos(lane.sidewalk, map)
+                Position::new(l, lane.spot_dist_along[idx] - (lane.spot_length / 2.0))
+                    .equiv_pos(lane.sidewalk, map)
             }
             ParkingSpot::Offstreet(b, _) => map.get_b(b).sidewalk_pos,
             ParkingSpot::Lot(pl, _) => map.get_pl(pl).sidewalk_pos,
@@ -663,16 +666,24 @@ struct ParkingLane {
     sidewalk: LaneID,
     // The front of the parking spot (farthest along the lane)
     spot_dist_along: Vec<Distance>,
+    spot_length: Distance,
+    /// Only freight vehicles may use a loading zone
+    loading_zone: bool,
 }
 
 impl ParkingLane {
     fn new(lane: &Lane, map: &Map) -> Option<ParkingLane> {
-        if lane.lane_type != LaneType::Parking {
+        if lane.lane_type != LaneType::Parking && lane.lane_type != LaneType::LoadingZone {
             return None;
         }
 
         let driving_lane = if let Some(l) = map.get_parent(lane.id).parking_to_driving(lane.id) {
             l
+        } else if lane.is_loading_zone() {
+            // Edits can put a loading zone on a road without any driving lanes. Nothing can
+            // reach it, so just skip it.
+            warn!("Loading zone {} has no driving lane", lane.id);
+            return None;
         } else {
             // Serious enough to blow up loudly.
             panic!("Parking lane {} has no driving lane!", lane.id);
@@ -690,20 +701,29 @@ impl ParkingLane {
             return None;
         };
 
+        let cfg = map.get_config();
+        let spot_length = lane.curbside_spot_length(cfg)?;
         Some(ParkingLane {
             parking_lane: lane.id,
             driving_lane,
             sidewalk,
-            spot_dist_along: (0..lane.number_parking_spots(map.get_config()))
-                .map(|idx| map.get_config().street_parking_spot_length * (2.0 + idx as f64))
+            // Leave a buffer at the start of the lane
+            spot_dist_along: (0..lane.number_parking_spots(cfg))
+                .map(|idx| cfg.street_parking_spot_length + spot_length * (1.0 + idx as f64))
                 .collect(),
+            spot_length,
+            loading_zone: lane.is_loading_zone(),
         })
     }
 
-    fn dist_along_for_car(&self, spot_idx: usize, vehicle: &Vehicle, map: &Map) -> Distance {
+    fn dist_along_for_car(&self, spot_idx: usize, vehicle: &Vehicle) -> Distance {
         // Find the offset to center this particular car in the parking spot
-        self.spot_dist_along[spot_idx]
-            - (map.get_config().street_parking_spot_length - vehicle.length) / 2.0
+        self.spot_dist_along[spot_idx] - (self.spot_length - vehicle.length) / 2.0
+    }
+
+    fn fits(&self, vehicle: &Vehicle) -> bool {
+        vehicle.length <= self.spot_length
+            && (!self.loading_zone || vehicle.vehicle_type.is_freight())
     }
 
     fn spots(&self) -> Vec<ParkingSpot> {
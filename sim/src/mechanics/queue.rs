@@ -228,24 +228,11 @@ impl Queue {
                             }
                             self.geom_len
                         }
-                        CarState::Crossing {
-                            ref time_int,
-                            ref dist_int,
-                            ..
-                        } => {
-                            // TODO Why percent_clamp_end? We process car updates in any order, so we might
-                            // calculate this before moving this car from Crossing to another state.
-                            dist_int.lerp(time_int.percent_clamp_end(now)).min(bound)
-                        }
-                        CarState::ChangingLanes {
-                            ref new_time,
-                            ref new_dist,
-                            ..
-                        } => {
-                            // Same as the Crossing logic
-                            new_dist.lerp(new_time.percent_clamp_end(now)).min(bound)
+                        CarState::Crossing { .. } | CarState::ChangingLanes { .. } => {
+                            car.state.dist_along_crossing(now).min(bound)
                         }
                         CarState::Unparking { front, .. } => front,
+                        CarState::Overtaking { front, .. } => front,
                         CarState::Parking(front, _, _) => front,
                         CarState::IdlingAtStop(front, _) => front,
                     };
@@ -490,6 +477,13 @@ impl Queue {
         }
     }
 
+    /// Record that a car has passed its leader using the oncoming lane, so they swap places.
+    pub fn swap_with_leader(&mut self, car: CarID, idx: usize) {
+        assert_eq!(self.members[idx], Queued::Vehicle(car));
+        assert!(matches!(self.members[idx - 1], Queued::Vehicle(_)));
+        self.members.swap(idx - 1, idx);
+    }
+
     /// Record that a car is starting to change lanes away from this queue.
     pub fn replace_car_with_dynamic_blockage(&mut self, car: &Car, idx: usize) {
         self.remove_car_from_idx(car.vehicle.id, idx);
@@ -608,6 +602,9 @@ fn dump_cars(dists: &[QueueEntry], cars: &FixedMap<CarID, Car>, id: Traversable,
                 CarState::Unparking { ref time_int, .. } => {
                     println!("  Unparking during {} .. {}", time_int.start, time_int.end);
                 }
+                CarState::Overtaking { ref time_int, .. } => {
+                    println!("  Overtaking during {} .. {}", time_int.start, time_int.end);
+                }
                 CarState::Parking(_, _, ref time_int) => {
                     println!("  Parking during {} .. {}", time_int.start, time_int.end);
                 }
@@ -170,21 +170,25 @@ impl WalkingSimState {
                                 .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
                         }
                         SidewalkPOI::TransitStop(stop) => {
-                            if let Some(route) = trips.ped_reached_bus_stop(
-                                now,
+                            let (route, maybe_stop2) = trips.ped_reached_bus_stop(
                                 ped.id,
                                 stop,
                                 ped.total_blocked_time,
                                 ped.path.total_length(),
                                 ctx,
-                                transit,
-                            ) {
-                                ped.state = PedState::WaitingForBus(route, now);
-                            } else {
-                                self.peds_per_traversable
-                                    .remove(ped.path.current_step().as_traversable(), ped.id);
-                                self.peds.remove(&id);
-                            }
+                            );
+                            ped.state = PedState::WaitingForBus(route, now);
+                            // A vehicle might already be at the stop
+                            transit.ped_waiting_for_bus(
+                                now,
+                                id,
+                                stop,
+                                route,
+                                maybe_stop2,
+                                trips,
+                                self,
+                                ctx.map,
+                            );
                         }
                         SidewalkPOI::Border(i) => {
                             self.peds_per_traversable
@@ -0,0 +1,412 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Time};
+use map_model::{BuildingID, Map, Path, PathConstraints, PathRequest, Position};
+
+use crate::sim::ConditionalRestrictions;
+use crate::{
+    CarID, Command, CreateCar, Event, Router, Scheduler, TripID, TripManager, Vehicle, VehicleSpec,
+    VehicleType, MIN_CAR_LENGTH,
+};
+
+/// How long a ride-hail vehicle waits at the curb for the passenger to get in
+pub(crate) const RIDE_HAIL_PICKUP_TIME: Duration = Duration::const_seconds(30.0);
+/// How long a ride-hail vehicle waits at the curb for the passenger to get out
+pub(crate) const RIDE_HAIL_DROPOFF_TIME: Duration = Duration::const_seconds(15.0);
+
+#[derive(Serialize, Deserialize, Clone)]
+struct FleetVehicle {
+    vehicle: Vehicle,
+    /// Where the vehicle appears when dispatched, and where it vanishes when it has nothing to do
+    depot: Position,
+    state: FleetState,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+enum FleetState {
+    /// Off-map at the depot
+    Idle,
+    DrivingToPickup(Request),
+    /// Waiting at the curb for the passenger to get in. The path goes to the dropoff.
+    Boarding(Request, Path),
+    DrivingToDropoff(Request),
+    /// Waiting at the curb for the passenger to get out
+    Alighting,
+    /// The request was cancelled while driving to it. Finish the current path, then find
+    /// something else to do.
+    Abandoned,
+    ReturningToDepot,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Request {
+    trip: TripID,
+    from: BuildingID,
+    to: BuildingID,
+    requested_at: Time,
+}
+
+/// What should a ride-hail vehicle do after reaching the curb?
+pub(crate) enum CurbArrival {
+    /// Pick up the passenger, then drive this path to their destination
+    PickUp(TripID, PathRequest),
+    DropOff,
+    /// The passenger was picked up, but their destination is unreachable
+    CantReachDropoff(TripID, String),
+    /// Nobody to pick up or drop off here; find something else to do
+    Nothing,
+    /// The vehicle is back at its depot and vanishes. Also returns queued requests that couldn't
+    /// be served, and should be cancelled.
+    ReachedDepot(Vec<(TripID, String)>),
+}
+
+/// Manages a fleet of ride-hailing vehicles. When a person requests a ride, the closest idle
+/// vehicle appears at its depot, drives to the curb in front of the person's building, waits for
+/// them to get in, drives to their destination, and waits for them to get out. Then it serves the
+/// oldest waiting request, or returns to its depot and vanishes. If every vehicle is busy, requests
+/// wait in a queue.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct RideHailSimState {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    vehicles: BTreeMap<CarID, FleetVehicle>,
+    /// Requests that no vehicle has been dispatched to yet, oldest first
+    waiting: VecDeque<Request>,
+
+    events: Vec<Event>,
+}
+
+impl RideHailSimState {
+    /// Spread the fleet evenly over the map's buildings, using each as a depot.
+    pub fn new(map: &Map, fleet_size: usize, trips: &mut TripManager) -> RideHailSimState {
+        let mut vehicles = BTreeMap::new();
+        let bldgs = map.all_buildings();
+        if !bldgs.is_empty() {
+            let step = (bldgs.len() / fleet_size.max(1)).max(1);
+            for b in bldgs.iter().step_by(step).take(fleet_size) {
+                let (max_accel, max_decel) = VehicleType::Car.default_accel_decel();
+                let vehicle = VehicleSpec {
+                    vehicle_type: VehicleType::Car,
+                    length: MIN_CAR_LENGTH,
+                    max_speed: None,
+                    max_accel,
+                    max_decel,
+                }
+                .make(
+                    CarID {
+                        id: trips.new_car_id(),
+                        vehicle_type: VehicleType::Car,
+                    },
+                    None,
+                );
+                vehicles.insert(
+                    vehicle.id,
+                    FleetVehicle {
+                        vehicle,
+                        depot: curb_pos(b.id, map),
+                        state: FleetState::Idle,
+                    },
+                );
+            }
+        }
+
+        RideHailSimState {
+            vehicles,
+            waiting: VecDeque::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Queue up a request, and dispatch a vehicle immediately if one is idle. Returns requests that
+    /// can't be served and should be cancelled -- maybe this one, if there's no fleet at all.
+    pub fn request_ride(
+        &mut self,
+        now: Time,
+        trip: TripID,
+        from: BuildingID,
+        to: BuildingID,
+        map: &Map,
+        restrictions: &mut ConditionalRestrictions,
+        scheduler: &mut Scheduler,
+    ) -> Vec<(TripID, String)> {
+        if self.vehicles.is_empty() {
+            return vec![(trip, "there's no ride-hailing fleet".to_string())];
+        }
+        self.waiting.push_back(Request {
+            trip,
+            from,
+            to,
+            requested_at: now,
+        });
+        self.dispatch(now, map, restrictions, scheduler)
+    }
+
+    /// Send idle vehicles to the oldest waiting requests. Returns requests that can't be served.
+    fn dispatch(
+        &mut self,
+        now: Time,
+        map: &Map,
+        restrictions: &mut ConditionalRestrictions,
+        scheduler: &mut Scheduler,
+    ) -> Vec<(TripID, String)> {
+        let mut failed = Vec::new();
+        let mut changed = false;
+        while let Some(req) = self.waiting.front() {
+            let pickup_pt = map.get_b(req.from).polygon.center();
+            let id = match self
+                .vehicles
+                .values()
+                .filter(|v| matches!(v.state, FleetState::Idle))
+                .min_by_key(|v| v.depot.pt(map).dist_to(pickup_pt))
+            {
+                Some(v) => v.vehicle.id,
+                None => break,
+            };
+            let req = self.waiting.pop_front().unwrap();
+
+            let fleet_vehicle = self.vehicles.get_mut(&id).unwrap();
+            match restrictions.pathfind(
+                map,
+                PathRequest::vehicle(
+                    fleet_vehicle.depot,
+                    curb_pos(req.from, map),
+                    PathConstraints::Car,
+                ),
+                now,
+            ) {
+                Ok(path) => {
+                    scheduler.push(
+                        now,
+                        Command::SpawnCar(
+                            CreateCar {
+                                router: Router::stop_at_curb(id, path),
+                                vehicle: fleet_vehicle.vehicle.clone(),
+                                maybe_parked_car: None,
+                                trip_and_person: None,
+                                maybe_route: None,
+                            },
+                            true,
+                        ),
+                    );
+                    fleet_vehicle.state = FleetState::DrivingToPickup(req);
+                    changed = true;
+                }
+                Err(err) => {
+                    failed.push((
+                        req.trip,
+                        format!("no ride-hail vehicle can reach {}: {}", req.from, err),
+                    ));
+                }
+            }
+        }
+        if changed {
+            self.record_utilization();
+        }
+        failed
+    }
+
+    /// A fleet vehicle reached the end of its path. `dist_crossed` is the length of that path.
+    pub fn vehicle_arrived(
+        &mut self,
+        now: Time,
+        id: CarID,
+        dist_crossed: Distance,
+        map: &Map,
+        restrictions: &mut ConditionalRestrictions,
+        scheduler: &mut Scheduler,
+    ) -> CurbArrival {
+        let fleet_vehicle = self.vehicles.get_mut(&id).unwrap();
+        match std::mem::replace(&mut fleet_vehicle.state, FleetState::Abandoned) {
+            FleetState::DrivingToPickup(req) => {
+                self.events
+                    .push(Event::RideHailLegFinished(id, dist_crossed, false));
+                let pickup = curb_pos(req.from, map);
+                match restrictions.pathfind(
+                    map,
+                    PathRequest::vehicle(pickup, curb_pos(req.to, map), PathConstraints::Car),
+                    now,
+                ) {
+                    Ok(path) => {
+                        self.events.push(Event::RideHailPickup(
+                            req.trip,
+                            id,
+                            now - req.requested_at,
+                        ));
+                        let trip = req.trip;
+                        let path_req = path.get_req().clone();
+                        fleet_vehicle.state = FleetState::Boarding(req, path);
+                        CurbArrival::PickUp(trip, path_req)
+                    }
+                    Err(err) => CurbArrival::CantReachDropoff(
+                        req.trip,
+                        format!("ride-hail vehicle can't reach {}: {}", req.to, err),
+                    ),
+                }
+            }
+            FleetState::DrivingToDropoff(_) => {
+                self.events
+                    .push(Event::RideHailLegFinished(id, dist_crossed, true));
+                fleet_vehicle.state = FleetState::Alighting;
+                CurbArrival::DropOff
+            }
+            FleetState::Abandoned => {
+                self.events
+                    .push(Event::RideHailLegFinished(id, dist_crossed, false));
+                CurbArrival::Nothing
+            }
+            FleetState::ReturningToDepot => {
+                self.events
+                    .push(Event::RideHailLegFinished(id, dist_crossed, false));
+                CurbArrival::ReachedDepot(self.vehicle_vanished(
+                    now,
+                    id,
+                    map,
+                    restrictions,
+                    scheduler,
+                ))
+            }
+            FleetState::Idle | FleetState::Boarding(_, _) | FleetState::Alighting => unreachable!(),
+        }
+    }
+
+    /// A fleet vehicle is done waiting at the curb. Decide where it goes next.
+    pub fn vehicle_departed(
+        &mut self,
+        now: Time,
+        id: CarID,
+        start: Position,
+        map: &Map,
+        restrictions: &mut ConditionalRestrictions,
+    ) -> Router {
+        let fleet_vehicle = self.vehicles.get_mut(&id).unwrap();
+        if let FleetState::Boarding(req, path) =
+            std::mem::replace(&mut fleet_vehicle.state, FleetState::Abandoned)
+        {
+            fleet_vehicle.state = FleetState::DrivingToDropoff(req);
+            return Router::stop_at_curb(id, path);
+        }
+
+        // Serve the oldest waiting request, if this vehicle can reach it. Otherwise, leave it for
+        // a vehicle starting from a depot.
+        if let Some(req) = self.waiting.front() {
+            if let Ok(path) = restrictions.pathfind(
+                map,
+                PathRequest::vehicle(start, curb_pos(req.from, map), PathConstraints::Car),
+                now,
+            ) {
+                fleet_vehicle.state =
+                    FleetState::DrivingToPickup(self.waiting.pop_front().unwrap());
+                return Router::stop_at_curb(id, path);
+            }
+        }
+
+        fleet_vehicle.state = FleetState::ReturningToDepot;
+        let path = restrictions
+            .pathfind(
+                map,
+                PathRequest::vehicle(start, fleet_vehicle.depot, PathConstraints::Car),
+                now,
+            )
+            .unwrap_or_else(|_| {
+                // Just vanish at the end of the current lane
+                Path::one_step(
+                    PathRequest::vehicle(
+                        start,
+                        Position::end(start.lane(), map),
+                        PathConstraints::Car,
+                    ),
+                    map,
+                )
+            });
+        Router::stop_at_curb(id, path)
+    }
+
+    /// The vehicle is back at the depot. Returns requests that couldn't be served.
+    fn vehicle_vanished(
+        &mut self,
+        now: Time,
+        id: CarID,
+        map: &Map,
+        restrictions: &mut ConditionalRestrictions,
+        scheduler: &mut Scheduler,
+    ) -> Vec<(TripID, String)> {
+        self.vehicles.get_mut(&id).unwrap().state = FleetState::Idle;
+        self.record_utilization();
+        self.dispatch(now, map, restrictions, scheduler)
+    }
+
+    /// Forget about a cancelled trip. If `vehicle_deleted`, the vehicle serving the trip was
+    /// abruptly removed from the map, so send it back to the depot.
+    pub fn trip_cancelled(&mut self, trip: TripID, vehicle_deleted: bool) {
+        self.waiting.retain(|req| req.trip != trip);
+        let mut changed = false;
+        for fleet_vehicle in self.vehicles.values_mut() {
+            let serving = match fleet_vehicle.state {
+                FleetState::DrivingToPickup(ref req)
+                | FleetState::Boarding(ref req, _)
+                | FleetState::DrivingToDropoff(ref req) => req.trip == trip,
+                _ => false,
+            };
+            if !serving {
+                continue;
+            }
+            if vehicle_deleted {
+                fleet_vehicle.state = FleetState::Idle;
+                changed = true;
+            } else if let FleetState::DrivingToPickup(_) = fleet_vehicle.state {
+                fleet_vehicle.state = FleetState::Abandoned;
+            }
+        }
+        if changed {
+            self.record_utilization();
+        }
+    }
+
+    pub fn is_fleet_vehicle(&self, id: CarID) -> bool {
+        self.vehicles.contains_key(&id)
+    }
+
+    /// (busy vehicles, fleet size)
+    pub fn utilization(&self) -> (usize, usize) {
+        let busy = self
+            .vehicles
+            .values()
+            .filter(|v| !matches!(v.state, FleetState::Idle))
+            .count();
+        (busy, self.vehicles.len())
+    }
+
+    pub fn num_waiting(&self) -> usize {
+        self.waiting.len()
+    }
+
+    fn record_utilization(&mut self) {
+        let (busy, total) = self.utilization();
+        self.events
+            .push(Event::RideHailFleetUtilization(busy, total));
+    }
+
+    pub fn collect_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+/// Where a ride-hail vehicle stops to serve a building: the driving lane closest to it, right in
+/// front if possible.
+fn curb_pos(b: BuildingID, map: &Map) -> Position {
+    let driving_lane = map.find_driving_lane_near_building(b);
+    let lane_len = map.get_l(driving_lane).length();
+    let sidewalk_pos = map.get_b(b).sidewalk_pos;
+    let dist = if driving_lane.road == sidewalk_pos.lane().road {
+        sidewalk_pos.equiv_pos(driving_lane, map).dist_along()
+    } else {
+        Distance::ZERO
+    };
+    // The front of the vehicle has to be far enough along for the whole vehicle to fit
+    Position::new(driving_lane, dist.max(MIN_CAR_LENGTH).min(lane_len))
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use geom::Distance;
+use geom::{Distance, Duration};
 use map_model::{
     BuildingID, IntersectionID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
     Position, Traversable, Turn, TurnID,
@@ -32,6 +32,9 @@ pub(crate) enum ActionAtEnd {
     GotoLaneEnd,
     StopBiking(SidewalkSpot),
     BusAtStop,
+    RideHailAtCurb,
+    /// No curbside space was free, so dwell in the travel lane for this long
+    DoublePark(Duration),
     GiveUpOnParking,
 }
 
@@ -55,6 +58,19 @@ enum Goal {
     FollowTransitRoute {
         end_dist: Distance,
     },
+    /// A ride-hail vehicle picking up or dropping off at the curb, or returning to its depot
+    StopAtCurb {
+        end_dist: Distance,
+    },
+    /// A freight vehicle stopping at a building to load or unload. It prefers a curbside spot on
+    /// the last lane, but will double-park rather than circle around looking.
+    Deliver {
+        target: BuildingID,
+        dwell: Duration,
+        /// Spot and cached distance along the last driving lane
+        spot: Option<(ParkingSpot, Distance)>,
+        double_park_dist: Option<Distance>,
+    },
 }
 
 impl Router {
@@ -116,6 +132,29 @@ impl Router {
         }
     }
 
+    pub fn stop_at_curb(owner: CarID, path: Path) -> Router {
+        Router {
+            goal: Goal::StopAtCurb {
+                end_dist: path.get_req().end.dist_along(),
+            },
+            path,
+            owner,
+        }
+    }
+
+    pub fn deliver(owner: CarID, path: Path, target: BuildingID, dwell: Duration) -> Router {
+        Router {
+            goal: Goal::Deliver {
+                target,
+                dwell,
+                spot: None,
+                double_park_dist: None,
+            },
+            path,
+            owner,
+        }
+    }
+
     pub fn head(&self) -> Traversable {
         self.path.current_step().as_traversable()
     }
@@ -143,7 +182,12 @@ impl Router {
                 ..
             } => stuck_end_dist.unwrap_or_else(|| spot.unwrap().1),
             Goal::BikeThenStop { ref goal } => goal.sidewalk_pos.dist_along(),
-            Goal::FollowTransitRoute { end_dist } => end_dist,
+            Goal::FollowTransitRoute { end_dist } | Goal::StopAtCurb { end_dist } => end_dist,
+            Goal::Deliver {
+                spot,
+                double_park_dist,
+                ..
+            } => double_park_dist.unwrap_or_else(|| spot.unwrap().1),
         }
     }
 
@@ -332,6 +376,79 @@ impl Router {
                     None
                 }
             }
+            Goal::StopAtCurb { end_dist } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::RideHailAtCurb)
+                } else {
+                    None
+                }
+            }
+            Goal::Deliver {
+                target,
+                dwell,
+                ref mut spot,
+                ref mut double_park_dist,
+            } => {
+                if let Some(d) = double_park_dist {
+                    if *d == front {
+                        return Some(ActionAtEnd::DoublePark(dwell));
+                    } else {
+                        return None;
+                    }
+                }
+
+                let need_new_spot = match spot {
+                    Some((s, _)) => !parking.is_free(*s),
+                    None => true,
+                };
+                if need_new_spot {
+                    let current_lane = self.path.current_step().as_lane();
+                    let end_dist = self.path.get_req().end.dist_along();
+                    // Only curbside spots count; a delivery doesn't pull into a garage or lot.
+                    // Pick whatever's closest to where we were headed.
+                    let best = parking
+                        .get_all_free_spots(
+                            Position::new(current_lane, front),
+                            vehicle,
+                            target,
+                            map,
+                        )
+                        .into_iter()
+                        .filter(|(s, _)| matches!(s, ParkingSpot::Onstreet(_, _)))
+                        .min_by_key(|(_, pos)| (pos.dist_along() - end_dist).abs());
+                    if let Some((new_spot, new_pos)) = best {
+                        if let Some((t, p)) = trip_and_person {
+                            events.push(Event::TripPhaseStarting(
+                                t,
+                                p,
+                                Some(PathRequest::vehicle(
+                                    Position::new(current_lane, front),
+                                    new_pos,
+                                    vehicle.vehicle_type.to_constraints(),
+                                )),
+                                TripPhaseType::Parking,
+                            ));
+                        }
+                        *spot = Some((new_spot, new_pos.dist_along()));
+                    } else {
+                        // Nowhere to pull over, so just stop in the lane and block it. If we've
+                        // already passed the building, stop right here.
+                        *spot = None;
+                        let d = end_dist.max(front);
+                        *double_park_dist = Some(d);
+                        if d == front {
+                            return Some(ActionAtEnd::DoublePark(dwell));
+                        }
+                        return None;
+                    }
+                }
+
+                if spot.unwrap().1 == front {
+                    Some(ActionAtEnd::StartParking(spot.unwrap().0))
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -508,7 +625,9 @@ impl Router {
 
     pub fn get_parking_spot_goal(&self) -> Option<&ParkingSpot> {
         match self.goal {
-            Goal::ParkNearBuilding { ref spot, .. } => spot.as_ref().map(|(s, _)| s),
+            Goal::ParkNearBuilding { ref spot, .. } | Goal::Deliver { ref spot, .. } => {
+                spot.as_ref().map(|(s, _)| s)
+            }
             _ => None,
         }
     }
@@ -25,8 +25,8 @@ pub(crate) enum Command {
     UpdateIntersection(IntersectionID),
     Callback(Duration),
     Pandemic(pandemic::Cmd),
-    /// The Time is redundant, just used to dedupe commands
-    StartBus(TransitRouteID, Time),
+    /// Which of the route's spawn times is this? Several runs may start at the same time.
+    StartBus(TransitRouteID, usize),
 }
 
 impl Command {
@@ -38,7 +38,7 @@ impl Command {
         }
     }
 
-    fn to_type(&self) -> CommandType {
+    pub(crate) fn to_type(&self) -> CommandType {
         match self {
             Command::SpawnCar(ref create, _) => CommandType::Car(create.vehicle.id),
             Command::SpawnPed(ref create) => CommandType::Ped(create.id),
@@ -49,7 +49,7 @@ impl Command {
             Command::UpdateIntersection(id) => CommandType::Intersection(*id),
             Command::Callback(_) => CommandType::Callback,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
-            Command::StartBus(r, t) => CommandType::StartBus(*r, *t),
+            Command::StartBus(r, idx) => CommandType::StartBus(*r, *idx),
         }
     }
 
@@ -72,7 +72,7 @@ impl Command {
 /// A smaller version of Command that satisfies many more properties. Only one Command per
 /// CommandType may exist at a time.
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Debug)]
-enum CommandType {
+pub(crate) enum CommandType {
     StartTrip(TripID),
     Car(CarID),
     CarLaggyHead(CarID),
@@ -80,7 +80,17 @@ enum CommandType {
     Intersection(IntersectionID),
     Callback,
     Pandemic(pandemic::Cmd),
-    StartBus(TransitRouteID, Time),
+    StartBus(TransitRouteID, usize),
+}
+
+impl CommandType {
+    pub fn agent(&self) -> Option<AgentID> {
+        match self {
+            CommandType::Car(id) | CommandType::CarLaggyHead(id) => Some(AgentID::Car(*id)),
+            CommandType::Ped(id) => Some(AgentID::Pedestrian(*id)),
+            _ => None,
+        }
+    }
 }
 
 /// A more compressed form of CommandType, just used for keeping stats on event processing.
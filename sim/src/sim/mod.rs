@@ -19,17 +19,20 @@ use map_model::{
 use synthpop::OrigPersonID;
 
 pub use self::queries::{AgentProperties, DelayCause};
+pub(crate) use self::restrictions::ConditionalRestrictions;
 // TODO Super weird for both of these to wind up here
 pub use self::scenario::{count_parked_cars_per_bldg, rand_dist};
 use crate::{
     AgentID, AlertLocation, Analytics, CarID, Command, CreateCar, DrivingSimState, Event,
     IntersectionSimState, PandemicModel, ParkedCar, ParkingSim, ParkingSimState, ParkingSpot,
-    Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot, StartTripArgs, TrafficRecorder,
-    TransitSimState, TripID, TripInfo, TripManager, TripPhaseType, Vehicle, VehicleSpec,
-    VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
+    Person, PersonID, RideHailSimState, Router, Scheduler, SidewalkPOI, SidewalkSpot, SimTrace,
+    StartTripArgs, TrafficRecorder, TransitSimState, TripCostModel, TripID, TripInfo, TripManager,
+    TripPhaseType, Vehicle, VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH, CAR_ACCEL,
+    CAR_DECEL, LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH,
 };
 
 mod queries;
+mod restrictions;
 mod scenario;
 
 // TODO Do something else.
@@ -43,6 +46,7 @@ pub struct Sim {
     walking: WalkingSimState,
     intersections: IntersectionSimState,
     transit: TransitSimState,
+    ridehail: RideHailSimState,
     trips: TripManager,
     #[serde(skip_serializing, skip_deserializing)]
     pandemic: Option<PandemicModel>,
@@ -59,9 +63,14 @@ pub struct Sim {
     highlighted_people: Option<BTreeSet<PersonID>>,
 
     analytics: Analytics,
+    trip_costs: TripCostModel,
     // This is created interactively, and there's no reason to preserve one for savestates.
     #[serde(skip_serializing, skip_deserializing)]
     recorder: Option<TrafficRecorder>,
+    #[serde(skip_serializing, skip_deserializing)]
+    trace: Option<SimTrace>,
+    #[serde(skip_serializing, skip_deserializing)]
+    restrictions: ConditionalRestrictions,
 
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
@@ -71,10 +80,21 @@ pub(crate) struct Ctx<'a> {
     pub parking: &'a mut ParkingSimState,
     pub intersections: &'a mut IntersectionSimState,
     pub scheduler: &'a mut Scheduler,
+    pub ridehail: &'a mut RideHailSimState,
     pub map: &'a Map,
     /// If present, live map edits are being processed, and the agents specified are in the process
     /// of being deleted. Some regular work should maybe be skipped.
     pub handling_live_edits: Option<BTreeSet<AgentID>>,
+    pub restrictions: &'a mut ConditionalRestrictions,
+}
+
+impl Ctx<'_> {
+    /// Calculates a path for something departing now, respecting any conditional restrictions in
+    /// effect now. The path isn't checked again later, so a vehicle already on its way may still
+    /// enter a road or make a turn that becomes restricted before it gets there.
+    pub fn pathfind(&mut self, req: PathRequest, now: Time) -> Result<Path> {
+        self.restrictions.pathfind(self.map, req, now)
+    }
 }
 
 /// Options controlling the traffic simulation.
@@ -127,6 +147,37 @@ pub struct SimOptions {
     /// quickly.
     #[structopt(long)]
     pub skip_analytics: bool,
+    /// Make vehicles speed up from a stop and brake before stopping, using each vehicle's max
+    /// acceleration and deceleration. Vehicles brake for stop signs, red lights, stopped leaders,
+    /// and the end of their trip. Off by default, in which case vehicles instantly move at their
+    /// max speed, matching older prebaked results.
+    #[structopt(long)]
+    pub kinematic_model: bool,
+    /// Allow vehicles stuck behind a slower leader on a road without another lane in their
+    /// direction to pass using the oncoming lane, when there's a big enough gap in oncoming
+    /// traffic. Oncoming traffic is blocked from the part of the lane used until the pass is done.
+    #[structopt(long)]
+    pub overtake_using_oncoming_lanes: bool,
+    /// How many vehicles serve ride-hailing trips. With none, those trips are cancelled.
+    #[structopt(long, default_value = "0")]
+    pub ride_hail_fleet_size: usize,
+    /// Vehicles serving a transit route hold this many passengers, instead of the default for
+    /// buses or trains, like `--transit_capacity 44=100`. Routes are identified by their short
+    /// name, which usually covers both directions. May be repeated.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_transit_capacity))]
+    pub transit_capacity: Vec<(String, usize)>,
+    /// Ignore conditional access and turn restrictions, like school streets or peak-hour bus
+    /// gates, and always pathfind with the map's static restrictions. While any restriction is in
+    /// effect, paths are calculated using a contraction hierarchy built for that combination of
+    /// restrictions and vehicle type. Each one takes about as long to build as the map's own, and
+    /// they're kept in memory (per thread) for the rest of the simulation, so maps with many
+    /// different time windows pay for that in startup time and memory throughout the day.
+    #[structopt(long)]
+    pub ignore_conditional_restrictions: bool,
+    /// A JSON file with prices and the value of time for each mode, used to calculate the
+    /// generalized cost of trips. If not specified, rough defaults are used.
+    #[structopt(long, parse(try_from_str = TripCostModel::load))]
+    pub trip_costs: Option<TripCostModel>,
 }
 
 impl SimOptions {
@@ -143,6 +194,12 @@ impl SimOptions {
             infinite_parking: false,
             disable_turn_conflicts: false,
             skip_analytics: false,
+            kinematic_model: false,
+            overtake_using_oncoming_lanes: false,
+            ride_hail_fleet_size: 0,
+            transit_capacity: Vec::new(),
+            ignore_conditional_restrictions: false,
+            trip_costs: None,
         }
     }
 }
@@ -158,6 +215,13 @@ fn parse_rng(x: &str) -> Result<XorShiftRng> {
     Ok(XorShiftRng::seed_from_u64(seed))
 }
 
+fn parse_transit_capacity(x: &str) -> Result<(String, usize)> {
+    match x.rsplit_once('=') {
+        Some((route, capacity)) => Ok((route.to_string(), capacity.parse()?)),
+        None => bail!("Bad --transit_capacity={}. Must be route=capacity", x),
+    }
+}
+
 #[derive(Clone)]
 pub enum AlertHandler {
     /// Just print the alert to STDOUT
@@ -205,13 +269,17 @@ impl Sim {
             opts.allow_block_the_box = true;
         }
 
+        let mut trips = TripManager::new();
+        let ridehail = RideHailSimState::new(map, opts.ride_hail_fleet_size, &mut trips);
+
         Sim {
             driving: DrivingSimState::new(map, &opts),
             parking: ParkingSimState::new(map, opts.infinite_parking, &mut timer),
             walking: WalkingSimState::new(),
             intersections: IntersectionSimState::new(map, &mut scheduler, &opts),
-            transit: TransitSimState::new(map),
-            trips: TripManager::new(),
+            transit: TransitSimState::new(map, &opts.transit_capacity),
+            ridehail,
+            trips,
             pandemic: opts.enable_pandemic_model.map(PandemicModel::new),
             scheduler,
             time: Time::START_OF_DAY,
@@ -224,7 +292,10 @@ impl Sim {
             alerts: opts.alerts,
 
             analytics: Analytics::new(!opts.skip_analytics),
+            trip_costs: opts.trip_costs.unwrap_or_default(),
             recorder: None,
+            trace: None,
+            restrictions: ConditionalRestrictions::new(opts.ignore_conditional_restrictions),
         }
     }
 
@@ -286,6 +357,8 @@ impl Sim {
             vehicle_type: VehicleType::Car,
             length: MIN_CAR_LENGTH,
             max_speed: None,
+            max_accel: CAR_ACCEL,
+            max_decel: CAR_DECEL,
         };
         let driving_lane = map.find_driving_lane_near_building(b);
 
@@ -328,12 +401,12 @@ impl Sim {
     }
 
     pub(crate) fn seed_bus_route(&mut self, route: &TransitRoute) {
-        for t in &route.spawn_times {
-            self.scheduler.push(*t, Command::StartBus(route.id, *t));
+        for (idx, t) in route.spawn_times.iter().enumerate() {
+            self.scheduler.push(*t, Command::StartBus(route.id, idx));
         }
     }
 
-    fn start_bus(&mut self, route: &TransitRoute, map: &Map) {
+    fn start_bus(&mut self, route: &TransitRoute, run: usize, map: &Map) {
         // Spawn one bus for the first leg.
         let path = self.transit.create_empty_route(route, map);
 
@@ -344,10 +417,13 @@ impl Sim {
             PathConstraints::Train => (VehicleType::Train, LIGHT_RAIL_LENGTH),
             _ => unreachable!(),
         };
+        let (max_accel, max_decel) = vehicle_type.default_accel_decel();
         let vehicle = VehicleSpec {
             vehicle_type,
             length,
             max_speed: None,
+            max_accel,
+            max_decel,
         }
         .make(
             CarID {
@@ -356,6 +432,7 @@ impl Sim {
             },
             None,
         );
+        self.transit.bus_scheduled(vehicle.id, route, run);
 
         self.scheduler.push(
             self.time,
@@ -433,13 +510,16 @@ impl Sim {
         self.time = time;
         let mut events = Vec::new();
         let mut halt = false;
+        let traced_cmd = self.trace.as_ref().map(|_| cmd.to_type());
 
         let mut ctx = Ctx {
             parking: &mut self.parking,
             intersections: &mut self.intersections,
             scheduler: &mut self.scheduler,
+            ridehail: &mut self.ridehail,
             map,
             handling_live_edits: None,
+            restrictions: &mut self.restrictions,
         };
 
         match cmd {
@@ -528,7 +608,7 @@ impl Sim {
                                 trip,
                                 person,
                                 Some(req),
-                                if id.vehicle_type == VehicleType::Car {
+                                if id.vehicle_type != VehicleType::Bike {
                                     TripPhaseType::Driving
                                 } else {
                                     TripPhaseType::Biking
@@ -630,20 +710,34 @@ impl Sim {
                     .unwrap()
                     .handle_cmd(self.time, cmd, &mut self.scheduler);
             }
-            Command::StartBus(r, _) => {
-                self.start_bus(map.get_tr(r), map);
+            Command::StartBus(r, run) => {
+                self.start_bus(map.get_tr(r), run, map);
             }
         }
 
         // Record events at precisely the time they occur.
         self.dispatch_events(events, map);
 
+        if let Some(cmd) = traced_cmd {
+            let agent = cmd.agent();
+            let agent_state = agent.map(|a| self.debug_agent_json(a));
+            let trace = self.trace.as_mut().unwrap();
+            if trace.record_step(self.time, cmd, agent_state.clone()) {
+                trace.dump = Some((
+                    trace.steps().len() - 1,
+                    agent_state.unwrap_or_else(|| "No agent involved".to_string()),
+                ));
+                halt = true;
+            }
+        }
+
         halt
     }
 
     fn dispatch_events(&mut self, mut events: Vec<Event>, map: &Map) {
         events.extend(self.trips.collect_events());
         events.extend(self.transit.collect_events());
+        events.extend(self.ridehail.collect_events());
         events.extend(self.driving.collect_events());
         events.extend(self.walking.collect_events());
         events.extend(self.intersections.collect_events());
@@ -655,6 +749,9 @@ impl Sim {
             if let Some(ref mut r) = self.recorder {
                 r.handle_event(self.time, &ev, map, &self.driving);
             }
+            if let Some(ref mut t) = self.trace {
+                t.event(&ev);
+            }
 
             self.analytics.event(ev, self.time, map);
         }
@@ -839,6 +936,7 @@ impl Sim {
     /// (trips cancelled, parked cars displaced).
     pub fn handle_live_edits(&mut self, map: &Map, timer: &mut Timer) -> (usize, usize) {
         self.edits_name = map.get_edits().edits_name.clone();
+        self.restrictions.clear();
 
         let (affected, num_parked_cars) = self.find_trips_affected_by_live_edits(map, timer);
         let num_trips_cancelled = affected.len();
@@ -851,8 +949,10 @@ impl Sim {
             parking: &mut self.parking,
             intersections: &mut self.intersections,
             scheduler: &mut self.scheduler,
+            ridehail: &mut self.ridehail,
             map,
             handling_live_edits: Some(affected_agents),
+            restrictions: &mut self.restrictions,
         };
         for (agent, trip) in affected {
             match agent {
@@ -966,8 +1066,10 @@ impl Sim {
                 parking: &mut self.parking,
                 intersections: &mut self.intersections,
                 scheduler: &mut self.scheduler,
+                ridehail: &mut self.ridehail,
                 map,
                 handling_live_edits: None,
+                restrictions: &mut self.restrictions,
             };
             let vehicle = self.driving.delete_car(id, self.time, &mut ctx);
             self.trips.cancel_trip(
@@ -985,6 +1087,24 @@ impl Sim {
     pub fn clear_alerts(&mut self) -> Vec<(Time, AlertLocation, String)> {
         std::mem::take(&mut self.analytics.alerts)
     }
+
+    /// Start or stop keeping a copy of every event, for consumers following along live.
+    pub fn capture_events(&mut self, enabled: bool) {
+        if !enabled {
+            self.analytics.captured_events = None;
+        } else if self.analytics.captured_events.is_none() {
+            self.analytics.captured_events = Some(Vec::new());
+        }
+    }
+
+    /// Returns all events captured since the last call, in the order they happened.
+    pub fn drain_captured_events(&mut self) -> Vec<(Time, Event)> {
+        self.analytics
+            .captured_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
 }
 
 // Callbacks
@@ -1024,6 +1144,20 @@ impl Sim {
     }
 }
 
+// Tracing
+impl Sim {
+    /// Start recording a trace of every step. If `stop_at_step` is specified, the simulation will
+    /// halt right after that step, and the trace will include a dump of the agent involved.
+    pub fn enable_trace(&mut self, stop_at_step: Option<usize>) {
+        assert!(self.trace.is_none());
+        self.trace = Some(SimTrace::new(stop_at_step));
+    }
+
+    pub fn take_trace(&mut self) -> Option<SimTrace> {
+        self.trace.take()
+    }
+}
+
 // Managing highlighted people
 impl Sim {
     pub fn set_highlighted_people(&mut self, people: BTreeSet<PersonID>) {
@@ -16,7 +16,8 @@ use crate::analytics::SlidingWindow;
 use crate::{
     AgentID, AgentType, Analytics, CarID, CommutersVehiclesCounts, DrawCarInput, DrawPedCrowdInput,
     DrawPedestrianInput, PandemicModel, ParkedCar, ParkingSim, PedestrianID, Person, PersonID,
-    PersonState, Sim, TripEndpoint, TripID, TripInfo, TripResult, UnzoomedAgent, VehicleType,
+    PersonState, Sim, TripCost, TripCostModel, TripEndpoint, TripID, TripInfo, TripResult,
+    UnzoomedAgent, VehicleType,
 };
 
 // TODO Many of these just delegate to an inner piece. This is unorganized and hard to maintain.
@@ -204,6 +205,8 @@ impl Sim {
             VehicleType::Bike,
             VehicleType::Bus,
             VehicleType::Train,
+            VehicleType::Delivery,
+            VehicleType::Truck,
         ] {
             let id = CarID {
                 id: idx,
@@ -313,6 +316,17 @@ impl Sim {
         &self.analytics
     }
 
+    pub fn get_trip_cost_model(&self) -> &TripCostModel {
+        &self.trip_costs
+    }
+
+    /// Calculates the generalized cost of a trip from what it's used so far, with the cost model
+    /// from `SimOptions`. The analytics may be from this simulation or prebaked results.
+    pub fn trip_cost(&self, analytics: &Analytics, id: TripID) -> Option<TripCost> {
+        let usage = analytics.trip_usage.get(&id)?;
+        Some(self.trip_costs.cost(self.trips.trip_info(id).mode, usage))
+    }
+
     /// For intersections with an agent waiting beyond some threshold, return when they started
     /// waiting. Sorted by earliest waiting (likely the root cause of gridlock).
     pub fn delayed_intersections(&self, threshold: Duration) -> Vec<(IntersectionID, Time)> {
@@ -343,6 +357,10 @@ impl Sim {
             .current_stage_and_remaining_time(self.time, i)
     }
 
+    pub fn current_signal_plan(&self, i: IntersectionID) -> usize {
+        self.intersections.current_signal_plan(i)
+    }
+
     // TODO This is an awkward copy of raw_throughput
     // TODO And it does NOT count buses/trains spawning
     pub fn all_arrivals_at_border(
@@ -386,6 +404,12 @@ impl Sim {
         self.transit.get_people_waiting_at_stop(at)
     }
 
+    /// (busy vehicles, fleet size, requests waiting for a vehicle to be dispatched)
+    pub fn get_ride_hail_fleet_status(&self) -> (usize, usize, usize) {
+        let (busy, total) = self.ridehail.utilization();
+        (busy, total, self.ridehail.num_waiting())
+    }
+
     pub fn generate_scenario(&self, map: &Map, name: String) -> Scenario {
         self.trips.generate_scenario(map, name)
     }
@@ -427,7 +451,7 @@ impl Sim {
                 let max_speed = match info.mode {
                     TripMode::Walk | TripMode::Transit => Some(person.ped_speed),
                     // TODO We should really search the vehicles and grab it from there
-                    TripMode::Drive => None,
+                    TripMode::Drive | TripMode::RideHail | TripMode::Freight => None,
                     // Assume just one bike
                     TripMode::Bike => {
                         person
@@ -474,7 +498,7 @@ impl Sim {
     pub fn get_draw_cars(&self, on: Traversable, map: &Map) -> Vec<DrawCarInput> {
         let mut results = Vec::new();
         if let Traversable::Lane(l) = on {
-            if map.get_l(l).is_parking() {
+            if map.get_l(l).is_parking() || map.get_l(l).is_loading_zone() {
                 return self.parking.get_draw_cars(l, map);
             }
             results.extend(self.parking.get_draw_cars_in_lots(l, map));
@@ -0,0 +1,53 @@
+use anyhow::Result;
+
+use geom::Time;
+use map_model::{Map, Path, PathConstraints, PathRequest, PathfinderCaching, RoutingParams};
+
+/// Paths depend on the time when conditional restrictions (like school streets or peak-hour bus
+/// gates) are in effect. Working out which ones apply means looking at every road, so the result
+/// is kept for a minute, since restrictions don't change more often than that.
+#[derive(Clone, Default)]
+pub(crate) struct ConditionalRestrictions {
+    ignore: bool,
+    /// The routing params at some minute
+    params: Option<(usize, RoutingParams)>,
+}
+
+impl ConditionalRestrictions {
+    pub fn new(ignore: bool) -> ConditionalRestrictions {
+        ConditionalRestrictions {
+            ignore,
+            params: None,
+        }
+    }
+
+    /// Calculates a path, respecting any conditional restrictions in effect at this time.
+    pub fn pathfind(&mut self, map: &Map, req: PathRequest, now: Time) -> Result<Path> {
+        // The walking graph doesn't use RoutingParams at all
+        if !self.ignore && req.constraints != PathConstraints::Pedestrian {
+            let params = self.routing_params_at(map, now);
+            if params != map.routing_params() {
+                return map.pathfind_with_params(req, params, PathfinderCaching::CacheCH);
+            }
+        }
+        map.pathfind(req)
+    }
+
+    /// The restrictions in effect may change after map edits.
+    pub fn clear(&mut self) {
+        self.params = None;
+    }
+
+    fn routing_params_at(&mut self, map: &Map, time: Time) -> &RoutingParams {
+        let minute = (time.inner_seconds() / 60.0) as usize;
+        if self
+            .params
+            .as_ref()
+            .map(|(m, _)| *m != minute)
+            .unwrap_or(true)
+        {
+            self.params = Some((minute, map.routing_params_at(time)));
+        }
+        &self.params.as_ref().unwrap().1
+    }
+}
@@ -7,12 +7,12 @@ use rand_xorshift::XorShiftRng;
 use abstutil::{prettyprint_usize, Counter, Timer};
 use geom::{Distance, Speed};
 use map_model::{BuildingID, Map, OffstreetParking, RoadID};
-use synthpop::{PersonSpec, Scenario, TripEndpoint, TripMode};
+use synthpop::{FreightVehicle, PersonSpec, Scenario, TripEndpoint, TripMode};
 
 use crate::make::fork_rng;
 use crate::{
     ParkingSpot, Sim, StartTripArgs, TripInfo, Vehicle, VehicleSpec, VehicleType, BIKE_LENGTH,
-    MAX_CAR_LENGTH, MIN_CAR_LENGTH,
+    DELIVERY_VAN_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH, TRUCK_LENGTH,
 };
 
 impl Sim {
@@ -57,6 +57,7 @@ impl Sim {
         timer.start_iter("trips for People", scenario.people.len());
         let mut parked_cars: Vec<(Vehicle, BuildingID)> = Vec::new();
         let mut schedule_trips = Vec::new();
+        let mut num_bad_ride_hails = 0;
         for p in &scenario.people {
             timer.next();
 
@@ -82,9 +83,18 @@ impl Sim {
                         modified: trip.modified,
                         cancellation_reason: if trip.cancelled {
                             Some("cancelled by ScenarioModifier".to_string())
+                        } else if trip.mode == TripMode::RideHail
+                            && !(matches!(trip.origin, TripEndpoint::Building(_))
+                                && matches!(trip.destination, TripEndpoint::Building(_)))
+                        {
+                            // The fleet only serves the curb in front of buildings. A
+                            // ScenarioModifier changing modes can produce these.
+                            num_bad_ride_hails += 1;
+                            Some("ride-hail trips must start and end at buildings".to_string())
                         } else {
                             None
                         },
+                        freight: trip.freight.clone(),
                     },
                     StartTripArgs {
                         retry_if_no_room,
@@ -94,6 +104,13 @@ impl Sim {
             }
         }
 
+        if num_bad_ride_hails > 0 {
+            warn!(
+                "Cancelling {} ride-hail trips that don't start and end at buildings",
+                prettyprint_usize(num_bad_ride_hails)
+            );
+        }
+
         // parked_cars is stable over map edits, so don't fork.
         parked_cars.shuffle(rng);
         seed_parked_cars(parked_cars, self, map, rng, timer);
@@ -116,13 +133,18 @@ fn get_vehicles(
     let mut vehicle_foreach_trip = Vec::new();
 
     let mut bike_idx = None;
+    // Freight vehicles are never parked between trips. They appear at a border or leave from a
+    // depot, so one of each type per person is enough.
+    let mut van_idx = None;
+    let mut truck_idx = None;
     // For each indexed car, is it parked somewhere, or off-map?
     let mut car_locations: Vec<(usize, Option<BuildingID>)> = Vec::new();
 
     // TODO If the trip is cancelled, this should be affected...
     for trip in &person.trips {
         let use_for_trip = match trip.mode {
-            TripMode::Walk | TripMode::Transit => None,
+            // Ride-hail trips use a vehicle from the fleet, not one owned by the person
+            TripMode::Walk | TripMode::Transit | TripMode::RideHail => None,
             TripMode::Bike => {
                 if bike_idx.is_none() {
                     bike_idx = Some(vehicle_specs.len());
@@ -130,6 +152,18 @@ fn get_vehicles(
                 }
                 bike_idx
             }
+            TripMode::Freight => {
+                let kind = trip.freight.as_ref().unwrap().vehicle;
+                let idx = match kind {
+                    FreightVehicle::Van => &mut van_idx,
+                    FreightVehicle::Truck => &mut truck_idx,
+                };
+                if idx.is_none() {
+                    *idx = Some(vehicle_specs.len());
+                    vehicle_specs.push(freight_vehicle(kind));
+                }
+                *idx
+            }
             TripMode::Drive => {
                 let need_parked_at = match trip.origin {
                     TripEndpoint::Building(b) => Some(b),
@@ -176,6 +210,7 @@ fn get_vehicles(
         if bike_idx.is_some() {
             n -= 1;
         }
+        n -= van_idx.iter().chain(truck_idx.iter()).count();
         if n > 1 {
             println!("Someone needs {} cars", n);
         }
@@ -190,10 +225,28 @@ fn get_vehicles(
 
 fn rand_car(rng: &mut XorShiftRng) -> VehicleSpec {
     let length = rand_dist(rng, MIN_CAR_LENGTH, MAX_CAR_LENGTH);
+    let (max_accel, max_decel) = VehicleType::Car.default_accel_decel();
     VehicleSpec {
         vehicle_type: VehicleType::Car,
         length,
         max_speed: None,
+        max_accel,
+        max_decel,
+    }
+}
+
+fn freight_vehicle(kind: FreightVehicle) -> VehicleSpec {
+    let (vehicle_type, length) = match kind {
+        FreightVehicle::Van => (VehicleType::Delivery, DELIVERY_VAN_LENGTH),
+        FreightVehicle::Truck => (VehicleType::Truck, TRUCK_LENGTH),
+    };
+    let (max_accel, max_decel) = vehicle_type.default_accel_decel();
+    VehicleSpec {
+        vehicle_type,
+        length,
+        max_speed: None,
+        max_accel,
+        max_decel,
     }
 }
 
@@ -203,10 +256,13 @@ fn rand_bike(rng: &mut XorShiftRng) -> VehicleSpec {
         Speed::miles_per_hour(8.0),
         map_model::MAX_BIKE_SPEED,
     ));
+    let (max_accel, max_decel) = VehicleType::Bike.default_accel_decel();
     VehicleSpec {
         vehicle_type: VehicleType::Bike,
         length: BIKE_LENGTH,
         max_speed,
+        max_accel,
+        max_decel,
     }
 }
 
@@ -0,0 +1,689 @@
+//! Flattens the series in Analytics into tidy tables, with one row per observation, so they can be
+//! analyzed with external tools like pandas or DuckDB. Map objects are identified by their OSM IDs
+//! (or GTFS IDs for transit), which stay stable across map rebuilds, instead of the internal IDs.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use geom::{Duration, Time};
+use map_model::{IntersectionID, LaneID, Map, MovementID, TransitRouteID, TransitStopID};
+
+use crate::{AgentType, Analytics, TripPhaseType};
+
+/// A table with a fixed set of typed columns
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<(String, ColumnType)>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    Text,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+    /// A missing value
+    Null,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableFormat {
+    CSV,
+    Parquet,
+}
+
+impl FromStr for TableFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(x: &str) -> Result<TableFormat> {
+        match x {
+            "csv" => Ok(TableFormat::CSV),
+            "parquet" => Ok(TableFormat::Parquet),
+            _ => bail!("unknown table format {}; use csv or parquet", x),
+        }
+    }
+}
+
+impl TableFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TableFormat::CSV => "csv",
+            TableFormat::Parquet => "parquet",
+        }
+    }
+}
+
+impl Table {
+    fn new(name: &str, columns: Vec<(&str, ColumnType)>) -> Table {
+        Table {
+            name: name.to_string(),
+            columns: columns
+                .into_iter()
+                .map(|(name, col_type)| (name.to_string(), col_type))
+                .collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, row: Vec<Value>) {
+        assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+    }
+
+    pub fn write(&self, path: &str, format: TableFormat) -> Result<()> {
+        match format {
+            TableFormat::CSV => self.write_csv(path),
+            TableFormat::Parquet => self.write_parquet(path),
+        }
+    }
+
+    pub fn write_csv(&self, path: &str) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(self.columns.iter().map(|(name, _)| name))?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(|value| match value {
+                Value::Int(x) => x.to_string(),
+                Value::Float(x) => x.to_string(),
+                Value::Bool(x) => x.to_string(),
+                Value::Text(x) => x.clone(),
+                Value::Null => String::new(),
+            }))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    pub fn write_parquet(&self, _: &str) -> Result<()> {
+        bail!("The sim crate was built without the parquet feature")
+    }
+
+    /// Every column is optional, so missing values are written as nulls.
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, path: &str) -> Result<()> {
+        use std::sync::Arc;
+
+        use parquet::column::writer::ColumnWriter;
+        use parquet::file::properties::WriterProperties;
+        use parquet::file::writer::{FileWriter, SerializedFileWriter};
+        use parquet::schema::parser::parse_message_type;
+
+        let mut message = format!("message {} {{\n", self.name);
+        for (name, col_type) in &self.columns {
+            message.push_str(&match col_type {
+                ColumnType::Int => format!("  OPTIONAL INT64 {};\n", name),
+                ColumnType::Float => format!("  OPTIONAL DOUBLE {};\n", name),
+                ColumnType::Bool => format!("  OPTIONAL BOOLEAN {};\n", name),
+                ColumnType::Text => format!("  OPTIONAL BYTE_ARRAY {} (UTF8);\n", name),
+            });
+        }
+        message.push('}');
+
+        let schema = Arc::new(parse_message_type(&message)?);
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer = SerializedFileWriter::new(std::fs::File::create(path)?, schema, props)?;
+        let mut row_group = writer.next_row_group()?;
+        let mut idx = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            let cells = self.rows.iter().map(|row| &row[idx]);
+            let def_levels: Vec<i16> = cells
+                .clone()
+                .map(|x| if *x == Value::Null { 0 } else { 1 })
+                .collect();
+            match column {
+                ColumnWriter::Int64ColumnWriter(ref mut w) => {
+                    let values: Vec<i64> = cells
+                        .filter_map(|x| match x {
+                            Value::Int(x) => Some(*x),
+                            _ => None,
+                        })
+                        .collect();
+                    w.write_batch(&values, Some(&def_levels), None)?;
+                }
+                ColumnWriter::DoubleColumnWriter(ref mut w) => {
+                    let values: Vec<f64> = cells
+                        .filter_map(|x| match x {
+                            Value::Float(x) => Some(*x),
+                            _ => None,
+                        })
+                        .collect();
+                    w.write_batch(&values, Some(&def_levels), None)?;
+                }
+                ColumnWriter::BoolColumnWriter(ref mut w) => {
+                    let values: Vec<bool> = cells
+                        .filter_map(|x| match x {
+                            Value::Bool(x) => Some(*x),
+                            _ => None,
+                        })
+                        .collect();
+                    w.write_batch(&values, Some(&def_levels), None)?;
+                }
+                ColumnWriter::ByteArrayColumnWriter(ref mut w) => {
+                    // parquet::data_type::ByteArray isn't public without the experimental
+                    // feature, so let write_batch infer it
+                    let values: Vec<_> = cells
+                        .filter_map(|x| match x {
+                            Value::Text(x) => Some(x.as_str().into()),
+                            _ => None,
+                        })
+                        .collect();
+                    w.write_batch(&values, Some(&def_levels), None)?;
+                }
+                _ => unreachable!(),
+            }
+            row_group.close_column(column)?;
+            idx += 1;
+        }
+        writer.close_row_group(row_group)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+impl Analytics {
+    /// Flatten every series into a tidy table.
+    pub fn to_tables(&self, map: &Map) -> Vec<Table> {
+        use ColumnType::*;
+
+        let mut tables = Vec::new();
+
+        {
+            let mut t = Table::new(
+                "road_thruput",
+                vec![
+                    ("osm_way_id", Int),
+                    ("osm_node1", Int),
+                    ("osm_node2", Int),
+                    ("agent_type", Text),
+                    ("hour", Int),
+                    ("count", Int),
+                ],
+            );
+            for ((r, agent_type, hour), count) in &self.road_thruput.counts {
+                let mut row = road_ids(map, *r);
+                row.extend(vec![
+                    agent(*agent_type),
+                    Value::Int(*hour as i64),
+                    Value::Int(*count as i64),
+                ]);
+                t.push(row);
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "intersection_thruput",
+                vec![
+                    ("osm_node_id", Int),
+                    ("agent_type", Text),
+                    ("hour", Int),
+                    ("count", Int),
+                ],
+            );
+            for ((i, agent_type, hour), count) in &self.intersection_thruput.counts {
+                t.push(vec![
+                    intersection_id(map, *i),
+                    agent(*agent_type),
+                    Value::Int(*hour as i64),
+                    Value::Int(*count as i64),
+                ]);
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "traffic_signal_thruput",
+                movement_columns(vec![("agent_type", Text), ("hour", Int), ("count", Int)]),
+            );
+            for ((m, agent_type, hour), count) in &self.traffic_signal_thruput.counts {
+                let mut row = movement_ids(map, m.i, m.idx);
+                row.extend(vec![
+                    agent(*agent_type),
+                    Value::Int(*hour as i64),
+                    Value::Int(*count as i64),
+                ]);
+                t.push(row);
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "intersection_delays",
+                movement_columns(vec![
+                    ("time_seconds", Float),
+                    ("delay_seconds", Float),
+                    ("agent_type", Text),
+                ]),
+            );
+            for (i, delays) in &self.intersection_delays {
+                for (idx, time, delay, agent_type) in delays {
+                    let mut row = movement_ids(map, *i, *idx);
+                    row.extend(vec![time_value(*time), seconds(*delay), agent(*agent_type)]);
+                    t.push(row);
+                }
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "trip_log",
+                vec![
+                    ("trip_id", Int),
+                    ("time_seconds", Float),
+                    ("phase", Text),
+                    ("route_gtfs_id", Text),
+                    ("stop_gtfs_id", Text),
+                ],
+            );
+            for (time, trip, _, phase) in &self.trip_log {
+                let (route, stop) = match phase {
+                    TripPhaseType::WaitingForBus(route, stop)
+                    | TripPhaseType::RidingBus(route, stop, _) => {
+                        (route_id(map, *route), stop_id(map, *stop))
+                    }
+                    _ => (Value::Null, Value::Null),
+                };
+                t.push(vec![
+                    Value::Int(trip.0 as i64),
+                    time_value(*time),
+                    Value::Text(phase_name(phase).to_string()),
+                    route,
+                    stop,
+                ]);
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "finished_trips",
+                vec![
+                    ("trip_id", Int),
+                    ("time_seconds", Float),
+                    ("mode", Text),
+                    ("duration_seconds", Float),
+                ],
+            );
+            for (time, trip, mode, duration) in &self.finished_trips {
+                t.push(vec![
+                    Value::Int(trip.0 as i64),
+                    time_value(*time),
+                    Value::Text(format!("{:?}", mode)),
+                    duration.map(seconds).unwrap_or(Value::Null),
+                ]);
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "bus_arrivals",
+                vec![
+                    ("time_seconds", Float),
+                    ("vehicle_id", Int),
+                    ("route_gtfs_id", Text),
+                    ("stop_gtfs_id", Text),
+                ],
+            );
+            for (time, car, route, stop) in &self.bus_arrivals {
+                t.push(vec![
+                    time_value(*time),
+                    Value::Int(car.id as i64),
+                    route_id(map, *route),
+                    stop_id(map, *stop),
+                ]);
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "passengers_boarding",
+                vec![
+                    ("stop_gtfs_id", Text),
+                    ("route_gtfs_id", Text),
+                    ("time_seconds", Float),
+                    ("wait_seconds", Float),
+                ],
+            );
+            for (stop, list) in &self.passengers_boarding {
+                for (time, route, wait) in list {
+                    t.push(vec![
+                        stop_id(map, *stop),
+                        route_id(map, *route),
+                        time_value(*time),
+                        seconds(*wait),
+                    ]);
+                }
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "passengers_alighting",
+                vec![
+                    ("stop_gtfs_id", Text),
+                    ("route_gtfs_id", Text),
+                    ("time_seconds", Float),
+                ],
+            );
+            for (stop, list) in &self.passengers_alighting {
+                for (time, route) in list {
+                    t.push(vec![
+                        stop_id(map, *stop),
+                        route_id(map, *route),
+                        time_value(*time),
+                    ]);
+                }
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "transit_lateness",
+                vec![
+                    ("stop_gtfs_id", Text),
+                    ("route_gtfs_id", Text),
+                    ("time_seconds", Float),
+                    ("lateness_seconds", Float),
+                ],
+            );
+            for (stop, list) in &self.transit_lateness {
+                for (time, route, lateness) in list {
+                    t.push(vec![
+                        stop_id(map, *stop),
+                        route_id(map, *route),
+                        time_value(*time),
+                        seconds(*lateness),
+                    ]);
+                }
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "parking_lane_changes",
+                vec![
+                    ("osm_way_id", Int),
+                    ("osm_node1", Int),
+                    ("osm_node2", Int),
+                    ("lane_index", Int),
+                    ("time_seconds", Float),
+                    ("filled", Bool),
+                ],
+            );
+            for (l, changes) in &self.parking_lane_changes {
+                for (time, filled) in changes {
+                    let mut row = lane_ids(map, *l);
+                    row.extend(vec![time_value(*time), Value::Bool(*filled)]);
+                    t.push(row);
+                }
+            }
+            tables.push(t);
+        }
+
+        {
+            let mut t = Table::new(
+                "parking_lot_changes",
+                vec![("osm_id", Text), ("time_seconds", Float), ("filled", Bool)],
+            );
+            for (pl, changes) in &self.parking_lot_changes {
+                let osm_id = map
+                    .maybe_get_pl(*pl)
+                    .map(|pl| Value::Text(pl.osm_id.to_string()))
+                    .unwrap_or(Value::Null);
+                for (time, filled) in changes {
+                    t.push(vec![
+                        osm_id.clone(),
+                        time_value(*time),
+                        Value::Bool(*filled),
+                    ]);
+                }
+            }
+            tables.push(t);
+        }
+
+        tables
+    }
+
+    /// Write every table into a directory, returning the paths written.
+    pub fn export_tables(&self, map: &Map, dir: &str, format: TableFormat) -> Result<Vec<String>> {
+        fs_err::create_dir_all(dir)?;
+        let mut paths = Vec::new();
+        for table in self.to_tables(map) {
+            let path = format!("{}/{}.{}", dir, table.name, format.extension());
+            table.write(&path, format)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+fn agent(agent_type: AgentType) -> Value {
+    Value::Text(format!("{:?}", agent_type))
+}
+
+fn time_value(time: Time) -> Value {
+    Value::Float(time.inner_seconds())
+}
+
+fn seconds(duration: Duration) -> Value {
+    Value::Float(duration.inner_seconds())
+}
+
+// The map might've been edited since the analytics were recorded, so be careful about looking up
+// IDs.
+
+fn road_ids(map: &Map, r: map_model::RoadID) -> Vec<Value> {
+    match map.maybe_get_r(r) {
+        Some(r) => vec![
+            Value::Int(r.orig_id.osm_way_id.0),
+            Value::Int(r.orig_id.i1.0),
+            Value::Int(r.orig_id.i2.0),
+        ],
+        None => vec![Value::Null, Value::Null, Value::Null],
+    }
+}
+
+fn lane_ids(map: &Map, l: LaneID) -> Vec<Value> {
+    let mut row = road_ids(map, l.road);
+    row.push(Value::Int(l.offset as i64));
+    row
+}
+
+fn intersection_id(map: &Map, i: IntersectionID) -> Value {
+    map.maybe_get_i(i)
+        .map(|i| Value::Int(i.orig_id.0))
+        .unwrap_or(Value::Null)
+}
+
+fn movement_columns(mut extra: Vec<(&str, ColumnType)>) -> Vec<(&str, ColumnType)> {
+    let mut columns = vec![
+        ("osm_node_id", ColumnType::Int),
+        ("from_osm_way_id", ColumnType::Int),
+        ("to_osm_way_id", ColumnType::Int),
+        ("crosswalk", ColumnType::Bool),
+    ];
+    columns.append(&mut extra);
+    columns
+}
+
+/// Movements are stored as an index into the intersection's movements
+fn movement_ids(map: &Map, i: IntersectionID, idx: u8) -> Vec<Value> {
+    let movement: Option<&MovementID> = map
+        .maybe_get_i(i)
+        .and_then(|i| i.movements.keys().nth(idx as usize));
+    match movement {
+        Some(m) => vec![
+            intersection_id(map, i),
+            Value::Int(map.get_r(m.from.road).orig_id.osm_way_id.0),
+            Value::Int(map.get_r(m.to.road).orig_id.osm_way_id.0),
+            Value::Bool(m.crosswalk),
+        ],
+        None => vec![
+            intersection_id(map, i),
+            Value::Null,
+            Value::Null,
+            Value::Null,
+        ],
+    }
+}
+
+fn route_id(map: &Map, route: TransitRouteID) -> Value {
+    map.maybe_get_tr(route)
+        .map(|tr| Value::Text(tr.gtfs_id.clone()))
+        .unwrap_or(Value::Null)
+}
+
+fn stop_id(map: &Map, stop: TransitStopID) -> Value {
+    map.maybe_get_ts(stop)
+        .map(|ts| Value::Text(ts.gtfs_id.clone()))
+        .unwrap_or(Value::Null)
+}
+
+fn phase_name(phase: &TripPhaseType) -> &'static str {
+    match phase {
+        TripPhaseType::Driving => "Driving",
+        TripPhaseType::Walking => "Walking",
+        TripPhaseType::Biking => "Biking",
+        TripPhaseType::Parking => "Parking",
+        TripPhaseType::WaitingForBus(_, _) => "WaitingForBus",
+        TripPhaseType::RidingBus(_, _, _) => "RidingBus",
+        TripPhaseType::WaitingForRideHail => "WaitingForRideHail",
+        TripPhaseType::RidingRideHail(_) => "RidingRideHail",
+        TripPhaseType::Delivering(_) => "Delivering",
+        TripPhaseType::Cancelled => "Cancelled",
+        TripPhaseType::Finished => "Finished",
+        TripPhaseType::DelayedStart => "DelayedStart",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Table {
+        let mut t = Table::new(
+            "example",
+            vec![
+                ("id", ColumnType::Int),
+                ("speed", ColumnType::Float),
+                ("blocked", ColumnType::Bool),
+                ("name", ColumnType::Text),
+            ],
+        );
+        t.push(vec![
+            Value::Int(-42),
+            Value::Float(1.5),
+            Value::Bool(true),
+            Value::Text("Broadway, northbound".to_string()),
+        ]);
+        t.push(vec![
+            Value::Int(7),
+            Value::Null,
+            Value::Bool(false),
+            Value::Null,
+        ]);
+        t
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("sim_tables_{}_{}", std::process::id(), name))
+            .display()
+            .to_string()
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let table = example();
+        let path = temp_path("example.csv");
+        table.write(&path, TableFormat::CSV).unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["id", "speed", "blocked", "name"]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(|x| x.unwrap()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], vec!["-42", "1.5", "true", "Broadway, northbound"]);
+        // Missing values are empty
+        assert_eq!(rows[1], vec!["7", "", "false", ""]);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_round_trip() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::Field;
+
+        let table = example();
+        let path = temp_path("example.parquet");
+        table.write(&path, TableFormat::Parquet).unwrap();
+
+        let reader = SerializedFileReader::try_from(path.as_str()).unwrap();
+        let rows: Vec<Vec<(String, Field)>> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                row.get_column_iter()
+                    .map(|(name, field)| (name.clone(), field.clone()))
+                    .collect()
+            })
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+
+        let named = |fields: Vec<Field>| -> Vec<(String, Field)> {
+            vec!["id", "speed", "blocked", "name"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .zip(fields)
+                .collect()
+        };
+        assert_eq!(
+            rows,
+            vec![
+                named(vec![
+                    Field::Long(-42),
+                    Field::Double(1.5),
+                    Field::Bool(true),
+                    Field::Str("Broadway, northbound".to_string()),
+                ]),
+                named(vec![
+                    Field::Long(7),
+                    Field::Null,
+                    Field::Bool(false),
+                    Field::Null,
+                ]),
+            ]
+        );
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    #[test]
+    fn test_parquet_without_feature() {
+        assert!(example()
+            .write(&temp_path("example.parquet"), TableFormat::Parquet)
+            .is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::hash::{Hash, Hasher};
+
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+
+use crate::scheduler::CommandType;
+use crate::{AgentID, Event};
+
+/// Records a compact fingerprint of every command the simulation processes. Two runs of the same
+/// scenario are deterministic, so comparing their traces finds the first step where they diverge,
+/// after a code change or map edit.
+///
+/// This is expensive, since the state of the agent involved in every step is serialized and
+/// hashed. Only use it for debugging. FNV is used instead of the std hasher, whose algorithm isn't
+/// guaranteed to stay the same between Rust releases, so traces from different builds compare.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimTrace {
+    steps: Vec<TraceStep>,
+    /// If present, halt the simulation after recording this step.
+    stop_at_step: Option<usize>,
+    /// When the simulation halts at `stop_at_step`, a dump of the agent involved in that step.
+    pub dump: Option<(usize, String)>,
+    /// The command-line flags used to set up the simulation, so the run can be replayed later.
+    /// The caller fills this out.
+    pub sim_flags: Vec<String>,
+
+    /// The hash of events that happened since the last step. This is the FNV state, since
+    /// FnvHasher itself isn't Clone.
+    #[serde(skip_serializing, skip_deserializing)]
+    pending_events: u64,
+}
+
+/// One command processed by the simulation
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub time: Time,
+    cmd: CommandType,
+    /// Covers the command, the state of the agent afterwards, and any events produced
+    hash: u64,
+}
+
+impl TraceStep {
+    pub fn describe_command(&self) -> String {
+        format!("{:?}", self.cmd)
+    }
+
+    /// The agent updated or spawned by this step, if any
+    pub fn agent(&self) -> Option<AgentID> {
+        self.cmd.agent()
+    }
+}
+
+/// Where two traces first disagree. If one trace ended before the other, its step is None.
+pub struct Divergence {
+    pub step: usize,
+    pub ours: Option<TraceStep>,
+    pub theirs: Option<TraceStep>,
+}
+
+impl SimTrace {
+    pub(crate) fn new(stop_at_step: Option<usize>) -> SimTrace {
+        SimTrace {
+            steps: Vec::new(),
+            stop_at_step,
+            dump: None,
+            sim_flags: Vec::new(),
+            pending_events: FnvHasher::default().finish(),
+        }
+    }
+
+    pub(crate) fn event(&mut self, ev: &Event) {
+        let mut hasher = FnvHasher::with_key(self.pending_events);
+        format!("{:?}", ev).hash(&mut hasher);
+        self.pending_events = hasher.finish();
+    }
+
+    /// Returns true if the simulation should halt now.
+    pub(crate) fn record_step(
+        &mut self,
+        time: Time,
+        cmd: CommandType,
+        agent_state: Option<String>,
+    ) -> bool {
+        let mut hasher = FnvHasher::with_key(std::mem::replace(
+            &mut self.pending_events,
+            FnvHasher::default().finish(),
+        ));
+        time.inner_seconds().to_bits().hash(&mut hasher);
+        cmd.hash(&mut hasher);
+        agent_state.hash(&mut hasher);
+        self.steps.push(TraceStep {
+            time,
+            cmd,
+            hash: hasher.finish(),
+        });
+        self.stop_at_step == Some(self.steps.len() - 1)
+    }
+
+    pub fn steps(&self) -> &Vec<TraceStep> {
+        &self.steps
+    }
+
+    pub fn find_divergence(&self, other: &SimTrace) -> Option<Divergence> {
+        for step in 0..self.steps.len().max(other.steps.len()) {
+            let ours = self.steps.get(step);
+            let theirs = other.steps.get(step);
+            if ours != theirs {
+                return Some(Divergence {
+                    step,
+                    ours: ours.cloned(),
+                    theirs: theirs.cloned(),
+                });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::Duration;
+
+    use super::*;
+    use crate::AlertLocation;
+
+    /// Records a step per second, each producing an alert with the given message
+    fn trace(events: Vec<&str>) -> SimTrace {
+        let mut trace = SimTrace::new(None);
+        for (idx, msg) in events.into_iter().enumerate() {
+            trace.event(&Event::Alert(AlertLocation::Nil, msg.to_string()));
+            trace.record_step(
+                Time::START_OF_DAY + Duration::seconds(idx as f64),
+                CommandType::Callback,
+                None,
+            );
+        }
+        trace
+    }
+
+    #[test]
+    fn test_find_divergence() {
+        let a = trace(vec!["a", "b", "c"]);
+        assert!(a.find_divergence(&a).is_none());
+        assert!(a.find_divergence(&trace(vec!["a", "b", "c"])).is_none());
+
+        // The commands and times are the same; only the events differ
+        let d = a.find_divergence(&trace(vec!["a", "x", "c"])).unwrap();
+        assert_eq!(d.step, 1);
+        assert_eq!(
+            d.ours.unwrap().time,
+            Time::START_OF_DAY + Duration::seconds(1.0)
+        );
+        assert!(d.theirs.is_some());
+
+        // One trace is a prefix of the other
+        let d = a.find_divergence(&trace(vec!["a", "b"])).unwrap();
+        assert_eq!(d.step, 2);
+        assert!(d.ours.is_some());
+        assert!(d.theirs.is_none());
+        let d = trace(vec!["a", "b"]).find_divergence(&a).unwrap();
+        assert_eq!(d.step, 2);
+        assert!(d.ours.is_none());
+        assert!(d.theirs.is_some());
+    }
+}
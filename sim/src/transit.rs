@@ -3,18 +3,25 @@ use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::Time;
+use geom::{Duration, Time};
 use map_model::{Map, Path, PathRequest, Position, TransitRoute, TransitRouteID, TransitStopID};
 
 use crate::sim::Ctx;
 use crate::{
-    AgentID, CarID, DrivingSimState, Event, PedestrianID, PersonID, Router, TripID, TripManager,
+    AgentID, CarID, DrivingSimState, Event, PedestrianID, PersonID, Problem, Router, TripManager,
     TripPhaseType, UnzoomedAgent, VehicleType, WalkingSimState,
 };
 
 // These index stops along a route, not stops along a single sidewalk.
 type StopIdx = usize;
 
+/// The minimum time a transit vehicle waits at a stop, even if nobody gets on or off.
+const MIN_DWELL_TIME: Duration = Duration::const_seconds(10.0);
+/// How much longer a transit vehicle waits at a stop for each passenger boarding
+const TIME_PER_BOARDING: Duration = Duration::const_seconds(2.5);
+/// How much longer a transit vehicle waits at a stop for each passenger alighting
+const TIME_PER_ALIGHTING: Duration = Duration::const_seconds(1.5);
+
 #[derive(Serialize, Deserialize, Clone)]
 struct Stop {
     id: TransitStopID,
@@ -36,7 +43,14 @@ struct Bus {
     route: TransitRouteID,
     /// Where does each passenger want to deboard?
     passengers: Vec<(PersonID, Option<TransitStopID>)>,
+    /// The maximum number of passengers
+    capacity: usize,
+    /// If the route has a schedule, when this vehicle should arrive at each stop
+    schedule: Option<Vec<Time>>,
     state: BusState,
+    /// How much longer to wait at the current stop, for passengers who boarded after the vehicle
+    /// arrived
+    late_boarding: Duration,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -68,22 +82,52 @@ pub(crate) struct TransitSimState {
     )]
     peds_waiting:
         BTreeMap<TransitStopID, Vec<(PedestrianID, TransitRouteID, Option<TransitStopID>, Time)>>,
+    /// Overrides the default capacity of vehicles serving some routes
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    route_capacity: BTreeMap<TransitRouteID, usize>,
+    /// The schedule of vehicles that haven't spawned yet
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    schedules: BTreeMap<CarID, Vec<Time>>,
 
     events: Vec<Event>,
 }
 
 impl TransitSimState {
-    pub fn new(map: &Map) -> TransitSimState {
+    /// `capacities` overrides the default capacity of vehicles serving routes with some short
+    /// name.
+    pub fn new(map: &Map, capacities: &[(String, usize)]) -> TransitSimState {
         // Keep this filled out always so get_passengers can return &Vec without a hassle
         let mut peds_waiting = BTreeMap::new();
         for ts in map.all_transit_stops().keys() {
             peds_waiting.insert(*ts, Vec::new());
         }
 
+        let mut route_capacity = BTreeMap::new();
+        for (name, capacity) in capacities {
+            let mut found = false;
+            for route in map.all_transit_routes() {
+                if &route.short_name == name {
+                    route_capacity.insert(route.id, *capacity);
+                    found = true;
+                }
+            }
+            if !found {
+                warn!("No transit route named {} to set the capacity of", name);
+            }
+        }
+
         TransitSimState {
             buses: BTreeMap::new(),
             routes: BTreeMap::new(),
             peds_waiting,
+            route_capacity,
+            schedules: BTreeMap::new(),
             events: Vec::new(),
         }
     }
@@ -153,22 +197,43 @@ impl TransitSimState {
         self.routes[&bus_route.id].start.clone()
     }
 
+    /// Called when a vehicle for one of the route's spawn times is about to spawn, maybe after
+    /// some delay. It follows that run's schedule, unless the times have been edited and there's
+    /// no schedule to follow.
+    pub fn bus_scheduled(&mut self, bus: CarID, route: &TransitRoute, run: usize) {
+        if route.spawn_times != route.orig_spawn_times {
+            return;
+        }
+        if let Some(times) = route.scheduled_arrivals.get(run) {
+            self.schedules.insert(bus, times.clone());
+        }
+    }
+
     pub fn bus_created(&mut self, bus: CarID, r: TransitRouteID) {
+        let schedule = self.schedules.remove(&bus);
         let route = self.routes.get_mut(&r).unwrap();
         route.active_vehicles.insert(bus);
+        let capacity = self
+            .route_capacity
+            .get(&r)
+            .cloned()
+            .unwrap_or_else(|| bus.vehicle_type.default_passenger_capacity());
         self.buses.insert(
             bus,
             Bus {
                 car: bus,
                 route: r,
                 passengers: Vec::new(),
+                capacity,
+                schedule,
                 state: BusState::DrivingToStop(0),
+                late_boarding: Duration::ZERO,
             },
         );
     }
 
-    /// If true, the bus is idling. If false, the bus actually arrived at a border and should now
-    /// vanish.
+    /// If Some, the bus idles for this long, letting passengers board and alight. If None, the bus
+    /// actually arrived at a border and should now vanish.
     pub fn bus_arrived_at_stop(
         &mut self,
         now: Time,
@@ -176,19 +241,23 @@ impl TransitSimState {
         trips: &mut TripManager,
         walking: &mut WalkingSimState,
         ctx: &mut Ctx,
-    ) -> bool {
+    ) -> Option<Duration> {
         let mut bus = self.buses.get_mut(&id).unwrap();
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
                 bus.state = BusState::AtStop(stop_idx);
                 let stop1 = self.routes[&bus.route].stops[stop_idx].id;
+                let lateness = bus.schedule.as_ref().map(|times| now - times[stop_idx]);
                 self.events
-                    .push(Event::BusArrivedAtStop(id, bus.route, stop1));
+                    .push(Event::BusArrivedAtStop(id, bus.route, stop1, lateness));
 
                 // Deboard existing passengers.
                 let mut still_riding = Vec::new();
+                let mut num_alighting = 0;
+                let mut num_boarding = 0;
                 for (person, maybe_stop2) in bus.passengers.drain(..) {
                     if Some(stop1) == maybe_stop2 {
+                        num_alighting += 1;
                         trips.person_left_bus(now, person, bus.car, ctx);
                         self.events.push(Event::PassengerAlightsTransit(
                             person, bus.car, bus.route, stop1,
@@ -204,7 +273,17 @@ impl TransitSimState {
                 for (ped, route, maybe_stop2, started_waiting) in
                     self.peds_waiting.remove(&stop1).unwrap()
                 {
-                    if bus.route == route {
+                    if bus.route == route && bus.passengers.len() >= bus.capacity {
+                        // The vehicle is full; they'll have to wait for the next one.
+                        if let Some(trip) = trips.agent_to_trip(AgentID::Pedestrian(ped)) {
+                            self.events.push(Event::ProblemEncountered(
+                                trip,
+                                Problem::DeniedBoarding(stop1, route),
+                            ));
+                        }
+                        still_waiting.push((ped, route, maybe_stop2, started_waiting));
+                    } else if bus.route == route {
+                        num_boarding += 1;
                         let (trip, person) = trips.ped_boarded_bus(
                             now,
                             ped,
@@ -244,7 +323,10 @@ impl TransitSimState {
                     }
                 }
                 self.peds_waiting.insert(stop1, still_waiting);
-                true
+                Some(MIN_DWELL_TIME.max(
+                    (num_boarding as f64) * TIME_PER_BOARDING
+                        + (num_alighting as f64) * TIME_PER_ALIGHTING,
+                ))
             }
             BusState::DrivingOffMap => {
                 self.routes
@@ -263,7 +345,7 @@ impl TransitSimState {
                     }
                     trips.transit_rider_reached_border(now, person, id, ctx);
                 }
-                false
+                None
             }
             BusState::AtStop(_) | BusState::Done => unreachable!(),
         }
@@ -276,8 +358,13 @@ impl TransitSimState {
             BusState::DrivingToStop(_) | BusState::DrivingOffMap | BusState::Done => unreachable!(),
             BusState::AtStop(stop_idx) => {
                 let stop = &route.stops[stop_idx];
-                self.events
-                    .push(Event::BusDepartedFromStop(id, bus.route, stop.id));
+                self.events.push(Event::BusDepartedFromStop(
+                    id,
+                    bus.route,
+                    stop.id,
+                    bus.passengers.len(),
+                    bus.capacity,
+                ));
                 if let Some(path) = stop.next_stop.clone() {
                     bus.state = BusState::DrivingToStop(stop_idx + 1);
                     Router::follow_bus_route(id, path)
@@ -300,28 +387,43 @@ impl TransitSimState {
         }
     }
 
-    /// Returns the bus if the pedestrian boarded immediately.
+    /// If a vehicle on the route is already at the stop and has room, the pedestrian boards it
+    /// right away, and the vehicle waits a bit longer. Otherwise, they start waiting.
     pub fn ped_waiting_for_bus(
         &mut self,
         now: Time,
         ped: PedestrianID,
-        trip: TripID,
-        person: PersonID,
         stop1: TransitStopID,
         route_id: TransitRouteID,
         maybe_stop2: Option<TransitStopID>,
+        trips: &mut TripManager,
+        walking: &mut WalkingSimState,
         map: &Map,
-    ) -> Option<CarID> {
+    ) {
         assert!(Some(stop1) != maybe_stop2);
         if let Some(route) = self.routes.get(&route_id) {
-            for bus in &route.active_vehicles {
-                if let BusState::AtStop(idx) = self.buses[bus].state {
+            for id in &route.active_vehicles {
+                let bus = self.buses.get_mut(id).unwrap();
+                if let BusState::AtStop(idx) = bus.state {
                     if route.stops[idx].id == stop1 {
-                        self.buses
-                            .get_mut(bus)
-                            .unwrap()
-                            .passengers
-                            .push((person, maybe_stop2));
+                        if bus.passengers.len() >= bus.capacity {
+                            if let Some(trip) = trips.agent_to_trip(AgentID::Pedestrian(ped)) {
+                                self.events.push(Event::ProblemEncountered(
+                                    trip,
+                                    Problem::DeniedBoarding(stop1, route_id),
+                                ));
+                            }
+                            continue;
+                        }
+                        let (trip, person) =
+                            trips.ped_boarded_bus(now, ped, bus.car, Duration::ZERO, walking);
+                        self.events.push(Event::PassengerBoardsTransit(
+                            person,
+                            bus.car,
+                            route_id,
+                            stop1,
+                            Duration::ZERO,
+                        ));
                         self.events.push(Event::TripPhaseStarting(
                             trip,
                             person,
@@ -332,11 +434,13 @@ impl TransitSimState {
                                 } else {
                                     route.end_at_border.as_ref().unwrap().get_req().end
                                 },
-                                bus.vehicle_type.to_constraints(),
+                                bus.car.vehicle_type.to_constraints(),
                             )),
-                            TripPhaseType::RidingBus(route_id, stop1, *bus),
+                            TripPhaseType::RidingBus(route_id, stop1, bus.car),
                         ));
-                        return Some(*bus);
+                        bus.passengers.push((person, maybe_stop2));
+                        bus.late_boarding += TIME_PER_BOARDING;
+                        return;
                     }
                 }
             }
@@ -351,7 +455,16 @@ impl TransitSimState {
             .get_mut(&stop1)
             .unwrap()
             .push((ped, route_id, maybe_stop2, now));
-        None
+    }
+
+    /// When a transit vehicle is ready to leave a stop, it first waits longer for anybody who
+    /// boarded late. Returns None for other vehicles, or if nobody boarded late.
+    pub fn extra_dwell_time(&mut self, id: CarID) -> Option<Duration> {
+        let bus = self.buses.get_mut(&id)?;
+        if bus.late_boarding == Duration::ZERO {
+            return None;
+        }
+        Some(std::mem::replace(&mut bus.late_boarding, Duration::ZERO))
     }
 
     pub fn collect_events(&mut self) -> Vec<Event> {
@@ -1,5 +1,6 @@
 use std::collections::{BTreeMap, VecDeque};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap, Counter};
@@ -9,15 +10,16 @@ use map_model::{
     TransitStopID,
 };
 use synthpop::{
-    IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose,
+    FreightTour, IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint, TripMode,
+    TripPurpose,
 };
 
 use crate::sim::Ctx;
 use crate::{
     AgentID, AgentType, AlertLocation, CarID, Command, CreateCar, CreatePedestrian, DrivingGoal,
-    Event, ParkedCar, ParkingSim, ParkingSpot, PedestrianID, PersonID, SidewalkPOI, SidewalkSpot,
-    StartTripArgs, TransitSimState, TripID, TripPhaseType, TripSpec, Vehicle, VehicleSpec,
-    VehicleType, WalkingSimState,
+    Event, ParkedCar, ParkingSim, ParkingSpot, PedestrianID, PersonID, Router, SidewalkPOI,
+    SidewalkSpot, StartTripArgs, TransitSimState, TripID, TripPhaseType, TripSpec, Vehicle,
+    VehicleSpec, VehicleType, WalkingSimState,
 };
 
 /// Manages people, each of which executes some trips through the day. Each trip is further broken
@@ -85,6 +87,7 @@ impl TripManager {
             vehicles,
             delayed_trips: Vec::new(),
             on_bus: None,
+            in_ride_hail: None,
         });
         self.get_person(id).unwrap()
     }
@@ -164,6 +167,7 @@ impl TripManager {
             info.start,
             info.end,
             info.mode,
+            info.freight.as_ref(),
             args.use_vehicle,
             args.retry_if_no_room,
             ctx.map,
@@ -196,11 +200,7 @@ impl TripManager {
 
                 let vehicle = person.get_vehicle(use_vehicle);
                 assert!(ctx.parking.lookup_parked_car(vehicle.id).is_none());
-                let constraints = if use_vehicle.vehicle_type == VehicleType::Bike {
-                    PathConstraints::Bike
-                } else {
-                    PathConstraints::Car
-                };
+                let constraints = use_vehicle.vehicle_type.to_constraints();
                 let req = PathRequest::vehicle(
                     start_pos,
                     goal.goal_pos(constraints, ctx.map).unwrap(),
@@ -208,7 +208,7 @@ impl TripManager {
                 );
                 let person = person.id;
 
-                match ctx.map.pathfind(req) {
+                match ctx.pathfind(req, now) {
                     Ok(path) => {
                         let router = goal.make_router(vehicle.id, path, ctx.map);
                         ctx.scheduler.push(
@@ -241,7 +241,7 @@ impl TripManager {
                     let walking_goal =
                         SidewalkSpot::parking_spot(parked_car.spot, ctx.map, ctx.parking);
                     let req = PathRequest::walking(start.sidewalk_pos, walking_goal.sidewalk_pos);
-                    match ctx.map.pathfind(req) {
+                    match ctx.pathfind(req, now) {
                         Ok(path) => {
                             ctx.scheduler.push(
                                 now,
@@ -309,7 +309,7 @@ impl TripManager {
                 person.state = PersonState::Trip(trip);
 
                 let req = PathRequest::walking(start.sidewalk_pos, goal.sidewalk_pos);
-                match ctx.map.pathfind(req) {
+                match ctx.pathfind(req, now) {
                     Ok(path) => {
                         ctx.scheduler.push(
                             now,
@@ -338,7 +338,7 @@ impl TripManager {
                         SidewalkSpot::building(start, ctx.map).sidewalk_pos,
                         walk_to.sidewalk_pos,
                     );
-                    match ctx.map.pathfind(req) {
+                    match ctx.pathfind(req, now) {
                         Ok(path) => {
                             // Where we start biking may have slightly changed due to live map
                             // edits!
@@ -412,7 +412,7 @@ impl TripManager {
 
                 let walk_to = SidewalkSpot::bus_stop(stop1, ctx.map);
                 let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
-                match ctx.map.pathfind(req) {
+                match ctx.pathfind(req, now) {
                     Ok(path) => {
                         ctx.scheduler.push(
                             now,
@@ -432,6 +432,97 @@ impl TripManager {
                     }
                 }
             }
+            TripSpec::UsingRideHail { start, goal } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+
+                // Wait at the curb
+                self.events
+                    .push(Event::PersonLeavesBuilding(person.id, start));
+                self.events.push(Event::TripPhaseStarting(
+                    trip,
+                    person.id,
+                    None,
+                    TripPhaseType::WaitingForRideHail,
+                ));
+                for (t, err) in ctx.ridehail.request_ride(
+                    now,
+                    trip,
+                    start,
+                    goal,
+                    ctx.map,
+                    ctx.restrictions,
+                    ctx.scheduler,
+                ) {
+                    self.cancel_trip(now, t, err, None, ctx);
+                }
+            }
+            TripSpec::Freight {
+                start_pos,
+                use_vehicle,
+                retry_if_no_room,
+                ..
+            } => {
+                if let TripEndpoint::Building(b) = self.trips[trip.0].info.start {
+                    assert_eq!(person.state, PersonState::Inside(b));
+                    self.events.push(Event::PersonLeavesBuilding(person.id, b));
+                } else {
+                    assert_eq!(person.state, PersonState::OffMap);
+                    self.events.push(Event::PersonEntersMap(
+                        person.id,
+                        AgentID::Car(use_vehicle),
+                        ctx.map.get_l(start_pos.lane()).src_i,
+                    ));
+                }
+                person.state = PersonState::Trip(trip);
+
+                let vehicle = person.get_vehicle(use_vehicle);
+                let person = person.id;
+                match self.freight_router(trip, use_vehicle, start_pos, now, ctx) {
+                    Ok(router) => {
+                        ctx.scheduler.push(
+                            now,
+                            Command::SpawnCar(
+                                CreateCar::for_appearing(vehicle, router, trip, person),
+                                retry_if_no_room,
+                            ),
+                        );
+                    }
+                    Err(err) => {
+                        self.cancel_trip(now, trip, err.to_string(), Some(vehicle), ctx);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Plans the route from wherever a freight vehicle is now to the next stop or exit.
+    fn freight_router(
+        &self,
+        trip: TripID,
+        car: CarID,
+        start: Position,
+        now: Time,
+        ctx: &mut Ctx,
+    ) -> Result<Router> {
+        let map = ctx.map;
+        let constraints = car.vehicle_type.to_constraints();
+        match self.trips[trip.0].legs[0] {
+            TripLeg::Deliver(_, b, dwell) => {
+                let end = DrivingGoal::ParkNear(b)
+                    .goal_pos(constraints, map)
+                    .ok_or_else(|| anyhow!("{} can't reach {}", car, b))?;
+                let path = ctx.pathfind(PathRequest::vehicle(start, end, constraints), now)?;
+                Ok(Router::deliver(car, path, b, dwell))
+            }
+            TripLeg::Drive(_, ref goal) => {
+                let end = goal
+                    .goal_pos(constraints, map)
+                    .ok_or_else(|| anyhow!("{} can't reach {:?}", car, goal))?;
+                let path = ctx.pathfind(PathRequest::vehicle(start, end, constraints), now)?;
+                Ok(goal.make_router(car, path, map))
+            }
+            _ => unreachable!(),
         }
     }
 
@@ -465,6 +556,48 @@ impl TripManager {
         trip.total_blocked_time += blocked_time;
         trip.total_distance += distance_crossed;
 
+        if let Some(TripLeg::Deliver(c, b, dwell)) = trip.legs.front().cloned() {
+            assert_eq!(car, c);
+            trip.legs.pop_front();
+            let id = trip.id;
+            let person = trip.person;
+            let parked_car = ctx.parking.lookup_parked_car(car).unwrap().clone();
+            if self.trips[id.0].legs.is_empty() {
+                ctx.parking.remove_parked_car(parked_car);
+                self.freight_returned_to_depot(now, id, b, ctx);
+                return;
+            }
+
+            self.events
+                .push(Event::FreightStopReached(car, b, Some(spot)));
+            self.events.push(Event::TripPhaseStarting(
+                id,
+                person,
+                None,
+                TripPhaseType::Delivering(b),
+            ));
+            let start = ctx
+                .parking
+                .spot_to_driving_pos(spot, &parked_car.vehicle, ctx.map);
+            match self.freight_router(id, car, start, now, ctx) {
+                Ok(router) => {
+                    // Stay parked while loading or unloading, then pull out again
+                    ctx.scheduler.push(
+                        now + dwell,
+                        Command::SpawnCar(
+                            CreateCar::for_parked_car(parked_car, router, id, person),
+                            true,
+                        ),
+                    );
+                }
+                Err(err) => {
+                    ctx.parking.remove_parked_car(parked_car.clone());
+                    self.cancel_trip(now, id, err.to_string(), Some(parked_car.vehicle), ctx);
+                }
+            }
+            return;
+        }
+
         match trip.legs.pop_front() {
             Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_))) => {
                 assert_eq!(car, c);
@@ -499,6 +632,78 @@ impl TripManager {
         );
     }
 
+    /// A freight vehicle couldn't find anywhere at the curb, so it's stopping in the travel lane.
+    pub fn freight_double_parked(&mut self, car: CarID) {
+        let trip = &self.trips[self.active_trip_mode[&AgentID::Car(car)].0];
+        let b = match trip.legs[0] {
+            TripLeg::Deliver(_, b, _) => b,
+            _ => unreachable!(),
+        };
+        // Returning to the depot isn't a delivery
+        if trip.legs.len() > 1 {
+            self.events.push(Event::FreightStopReached(car, b, None));
+            self.events.push(Event::TripPhaseStarting(
+                trip.id,
+                trip.person,
+                None,
+                TripPhaseType::Delivering(b),
+            ));
+        }
+    }
+
+    /// A double-parked freight vehicle is done at a stop. Returns the route to the next stop, or
+    /// None if the vehicle should vanish, because it's back at the depot or the trip failed.
+    pub fn freight_departed_double_park(
+        &mut self,
+        now: Time,
+        car: CarID,
+        pos: Position,
+        blocked_time: Duration,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) -> Option<Router> {
+        let id = self.active_trip_mode[&AgentID::Car(car)];
+        let trip = &mut self.trips[id.0];
+        trip.total_blocked_time += blocked_time;
+        trip.total_distance += distance_crossed;
+
+        let b = match trip.legs.pop_front() {
+            Some(TripLeg::Deliver(c, b, _)) => {
+                assert_eq!(car, c);
+                b
+            }
+            _ => unreachable!(),
+        };
+        if trip.legs.is_empty() {
+            self.active_trip_mode.remove(&AgentID::Car(car));
+            self.freight_returned_to_depot(now, id, b, ctx);
+            return None;
+        }
+
+        match self.freight_router(id, car, pos, now, ctx) {
+            Ok(router) => {
+                self.events.push(Event::TripPhaseStarting(
+                    id,
+                    self.trips[id.0].person,
+                    Some(router.get_path().get_req().clone()),
+                    TripPhaseType::Driving,
+                ));
+                Some(router)
+            }
+            Err(err) => {
+                self.cancel_trip(now, id, err.to_string(), None, ctx);
+                None
+            }
+        }
+    }
+
+    fn freight_returned_to_depot(&mut self, now: Time, id: TripID, b: BuildingID, ctx: &mut Ctx) {
+        let person = self.trips[id.0].person;
+        self.people[person.0].state = PersonState::Inside(b);
+        self.events.push(Event::PersonEntersBuilding(person, b));
+        self.trip_finished(now, id, ctx);
+    }
+
     pub fn ped_reached_parking_spot(
         &mut self,
         now: Time,
@@ -547,7 +752,7 @@ impl TripManager {
 
         let person = trip.person;
         let trip = trip.id;
-        match ctx.map.pathfind(req) {
+        match ctx.pathfind(req, now) {
             Ok(path) => {
                 let router = drive_to.make_router(parked_car.vehicle.id, path, ctx.map);
                 ctx.scheduler.push(
@@ -615,8 +820,7 @@ impl TripManager {
                 req.start.lane()
             ))
         } else {
-            ctx.map
-                .pathfind(req)
+            ctx.pathfind(req, now)
                 .map(|path| drive_to.make_router(bike, path, ctx.map))
         };
         match maybe_router {
@@ -697,16 +901,15 @@ impl TripManager {
     }
 
     /// If no route is returned, the pedestrian boarded a bus immediately.
+    /// Returns the route to wait for, and where to get off.
     pub fn ped_reached_bus_stop(
         &mut self,
-        now: Time,
         ped: PedestrianID,
         stop: TransitStopID,
         blocked_time: Duration,
         distance_crossed: Distance,
         ctx: &mut Ctx,
-        transit: &mut TransitSimState,
-    ) -> Option<TransitRouteID> {
+    ) -> (TransitRouteID, Option<TransitStopID>) {
         let trip = &mut self.trips[self.active_trip_mode[&AgentID::Pedestrian(ped)].0];
         trip.total_blocked_time += blocked_time;
         trip.total_distance += distance_crossed;
@@ -725,27 +928,7 @@ impl TripManager {
                     None,
                     TripPhaseType::WaitingForBus(route, stop),
                 ));
-                if let Some(bus) = transit.ped_waiting_for_bus(
-                    now,
-                    ped,
-                    trip.id,
-                    trip.person,
-                    stop,
-                    route,
-                    maybe_stop2,
-                    ctx.map,
-                ) {
-                    trip.legs.pop_front();
-                    self.active_trip_mode
-                        .remove(&AgentID::Pedestrian(ped))
-                        .unwrap();
-                    self.active_trip_mode
-                        .insert(AgentID::BusPassenger(trip.person, bus), trip.id);
-                    self.people[trip.person.0].on_bus = Some(bus);
-                    None
-                } else {
-                    Some(route)
-                }
+                (route, maybe_stop2)
             }
             _ => unreachable!(),
         }
@@ -795,6 +978,44 @@ impl TripManager {
         self.spawn_ped(now, id, start, ctx);
     }
 
+    pub fn ride_hail_picked_up(&mut self, trip: TripID, car: CarID, req: PathRequest) {
+        let person = self.trips[trip.0].person;
+        self.agent_starting_trip_leg(AgentID::Car(car), trip);
+        self.people[person.0].in_ride_hail = Some(car);
+        self.events.push(Event::TripPhaseStarting(
+            trip,
+            person,
+            Some(req),
+            TripPhaseType::RidingRideHail(car),
+        ));
+    }
+
+    pub fn ride_hail_dropped_off(
+        &mut self,
+        now: Time,
+        car: CarID,
+        blocked_time: Duration,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) {
+        let trip = &mut self.trips[self.active_trip_mode.remove(&AgentID::Car(car)).unwrap().0];
+        trip.total_blocked_time += blocked_time;
+        trip.total_distance += distance_crossed;
+
+        let bldg = match trip.legs.pop_front().unwrap() {
+            TripLeg::RideHail(b) => b,
+            _ => unreachable!(),
+        };
+        let person = &mut self.people[trip.person.0];
+        person.in_ride_hail = None;
+        person.state = PersonState::Inside(bldg);
+        self.events
+            .push(Event::PersonEntersBuilding(trip.person, bldg));
+
+        let id = trip.id;
+        self.trip_finished(now, id, ctx);
+    }
+
     pub fn ped_reached_border(
         &mut self,
         now: Time,
@@ -937,7 +1158,7 @@ impl TripManager {
         };
 
         let req = PathRequest::walking(start.sidewalk_pos, walk_to.sidewalk_pos);
-        match ctx.map.pathfind(req) {
+        match ctx.pathfind(req, now) {
             Ok(path) => {
                 let person = &self.people[trip.person.0];
                 ctx.scheduler.push(
@@ -1006,15 +1227,24 @@ impl TripManager {
             TripEndpoint::SuddenlyAppear(_) => unreachable!(),
         };
 
+        // Ride-hail vehicles belong to the fleet, not the person, so don't warp them anywhere
+        let abandoned_vehicle = if let TripLeg::RideHail(_) = trip.legs[0] {
+            ctx.ridehail.trip_cancelled(id, abandoned_vehicle.is_some());
+            self.people[person.0].in_ride_hail = None;
+            None
+        } else {
+            abandoned_vehicle
+        };
+
         // Don't forget the car!
         if let Some(vehicle) = abandoned_vehicle {
-            if vehicle.vehicle_type == VehicleType::Car {
-                // First remove the parked car, if needed. Maybe the trip was cancelled while the
-                // car was parked in the starting building.
-                if let Some(parked_car) = ctx.parking.lookup_parked_car(vehicle.id).cloned() {
-                    ctx.parking.remove_parked_car(parked_car);
-                }
+            // First remove the parked car, if needed. Maybe the trip was cancelled while the car
+            // was parked in the starting building, or a freight vehicle was parked at a stop.
+            if let Some(parked_car) = ctx.parking.lookup_parked_car(vehicle.id).cloned() {
+                ctx.parking.remove_parked_car(parked_car);
+            }
 
+            if vehicle.vehicle_type == VehicleType::Car {
                 if let TripEndpoint::Building(b) = trip.info.end {
                     let driving_lane = ctx.map.find_driving_lane_near_building(b);
                     if let Some(spot) = ctx
@@ -1057,7 +1287,7 @@ impl TripManager {
         } else {
             // If the trip was cancelled because we'e totally out of parking, don't forget to clean
             // this up.
-            if let TripLeg::Drive(c, _) = &trip.legs[0] {
+            if let TripLeg::Drive(c, _) | TripLeg::Deliver(c, _, _) = &trip.legs[0] {
                 if let Some(t) = self.active_trip_mode.remove(&AgentID::Car(*c)) {
                     assert_eq!(t, trip.id);
                 }
@@ -1103,8 +1333,13 @@ impl TripManager {
         let person = &self.people[trip.person.0];
         let a = match &trip.legs[0] {
             TripLeg::Walk(_) => AgentID::Pedestrian(person.ped),
-            TripLeg::Drive(c, _) => AgentID::Car(*c),
+            TripLeg::Drive(c, _) | TripLeg::Deliver(c, _, _) => AgentID::Car(*c),
             TripLeg::RideBus(_, _) => AgentID::BusPassenger(person.id, person.on_bus.unwrap()),
+            // Still waiting to be picked up
+            TripLeg::RideHail(_) => match person.in_ride_hail {
+                Some(car) => AgentID::Car(car),
+                None => return TripResult::ModeChange,
+            },
         };
         if self.active_trip_mode.get(&a) == Some(&id) {
             TripResult::Ok(a)
@@ -1159,6 +1394,7 @@ impl TripManager {
             cyclists: 0,
 
             sov_drivers: 0,
+            freight_vehicles: 0,
 
             buses,
             trains,
@@ -1175,6 +1411,9 @@ impl TripManager {
                     VehicleType::Bike => {
                         cnt.cyclists += 1;
                     }
+                    VehicleType::Delivery | VehicleType::Truck => {
+                        cnt.freight_vehicles += 1;
+                    }
                     VehicleType::Bus | VehicleType::Train => unreachable!(),
                 },
                 AgentID::BusPassenger(_, c) => match c.vehicle_type {
@@ -1184,7 +1423,10 @@ impl TripManager {
                     VehicleType::Train => {
                         cnt.train_riders += 1;
                     }
-                    VehicleType::Car | VehicleType::Bike => unreachable!(),
+                    VehicleType::Car
+                    | VehicleType::Bike
+                    | VehicleType::Delivery
+                    | VehicleType::Truck => unreachable!(),
                 },
                 // These're counted separately
                 AgentID::Pedestrian(_) => {}
@@ -1266,7 +1508,7 @@ impl TripManager {
                     let agent_type = match t.info.mode {
                         TripMode::Walk => AgentType::Pedestrian,
                         TripMode::Bike => AgentType::Bike,
-                        TripMode::Drive => AgentType::Car,
+                        TripMode::Drive | TripMode::RideHail | TripMode::Freight => AgentType::Car,
                         // TODO Not true for long. People will be able to spawn at borders already
                         // on a bus.
                         TripMode::Transit => AgentType::Pedestrian,
@@ -1291,13 +1533,15 @@ impl TripManager {
                     .iter()
                     .map(|t| {
                         let trip = &self.trips[t.0];
-                        IndividTrip::new(
+                        let mut individ = IndividTrip::new(
                             trip.info.departure,
                             trip.info.purpose,
                             trip.info.start,
                             trip.info.end,
                             trip.info.mode,
-                        )
+                        );
+                        individ.freight = trip.info.freight.clone();
+                        individ
                     })
                     .collect(),
             });
@@ -1330,6 +1574,8 @@ pub struct TripInfo {
     /// Did a ScenarioModifier apply to this?
     pub modified: bool,
     pub cancellation_reason: Option<String>,
+    /// The stops of a freight tour
+    pub freight: Option<FreightTour>,
 }
 
 impl Trip {
@@ -1352,6 +1598,11 @@ pub(crate) enum TripLeg {
     Drive(CarID, DrivingGoal),
     /// Maybe get off at a stop, maybe ride off-map
     RideBus(TransitRouteID, Option<TransitStopID>),
+    /// Wait for a vehicle from the ride-hailing fleet, then ride it to this building
+    RideHail(BuildingID),
+    /// Drive a freight vehicle to this building, then stop at the curb for some time. If this is
+    /// the last leg, the vehicle instead disappears into its depot there.
+    Deliver(CarID, BuildingID, Duration),
 }
 
 pub enum TripResult<T> {
@@ -1397,6 +1648,7 @@ pub struct Person {
 
     delayed_trips: Vec<(TripID, StartTripArgs)>,
     on_bus: Option<CarID>,
+    in_ride_hail: Option<CarID>,
 }
 
 impl Person {
@@ -1422,6 +1674,7 @@ pub struct CommutersVehiclesCounts {
     pub cyclists: usize,
 
     pub sov_drivers: usize,
+    pub freight_vehicles: usize,
 
     pub buses: usize,
     pub trains: usize,
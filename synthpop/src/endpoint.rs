@@ -46,13 +46,17 @@ impl TripEndpoint {
                     PathRequest::vehicle(start, end, PathConstraints::Car)
                 }
             }
+            // The fleet vehicle picks up at the curb, not from a driveway
+            TripMode::RideHail => PathRequest::vehicle(start, end, PathConstraints::Car),
+            // Freight vehicles aren't stored in private garages; they appear at the curb
+            TripMode::Freight => PathRequest::vehicle(start, end, PathConstraints::Truck),
         })
     }
 
     fn pos(self, mode: TripMode, from: bool, map: &Map) -> Option<Position> {
         match mode {
             TripMode::Walk | TripMode::Transit => self.sidewalk_pos(map, from),
-            TripMode::Drive | TripMode::Bike => {
+            TripMode::Drive | TripMode::Bike | TripMode::RideHail | TripMode::Freight => {
                 let constraints = mode.to_constraints();
                 if from {
                     match self {
@@ -73,7 +77,7 @@ impl TripEndpoint {
 
                 match self {
                     TripEndpoint::Building(b) => match constraints {
-                        PathConstraints::Car => {
+                        PathConstraints::Car | PathConstraints::Truck => {
                             let driving_lane = map.find_driving_lane_near_building(b);
                             let sidewalk_pos = map.get_b(b).sidewalk_pos;
                             if driving_lane.road == sidewalk_pos.lane().road {
@@ -4,10 +4,13 @@
 use anyhow::Result;
 use serde::Deserialize;
 
-use geom::{Distance, FindClosest, LonLat, Time};
+use geom::{Distance, Duration, FindClosest, LonLat, Time};
 use map_model::Map;
 
-use crate::{IndividTrip, MapBorders, PersonSpec, TripEndpoint, TripMode, TripPurpose};
+use crate::{
+    FreightStop, FreightTour, FreightVehicle, IndividTrip, MapBorders, PersonSpec, TripEndpoint,
+    TripMode, TripPurpose,
+};
 
 #[derive(Deserialize)]
 pub struct ExternalPerson {
@@ -21,6 +24,9 @@ pub struct ExternalTrip {
     pub destination: ExternalTripEndpoint,
     pub mode: TripMode,
     pub purpose: TripPurpose,
+    /// Required for `TripMode::Freight`, and not allowed otherwise
+    #[serde(default)]
+    pub freight: Option<ExternalFreightTour>,
 }
 
 #[derive(Deserialize)]
@@ -29,13 +35,26 @@ pub enum ExternalTripEndpoint {
     Position(LonLat),
 }
 
+#[derive(Deserialize)]
+pub struct ExternalFreightTour {
+    pub vehicle: FreightVehicle,
+    pub stops: Vec<ExternalFreightStop>,
+}
+
+#[derive(Deserialize)]
+pub struct ExternalFreightStop {
+    pub position: LonLat,
+    pub dwell: Duration,
+}
+
 impl ExternalPerson {
     /// Import external scenario data. The main difference between `ExternalPerson` and
     /// `PersonSpec` is a way to specify endpoints by a `LonLat`. This is snapped to the nearest
     /// building. If the point is outside of the map boundary, it's snapped to the nearest border
     /// (by Euclidean distance -- the network outside the given map isn't known). Failure happens
-    /// if a point is within the map, but not close enough to any buildings. If `skip_problems` is
-    /// true, then those failures are logged; otherwise this panics at the first problem.
+    /// if a point is within the map, but not close enough to any buildings. Freight stops are always
+    /// snapped to the nearest building. If `skip_problems` is true, then those failures are
+    /// logged; otherwise this panics at the first problem.
     pub fn import(
         map: &Map,
         input: Vec<ExternalPerson>,
@@ -70,6 +89,36 @@ impl ExternalPerson {
             }
         };
 
+        let lookup_tour = |tour, mode| match (tour, mode) {
+            (None, TripMode::Freight) => Err(anyhow!("Freight trip has no tour")),
+            (None, _) => Ok(None),
+            (Some(ExternalFreightTour { vehicle, stops }), TripMode::Freight) => {
+                let mut tour = FreightTour {
+                    vehicle,
+                    stops: Vec::new(),
+                };
+                for stop in stops {
+                    let pt = stop.position.to_pt(map.get_gps_bounds());
+                    match closest.closest_pt(pt, Distance::meters(100.0)) {
+                        Some((TripEndpoint::Building(building), _)) => {
+                            tour.stops.push(FreightStop {
+                                building,
+                                dwell: stop.dwell,
+                            });
+                        }
+                        _ => {
+                            return Err(anyhow!("No building within 100m of {}", stop.position));
+                        }
+                    }
+                }
+                Ok(Some(tour))
+            }
+            (Some(_), mode) => Err(anyhow!(
+                "Only freight trips have a tour, not {} trips",
+                mode.ongoing_verb()
+            )),
+        };
+
         let mut results = Vec::new();
         for person in input {
             let mut spec = PersonSpec {
@@ -77,7 +126,18 @@ impl ExternalPerson {
                 trips: Vec::new(),
             };
             for trip in person.trips {
-                spec.trips.push(IndividTrip::new(
+                let tour = match lookup_tour(trip.freight, trip.mode) {
+                    Ok(tour) => tour,
+                    Err(err) => {
+                        if skip_problems {
+                            warn!("Skipping person: {}", err);
+                            continue;
+                        } else {
+                            return Err(err);
+                        }
+                    }
+                };
+                let mut individ_trip = IndividTrip::new(
                     trip.departure,
                     trip.purpose,
                     match lookup_pt(trip.origin, true, trip.mode) {
@@ -103,7 +163,11 @@ impl ExternalPerson {
                         }
                     },
                     trip.mode,
-                ));
+                );
+                if let Some(tour) = tour {
+                    individ_trip = individ_trip.with_freight(tour);
+                }
+                spec.trips.push(individ_trip);
             }
             results.push(spec);
         }
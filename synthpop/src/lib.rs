@@ -20,7 +20,9 @@ pub use self::counts::TrafficCounts;
 pub use self::endpoint::TripEndpoint;
 pub use self::external::{ExternalPerson, ExternalTrip, ExternalTripEndpoint};
 pub use self::modifier::ScenarioModifier;
-pub use self::scenario::{IndividTrip, PersonSpec, Scenario, TripPurpose};
+pub use self::scenario::{
+    FreightStop, FreightTour, FreightVehicle, IndividTrip, PersonSpec, Scenario, TripPurpose,
+};
 
 mod borders;
 mod counts;
@@ -39,6 +41,11 @@ pub enum TripMode {
     Bike,
     Transit,
     Drive,
+    /// Summon a vehicle from the simulated ride-hailing fleet, which picks up and drops off at
+    /// the curb.
+    RideHail,
+    /// Drive a delivery van or truck, stopping at a sequence of buildings along the way.
+    Freight,
 }
 
 impl TripMode {
@@ -48,6 +55,8 @@ impl TripMode {
             TripMode::Bike,
             TripMode::Transit,
             TripMode::Drive,
+            TripMode::RideHail,
+            TripMode::Freight,
         ]
     }
 
@@ -57,6 +66,8 @@ impl TripMode {
             TripMode::Bike => "bike",
             TripMode::Transit => "use transit",
             TripMode::Drive => "drive",
+            TripMode::RideHail => "take a ride-hail",
+            TripMode::Freight => "make deliveries",
         }
     }
 
@@ -67,6 +78,8 @@ impl TripMode {
             TripMode::Bike => "biking",
             TripMode::Transit => "using transit",
             TripMode::Drive => "driving",
+            TripMode::RideHail => "riding in a ride-hail",
+            TripMode::Freight => "making deliveries",
         }
     }
 
@@ -76,6 +89,8 @@ impl TripMode {
             TripMode::Bike => "Bike",
             TripMode::Transit => "Bus",
             TripMode::Drive => "Car",
+            TripMode::RideHail => "Ride-hail",
+            TripMode::Freight => "Freight",
         }
     }
 
@@ -85,7 +100,9 @@ impl TripMode {
             TripMode::Bike => PathConstraints::Bike,
             // TODO WRONG
             TripMode::Transit => PathConstraints::Bus,
-            TripMode::Drive => PathConstraints::Car,
+            TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
+            // Vans could use more roads, but this is the conservative choice
+            TripMode::Freight => PathConstraints::Truck,
         }
     }
 
@@ -96,6 +113,7 @@ impl TripMode {
             // TODO The bijection breaks down... transit rider vs train vs bus...
             PathConstraints::Bus | PathConstraints::Train => TripMode::Transit,
             PathConstraints::Car => TripMode::Drive,
+            PathConstraints::Truck => TripMode::Freight,
         }
     }
 }
@@ -6,8 +6,8 @@ use serde::{Deserialize, Serialize};
 
 use abstio::{CityName, MapName};
 use abstutil::prettyprint_usize;
-use geom::Time;
-use map_model::Map;
+use geom::{Duration, Time};
+use map_model::{BuildingID, Map};
 
 use crate::{OrigPersonID, TripEndpoint, TripMode};
 
@@ -42,6 +42,31 @@ pub struct IndividTrip {
     pub cancelled: bool,
     /// Did a ScenarioModifier affect this?
     pub modified: bool,
+    /// Only set for `TripMode::Freight`. The vehicle visits each stop in order before heading to
+    /// the destination.
+    #[serde(default)]
+    pub freight: Option<FreightTour>,
+}
+
+/// A delivery round made by one vehicle.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FreightTour {
+    pub vehicle: FreightVehicle,
+    /// May be empty, in which case the trip just drives from the origin to the destination.
+    pub stops: Vec<FreightStop>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum FreightVehicle {
+    Van,
+    Truck,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub struct FreightStop {
+    pub building: BuildingID,
+    /// How long the vehicle stays at the curb to load or unload
+    pub dwell: Duration,
 }
 
 impl IndividTrip {
@@ -60,8 +85,16 @@ impl IndividTrip {
             purpose,
             cancelled: false,
             modified: false,
+            freight: None,
         }
     }
+
+    /// Turns this into a freight trip, stopping at some buildings before the destination.
+    pub fn with_freight(mut self, tour: FreightTour) -> IndividTrip {
+        self.mode = TripMode::Freight;
+        self.freight = Some(tour);
+        self
+    }
 }
 
 /// Lifted from Seattle's Soundcast model, but seems general enough to use anyhere.
@@ -195,13 +228,21 @@ impl PersonSpec {
         }
 
         for trip in &self.trips {
-            if trip.origin == trip.destination {
+            if trip.origin == trip.destination && trip.freight.is_none() {
                 bail!(
                     "Person ({:?}) has a trip from/to the same place: {:?}",
                     self.orig_id,
                     trip.origin
                 );
             }
+            if trip.freight.is_some() != (trip.mode == TripMode::Freight) {
+                bail!(
+                    "Person ({:?}) has a trip with mode {:?}, but freight stops {:?}",
+                    self.orig_id,
+                    trip.mode,
+                    trip.freight
+                );
+            }
         }
 
         Ok(())
@@ -1,5 +1,6 @@
 //! Integration tests
 
+use std::collections::BTreeSet;
 use std::io::Write;
 
 use anyhow::Result;
@@ -8,8 +9,12 @@ use rand::seq::SliceRandom;
 
 use abstio::{CityName, MapName};
 use abstutil::Timer;
-use geom::{Distance, Duration, Time};
-use map_model::{IntersectionID, Map, Perimeter};
+use geom::{Distance, Duration, Speed, Time};
+use map_model::raw::RawMap;
+use map_model::{
+    osm, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map, MigrationOutcome,
+    Perimeter, PermanentMapEdits, RawToMapOptions,
+};
 use synthpop::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
 
 fn main() -> Result<()> {
@@ -19,6 +24,10 @@ fn main() -> Result<()> {
         "../tests/input/lane_selection.osm",
     )))?;
     test_map_importer()?;
+    test_osm_changes()?;
+    test_edit_migration()?;
+    test_table_export()?;
+    test_give_way()?;
     check_proposals()?;
     smoke_test()?;
     Ok(())
@@ -46,12 +55,61 @@ fn test_map_importer() -> Result<()> {
     Ok(())
 }
 
+/// Apply an OSM change file to a test map, then check that updating the existing map in place
+/// matches rebuilding it from scratch.
+fn test_osm_changes() -> Result<()> {
+    let path = abstio::path("../tests/input/left_turn_and_bike_lane.osm");
+    let osm_changes = vec![abstio::path("../tests/input/left_turn_and_bike_lane.osc")];
+    let mut timer = Timer::new("test OSM changes");
+    let opts = RawToMapOptions::default();
+
+    let mut updated = Map::create_from_raw(
+        import_raw(path.clone(), Vec::new()),
+        opts.clone(),
+        &mut timer,
+    );
+    updated.update_from_raw(
+        import_raw(path.clone(), Vec::new()),
+        import_raw(path.clone(), osm_changes.clone()),
+        &opts,
+        &mut timer,
+    )?;
+    let rebuilt = Map::create_from_raw(import_raw(path, osm_changes), opts, &mut timer);
+
+    for (r1, r2) in updated.all_roads().iter().zip(rebuilt.all_roads()) {
+        if updated.get_r_edit(r1.id) != rebuilt.get_r_edit(r2.id) {
+            anyhow::bail!("{} differs after updating in place", r1.orig_id);
+        }
+    }
+    for (i1, i2) in updated
+        .all_intersections()
+        .iter()
+        .zip(rebuilt.all_intersections())
+    {
+        if i1.intersection_type != i2.intersection_type {
+            anyhow::bail!("{} differs after updating in place", i1.orig_id);
+        }
+    }
+    let turns = |map: &Map| map.all_turns().map(|t| t.id).collect::<BTreeSet<_>>();
+    if turns(&updated) != turns(&rebuilt) {
+        anyhow::bail!("Turns differ after updating in place");
+    }
+    Ok(())
+}
+
 /// Run the contents of a .osm through the full map importer with default options.
 fn import_map(path: String) -> Map {
+    let mut timer = Timer::new("convert synthetic map");
+    let raw = import_raw(path, Vec::new());
+    Map::create_from_raw(raw, RawToMapOptions::default(), &mut timer)
+}
+
+/// Convert a .osm to a RawMap with default options, applying OSM change files on top.
+fn import_raw(path: String, osm_changes: Vec<String>) -> RawMap {
     let mut timer = Timer::new("convert synthetic map");
     let name = MapName::new("zz", "oneshot", &abstutil::basename(&path));
     let clip = None;
-    let raw = convert_osm::convert(
+    convert_osm::convert(
         path,
         name,
         clip,
@@ -71,10 +129,12 @@ fn import_map(path: String) -> Map {
             skip_local_roads: false,
             filter_crosswalks: false,
             gtfs_url: None,
+            gtfs_service_date: None,
+            elevation: convert_osm::ElevationBackend::Docker,
+            osm_changes,
         },
         &mut timer,
-    );
-    Map::create_from_raw(raw, map_model::RawToMapOptions::default(), &mut timer)
+    )
 }
 
 /// Verify what turns are generated by writing (from lane, to lane, turn type).
@@ -141,6 +201,154 @@ fn dump_route_goldenfile(map: &map_model::Map) -> Result<()> {
     Ok(())
 }
 
+/// Save edits on a test map, then load them on the same map with every OSM ID changed. The edits
+/// should be matched again by location, unless they were saved without locations.
+fn test_edit_migration() -> Result<()> {
+    let mut old_map = import_map(abstio::path("../tests/input/left_turn_and_bike_lane.osm"));
+    let mut new_map = import_map(abstio::path(
+        "../tests/input/left_turn_and_bike_lane_renumbered.osm",
+    ));
+
+    let west_road = |map: &Map, way: i64| {
+        map.all_roads()
+            .iter()
+            .find(|r| r.orig_id.osm_way_id == osm::WayID(way))
+            .unwrap()
+            .id
+    };
+    let center = |map: &Map, node: i64| map.find_i_by_osm_id(osm::NodeID(node)).unwrap();
+
+    // Lower the speed limit on the west road and put a signal in the middle
+    let r = west_road(&old_map, 102);
+    let i = center(&old_map, 1);
+    let mut edits = old_map.get_edits().clone();
+    edits.commands.push(old_map.edit_road_cmd(r, |new| {
+        new.speed_limit = Speed::miles_per_hour(15.0);
+    }));
+    edits.commands.push(EditCmd::ChangeIntersection {
+        i,
+        old: old_map.get_i_edit(i),
+        new: EditIntersection::TrafficSignal(
+            ControlTrafficSignal::new(&old_map, i).export(&old_map),
+        ),
+    });
+    old_map.must_apply_edits(edits, &mut Timer::throwaway());
+    let perma = old_map.get_edits().to_permanent(&old_map);
+
+    // Nothing changes on the same map
+    let (_, report) = perma.clone().migrate(&old_map);
+    if !report
+        .iter()
+        .all(|item| matches!(item.outcome, MigrationOutcome::CarriedOver))
+    {
+        anyhow::bail!("Edits didn't carry over to the same map: {:?}", report);
+    }
+
+    // Everything moves to the renumbered map
+    let (edits, report) = perma.clone().migrate(&new_map);
+    if report.len() != 2
+        || !report
+            .iter()
+            .all(|item| matches!(item.outcome, MigrationOutcome::Moved(_)))
+    {
+        anyhow::bail!("Edits didn't move to the renumbered map: {:?}", report);
+    }
+    new_map.must_apply_edits(edits, &mut Timer::throwaway());
+    if new_map.get_r(west_road(&new_map, 2102)).speed_limit != Speed::miles_per_hour(15.0) {
+        anyhow::bail!("The speed limit edit landed on the wrong road");
+    }
+    if !new_map.get_i(center(&new_map, 11)).is_traffic_signal() {
+        anyhow::bail!("The traffic signal edit landed on the wrong intersection");
+    }
+
+    // Edits saved before locations were recorded can't be matched, so they're dropped
+    let mut legacy = serde_json::to_value(&perma)?;
+    let legacy_obj = legacy.as_object_mut().unwrap();
+    legacy_obj.remove("road_locations");
+    legacy_obj.remove("intersection_locations");
+    let legacy: PermanentMapEdits = serde_json::from_value(legacy)?;
+    let (edits, report) = legacy.migrate(&new_map);
+    if !edits.commands.is_empty()
+        || !report
+            .iter()
+            .all(|item| matches!(item.outcome, MigrationOutcome::Dropped(_)))
+    {
+        anyhow::bail!(
+            "Edits without locations were applied to the renumbered map: {:?}",
+            report
+        );
+    }
+
+    Ok(())
+}
+
+/// Tables exported from analytics should identify roads and intersections by their OSM IDs, which
+/// stay stable when the map is rebuilt, not by the internal IDs.
+fn test_table_export() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/left_turn_and_bike_lane.osm"));
+    let mut analytics = sim::Analytics::new(true);
+    for r in map.all_roads() {
+        analytics
+            .road_thruput
+            .counts
+            .insert((r.id, sim::AgentType::Car, 8), 1);
+    }
+    for i in map.all_intersections() {
+        analytics
+            .intersection_thruput
+            .counts
+            .insert((i.id, sim::AgentType::Bike, 8), 1);
+    }
+    let tables = analytics.to_tables(&map);
+    let table = |name: &str| tables.iter().find(|t| t.name == name).unwrap();
+    let int = |x: &sim::Value| match x {
+        sim::Value::Int(x) => Some(*x),
+        _ => None,
+    };
+
+    let expected_roads: BTreeSet<Vec<Option<i64>>> = map
+        .all_roads()
+        .iter()
+        .map(|r| {
+            vec![
+                Some(r.orig_id.osm_way_id.0),
+                Some(r.orig_id.i1.0),
+                Some(r.orig_id.i2.0),
+            ]
+        })
+        .collect();
+    let actual_roads: BTreeSet<Vec<Option<i64>>> = table("road_thruput")
+        .rows
+        .iter()
+        .map(|row| row[0..3].iter().map(int).collect())
+        .collect();
+    if actual_roads != expected_roads {
+        anyhow::bail!(
+            "road_thruput isn't keyed by OSM way and node IDs: {:?}",
+            actual_roads
+        );
+    }
+
+    let expected_intersections: BTreeSet<Option<i64>> = map
+        .all_intersections()
+        .iter()
+        .map(|i| Some(i.orig_id.0))
+        .collect();
+    let actual_intersections: BTreeSet<Option<i64>> = table("intersection_thruput")
+        .rows
+        .iter()
+        .map(|row| int(&row[0]))
+        .collect();
+    if actual_intersections != expected_intersections {
+        anyhow::bail!(
+            "intersection_thruput isn't keyed by OSM node IDs: {:?}",
+            actual_intersections
+        );
+    }
+
+    Ok(())
+}
+
 /// Verify all edits under version control can be correctly apply to their map.
 fn check_proposals() -> Result<()> {
     let mut timer = Timer::new("check all proposals");
@@ -241,6 +449,87 @@ fn test_lane_changing(map: &Map) -> Result<()> {
     Ok(())
 }
 
+/// Check that side streets give way where it's tagged, and that traffic waits for a gap before
+/// entering the main road.
+fn test_give_way() -> Result<()> {
+    let map = import_map(abstio::path("../tests/input/give_way.osm"));
+    let i = |id| map.find_i_by_osm_id(osm::NodeID(id)).unwrap();
+
+    // The side street gives way to Main Street, because of the sign tagged along it. Main Street
+    // gives way to the bigger road, because the sign is tagged at the junction.
+    for (node, yielding_way) in [(-2, -11), (-3, -10)] {
+        let intersection = map.get_i(i(node));
+        if !intersection.is_yield() {
+            anyhow::bail!("{} isn't a yield intersection", intersection.orig_id);
+        }
+        for (r, cfg) in &map.get_stop_sign(intersection.id).roads {
+            let should_yield = map.get_r(*r).orig_id.osm_way_id.0 == yielding_way;
+            if cfg.must_stop != should_yield {
+                anyhow::bail!(
+                    "At {}, {} should yield is {}, but got {}",
+                    intersection.orig_id,
+                    map.get_r(*r).orig_id,
+                    should_yield,
+                    cfg.must_stop
+                );
+            }
+        }
+    }
+
+    // Run cars starting from some borders, all heading west. Return their trip times.
+    let run = |trips: Vec<(f64, i64)>| -> Vec<Duration> {
+        let mut scenario = Scenario::empty(&map, "give_way");
+        for (depart, from) in trips {
+            scenario.people.push(PersonSpec {
+                orig_id: None,
+                trips: vec![IndividTrip::new(
+                    Time::START_OF_DAY + Duration::seconds(depart),
+                    TripPurpose::Shopping,
+                    TripEndpoint::Border(i(from)),
+                    TripEndpoint::Border(i(-1)),
+                    TripMode::Drive,
+                )],
+            });
+        }
+        let mut opts = sim::SimOptions::new("test_give_way");
+        opts.alerts = sim::AlertHandler::Silence;
+        let mut sim = sim::Sim::new(&map, opts);
+        let mut rng = sim::SimFlags::for_test("test_give_way").make_rng();
+        sim.instantiate(&scenario, &map, &mut rng, &mut Timer::throwaway());
+        while !sim.is_done() {
+            sim.tiny_step(&map, &mut None);
+        }
+        let mut finished = sim.get_analytics().finished_trips.clone();
+        finished.sort_by_key(|(_, id, _, _)| *id);
+        finished
+            .into_iter()
+            .map(|(_, _, _, dt)| dt.unwrap())
+            .collect()
+    };
+    // The car on the main road reaches the side street a moment after the side street car is
+    // ready to turn onto it. There's not enough of a gap, so the side street car waits.
+    let side_street_alone = run(vec![(10.0, -5)])[0];
+    let main_road_alone = run(vec![(8.0, -4)])[0];
+    let both = run(vec![(10.0, -5), (8.0, -4)]);
+    if both[0] <= side_street_alone {
+        anyhow::bail!(
+            "The side street car took {} with traffic on the main road, and {} alone; it didn't \
+             give way",
+            both[0],
+            side_street_alone
+        );
+    }
+    if both[1] != main_road_alone {
+        anyhow::bail!(
+            "The main road car took {} with a side street car, and {} alone; it was held up",
+            both[1],
+            main_road_alone
+        );
+    }
+
+    Ok(())
+}
+
 /// Generate single blocks and merged LTN-style blocks for some maps, counting the number of
 /// failures. Store in a goldenfile, so somebody can manually do a visual diff if anything changes.
 fn test_blockfinding() -> Result<()> {
@@ -31,6 +31,12 @@ pub struct Plan {
     pub stages: Vec<Stage>,
     /// Relative to a central clock, delay the first stage by this many seconds.
     pub offset_seconds: usize,
+    /// If present, the signal is coordinated with others along a corridor sharing this common
+    /// cycle length, in seconds. The first stage is the coordinated one; it absorbs any time that
+    /// actuated stages don't use, so that each cycle starts at `offset_seconds` past the central
+    /// clock's cycle boundary.
+    #[serde(default)]
+    pub cycle_length_seconds: Option<usize>,
 }
 
 /// A traffic signal is in one stage at any time. The stage describes what movements are possible.
@@ -60,6 +66,27 @@ pub enum StageType {
     /// is 20, and additional is 40, the maximum cycle duration is 60.
     /// If there are crosswalks, the minimum is the minimum for the maximum crosswalks
     Variable(usize, usize, usize),
+    /// Controlled by detectors on the approach lanes.
+    Actuated(Actuation),
+}
+
+/// Settings for a stage controlled by virtual loop detectors on the lanes approaching the
+/// intersection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Actuation {
+    /// Once the stage starts, it lasts at least this many seconds. This must leave enough time
+    /// for any crosswalks in the stage.
+    pub min_green_seconds: usize,
+    /// Even if vehicles keep arriving, the stage ends after this many seconds ("max-out").
+    pub max_green_seconds: usize,
+    /// After the minimum green, the stage ends once no vehicle has been detected for this many
+    /// seconds ("gap-out").
+    pub passage_seconds: usize,
+    /// If true, the stage is served every cycle for pedestrians. Otherwise, the stage is skipped
+    /// when nobody is detected or waiting for it.
+    pub pedestrian_recall: bool,
+    /// Vehicles within this many meters of the stop line place a call on the detector.
+    pub detector_length_meters: usize,
 }
 
 /// A movement through an intersection.